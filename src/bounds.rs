@@ -0,0 +1,417 @@
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: nalgebra_glm::Vec3,
+    pub max: nalgebra_glm::Vec3,
+}
+
+impl Aabb {
+    pub fn new(min: nalgebra_glm::Vec3, max: nalgebra_glm::Vec3) -> Self {
+        Self { min, max }
+    }
+
+    pub fn from_points<'a>(points: impl IntoIterator<Item = &'a nalgebra_glm::Vec3>) -> Self {
+        let mut points = points.into_iter();
+        let Some(first) = points.next() else {
+            return Self::new(nalgebra_glm::Vec3::zeros(), nalgebra_glm::Vec3::zeros());
+        };
+        points.fold(Self::new(*first, *first), |aabb, point| {
+            Self::new(
+                nalgebra_glm::min2(&aabb.min, point),
+                nalgebra_glm::max2(&aabb.max, point),
+            )
+        })
+    }
+
+    pub fn from_sphere(center: &nalgebra_glm::Vec3, radius: f32) -> Self {
+        let extent = nalgebra_glm::vec3(radius, radius, radius);
+        Self::new(center - extent, center + extent)
+    }
+
+    pub fn center(&self) -> nalgebra_glm::Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn half_extents(&self) -> nalgebra_glm::Vec3 {
+        (self.max - self.min) * 0.5
+    }
+
+    pub fn surface_area(&self) -> f32 {
+        let size = self.max - self.min;
+        2.0 * (size.x * size.y + size.y * size.z + size.z * size.x)
+    }
+
+    pub fn union(&self, other: &Aabb) -> Self {
+        Self::new(
+            nalgebra_glm::min2(&self.min, &other.min),
+            nalgebra_glm::max2(&self.max, &other.max),
+        )
+    }
+
+    pub fn expanded(&self, margin: f32) -> Self {
+        let margin = nalgebra_glm::vec3(margin, margin, margin);
+        Self::new(self.min - margin, self.max + margin)
+    }
+
+    pub fn transformed(&self, transform: &nalgebra_glm::Mat4) -> Self {
+        let center = transform * self.center().push(1.0);
+        let half_extents = self.half_extents();
+        let rotation = nalgebra_glm::mat4_to_mat3(transform).abs();
+        let extent = rotation * half_extents;
+        Self::new(center.xyz() - extent, center.xyz() + extent)
+    }
+
+    pub fn contains(&self, other: &Aabb) -> bool {
+        self.min.x <= other.min.x
+            && self.min.y <= other.min.y
+            && self.min.z <= other.min.z
+            && self.max.x >= other.max.x
+            && self.max.y >= other.max.y
+            && self.max.z >= other.max.z
+    }
+
+    pub fn contains_point(&self, point: &nalgebra_glm::Vec3) -> bool {
+        (0..3).all(|axis| point[axis] >= self.min[axis] && point[axis] <= self.max[axis])
+    }
+
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        (0..3).all(|axis| self.min[axis] <= other.max[axis] && self.max[axis] >= other.min[axis])
+    }
+
+    pub fn intersects_sphere(&self, center: &nalgebra_glm::Vec3, radius: f32) -> bool {
+        let closest = nalgebra_glm::clamp_vec(center, &self.min, &self.max);
+        nalgebra_glm::distance2(&closest, center) <= radius * radius
+    }
+
+    pub fn intersects_ray(&self, ray: &Ray, max_distance: f32) -> Option<f32> {
+        let mut near = 0.0_f32;
+        let mut far = max_distance;
+        for axis in 0..3 {
+            let inverse = 1.0 / ray.direction[axis];
+            let mut t0 = (self.min[axis] - ray.origin[axis]) * inverse;
+            let mut t1 = (self.max[axis] - ray.origin[axis]) * inverse;
+            if inverse < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            near = if t0.is_nan() { near } else { near.max(t0) };
+            far = if t1.is_nan() { far } else { far.min(t1) };
+            if near > far {
+                return None;
+            }
+        }
+        Some(near)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ray {
+    pub origin: nalgebra_glm::Vec3,
+    pub direction: nalgebra_glm::Vec3,
+}
+
+impl Ray {
+    pub fn new(origin: nalgebra_glm::Vec3, direction: nalgebra_glm::Vec3) -> Self {
+        Self {
+            origin,
+            direction: nalgebra_glm::normalize(&direction),
+        }
+    }
+
+    pub fn from_screen(
+        inverse_view_projection: &nalgebra_glm::Mat4,
+        ndc: nalgebra_glm::Vec2,
+    ) -> Self {
+        let unproject = |depth: f32| {
+            let point = inverse_view_projection * nalgebra_glm::vec4(ndc.x, ndc.y, depth, 1.0);
+            point.xyz() / point.w
+        };
+        let near = unproject(0.0);
+        let far = unproject(1.0);
+        Self::new(near, far - near)
+    }
+
+    pub fn at(&self, distance: f32) -> nalgebra_glm::Vec3 {
+        self.origin + self.direction * distance
+    }
+
+    pub fn intersects_plane(
+        &self,
+        point: &nalgebra_glm::Vec3,
+        normal: &nalgebra_glm::Vec3,
+    ) -> Option<f32> {
+        let denominator = nalgebra_glm::dot(normal, &self.direction);
+        if denominator.abs() < 1e-4 {
+            return None;
+        }
+        let distance = nalgebra_glm::dot(normal, &(point - self.origin)) / denominator;
+        (distance >= 0.0).then_some(distance)
+    }
+
+    pub fn closest_to_line(
+        &self,
+        point: &nalgebra_glm::Vec3,
+        direction: &nalgebra_glm::Vec3,
+    ) -> Option<f32> {
+        let alignment = nalgebra_glm::dot(direction, &self.direction);
+        let denominator = 1.0 - alignment * alignment;
+        if denominator < 1e-4 {
+            return None;
+        }
+        let offset = point - self.origin;
+        Some(
+            (alignment * nalgebra_glm::dot(&self.direction, &offset)
+                - nalgebra_glm::dot(direction, &offset))
+                / denominator,
+        )
+    }
+
+    pub fn intersects_triangle(
+        &self,
+        a: &nalgebra_glm::Vec3,
+        b: &nalgebra_glm::Vec3,
+        c: &nalgebra_glm::Vec3,
+    ) -> Option<f32> {
+        let edge1 = b - a;
+        let edge2 = c - a;
+        let p = self.direction.cross(&edge2);
+        let determinant = edge1.dot(&p);
+        if determinant.abs() < f32::EPSILON {
+            return None;
+        }
+        let inverse = 1.0 / determinant;
+        let t = self.origin - a;
+        let u = t.dot(&p) * inverse;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+        let q = t.cross(&edge1);
+        let v = self.direction.dot(&q) * inverse;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+        let distance = edge2.dot(&q) * inverse;
+        (distance >= 0.0).then_some(distance)
+    }
+
+    pub fn intersects_mesh(
+        &self,
+        positions: &[nalgebra_glm::Vec3],
+        indices: &[u32],
+        transform: &nalgebra_glm::Mat4,
+        max_distance: f32,
+    ) -> Option<f32> {
+        let positions = positions
+            .iter()
+            .map(|position| (transform * position.push(1.0)).xyz())
+            .collect::<Vec<_>>();
+        indices
+            .as_chunks::<3>()
+            .0
+            .iter()
+            .filter_map(|[a, b, c]| {
+                self.intersects_triangle(
+                    &positions[*a as usize],
+                    &positions[*b as usize],
+                    &positions[*c as usize],
+                )
+            })
+            .filter(|distance| *distance <= max_distance)
+            .min_by(f32::total_cmp)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frustum {
+    pub planes: [nalgebra_glm::Vec4; 6],
+}
+
+impl Frustum {
+    pub fn from_view_projection(view_projection: &nalgebra_glm::Mat4) -> Self {
+        let row = |index: usize| view_projection.row(index).transpose();
+        let planes = [
+            row(3) + row(0),
+            row(3) - row(0),
+            row(3) + row(1),
+            row(3) - row(1),
+            row(2),
+            row(3) - row(2),
+        ];
+        Self {
+            planes: planes
+                .map(|plane| plane / nalgebra_glm::length(&plane.xyz()).max(f32::EPSILON)),
+        }
+    }
+
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        let center = aabb.center();
+        let half_extents = aabb.half_extents();
+        self.planes.iter().all(|plane| {
+            let normal = plane.xyz();
+            let radius = nalgebra_glm::dot(&normal.abs(), &half_extents);
+            nalgebra_glm::dot(&normal, &center) + plane.w >= -radius
+        })
+    }
+
+    pub fn intersects_sphere(&self, center: &nalgebra_glm::Vec3, radius: f32) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| nalgebra_glm::dot(&plane.xyz(), center) + plane.w >= -radius)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frustum() -> Frustum {
+        let projection = nalgebra_glm::perspective_lh_zo(1.0, 90_f32.to_radians(), 0.1, 100.0);
+        let view = nalgebra_glm::look_at_lh(
+            &nalgebra_glm::vec3(0.0, 0.0, 0.0),
+            &nalgebra_glm::vec3(0.0, 0.0, 1.0),
+            &nalgebra_glm::Vec3::y(),
+        );
+        Frustum::from_view_projection(&(projection * view))
+    }
+
+    #[test]
+    fn ray_hits_triangle_inside_and_misses_outside() {
+        let a = nalgebra_glm::vec3(-1.0, -1.0, 5.0);
+        let b = nalgebra_glm::vec3(1.0, -1.0, 5.0);
+        let c = nalgebra_glm::vec3(0.0, 1.0, 5.0);
+        let ray = Ray::new(
+            nalgebra_glm::Vec3::zeros(),
+            nalgebra_glm::vec3(0.0, 0.0, 1.0),
+        );
+        let distance = ray.intersects_triangle(&a, &b, &c).unwrap();
+        assert!((distance - 5.0).abs() < 1e-5);
+
+        let beside = Ray::new(
+            nalgebra_glm::vec3(2.0, 0.0, 0.0),
+            nalgebra_glm::vec3(0.0, 0.0, 1.0),
+        );
+        assert_eq!(beside.intersects_triangle(&a, &b, &c), None);
+
+        let away = Ray::new(
+            nalgebra_glm::Vec3::zeros(),
+            nalgebra_glm::vec3(0.0, 0.0, -1.0),
+        );
+        assert_eq!(away.intersects_triangle(&a, &b, &c), None);
+
+        let parallel = Ray::new(
+            nalgebra_glm::vec3(0.0, 0.0, 5.0),
+            nalgebra_glm::vec3(1.0, 0.0, 0.0),
+        );
+        assert_eq!(parallel.intersects_triangle(&a, &b, &c), None);
+    }
+
+    #[test]
+    fn ray_intersects_plane_in_front_only() {
+        let point = nalgebra_glm::vec3(0.0, 2.0, 0.0);
+        let normal = nalgebra_glm::Vec3::y();
+        let down = Ray::new(
+            nalgebra_glm::vec3(1.0, 5.0, 1.0),
+            nalgebra_glm::vec3(0.0, -1.0, 0.0),
+        );
+        let distance = down.intersects_plane(&point, &normal).unwrap();
+        assert!((distance - 3.0).abs() < 1e-5);
+        assert!((down.at(distance).y - 2.0).abs() < 1e-5);
+
+        let up = Ray::new(
+            nalgebra_glm::vec3(1.0, 5.0, 1.0),
+            nalgebra_glm::vec3(0.0, 1.0, 0.0),
+        );
+        assert_eq!(up.intersects_plane(&point, &normal), None);
+
+        let parallel = Ray::new(
+            nalgebra_glm::vec3(1.0, 5.0, 1.0),
+            nalgebra_glm::vec3(1.0, 0.0, 0.0),
+        );
+        assert_eq!(parallel.intersects_plane(&point, &normal), None);
+    }
+
+    #[test]
+    fn ray_hits_mesh_at_nearest_transformed_triangle() {
+        let positions = [
+            nalgebra_glm::vec3(-1.0, -1.0, 0.0),
+            nalgebra_glm::vec3(1.0, -1.0, 0.0),
+            nalgebra_glm::vec3(0.0, 1.0, 0.0),
+        ];
+        let transform = nalgebra_glm::translation(&nalgebra_glm::vec3(0.0, 0.0, 4.0));
+        let ray = Ray::new(
+            nalgebra_glm::Vec3::zeros(),
+            nalgebra_glm::vec3(0.0, 0.0, 1.0),
+        );
+        let distance = ray
+            .intersects_mesh(&positions, &[0, 1, 2], &transform, 10.0)
+            .unwrap();
+        assert!((distance - 4.0).abs() < 1e-5);
+        assert_eq!(
+            ray.intersects_mesh(&positions, &[0, 1, 2], &transform, 3.0),
+            None
+        );
+    }
+
+    #[test]
+    fn ray_intersects_aabb_within_range() {
+        let aabb = Aabb::new(
+            nalgebra_glm::vec3(-1.0, -1.0, 2.0),
+            nalgebra_glm::vec3(1.0, 1.0, 4.0),
+        );
+        let ray = Ray::new(
+            nalgebra_glm::Vec3::zeros(),
+            nalgebra_glm::vec3(0.0, 0.0, 1.0),
+        );
+        assert_eq!(aabb.intersects_ray(&ray, 10.0), Some(2.0));
+        assert_eq!(aabb.intersects_ray(&ray, 1.0), None);
+        let inside = Ray::new(
+            nalgebra_glm::vec3(0.0, 0.0, 3.0),
+            nalgebra_glm::vec3(1.0, 0.0, 0.0),
+        );
+        assert_eq!(aabb.intersects_ray(&inside, 10.0), Some(0.0));
+    }
+
+    #[test]
+    fn transformed_aabb_encloses_rotated_corners() {
+        let aabb = Aabb::new(
+            nalgebra_glm::vec3(-1.0, -1.0, -1.0),
+            nalgebra_glm::vec3(1.0, 1.0, 1.0),
+        );
+        let transform = nalgebra_glm::translation(&nalgebra_glm::vec3(5.0, 0.0, 0.0))
+            * nalgebra_glm::rotation(45_f32.to_radians(), &nalgebra_glm::Vec3::y());
+        let transformed = aabb.transformed(&transform);
+        let expanded = transformed.expanded(1e-4);
+        for corner in 0..8 {
+            let local = nalgebra_glm::vec3(
+                if corner & 1 == 0 { -1.0 } else { 1.0 },
+                if corner & 2 == 0 { -1.0 } else { 1.0 },
+                if corner & 4 == 0 { -1.0 } else { 1.0 },
+            );
+            let world = (transform * local.push(1.0)).xyz();
+            assert!(expanded.contains_point(&world));
+        }
+        assert!((transformed.half_extents().x - 2_f32.sqrt()).abs() < 1e-4);
+    }
+
+    #[test]
+    fn frustum_contains_points_in_view_only() {
+        let frustum = frustum();
+        let inside = nalgebra_glm::vec3(0.0, 0.0, 10.0);
+        assert!(frustum.intersects_sphere(&inside, 0.0));
+        assert!(frustum.intersects_sphere(&nalgebra_glm::vec3(9.0, 0.0, 10.0), 0.0));
+        assert!(!frustum.intersects_sphere(&nalgebra_glm::vec3(11.0, 0.0, 10.0), 0.0));
+        assert!(!frustum.intersects_sphere(&nalgebra_glm::vec3(0.0, 0.0, -10.0), 0.0));
+        assert!(!frustum.intersects_sphere(&nalgebra_glm::vec3(0.0, 0.0, 150.0), 0.0));
+        assert!(!frustum.intersects_sphere(&nalgebra_glm::vec3(0.0, 0.0, 0.05), 0.0));
+        assert!(frustum.intersects_sphere(&nalgebra_glm::vec3(11.0, 0.0, 10.0), 2.0));
+
+        let straddling = Aabb::new(
+            nalgebra_glm::vec3(9.0, -1.0, 9.0),
+            nalgebra_glm::vec3(12.0, 1.0, 11.0),
+        );
+        assert!(frustum.intersects_aabb(&straddling));
+        let behind = Aabb::new(
+            nalgebra_glm::vec3(-1.0, -1.0, -3.0),
+            nalgebra_glm::vec3(1.0, 1.0, -2.0),
+        );
+        assert!(!frustum.intersects_aabb(&behind));
+    }
+}
@@ -0,0 +1,483 @@
+use crate::bounds::{Aabb, Frustum, Ray};
+
+const FAT_MARGIN: f32 = 0.05;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ProxyId(usize);
+
+#[derive(Debug, Clone)]
+struct Node<T> {
+    fat_bounds: Aabb,
+    bounds: Aabb,
+    parent: Option<usize>,
+    children: Option<[usize; 2]>,
+    item: Option<T>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Bvh<T> {
+    nodes: Vec<Node<T>>,
+    free_nodes: Vec<usize>,
+    root: Option<usize>,
+    len: usize,
+}
+
+impl<T> Default for Bvh<T> {
+    fn default() -> Self {
+        Self {
+            nodes: Vec::new(),
+            free_nodes: Vec::new(),
+            root: None,
+            len: 0,
+        }
+    }
+}
+
+impl<T: Copy> Bvh<T> {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn bounds(&self) -> Option<Aabb> {
+        self.root.map(|root| self.nodes[root].fat_bounds)
+    }
+
+    pub fn item(&self, proxy: ProxyId) -> Option<T> {
+        self.nodes.get(proxy.0).and_then(|node| node.item)
+    }
+
+    pub fn item_bounds(&self, proxy: ProxyId) -> Option<Aabb> {
+        self.nodes
+            .get(proxy.0)
+            .filter(|node| node.item.is_some())
+            .map(|node| node.bounds)
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    pub fn insert(&mut self, bounds: Aabb, item: T) -> ProxyId {
+        let leaf = self.allocate(Node {
+            fat_bounds: bounds.expanded(FAT_MARGIN),
+            bounds,
+            parent: None,
+            children: None,
+            item: Some(item),
+        });
+        self.insert_leaf(leaf);
+        self.len += 1;
+        ProxyId(leaf)
+    }
+
+    pub fn remove(&mut self, proxy: ProxyId) -> Option<T> {
+        let item = self.nodes.get_mut(proxy.0)?.item.take()?;
+        self.remove_leaf(proxy.0);
+        self.free_nodes.push(proxy.0);
+        self.len -= 1;
+        Some(item)
+    }
+
+    pub fn update(&mut self, proxy: ProxyId, bounds: Aabb) -> bool {
+        let Some(node) = self
+            .nodes
+            .get_mut(proxy.0)
+            .filter(|node| node.item.is_some())
+        else {
+            return false;
+        };
+        node.bounds = bounds;
+        if node.fat_bounds.contains(&bounds) {
+            return false;
+        }
+        self.remove_leaf(proxy.0);
+        self.nodes[proxy.0].fat_bounds = bounds.expanded(FAT_MARGIN);
+        self.insert_leaf(proxy.0);
+        true
+    }
+
+    pub fn query_aabb(&self, bounds: &Aabb) -> Vec<T> {
+        self.query(|node| node.intersects(bounds))
+    }
+
+    pub fn query_sphere(&self, center: &nalgebra_glm::Vec3, radius: f32) -> Vec<T> {
+        self.query(|node| node.intersects_sphere(center, radius))
+    }
+
+    pub fn query_frustum(&self, frustum: &Frustum) -> Vec<T> {
+        self.query(|node| frustum.intersects_aabb(node))
+    }
+
+    pub fn query_ray(&self, ray: &Ray, max_distance: f32) -> Vec<(T, f32)> {
+        let mut hits = Vec::new();
+        self.traverse(
+            |node| node.intersects_ray(ray, max_distance).is_some(),
+            |item, bounds| {
+                if let Some(distance) = bounds.intersects_ray(ray, max_distance) {
+                    hits.push((item, distance));
+                }
+            },
+        );
+        hits.sort_by(|a, b| a.1.total_cmp(&b.1));
+        hits
+    }
+
+    pub fn raycast(&self, ray: &Ray, max_distance: f32) -> Option<(T, f32)> {
+        let mut closest: Option<(T, f32)> = None;
+        let mut stack = self.root.into_iter().collect::<Vec<_>>();
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            let limit = closest.map_or(max_distance, |(_, distance)| distance);
+            if node.fat_bounds.intersects_ray(ray, limit).is_none() {
+                continue;
+            }
+            match (node.children, node.item) {
+                (Some(children), _) => stack.extend(children),
+                (None, Some(item)) => {
+                    if let Some(distance) = node.bounds.intersects_ray(ray, limit) {
+                        closest = Some((item, distance));
+                    }
+                }
+                (None, None) => {}
+            }
+        }
+        closest
+    }
+
+    fn query(&self, test: impl Fn(&Aabb) -> bool) -> Vec<T> {
+        let mut items = Vec::new();
+        self.traverse(&test, |item, bounds| {
+            if test(bounds) {
+                items.push(item);
+            }
+        });
+        items
+    }
+
+    fn traverse(&self, test: impl Fn(&Aabb) -> bool, mut visit: impl FnMut(T, &Aabb)) {
+        let mut stack = self.root.into_iter().collect::<Vec<_>>();
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if !test(&node.fat_bounds) {
+                continue;
+            }
+            match (node.children, node.item) {
+                (Some(children), _) => stack.extend(children),
+                (None, Some(item)) => visit(item, &node.bounds),
+                (None, None) => {}
+            }
+        }
+    }
+
+    fn allocate(&mut self, node: Node<T>) -> usize {
+        match self.free_nodes.pop() {
+            Some(index) => {
+                self.nodes[index] = node;
+                index
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        }
+    }
+
+    fn insert_leaf(&mut self, leaf: usize) {
+        let Some(root) = self.root else {
+            self.nodes[leaf].parent = None;
+            self.root = Some(leaf);
+            return;
+        };
+
+        let leaf_bounds = self.nodes[leaf].fat_bounds;
+        let mut sibling = root;
+        while let Some([left, right]) = self.nodes[sibling].children {
+            let combined = self.nodes[sibling].fat_bounds.union(&leaf_bounds);
+            let cost = 2.0 * combined.surface_area();
+            let inherited = cost - 2.0 * self.nodes[sibling].fat_bounds.surface_area();
+            let child_cost = |child: usize| {
+                let bounds = self.nodes[child].fat_bounds;
+                let merged = bounds.union(&leaf_bounds).surface_area();
+                match self.nodes[child].children {
+                    Some(_) => merged - bounds.surface_area() + inherited,
+                    None => merged + inherited,
+                }
+            };
+            let (left_cost, right_cost) = (child_cost(left), child_cost(right));
+            if cost < left_cost && cost < right_cost {
+                break;
+            }
+            sibling = if left_cost < right_cost { left } else { right };
+        }
+
+        let old_parent = self.nodes[sibling].parent;
+        let new_parent = self.allocate(Node {
+            fat_bounds: self.nodes[sibling].fat_bounds.union(&leaf_bounds),
+            bounds: self.nodes[sibling].fat_bounds.union(&leaf_bounds),
+            parent: old_parent,
+            children: Some([sibling, leaf]),
+            item: None,
+        });
+        self.nodes[sibling].parent = Some(new_parent);
+        self.nodes[leaf].parent = Some(new_parent);
+        match old_parent {
+            Some(old_parent) => self.replace_child(old_parent, sibling, new_parent),
+            None => self.root = Some(new_parent),
+        }
+        self.refit(self.nodes[new_parent].parent);
+    }
+
+    fn remove_leaf(&mut self, leaf: usize) {
+        let Some(parent) = self.nodes[leaf].parent else {
+            self.root = None;
+            return;
+        };
+        let Some([left, right]) = self.nodes[parent].children else {
+            return;
+        };
+        let sibling = if left == leaf { right } else { left };
+        let grandparent = self.nodes[parent].parent;
+        self.nodes[sibling].parent = grandparent;
+        match grandparent {
+            Some(grandparent) => {
+                self.replace_child(grandparent, parent, sibling);
+                self.refit(Some(grandparent));
+            }
+            None => self.root = Some(sibling),
+        }
+        self.nodes[parent].children = None;
+        self.free_nodes.push(parent);
+        self.nodes[leaf].parent = None;
+    }
+
+    fn replace_child(&mut self, parent: usize, old_child: usize, new_child: usize) {
+        if let Some(children) = self.nodes[parent].children.as_mut() {
+            for child in children.iter_mut().filter(|child| **child == old_child) {
+                *child = new_child;
+            }
+        }
+    }
+
+    fn refit(&mut self, mut index: Option<usize>) {
+        while let Some(current) = index {
+            if let Some([left, right]) = self.nodes[current].children {
+                let bounds = self.nodes[left]
+                    .fat_bounds
+                    .union(&self.nodes[right].fat_bounds);
+                self.nodes[current].fat_bounds = bounds;
+                self.nodes[current].bounds = bounds;
+            }
+            index = self.nodes[current].parent;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn next(&mut self) -> f32 {
+            self.0 = self
+                .0
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (self.0 >> 40) as f32 / (1u64 << 24) as f32
+        }
+
+        fn range(&mut self, min: f32, max: f32) -> f32 {
+            min + (max - min) * self.next()
+        }
+
+        fn aabb(&mut self) -> Aabb {
+            let center = nalgebra_glm::vec3(
+                self.range(-50.0, 50.0),
+                self.range(-50.0, 50.0),
+                self.range(-50.0, 50.0),
+            );
+            let half_extents = nalgebra_glm::vec3(
+                self.range(0.1, 3.0),
+                self.range(0.1, 3.0),
+                self.range(0.1, 3.0),
+            );
+            Aabb::new(center - half_extents, center + half_extents)
+        }
+    }
+
+    fn unit_box(center: nalgebra_glm::Vec3) -> Aabb {
+        Aabb::new(
+            center - nalgebra_glm::vec3(0.5, 0.5, 0.5),
+            center + nalgebra_glm::vec3(0.5, 0.5, 0.5),
+        )
+    }
+
+    fn sorted(mut items: Vec<usize>) -> Vec<usize> {
+        items.sort_unstable();
+        items
+    }
+
+    fn sorted_chars(mut items: Vec<char>) -> Vec<char> {
+        items.sort_unstable();
+        items
+    }
+
+    fn populated() -> (Bvh<usize>, Vec<(ProxyId, Aabb)>) {
+        let mut rng = Lcg(7);
+        let mut bvh = Bvh::default();
+        let mut proxies = (0..200)
+            .map(|item| {
+                let bounds = rng.aabb();
+                (Some(bvh.insert(bounds, item)), bounds)
+            })
+            .collect::<Vec<_>>();
+        for item in (0..proxies.len()).step_by(7) {
+            let proxy = proxies[item].0.take().unwrap();
+            assert_eq!(bvh.remove(proxy), Some(item));
+        }
+        for (proxy, bounds) in proxies.iter_mut().skip(3).step_by(5) {
+            if let Some(proxy) = proxy {
+                *bounds = rng.aabb();
+                bvh.update(*proxy, *bounds);
+            }
+        }
+        let proxies = proxies
+            .into_iter()
+            .filter_map(|(proxy, bounds)| Some((proxy?, bounds)))
+            .collect::<Vec<_>>();
+        (bvh, proxies)
+    }
+
+    fn brute_force(
+        bvh: &Bvh<usize>,
+        proxies: &[(ProxyId, Aabb)],
+        predicate: impl Fn(&Aabb) -> bool,
+    ) -> Vec<usize> {
+        sorted(
+            proxies
+                .iter()
+                .filter(|(_, bounds)| predicate(bounds))
+                .map(|(proxy, _)| bvh.item(*proxy).unwrap())
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn insert_remove_and_update_track_items() {
+        let mut bvh = Bvh::default();
+        assert!(bvh.is_empty());
+        assert_eq!(bvh.bounds(), None);
+
+        let a = bvh.insert(unit_box(nalgebra_glm::vec3(0.0, 0.0, 0.0)), 'a');
+        let b = bvh.insert(unit_box(nalgebra_glm::vec3(5.0, 0.0, 0.0)), 'b');
+        let c = bvh.insert(unit_box(nalgebra_glm::vec3(0.0, 5.0, 0.0)), 'c');
+        assert_eq!(bvh.len(), 3);
+        assert_eq!(bvh.item(b), Some('b'));
+        assert!(bvh.bounds().unwrap().contains(&bvh.item_bounds(c).unwrap()));
+
+        assert_eq!(bvh.remove(b), Some('b'));
+        assert_eq!(bvh.remove(b), None);
+        assert_eq!(bvh.item(b), None);
+        assert_eq!(bvh.item_bounds(b), None);
+        assert!(!bvh.update(b, unit_box(nalgebra_glm::Vec3::zeros())));
+        assert_eq!(bvh.len(), 2);
+        assert_eq!(
+            sorted_chars(bvh.query_aabb(&bvh.bounds().unwrap())),
+            ['a', 'c']
+        );
+
+        let moved = unit_box(nalgebra_glm::vec3(-20.0, 0.0, 0.0));
+        assert!(bvh.update(a, moved));
+        assert_eq!(bvh.item_bounds(a), Some(moved));
+        assert_eq!(bvh.query_aabb(&moved), ['a']);
+
+        bvh.clear();
+        assert!(bvh.is_empty());
+        assert!(bvh.query_aabb(&moved).is_empty());
+    }
+
+    #[test]
+    fn update_within_fat_bounds_does_not_reinsert() {
+        let mut bvh = Bvh::default();
+        let proxy = bvh.insert(unit_box(nalgebra_glm::Vec3::zeros()), 0);
+        bvh.insert(unit_box(nalgebra_glm::vec3(4.0, 0.0, 0.0)), 1);
+        let fat_bounds = bvh.bounds().unwrap();
+
+        let nudged = unit_box(nalgebra_glm::vec3(FAT_MARGIN * 0.5, 0.0, 0.0));
+        assert!(!bvh.update(proxy, nudged));
+        assert_eq!(bvh.item_bounds(proxy), Some(nudged));
+        assert_eq!(bvh.bounds(), Some(fat_bounds));
+        assert_eq!(bvh.query_aabb(&nudged), [0]);
+
+        let moved = unit_box(nalgebra_glm::vec3(FAT_MARGIN * 2.0, 0.0, 0.0));
+        assert!(bvh.update(proxy, moved));
+        assert_eq!(bvh.item_bounds(proxy), Some(moved));
+        assert_eq!(bvh.len(), 2);
+    }
+
+    #[test]
+    fn queries_match_brute_force() {
+        let (bvh, proxies) = populated();
+        assert_eq!(bvh.len(), proxies.len());
+
+        let mut rng = Lcg(42);
+        for _ in 0..20 {
+            let region = rng.aabb().expanded(rng.range(0.0, 20.0));
+            assert_eq!(
+                sorted(bvh.query_aabb(&region)),
+                brute_force(&bvh, &proxies, |bounds| bounds.intersects(&region))
+            );
+
+            let center = nalgebra_glm::vec3(
+                rng.range(-50.0, 50.0),
+                rng.range(-50.0, 50.0),
+                rng.range(-50.0, 50.0),
+            );
+            let radius = rng.range(1.0, 25.0);
+            assert_eq!(
+                sorted(bvh.query_sphere(&center, radius)),
+                brute_force(&bvh, &proxies, |bounds| {
+                    bounds.intersects_sphere(&center, radius)
+                })
+            );
+
+            let target = nalgebra_glm::vec3(
+                rng.range(-50.0, 50.0),
+                rng.range(-50.0, 50.0),
+                rng.range(-50.0, 50.0),
+            );
+            let view = nalgebra_glm::look_at_lh(&center, &target, &nalgebra_glm::Vec3::y());
+            let projection = nalgebra_glm::perspective_lh_zo(1.5, rng.range(0.5, 1.5), 0.1, 60.0);
+            let frustum = Frustum::from_view_projection(&(projection * view));
+            assert_eq!(
+                sorted(bvh.query_frustum(&frustum)),
+                brute_force(&bvh, &proxies, |bounds| frustum.intersects_aabb(bounds))
+            );
+
+            let ray = Ray::new(center, target - center);
+            let max_distance = rng.range(10.0, 150.0);
+            let mut expected = proxies
+                .iter()
+                .filter_map(|(proxy, bounds)| {
+                    let distance = bounds.intersects_ray(&ray, max_distance)?;
+                    Some((bvh.item(*proxy).unwrap(), distance))
+                })
+                .collect::<Vec<_>>();
+            expected.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+            let mut hits = bvh.query_ray(&ray, max_distance);
+            hits.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+            assert_eq!(hits, expected);
+
+            let closest = bvh.raycast(&ray, max_distance);
+            assert_eq!(
+                closest.map(|(_, distance)| distance),
+                expected.first().map(|(_, distance)| *distance)
+            );
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use crate::SceneObject;
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+
+pub type ObjectComponents = Vec<(TypeId, Box<dyn Any>)>;
+
+#[derive(Default)]
+pub struct ComponentStore {
+    components: HashMap<TypeId, HashMap<SceneObject, Box<dyn Any>>>,
+}
+
+impl ComponentStore {
+    pub fn get<T: 'static>(&self, object: SceneObject) -> Option<&T> {
+        self.components
+            .get(&TypeId::of::<T>())?
+            .get(&object)?
+            .downcast_ref()
+    }
+
+    pub fn get_mut<T: 'static>(&mut self, object: SceneObject) -> Option<&mut T> {
+        self.components
+            .get_mut(&TypeId::of::<T>())?
+            .get_mut(&object)?
+            .downcast_mut()
+    }
+
+    pub fn insert<T: 'static>(&mut self, object: SceneObject, component: T) {
+        self.components
+            .entry(TypeId::of::<T>())
+            .or_default()
+            .insert(object, Box::new(component));
+    }
+
+    pub fn remove<T: 'static>(&mut self, object: SceneObject) -> Option<T> {
+        let components = self.components.get_mut(&TypeId::of::<T>())?;
+        let component = components.remove(&object)?.downcast().ok()?;
+        if components.is_empty() {
+            self.components.remove(&TypeId::of::<T>());
+        }
+        Some(*component)
+    }
+
+    pub fn set<T: 'static>(&mut self, object: SceneObject, component: Option<T>) {
+        match component {
+            Some(component) => self.insert(object, component),
+            None => {
+                self.remove::<T>(object);
+            }
+        }
+    }
+
+    pub fn take_all(&mut self, object: SceneObject) -> ObjectComponents {
+        let mut taken = Vec::new();
+        self.components.retain(|type_id, components| {
+            if let Some(component) = components.remove(&object) {
+                taken.push((*type_id, component));
+            }
+            !components.is_empty()
+        });
+        taken
+    }
+
+    pub fn restore_all(&mut self, object: SceneObject, components: ObjectComponents) {
+        for (type_id, component) in components {
+            self.components
+                .entry(type_id)
+                .or_default()
+                .insert(object, component);
+        }
+    }
+
+    pub fn iter<T: 'static>(&self) -> impl Iterator<Item = (SceneObject, &T)> {
+        self.components
+            .get(&TypeId::of::<T>())
+            .into_iter()
+            .flatten()
+            .filter_map(|(object, component)| Some((*object, component.downcast_ref()?)))
+    }
+}
@@ -0,0 +1,453 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Mutex, OnceLock};
+use web_time::{Duration, Instant};
+
+pub const LOG_CAPACITY: usize = 4096;
+pub const COMMAND_HISTORY_CAPACITY: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogEntry {
+    pub level: log::Level,
+    pub target: String,
+    pub message: String,
+    pub elapsed: Duration,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LogBuffer {
+    entries: VecDeque<LogEntry>,
+    next_index: usize,
+}
+
+impl LogBuffer {
+    pub fn push(&mut self, entry: LogEntry) {
+        if self.entries.len() == LOG_CAPACITY {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+        self.next_index += 1;
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &LogEntry> {
+        self.entries.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn first_index(&self) -> usize {
+        self.next_index - self.entries.len()
+    }
+
+    pub fn next_index(&self) -> usize {
+        self.next_index
+    }
+}
+
+pub fn log_buffer() -> &'static Mutex<LogBuffer> {
+    static BUFFER: OnceLock<Mutex<LogBuffer>> = OnceLock::new();
+    BUFFER.get_or_init(Mutex::default)
+}
+
+fn start_time() -> Instant {
+    static START: OnceLock<Instant> = OnceLock::new();
+    *START.get_or_init(Instant::now)
+}
+
+pub struct ConsoleLogger {
+    inner: Box<dyn log::Log>,
+    inner_level: log::LevelFilter,
+    level: log::LevelFilter,
+}
+
+impl ConsoleLogger {
+    pub fn new(inner: Box<dyn log::Log>, inner_level: log::LevelFilter) -> Self {
+        start_time();
+        Self {
+            inner,
+            inner_level,
+            level: log::LevelFilter::Info,
+        }
+    }
+
+    pub fn with_level(mut self, level: log::LevelFilter) -> Self {
+        self.level = level;
+        self
+    }
+
+    pub fn init(self) -> Result<(), log::SetLoggerError> {
+        log::set_max_level(self.level.max(self.inner_level));
+        log::set_boxed_logger(Box::new(self))
+    }
+}
+
+impl log::Log for ConsoleLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= self.level || self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &log::Record) {
+        if record.level() <= self.level
+            && let Ok(mut buffer) = log_buffer().lock()
+        {
+            buffer.push(LogEntry {
+                level: record.level(),
+                target: record.target().to_string(),
+                message: record.args().to_string(),
+                elapsed: start_time().elapsed(),
+            });
+        }
+        if self.inner.enabled(record.metadata()) {
+            self.inner.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub struct WebConsoleLogger;
+
+#[cfg(target_arch = "wasm32")]
+impl log::Log for WebConsoleLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::Level::Info
+    }
+
+    fn log(&self, record: &log::Record) {
+        console_log::log(record);
+    }
+
+    fn flush(&self) {}
+}
+
+pub type CommandResult = Result<Option<String>, String>;
+pub type CommandFn<C> = Box<dyn Fn(&mut C, &[String]) -> CommandResult>;
+
+struct CommandEntry<C> {
+    usage: String,
+    run: CommandFn<C>,
+}
+
+pub struct CommandRegistry<C> {
+    commands: BTreeMap<String, CommandEntry<C>>,
+}
+
+impl<C> Default for CommandRegistry<C> {
+    fn default() -> Self {
+        Self {
+            commands: BTreeMap::new(),
+        }
+    }
+}
+
+impl<C> CommandRegistry<C> {
+    pub fn register(
+        &mut self,
+        name: &str,
+        usage: &str,
+        run: impl Fn(&mut C, &[String]) -> CommandResult + 'static,
+    ) {
+        self.commands.insert(
+            name.to_string(),
+            CommandEntry {
+                usage: usage.to_string(),
+                run: Box::new(run),
+            },
+        );
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.commands.keys().map(String::as_str)
+    }
+
+    pub fn usage(&self, name: &str) -> Option<&str> {
+        self.commands
+            .get(name)
+            .map(|command| command.usage.as_str())
+    }
+
+    pub fn execute(&self, context: &mut C, line: &str) -> CommandResult {
+        let arguments = tokenize(line)?;
+        let Some((name, arguments)) = arguments.split_first() else {
+            return Ok(None);
+        };
+        let command = self
+            .commands
+            .get(name)
+            .ok_or_else(|| format!("Unknown command '{name}', type 'help' for a list"))?;
+        (command.run)(context, arguments)
+    }
+}
+
+pub fn tokenize(line: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_token = false;
+    let mut quoted = false;
+    for character in line.chars() {
+        match character {
+            '"' => {
+                quoted = !quoted;
+                in_token = true;
+            }
+            character if character.is_whitespace() && !quoted => {
+                if in_token {
+                    tokens.push(std::mem::take(&mut current));
+                    in_token = false;
+                }
+            }
+            character => {
+                current.push(character);
+                in_token = true;
+            }
+        }
+    }
+    if quoted {
+        return Err("Unterminated quote".to_string());
+    }
+    if in_token {
+        tokens.push(current);
+    }
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EntryKind {
+    Log(log::Level),
+    Input,
+    Output,
+    Error,
+}
+
+pub struct Console<C> {
+    pub commands: CommandRegistry<C>,
+    pub level: log::LevelFilter,
+    pub target_filter: String,
+    pub search: String,
+    pub auto_scroll: bool,
+    input: String,
+    output: Vec<(usize, EntryKind, String)>,
+    history: VecDeque<String>,
+    history_cursor: Option<usize>,
+}
+
+impl<C> Default for Console<C> {
+    fn default() -> Self {
+        Self {
+            commands: CommandRegistry::default(),
+            level: log::LevelFilter::Info,
+            target_filter: String::new(),
+            search: String::new(),
+            auto_scroll: true,
+            input: String::new(),
+            output: Vec::new(),
+            history: VecDeque::new(),
+            history_cursor: None,
+        }
+    }
+}
+
+impl<C> Console<C> {
+    pub fn execute(&mut self, context: &mut C, line: &str) {
+        let line = line.trim();
+        if line.is_empty() {
+            return;
+        }
+        if self.history.back().is_none_or(|last| last != line) {
+            if self.history.len() == COMMAND_HISTORY_CAPACITY {
+                self.history.pop_front();
+            }
+            self.history.push_back(line.to_string());
+        }
+        self.history_cursor = None;
+        self.push_output(EntryKind::Input, format!("> {line}"));
+
+        match line.split_whitespace().next() {
+            Some("help") => {
+                let help = ["help", "clear"]
+                    .into_iter()
+                    .chain(
+                        self.commands
+                            .names()
+                            .map(|name| self.commands.usage(name).unwrap_or(name)),
+                    )
+                    .map(|usage| format!("  {usage}"))
+                    .collect::<Vec<_>>();
+                self.push_output(EntryKind::Output, help.join("\n"));
+            }
+            Some("clear") => self.clear(),
+            _ => match self.commands.execute(context, line) {
+                Ok(Some(message)) => self.push_output(EntryKind::Output, message),
+                Ok(None) => {}
+                Err(error) => self.push_output(EntryKind::Error, error),
+            },
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.output.clear();
+        if let Ok(mut buffer) = log_buffer().lock() {
+            buffer.clear();
+        }
+    }
+
+    fn push_output(&mut self, kind: EntryKind, message: String) {
+        let position = log_buffer().lock().map_or(0, |buffer| buffer.next_index());
+        self.output.extend(
+            message
+                .lines()
+                .map(|line| (position, kind, line.to_string())),
+        );
+        if self.output.len() > LOG_CAPACITY {
+            self.output.drain(..self.output.len() - LOG_CAPACITY);
+        }
+    }
+
+    fn lines(&self) -> Vec<(EntryKind, String)> {
+        let Ok(buffer) = log_buffer().lock() else {
+            return Vec::new();
+        };
+        let target_filter = self.target_filter.trim();
+        let search = self.search.trim().to_lowercase();
+        let mut output = self.output.iter().peekable();
+        let mut lines = Vec::new();
+        for (index, entry) in (buffer.first_index()..).zip(buffer.entries()) {
+            while let Some((_, kind, message)) =
+                output.next_if(|(position, _, _)| *position <= index)
+            {
+                lines.push((*kind, message.clone()));
+            }
+            if entry.level > self.level
+                || (!target_filter.is_empty() && !entry.target.starts_with(target_filter))
+            {
+                continue;
+            }
+            let line = format!(
+                "[{:>8.3}] {:<5} {}: {}",
+                entry.elapsed.as_secs_f32(),
+                entry.level,
+                entry.target,
+                entry.message
+            );
+            lines.extend(
+                line.lines()
+                    .map(|line| (EntryKind::Log(entry.level), line.to_string())),
+            );
+        }
+        lines.extend(output.map(|(_, kind, message)| (*kind, message.clone())));
+        if !search.is_empty() {
+            lines.retain(|(_, line)| line.to_lowercase().contains(&search));
+        }
+        lines
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui, context: &mut C) {
+        let lines = self.lines();
+        ui.horizontal(|ui| {
+            ui.heading("Console");
+            ui.separator();
+            egui::ComboBox::from_id_salt("Console Level")
+                .selected_text(self.level.as_str())
+                .show_ui(ui, |ui| {
+                    for level in log::LevelFilter::iter() {
+                        ui.selectable_value(&mut self.level, level, level.as_str());
+                    }
+                });
+            ui.label("Target");
+            ui.add(egui::TextEdit::singleline(&mut self.target_filter).desired_width(100.0));
+            ui.label("🔍");
+            ui.add(egui::TextEdit::singleline(&mut self.search).desired_width(120.0));
+            ui.checkbox(&mut self.auto_scroll, "Auto-Scroll");
+            if ui.button("Copy").clicked() {
+                let text = lines
+                    .iter()
+                    .map(|(_, line)| line.as_str())
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                ui.ctx().copy_text(text);
+            }
+            if ui.button("Clear").clicked() {
+                self.clear();
+            }
+        });
+
+        let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+        let input_height = ui.spacing().interact_size.y + ui.spacing().item_spacing.y * 2.0;
+        egui::ScrollArea::vertical()
+            .auto_shrink([false, false])
+            .max_height((ui.available_height() - input_height).max(row_height))
+            .stick_to_bottom(self.auto_scroll)
+            .show_rows(ui, row_height, lines.len(), |ui, rows| {
+                for (kind, line) in &lines[rows] {
+                    let color = match kind {
+                        EntryKind::Log(log::Level::Error) | EntryKind::Error => {
+                            ui.visuals().error_fg_color
+                        }
+                        EntryKind::Log(log::Level::Warn) => ui.visuals().warn_fg_color,
+                        EntryKind::Log(log::Level::Info) | EntryKind::Output => {
+                            ui.visuals().text_color()
+                        }
+                        EntryKind::Log(_) => ui.visuals().weak_text_color(),
+                        EntryKind::Input => ui.visuals().strong_text_color(),
+                    };
+                    ui.label(egui::RichText::new(line).monospace().color(color));
+                }
+            });
+
+        ui.horizontal(|ui| {
+            ui.label(">");
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut self.input)
+                    .font(egui::TextStyle::Monospace)
+                    .hint_text("Type 'help' for a list of commands")
+                    .desired_width(f32::INFINITY),
+            );
+            if response.has_focus() {
+                let (up, down) = ui.input(|input| {
+                    (
+                        input.key_pressed(egui::Key::ArrowUp),
+                        input.key_pressed(egui::Key::ArrowDown),
+                    )
+                });
+                if up || down {
+                    self.browse_history(up);
+                }
+            }
+            if response.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter)) {
+                let line = std::mem::take(&mut self.input);
+                self.execute(context, &line);
+                response.request_focus();
+            }
+        });
+    }
+
+    fn browse_history(&mut self, backwards: bool) {
+        if self.history.is_empty() {
+            return;
+        }
+        let last = self.history.len() - 1;
+        self.history_cursor = match (self.history_cursor, backwards) {
+            (None, true) => Some(last),
+            (None, false) => None,
+            (Some(cursor), true) => Some(cursor.saturating_sub(1)),
+            (Some(cursor), false) if cursor < last => Some(cursor + 1),
+            (Some(_), false) => None,
+        };
+        self.input = self
+            .history_cursor
+            .and_then(|cursor| self.history.get(cursor).cloned())
+            .unwrap_or_default();
+    }
+}
@@ -0,0 +1,72 @@
+struct CullUniform {
+    planes: array<vec4<f32>, 12>,
+    bounding_sphere: vec4<f32>,
+    instance_count: u32,
+    view_count: u32,
+    _padding0: u32,
+    _padding1: u32,
+};
+
+struct Instance {
+    model: mat4x4<f32>,
+    color: vec4<f32>,
+};
+
+struct DrawArgs {
+    index_count: u32,
+    instance_count: atomic<u32>,
+    first_index: u32,
+    base_vertex: i32,
+    first_instance: u32,
+};
+
+@group(0) @binding(0)
+var<uniform> cull: CullUniform;
+
+@group(1) @binding(0)
+var<storage, read> instances: array<Instance>;
+
+@group(1) @binding(1)
+var<storage, read_write> visible_instances: array<Instance>;
+
+@group(1) @binding(2)
+var<storage, read_write> draw_args: DrawArgs;
+
+fn sphere_in_view(view: u32, center: vec3<f32>, radius: f32) -> bool {
+    for (var plane_index = 0u; plane_index < 6u; plane_index++) {
+        let plane = cull.planes[view * 6u + plane_index];
+        if (dot(plane.xyz, center) + plane.w < -radius) {
+            return false;
+        }
+    }
+    return true;
+}
+
+@compute @workgroup_size(64)
+fn cull_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let index = id.x;
+    if (index >= cull.instance_count) {
+        return;
+    }
+
+    let instance = instances[index];
+    let center = (instance.model * vec4<f32>(cull.bounding_sphere.xyz, 1.0)).xyz;
+    let scale = max(
+        length(instance.model[0].xyz),
+        max(length(instance.model[1].xyz), length(instance.model[2].xyz)),
+    );
+    let radius = cull.bounding_sphere.w * scale;
+
+    var visible = false;
+    for (var view = 0u; view < cull.view_count; view++) {
+        if (sphere_in_view(view, center, radius)) {
+            visible = true;
+            break;
+        }
+    }
+
+    if (visible) {
+        let slot = atomicAdd(&draw_args.instance_count, 1u);
+        visible_instances[slot] = instance;
+    }
+}
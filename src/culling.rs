@@ -0,0 +1,353 @@
+use crate::bounds::Frustum;
+use crate::instancing::InstanceBatch;
+use crate::perf;
+use crate::render_graph::{BufferHandle, RenderGraph};
+use crate::resources::{ResourceTracker, Tracked};
+use crate::uniform_ring::{UniformRing, UniformSlot};
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+pub const MAX_CULL_VIEWS: usize = 2;
+
+const OWNER: &str = "Culling";
+
+const WORKGROUP_SIZE: u32 = 64;
+const DRAW_ARGS_SIZE: u64 = std::mem::size_of::<wgpu::util::DrawIndexedIndirectArgs>() as u64;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct CullUniform {
+    planes: [[f32; 4]; 6 * MAX_CULL_VIEWS],
+    bounding_sphere: [f32; 4],
+    instance_count: u32,
+    view_count: u32,
+    _padding: [u32; 2],
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CullingStats {
+    pub tested: u32,
+    pub drawn: u32,
+}
+
+struct CullPipeline {
+    pipeline: Tracked<wgpu::ComputePipeline>,
+    batch_bind_group_layout: wgpu::BindGroupLayout,
+    uniforms: UniformRing<CullUniform>,
+}
+
+pub struct FrustumCuller {
+    pub enabled: bool,
+    pipeline: Option<CullPipeline>,
+    slots: Vec<UniformSlot>,
+    readback_buffer: Option<Tracked<wgpu::Buffer>>,
+    readback_tested: Vec<u32>,
+    readback_ready: Arc<AtomicBool>,
+    readback_pending: bool,
+    copy_stats: bool,
+    stats: CullingStats,
+}
+
+impl FrustumCuller {
+    pub fn new(device: &wgpu::Device) -> Self {
+        Self {
+            enabled: true,
+            pipeline: Self::is_supported(device).then(|| CullPipeline::new(device)),
+            slots: Vec::new(),
+            readback_buffer: None,
+            readback_tested: Vec::new(),
+            readback_ready: Arc::new(AtomicBool::new(false)),
+            readback_pending: false,
+            copy_stats: false,
+            stats: CullingStats::default(),
+        }
+    }
+
+    pub fn is_supported(device: &wgpu::Device) -> bool {
+        let limits = device.limits();
+        limits.max_compute_workgroups_per_dimension > 0
+            && limits.max_storage_buffers_per_shader_stage >= 3
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.enabled && self.pipeline.is_some()
+    }
+
+    pub fn stats(&self) -> CullingStats {
+        self.stats
+    }
+
+    pub fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        batches: &mut [InstanceBatch],
+        view_projections: &[nalgebra_glm::Mat4],
+    ) {
+        self.read_stats(device);
+        let tested = batches
+            .iter()
+            .map(|batch| batch.uploaded_len() as u32)
+            .collect::<Vec<_>>();
+        self.slots.clear();
+        self.copy_stats = false;
+
+        let Some(pipeline) = self.pipeline.as_mut().filter(|_| self.enabled) else {
+            let total = tested.iter().sum();
+            self.stats = CullingStats {
+                tested: total,
+                drawn: total,
+            };
+            return;
+        };
+
+        let mut planes = [[0.0; 4]; 6 * MAX_CULL_VIEWS];
+        let view_count = view_projections.len().min(MAX_CULL_VIEWS);
+        for (view, view_projection) in view_projections.iter().take(view_count).enumerate() {
+            let frustum = Frustum::from_view_projection(view_projection);
+            for (index, plane) in frustum.planes.iter().enumerate() {
+                planes[view * 6 + index] = (*plane).into();
+            }
+        }
+
+        if batches.is_empty() {
+            self.stats = CullingStats::default();
+        }
+        pipeline.uniforms.begin_frame();
+        for batch in batches.iter_mut() {
+            let slot = pipeline.uniforms.push(&CullUniform {
+                planes,
+                bounding_sphere: batch.bounding_sphere.into(),
+                instance_count: batch.uploaded_len() as u32,
+                view_count: view_count as u32,
+                _padding: [0; 2],
+            });
+            self.slots.push(slot);
+
+            if batch.cull_bind_group.is_none() {
+                batch.cull_bind_group = Some(pipeline.create_batch_bind_group(device, batch));
+            }
+            let draw_args = wgpu::util::DrawIndexedIndirectArgs {
+                index_count: batch.index_count,
+                instance_count: 0,
+                first_index: 0,
+                base_vertex: 0,
+                first_instance: 0,
+            };
+            perf::write_buffer(queue, &batch.indirect_buffer, 0, draw_args.as_bytes());
+        }
+        pipeline.uniforms.upload(device, queue);
+
+        if !self.readback_pending && !batches.is_empty() {
+            let required = batches.len() as u64 * DRAW_ARGS_SIZE;
+            if self
+                .readback_buffer
+                .as_ref()
+                .is_none_or(|buffer| buffer.size() < required)
+            {
+                self.readback_buffer = Some(ResourceTracker::new(device, OWNER).create_buffer(
+                    &wgpu::BufferDescriptor {
+                        label: Some("Culling Readback Buffer"),
+                        size: required.next_power_of_two(),
+                        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                        mapped_at_creation: false,
+                    },
+                ));
+            }
+            self.readback_tested = tested;
+            self.copy_stats = true;
+        }
+    }
+
+    pub fn add_pass<'a>(
+        &'a self,
+        graph: &mut RenderGraph<'a>,
+        batches: &'a [InstanceBatch],
+    ) -> Vec<BufferHandle> {
+        let Some(pipeline) = self.pipeline.as_ref().filter(|_| self.enabled) else {
+            return Vec::new();
+        };
+
+        let handles = batches
+            .iter()
+            .flat_map(|batch| {
+                [
+                    graph.import_buffer(&batch.indirect_buffer),
+                    graph.import_buffer(&batch.visible_buffer),
+                ]
+            })
+            .collect::<Vec<_>>();
+        let mut pass = graph.add_pass("Cull Pass");
+        for handle in &handles {
+            pass = pass.write_buffer(*handle);
+        }
+        pass.encode(move |encoder, _| {
+            {
+                let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                    label: Some("Frustum Cull Pass"),
+                    timestamp_writes: None,
+                });
+                compute_pass.set_pipeline(&pipeline.pipeline);
+                for (batch, slot) in batches.iter().zip(&self.slots) {
+                    let Some(bind_group) = batch.cull_bind_group.as_ref() else {
+                        continue;
+                    };
+                    if batch.uploaded_len() == 0 {
+                        continue;
+                    }
+                    compute_pass.set_bind_group(
+                        0,
+                        pipeline.uniforms.bind_group(),
+                        &[pipeline.uniforms.offset(*slot)],
+                    );
+                    compute_pass.set_bind_group(1, bind_group, &[]);
+                    compute_pass.dispatch_workgroups(
+                        (batch.uploaded_len() as u32).div_ceil(WORKGROUP_SIZE),
+                        1,
+                        1,
+                    );
+                }
+            }
+
+            if let Some(readback_buffer) = self.readback_buffer.as_ref().filter(|_| self.copy_stats)
+            {
+                for (index, batch) in batches.iter().enumerate() {
+                    encoder.copy_buffer_to_buffer(
+                        &batch.indirect_buffer,
+                        0,
+                        readback_buffer,
+                        index as u64 * DRAW_ARGS_SIZE,
+                        DRAW_ARGS_SIZE,
+                    );
+                }
+            }
+        });
+        handles
+    }
+
+    pub fn finish_frame(&mut self) {
+        if !self.copy_stats {
+            return;
+        }
+        let Some(readback_buffer) = self.readback_buffer.as_ref() else {
+            return;
+        };
+        let ready = self.readback_ready.clone();
+        readback_buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                ready.store(result.is_ok(), Ordering::Release);
+            });
+        self.readback_pending = true;
+        self.copy_stats = false;
+    }
+
+    fn read_stats(&mut self, device: &wgpu::Device) {
+        if !self.readback_pending {
+            return;
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        let _ = device.poll(wgpu::PollType::Poll);
+        #[cfg(target_arch = "wasm32")]
+        let _ = device;
+        if !self.readback_ready.swap(false, Ordering::Acquire) {
+            return;
+        }
+        let Some(readback_buffer) = self.readback_buffer.as_ref() else {
+            return;
+        };
+        {
+            let data = readback_buffer.slice(..).get_mapped_range();
+            let drawn = (0..self.readback_tested.len())
+                .map(|index| {
+                    let offset = index * DRAW_ARGS_SIZE as usize + 4;
+                    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap_or([0; 4]))
+                })
+                .sum();
+            self.stats = CullingStats {
+                tested: self.readback_tested.iter().sum(),
+                drawn,
+            };
+        }
+        readback_buffer.unmap();
+        self.readback_pending = false;
+    }
+}
+
+impl CullPipeline {
+    fn new(device: &wgpu::Device) -> Self {
+        let uniforms = UniformRing::new(device, "Cull Uniform Ring", wgpu::ShaderStages::COMPUTE);
+
+        let storage_entry = |binding, read_only| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let batch_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Cull Batch Bind Group Layout"),
+                entries: &[
+                    storage_entry(0, true),
+                    storage_entry(1, false),
+                    storage_entry(2, false),
+                ],
+            });
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("cull.wgsl"));
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Cull Pipeline Layout"),
+            bind_group_layouts: &[
+                Some(&uniforms.bind_group_layout),
+                Some(&batch_bind_group_layout),
+            ],
+            immediate_size: 0,
+        });
+        let pipeline = ResourceTracker::new(device, OWNER).create_compute_pipeline(
+            &wgpu::ComputePipelineDescriptor {
+                label: Some("Cull Pipeline"),
+                layout: Some(&pipeline_layout),
+                module: &shader,
+                entry_point: Some("cull_main"),
+                compilation_options: Default::default(),
+                cache: None,
+            },
+        );
+
+        Self {
+            pipeline,
+            batch_bind_group_layout,
+            uniforms,
+        }
+    }
+
+    fn create_batch_bind_group(
+        &self,
+        device: &wgpu::Device,
+        batch: &InstanceBatch,
+    ) -> Tracked<wgpu::BindGroup> {
+        ResourceTracker::new(device, OWNER).create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Cull Batch Bind Group"),
+            layout: &self.batch_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: batch.instance_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: batch.visible_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: batch.indirect_buffer.as_entire_binding(),
+                },
+            ],
+        })
+    }
+}
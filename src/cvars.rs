@@ -0,0 +1,359 @@
+use crate::console::tokenize;
+
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CvarValue {
+    Bool(bool),
+    Int(i64),
+    Float(f32),
+    Color([f32; 4]),
+    String(String),
+}
+
+impl CvarValue {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::Bool(_) => "bool",
+            Self::Int(_) => "int",
+            Self::Float(_) => "float",
+            Self::Color(_) => "color",
+            Self::String(_) => "string",
+        }
+    }
+
+    pub fn parse_as(&self, source: &str) -> Result<Self, String> {
+        let source = source.trim();
+        let invalid = |error: &dyn std::fmt::Display| {
+            format!("Invalid {} '{source}': {error}", self.type_name())
+        };
+        match self {
+            Self::Bool(_) => match source {
+                "on" | "true" | "1" => Ok(Self::Bool(true)),
+                "off" | "false" | "0" => Ok(Self::Bool(false)),
+                _ => Err(invalid(&"expected on or off")),
+            },
+            Self::Int(_) => source
+                .parse()
+                .map(Self::Int)
+                .map_err(|error| invalid(&error)),
+            Self::Float(_) => source
+                .parse()
+                .map(Self::Float)
+                .map_err(|error| invalid(&error)),
+            Self::Color(_) => {
+                let components = source
+                    .split(|character: char| character == ',' || character.is_whitespace())
+                    .filter(|component| !component.is_empty())
+                    .map(str::parse::<f32>)
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|error| invalid(&error))?;
+                match components[..] {
+                    [r, g, b] => Ok(Self::Color([r, g, b, 1.0])),
+                    [r, g, b, a] => Ok(Self::Color([r, g, b, a])),
+                    _ => Err(invalid(&"expected 3 or 4 components")),
+                }
+            }
+            Self::String(_) => Ok(Self::String(source.to_string())),
+        }
+    }
+}
+
+impl std::fmt::Display for CvarValue {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Bool(value) => write!(formatter, "{}", if *value { "on" } else { "off" }),
+            Self::Int(value) => write!(formatter, "{value}"),
+            Self::Float(value) => write!(formatter, "{value}"),
+            Self::Color([r, g, b, a]) => write!(formatter, "{r} {g} {b} {a}"),
+            Self::String(value) => write!(formatter, "{value}"),
+        }
+    }
+}
+
+pub trait CvarType: Sized {
+    fn into_value(self) -> CvarValue;
+
+    fn from_value(value: &CvarValue) -> Option<Self>;
+}
+
+impl CvarType for bool {
+    fn into_value(self) -> CvarValue {
+        CvarValue::Bool(self)
+    }
+
+    fn from_value(value: &CvarValue) -> Option<Self> {
+        match value {
+            CvarValue::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+impl CvarType for i32 {
+    fn into_value(self) -> CvarValue {
+        CvarValue::Int(self.into())
+    }
+
+    fn from_value(value: &CvarValue) -> Option<Self> {
+        match value {
+            CvarValue::Int(value) => (*value).try_into().ok(),
+            _ => None,
+        }
+    }
+}
+
+impl CvarType for u32 {
+    fn into_value(self) -> CvarValue {
+        CvarValue::Int(self.into())
+    }
+
+    fn from_value(value: &CvarValue) -> Option<Self> {
+        match value {
+            CvarValue::Int(value) => (*value).try_into().ok(),
+            _ => None,
+        }
+    }
+}
+
+impl CvarType for f32 {
+    fn into_value(self) -> CvarValue {
+        CvarValue::Float(self)
+    }
+
+    fn from_value(value: &CvarValue) -> Option<Self> {
+        match value {
+            CvarValue::Float(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+impl CvarType for [f32; 4] {
+    fn into_value(self) -> CvarValue {
+        CvarValue::Color(self)
+    }
+
+    fn from_value(value: &CvarValue) -> Option<Self> {
+        match value {
+            CvarValue::Color(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+impl CvarType for String {
+    fn into_value(self) -> CvarValue {
+        CvarValue::String(self)
+    }
+
+    fn from_value(value: &CvarValue) -> Option<Self> {
+        match value {
+            CvarValue::String(value) => Some(value.clone()),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Cvar<T> {
+    index: usize,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for Cvar<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Cvar<T> {}
+
+#[derive(Debug, Clone)]
+pub struct CvarInfo {
+    pub name: String,
+    pub description: String,
+    pub default: CvarValue,
+    pub value: CvarValue,
+    pub persist: bool,
+    changed: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CvarRegistry {
+    cvars: Vec<CvarInfo>,
+    lookup: BTreeMap<String, usize>,
+    aliases: BTreeMap<String, usize>,
+}
+
+impl CvarRegistry {
+    pub fn declare<T: CvarType>(&mut self, name: &str, description: &str, default: T) -> Cvar<T> {
+        let default = default.into_value();
+        let index = match self.lookup.get(name) {
+            Some(&index) => {
+                let cvar = &mut self.cvars[index];
+                if cvar.default.type_name() != default.type_name() {
+                    log::warn!(
+                        "Cvar {name} redeclared as {} (was {})",
+                        default.type_name(),
+                        cvar.default.type_name()
+                    );
+                    cvar.value = default.clone();
+                }
+                cvar.description = description.to_string();
+                cvar.default = default;
+                index
+            }
+            None => {
+                self.cvars.push(CvarInfo {
+                    name: name.to_string(),
+                    description: description.to_string(),
+                    value: default.clone(),
+                    default,
+                    persist: true,
+                    changed: true,
+                });
+                self.lookup.insert(name.to_string(), self.cvars.len() - 1);
+                self.cvars.len() - 1
+            }
+        };
+        Cvar {
+            index,
+            marker: PhantomData,
+        }
+    }
+
+    pub fn declare_transient<T: CvarType>(
+        &mut self,
+        name: &str,
+        description: &str,
+        default: T,
+    ) -> Cvar<T> {
+        let cvar = self.declare(name, description, default);
+        self.cvars[cvar.index].persist = false;
+        cvar
+    }
+
+    pub fn alias<T>(&mut self, alias: &str, cvar: Cvar<T>) {
+        self.aliases.insert(alias.to_string(), cvar.index);
+    }
+
+    fn index(&self, name: &str) -> Result<usize, String> {
+        self.lookup
+            .get(name)
+            .or_else(|| self.aliases.get(name))
+            .copied()
+            .ok_or_else(|| format!("Unknown cvar '{name}'"))
+    }
+
+    pub fn get<T: CvarType>(&self, cvar: Cvar<T>) -> T {
+        let info = &self.cvars[cvar.index];
+        T::from_value(&info.value)
+            .or_else(|| T::from_value(&info.default))
+            .expect("Cvar handle does not match its declared type")
+    }
+
+    pub fn set<T: CvarType>(&mut self, cvar: Cvar<T>, value: T) {
+        self.set_value(cvar.index, value.into_value());
+    }
+
+    pub fn take_changed<T>(&mut self, cvar: Cvar<T>) -> bool {
+        std::mem::take(&mut self.cvars[cvar.index].changed)
+    }
+
+    pub fn info(&self, name: &str) -> Option<&CvarInfo> {
+        self.index(name).ok().map(|index| &self.cvars[index])
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &CvarInfo> {
+        self.lookup.values().map(|&index| &self.cvars[index])
+    }
+
+    pub fn set_str(&mut self, name: &str, source: &str) -> Result<(), String> {
+        let index = self.index(name)?;
+        let value = self.cvars[index].default.parse_as(source)?;
+        self.set_value(index, value);
+        Ok(())
+    }
+
+    pub fn reset(&mut self, name: &str) -> Result<(), String> {
+        let index = self.index(name)?;
+        self.set_value(index, self.cvars[index].default.clone());
+        Ok(())
+    }
+
+    fn set_value(&mut self, index: usize, value: CvarValue) {
+        let cvar = &mut self.cvars[index];
+        if cvar.value != value {
+            log::info!("{} = {value}", cvar.name);
+            cvar.value = value;
+            cvar.changed = true;
+        }
+    }
+
+    pub fn apply_args(&mut self, arguments: impl IntoIterator<Item = String>) -> Vec<String> {
+        let mut errors = Vec::new();
+        for argument in arguments {
+            let Some((name, value)) = argument
+                .strip_prefix("--")
+                .and_then(|argument| argument.split_once('='))
+            else {
+                continue;
+            };
+            if let Err(error) = self.set_str(name, value) {
+                errors.push(error);
+            }
+        }
+        errors
+    }
+
+    pub fn apply_config(&mut self, source: &str) -> Vec<String> {
+        let mut errors = Vec::new();
+        for (number, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let result = tokenize(line).and_then(|tokens| match tokens.split_first() {
+                Some((name, value)) => self.set_str(name, &value.join(" ")),
+                None => Ok(()),
+            });
+            if let Err(error) = result {
+                errors.push(format!("line {}: {error}", number + 1));
+            }
+        }
+        errors
+    }
+
+    pub fn to_config(&self) -> String {
+        self.iter()
+            .filter(|cvar| cvar.persist && cvar.value != cvar.default)
+            .map(|cvar| {
+                let value = cvar.value.to_string();
+                if value.is_empty() || value.contains(char::is_whitespace) {
+                    format!("{} \"{value}\"\n", cvar.name)
+                } else {
+                    format!("{} {value}\n", cvar.name)
+                }
+            })
+            .collect()
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(&mut self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let source = std::fs::read_to_string(path)?;
+        for error in self.apply_config(&source) {
+            log::warn!("{path}: {error}");
+        }
+        log::info!("Loaded cvars from {path}");
+        Ok(())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        std::fs::write(path, self.to_config())?;
+        log::info!("Saved cvars to {path}");
+        Ok(())
+    }
+}
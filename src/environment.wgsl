@@ -0,0 +1,24 @@
+struct EnvironmentUniform {
+    intensity: f32,
+    prefiltered_mip_count: f32,
+    sky_intensity: f32,
+    _padding: f32,
+};
+
+@group(2) @binding(0)
+var environment_map: texture_cube<f32>;
+
+@group(2) @binding(1)
+var irradiance_map: texture_cube<f32>;
+
+@group(2) @binding(2)
+var prefiltered_map: texture_cube<f32>;
+
+@group(2) @binding(3)
+var brdf_lut: texture_2d<f32>;
+
+@group(2) @binding(4)
+var environment_sampler: sampler;
+
+@group(2) @binding(5)
+var<uniform> environment: EnvironmentUniform;
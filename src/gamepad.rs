@@ -0,0 +1,441 @@
+use crate::cvars::{Cvar, CvarRegistry};
+use crate::input::{Actions, GamepadAxis, GamepadButton, InputSource};
+
+use web_time::Duration;
+#[cfg(feature = "gamepad")]
+use web_time::Instant;
+
+pub const DEFAULT_STICK_DEADZONE: f32 = 0.15;
+pub const DEFAULT_TRIGGER_DEADZONE: f32 = 0.05;
+
+const NAVIGATION_BUTTONS: [(GamepadButton, egui::Key, egui::Modifiers); 8] = [
+    (
+        GamepadButton::DPadUp,
+        egui::Key::ArrowUp,
+        egui::Modifiers::NONE,
+    ),
+    (
+        GamepadButton::DPadDown,
+        egui::Key::ArrowDown,
+        egui::Modifiers::NONE,
+    ),
+    (
+        GamepadButton::DPadLeft,
+        egui::Key::ArrowLeft,
+        egui::Modifiers::NONE,
+    ),
+    (
+        GamepadButton::DPadRight,
+        egui::Key::ArrowRight,
+        egui::Modifiers::NONE,
+    ),
+    (
+        GamepadButton::South,
+        egui::Key::Enter,
+        egui::Modifiers::NONE,
+    ),
+    (
+        GamepadButton::East,
+        egui::Key::Escape,
+        egui::Modifiers::NONE,
+    ),
+    (
+        GamepadButton::RightBumper,
+        egui::Key::Tab,
+        egui::Modifiers::NONE,
+    ),
+    (
+        GamepadButton::LeftBumper,
+        egui::Key::Tab,
+        egui::Modifiers::SHIFT,
+    ),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GamepadInfo {
+    pub id: usize,
+    pub name: String,
+    pub rumble: bool,
+}
+
+pub fn stick_deadzone(x: f32, y: f32, deadzone: f32) -> (f32, f32) {
+    let magnitude = (x * x + y * y).sqrt();
+    if magnitude <= deadzone || magnitude <= f32::EPSILON {
+        return (0.0, 0.0);
+    }
+    let scale = ((magnitude - deadzone) / (1.0 - deadzone)).min(1.0) / magnitude;
+    (x * scale, y * scale)
+}
+
+pub fn trigger_deadzone(value: f32, deadzone: f32) -> f32 {
+    if value <= deadzone {
+        0.0
+    } else {
+        ((value - deadzone) / (1.0 - deadzone)).min(1.0)
+    }
+}
+
+pub fn navigation_events(pressed: &[GamepadButton]) -> Vec<egui::Event> {
+    NAVIGATION_BUTTONS
+        .iter()
+        .filter(|(button, _, _)| pressed.contains(button))
+        .flat_map(|&(_, key, modifiers)| {
+            [true, false].map(|pressed| egui::Event::Key {
+                key,
+                physical_key: Some(key),
+                pressed,
+                repeat: false,
+                modifiers,
+            })
+        })
+        .collect()
+}
+
+#[derive(Clone, Copy)]
+pub struct GamepadCvars {
+    pub stick_deadzone: Cvar<f32>,
+    pub trigger_deadzone: Cvar<f32>,
+}
+
+impl GamepadCvars {
+    pub fn declare(cvars: &mut CvarRegistry) -> Self {
+        Self {
+            stick_deadzone: cvars.declare(
+                "input.stick_deadzone",
+                "Radial deadzone applied to gamepad sticks",
+                DEFAULT_STICK_DEADZONE,
+            ),
+            trigger_deadzone: cvars.declare(
+                "input.trigger_deadzone",
+                "Deadzone applied to gamepad triggers",
+                DEFAULT_TRIGGER_DEADZONE,
+            ),
+        }
+    }
+
+    pub fn apply(&self, cvars: &mut CvarRegistry, gamepads: &mut Gamepads) {
+        if cvars.take_changed(self.stick_deadzone) {
+            gamepads.stick_deadzone = cvars.get(self.stick_deadzone).clamp(0.0, 0.95);
+        }
+        if cvars.take_changed(self.trigger_deadzone) {
+            gamepads.trigger_deadzone = cvars.get(self.trigger_deadzone).clamp(0.0, 0.95);
+        }
+    }
+}
+
+pub struct Gamepads {
+    pub stick_deadzone: f32,
+    pub trigger_deadzone: f32,
+    pub navigation: bool,
+    connected: Vec<GamepadInfo>,
+    active: Option<usize>,
+    state: GamepadState,
+    #[cfg(feature = "gamepad")]
+    gilrs: Option<gilrs::Gilrs>,
+    #[cfg(feature = "gamepad")]
+    effects: Vec<(gilrs::ff::Effect, Instant)>,
+}
+
+impl Default for Gamepads {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Gamepads {
+    pub fn new() -> Self {
+        #[cfg(feature = "gamepad")]
+        let gilrs = match gilrs::Gilrs::new() {
+            Ok(gilrs) => Some(gilrs),
+            Err(gilrs::Error::NotImplemented(gilrs)) => {
+                log::warn!("Gamepads are not supported on this platform");
+                Some(gilrs)
+            }
+            Err(error) => {
+                log::error!("Failed to initialize gamepads: {error}");
+                None
+            }
+        };
+        Self {
+            stick_deadzone: DEFAULT_STICK_DEADZONE,
+            trigger_deadzone: DEFAULT_TRIGGER_DEADZONE,
+            navigation: true,
+            connected: Vec::new(),
+            active: None,
+            state: GamepadState::default(),
+            #[cfg(feature = "gamepad")]
+            gilrs,
+            #[cfg(feature = "gamepad")]
+            effects: Vec::new(),
+        }
+    }
+
+    pub fn is_supported(&self) -> bool {
+        #[cfg(feature = "gamepad")]
+        return self.gilrs.is_some();
+        #[cfg(not(feature = "gamepad"))]
+        false
+    }
+
+    pub fn connected(&self) -> &[GamepadInfo] {
+        &self.connected
+    }
+
+    pub fn state(&self) -> &GamepadState {
+        &self.state
+    }
+
+    pub fn active(&self) -> Option<&GamepadInfo> {
+        let active = self.active?;
+        self.connected.iter().find(|gamepad| gamepad.id == active)
+    }
+
+    #[cfg(feature = "gamepad")]
+    fn refresh_connected(&mut self) {
+        let Some(gilrs) = self.gilrs.as_ref() else {
+            return;
+        };
+        self.connected = gilrs
+            .gamepads()
+            .map(|(id, gamepad)| GamepadInfo {
+                id: id.into(),
+                name: gamepad.name().to_string(),
+                rumble: gamepad.is_ff_supported(),
+            })
+            .collect();
+        if self
+            .active
+            .is_none_or(|active| !self.connected.iter().any(|gamepad| gamepad.id == active))
+        {
+            self.active = self.connected.first().map(|gamepad| gamepad.id);
+        }
+    }
+
+    #[cfg(feature = "gamepad")]
+    pub fn poll(&mut self, actions: &mut Actions) -> Vec<egui::Event> {
+        let now = Instant::now();
+        self.effects.retain(|(_, until)| *until > now);
+
+        let Some(gilrs) = self.gilrs.as_mut() else {
+            return Vec::new();
+        };
+        let mut pressed = Vec::new();
+        let mut connection_changed = false;
+        while let Some(gilrs::Event { id, event, .. }) = gilrs.next_event() {
+            match event {
+                gilrs::EventType::Connected => {
+                    log::info!("Gamepad connected: {} ({id})", gilrs.gamepad(id).name());
+                    connection_changed = true;
+                }
+                gilrs::EventType::Disconnected => {
+                    log::info!("Gamepad disconnected: {id}");
+                    connection_changed = true;
+                }
+                gilrs::EventType::ButtonPressed(button, _) => {
+                    self.active = Some(id.into());
+                    if let Some(button) = map_button(button) {
+                        pressed.push(button);
+                    }
+                }
+                gilrs::EventType::AxisChanged(..) | gilrs::EventType::ButtonChanged(..) => {
+                    self.active = Some(id.into());
+                }
+                _ => (),
+            }
+        }
+        if connection_changed {
+            self.refresh_connected();
+        }
+
+        let state = self
+            .active
+            .and_then(|active| {
+                let gilrs = self.gilrs.as_ref()?;
+                let (_, gamepad) = gilrs
+                    .gamepads()
+                    .find(|(id, _)| usize::from(*id) == active)?;
+                Some(read_state(&gamepad))
+            })
+            .unwrap_or_default();
+        self.apply_state(actions, &state);
+        self.state = state;
+
+        if self.navigation {
+            navigation_events(&pressed)
+        } else {
+            Vec::new()
+        }
+    }
+
+    #[cfg(not(feature = "gamepad"))]
+    pub fn poll(&mut self, actions: &mut Actions) -> Vec<egui::Event> {
+        self.apply_state(actions, &GamepadState::default());
+        Vec::new()
+    }
+
+    pub fn apply_state(&self, actions: &mut Actions, state: &GamepadState) {
+        let (left_x, left_y) =
+            stick_deadzone(state.left_stick.0, state.left_stick.1, self.stick_deadzone);
+        let (right_x, right_y) = stick_deadzone(
+            state.right_stick.0,
+            state.right_stick.1,
+            self.stick_deadzone,
+        );
+        let axes = [
+            (GamepadAxis::LeftStickX, left_x),
+            (GamepadAxis::LeftStickY, left_y),
+            (GamepadAxis::RightStickX, right_x),
+            (GamepadAxis::RightStickY, right_y),
+            (
+                GamepadAxis::LeftTrigger,
+                trigger_deadzone(state.left_trigger, self.trigger_deadzone),
+            ),
+            (
+                GamepadAxis::RightTrigger,
+                trigger_deadzone(state.right_trigger, self.trigger_deadzone),
+            ),
+        ];
+        for (axis, value) in axes {
+            actions.set_source(InputSource::GamepadAxis(axis), value);
+        }
+        for (button, down) in &state.buttons {
+            actions.set_source(
+                InputSource::GamepadButton(*button),
+                f32::from(u8::from(*down)),
+            );
+        }
+    }
+
+    pub fn rumble(&mut self, strength: f32, duration: Duration) -> Result<(), String> {
+        #[cfg(feature = "gamepad")]
+        {
+            let gilrs = self
+                .gilrs
+                .as_mut()
+                .ok_or_else(|| "Gamepads are not available".to_string())?;
+            let targets = gilrs
+                .gamepads()
+                .filter(|(_, gamepad)| gamepad.is_ff_supported())
+                .map(|(id, _)| id)
+                .collect::<Vec<_>>();
+            if targets.is_empty() {
+                return Err("No connected gamepad supports rumble".to_string());
+            }
+            let duration_ms = duration.as_millis().min(u32::MAX as u128) as u32;
+            let magnitude = (strength.clamp(0.0, 1.0) * u16::MAX as f32) as u16;
+            let effect = gilrs::ff::EffectBuilder::new()
+                .add_effect(gilrs::ff::BaseEffect {
+                    kind: gilrs::ff::BaseEffectType::Strong { magnitude },
+                    scheduling: gilrs::ff::Replay {
+                        play_for: gilrs::ff::Ticks::from_ms(duration_ms),
+                        ..Default::default()
+                    },
+                    envelope: Default::default(),
+                })
+                .add_effect(gilrs::ff::BaseEffect {
+                    kind: gilrs::ff::BaseEffectType::Weak { magnitude },
+                    scheduling: gilrs::ff::Replay {
+                        play_for: gilrs::ff::Ticks::from_ms(duration_ms),
+                        ..Default::default()
+                    },
+                    envelope: Default::default(),
+                })
+                .gamepads(&targets)
+                .repeat(gilrs::ff::Repeat::For(gilrs::ff::Ticks::from_ms(
+                    duration_ms,
+                )))
+                .finish(gilrs)
+                .map_err(|error| format!("Failed to create rumble effect: {error}"))?;
+            effect
+                .play()
+                .map_err(|error| format!("Failed to play rumble effect: {error}"))?;
+            self.effects.push((effect, Instant::now() + duration));
+            Ok(())
+        }
+        #[cfg(not(feature = "gamepad"))]
+        {
+            let _ = (strength, duration);
+            Err("Built without the gamepad feature".to_string())
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct GamepadState {
+    pub left_stick: (f32, f32),
+    pub right_stick: (f32, f32),
+    pub left_trigger: f32,
+    pub right_trigger: f32,
+    pub buttons: Vec<(GamepadButton, bool)>,
+}
+
+#[cfg(feature = "gamepad")]
+fn map_button(button: gilrs::Button) -> Option<GamepadButton> {
+    Some(match button {
+        gilrs::Button::South => GamepadButton::South,
+        gilrs::Button::East => GamepadButton::East,
+        gilrs::Button::North => GamepadButton::North,
+        gilrs::Button::West => GamepadButton::West,
+        gilrs::Button::LeftTrigger => GamepadButton::LeftBumper,
+        gilrs::Button::RightTrigger => GamepadButton::RightBumper,
+        gilrs::Button::LeftTrigger2 => GamepadButton::LeftTrigger,
+        gilrs::Button::RightTrigger2 => GamepadButton::RightTrigger,
+        gilrs::Button::Select => GamepadButton::Select,
+        gilrs::Button::Start => GamepadButton::Start,
+        gilrs::Button::LeftThumb => GamepadButton::LeftStick,
+        gilrs::Button::RightThumb => GamepadButton::RightStick,
+        gilrs::Button::DPadUp => GamepadButton::DPadUp,
+        gilrs::Button::DPadDown => GamepadButton::DPadDown,
+        gilrs::Button::DPadLeft => GamepadButton::DPadLeft,
+        gilrs::Button::DPadRight => GamepadButton::DPadRight,
+        _ => return None,
+    })
+}
+
+#[cfg(feature = "gamepad")]
+fn read_state(gamepad: &gilrs::Gamepad<'_>) -> GamepadState {
+    let trigger = |button, axis| {
+        gamepad
+            .button_data(button)
+            .map(gilrs::ev::state::ButtonData::value)
+            .unwrap_or_else(|| {
+                (gamepad.value(axis) + 1.0)
+                    * 0.5
+                    * f32::from(u8::from(gamepad.axis_data(axis).is_some()))
+            })
+    };
+    let buttons = [
+        gilrs::Button::South,
+        gilrs::Button::East,
+        gilrs::Button::North,
+        gilrs::Button::West,
+        gilrs::Button::LeftTrigger,
+        gilrs::Button::RightTrigger,
+        gilrs::Button::LeftTrigger2,
+        gilrs::Button::RightTrigger2,
+        gilrs::Button::Select,
+        gilrs::Button::Start,
+        gilrs::Button::LeftThumb,
+        gilrs::Button::RightThumb,
+        gilrs::Button::DPadUp,
+        gilrs::Button::DPadDown,
+        gilrs::Button::DPadLeft,
+        gilrs::Button::DPadRight,
+    ]
+    .into_iter()
+    .filter_map(|button| Some((map_button(button)?, gamepad.is_pressed(button))))
+    .collect();
+    GamepadState {
+        left_stick: (
+            gamepad.value(gilrs::Axis::LeftStickX),
+            gamepad.value(gilrs::Axis::LeftStickY),
+        ),
+        right_stick: (
+            gamepad.value(gilrs::Axis::RightStickX),
+            gamepad.value(gilrs::Axis::RightStickY),
+        ),
+        left_trigger: trigger(gilrs::Button::LeftTrigger2, gilrs::Axis::LeftZ),
+        right_trigger: trigger(gilrs::Button::RightTrigger2, gilrs::Axis::RightZ),
+        buttons,
+    }
+}
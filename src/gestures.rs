@@ -0,0 +1,289 @@
+use crate::input::{Actions, InputEvent, InputSource, TouchGesture};
+
+use web_time::Duration;
+use winit::event::{TouchPhase, WindowEvent};
+
+pub const TAP_MAX_DURATION: Duration = Duration::from_millis(300);
+pub const DOUBLE_TAP_INTERVAL: Duration = Duration::from_millis(350);
+pub const LONG_PRESS_DURATION: Duration = Duration::from_millis(500);
+pub const DEFAULT_TOUCH_SLOP: f32 = 16.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GestureEvent {
+    pub gesture: TouchGesture,
+    pub position: nalgebra_glm::Vec2,
+    pub value: f32,
+}
+
+impl GestureEvent {
+    pub fn is_discrete(&self) -> bool {
+        matches!(
+            self.gesture,
+            TouchGesture::Tap | TouchGesture::DoubleTap | TouchGesture::LongPress
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TouchPoint {
+    id: u64,
+    position: nalgebra_glm::Vec2,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TouchSession {
+    started: Duration,
+    start: nalgebra_glm::Vec2,
+    max_touches: usize,
+    moved: bool,
+    long_pressed: bool,
+    captured: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TwoFingerMetrics {
+    center: nalgebra_glm::Vec2,
+    spread: f32,
+    angle: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct GestureRecognizer {
+    pub slop: f32,
+    touches: Vec<TouchPoint>,
+    session: Option<TouchSession>,
+    clock: Duration,
+    last_tap: Option<(Duration, nalgebra_glm::Vec2)>,
+    pending: Vec<GestureEvent>,
+    events: Vec<GestureEvent>,
+    touch_input: bool,
+}
+
+impl Default for GestureRecognizer {
+    fn default() -> Self {
+        Self {
+            slop: DEFAULT_TOUCH_SLOP,
+            touches: Vec::new(),
+            session: None,
+            clock: Duration::ZERO,
+            last_tap: None,
+            pending: Vec::new(),
+            events: Vec::new(),
+            touch_input: false,
+        }
+    }
+}
+
+impl GestureRecognizer {
+    pub fn is_touch_input(&self) -> bool {
+        self.touch_input
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.touches.is_empty()
+    }
+
+    pub fn touch_count(&self) -> usize {
+        self.touches.len()
+    }
+
+    pub fn events(&self) -> &[GestureEvent] {
+        &self.events
+    }
+
+    pub fn pending_start(&self) -> Option<nalgebra_glm::Vec2> {
+        self.session
+            .filter(|session| session.captured.is_none())
+            .map(|session| session.start)
+    }
+
+    pub fn claim(&mut self, accept: impl FnOnce(nalgebra_glm::Vec2) -> bool) {
+        if let Some(session) = self.session.as_mut()
+            && session.captured.is_none()
+        {
+            session.captured = Some(accept(session.start));
+        }
+    }
+
+    pub fn cancel(&mut self) {
+        self.touches.clear();
+        self.session = None;
+        self.pending.clear();
+    }
+
+    pub fn handle_window_event(&mut self, event: &WindowEvent) {
+        if let Some(event) = InputEvent::from_window_event(event) {
+            self.handle_input_event(&event);
+        }
+    }
+
+    pub fn handle_input_event(&mut self, event: &InputEvent) {
+        match *event {
+            InputEvent::Touch { id, phase, x, y } => {
+                self.touch_input = true;
+                self.handle_touch(id, phase, nalgebra_glm::vec2(x as f32, y as f32));
+            }
+            InputEvent::CursorMoved { .. } | InputEvent::MouseButton { .. } => {
+                if !self.is_active() {
+                    self.touch_input = false;
+                }
+            }
+            InputEvent::Focused(false) => self.cancel(),
+            _ => (),
+        }
+    }
+
+    pub fn handle_touch(&mut self, id: u64, phase: TouchPhase, position: nalgebra_glm::Vec2) {
+        let now = self.clock;
+        match phase {
+            TouchPhase::Started => {
+                if self.touches.is_empty() {
+                    self.pending.clear();
+                    self.session = Some(TouchSession {
+                        started: now,
+                        start: position,
+                        max_touches: 0,
+                        moved: false,
+                        long_pressed: false,
+                        captured: None,
+                    });
+                }
+                self.touches.retain(|touch| touch.id != id);
+                self.touches.push(TouchPoint { id, position });
+                if let Some(session) = self.session.as_mut() {
+                    session.max_touches = session.max_touches.max(self.touches.len());
+                }
+            }
+            TouchPhase::Moved => self.handle_move(id, position),
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                self.touches.retain(|touch| touch.id != id);
+                if phase == TouchPhase::Ended && self.touches.is_empty() {
+                    self.handle_release(position, now);
+                }
+            }
+        }
+    }
+
+    fn handle_move(&mut self, id: u64, position: nalgebra_glm::Vec2) {
+        let Some(index) = self.touches.iter().position(|touch| touch.id == id) else {
+            return;
+        };
+        let Some(mut session) = self.session else {
+            return;
+        };
+        let before = self.two_finger_metrics();
+        let previous = self.touches[index].position;
+        self.touches[index].position = position;
+
+        if !session.moved && nalgebra_glm::distance(&position, &session.start) > self.slop {
+            session.moved = true;
+        }
+        match (self.touches.len(), before, self.two_finger_metrics()) {
+            (1, _, _) if session.moved && session.max_touches == 1 && !session.long_pressed => {
+                let delta = position - previous;
+                self.emit(TouchGesture::DragX, position, delta.x);
+                self.emit(TouchGesture::DragY, position, delta.y);
+            }
+            (2, Some(before), Some(after)) => {
+                session.moved = true;
+                let pan = after.center - before.center;
+                self.emit(TouchGesture::PanX, after.center, pan.x);
+                self.emit(TouchGesture::PanY, after.center, pan.y);
+                if before.spread > f32::EPSILON && after.spread > f32::EPSILON {
+                    self.emit(
+                        TouchGesture::Pinch,
+                        after.center,
+                        (after.spread / before.spread).ln(),
+                    );
+                }
+                let mut rotation = after.angle - before.angle;
+                if rotation > std::f32::consts::PI {
+                    rotation -= std::f32::consts::TAU;
+                } else if rotation < -std::f32::consts::PI {
+                    rotation += std::f32::consts::TAU;
+                }
+                self.emit(TouchGesture::Rotate, after.center, rotation);
+            }
+            _ => (),
+        }
+        self.session = Some(session);
+    }
+
+    fn handle_release(&mut self, position: nalgebra_glm::Vec2, now: Duration) {
+        let Some(session) = self.session else {
+            return;
+        };
+        if session.max_touches != 1
+            || session.moved
+            || session.long_pressed
+            || now - session.started > TAP_MAX_DURATION
+        {
+            return;
+        }
+        let double_tap = self.last_tap.is_some_and(|(time, last)| {
+            now - time <= DOUBLE_TAP_INTERVAL
+                && nalgebra_glm::distance(&last, &position) <= self.slop * 2.0
+        });
+        if double_tap {
+            self.last_tap = None;
+            self.emit(TouchGesture::DoubleTap, position, 1.0);
+        } else {
+            self.last_tap = Some((now, position));
+            self.emit(TouchGesture::Tap, position, 1.0);
+        }
+    }
+
+    fn two_finger_metrics(&self) -> Option<TwoFingerMetrics> {
+        let [first, second, ..] = self.touches.as_slice() else {
+            return None;
+        };
+        let offset = second.position - first.position;
+        Some(TwoFingerMetrics {
+            center: (first.position + second.position) * 0.5,
+            spread: nalgebra_glm::length(&offset),
+            angle: offset.y.atan2(offset.x),
+        })
+    }
+
+    fn emit(&mut self, gesture: TouchGesture, position: nalgebra_glm::Vec2, value: f32) {
+        if value != 0.0 {
+            self.pending.push(GestureEvent {
+                gesture,
+                position,
+                value,
+            });
+        }
+    }
+
+    pub fn update(&mut self, actions: &mut Actions, delta_time: Duration) {
+        self.clock += delta_time;
+        self.events.clear();
+        if let Some(session) = self.session.as_mut()
+            && let [touch] = self.touches.as_slice()
+            && session.max_touches == 1
+            && !session.moved
+            && !session.long_pressed
+            && self.clock - session.started >= LONG_PRESS_DURATION
+        {
+            session.long_pressed = true;
+            self.pending.push(GestureEvent {
+                gesture: TouchGesture::LongPress,
+                position: touch.position,
+                value: 1.0,
+            });
+        }
+
+        match self.session.and_then(|session| session.captured) {
+            Some(true) => {
+                for event in self.pending.drain(..) {
+                    actions.set_source(InputSource::Touch(event.gesture), event.value);
+                    if event.is_discrete() {
+                        self.events.push(event);
+                    }
+                }
+            }
+            Some(false) => self.pending.clear(),
+            None => (),
+        }
+    }
+}
@@ -0,0 +1,650 @@
+use crate::bounds::Ray;
+use crate::history::TransformEdit;
+use crate::{Scene, SceneObject};
+
+const AXIS_COLORS: [egui::Color32; 3] = [
+    egui::Color32::from_rgb(230, 70, 70),
+    egui::Color32::from_rgb(110, 200, 60),
+    egui::Color32::from_rgb(70, 120, 235),
+];
+const ACTIVE_COLOR: egui::Color32 = egui::Color32::from_rgb(255, 210, 60);
+const CENTER_COLOR: egui::Color32 = egui::Color32::from_gray(220);
+const PICK_RADIUS: f32 = 8.0;
+const PLANE_EXTENT: (f32, f32) = (0.25, 0.45);
+const RING_SEGMENTS: usize = 64;
+const MIN_SCALE: f32 = 0.01;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum GizmoMode {
+    Translate,
+    Rotate,
+    Scale,
+}
+
+impl GizmoMode {
+    pub const ALL: [Self; 3] = [Self::Translate, Self::Rotate, Self::Scale];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Translate => "Translate",
+            Self::Rotate => "Rotate",
+            Self::Scale => "Scale",
+        }
+    }
+
+    pub fn shortcut(self) -> egui::Key {
+        match self {
+            Self::Translate => egui::Key::W,
+            Self::Rotate => egui::Key::E,
+            Self::Scale => egui::Key::R,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum GizmoSpace {
+    World,
+    Local,
+}
+
+impl GizmoSpace {
+    pub const ALL: [Self; 2] = [Self::World, Self::Local];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::World => "World",
+            Self::Local => "Local",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct GizmoSettings {
+    pub mode: GizmoMode,
+    pub space: GizmoSpace,
+    pub snapping: bool,
+    pub translate_snap: f32,
+    pub rotate_snap: f32,
+    pub scale_snap: f32,
+    pub size: f32,
+}
+
+impl Default for GizmoSettings {
+    fn default() -> Self {
+        Self {
+            mode: GizmoMode::Translate,
+            space: GizmoSpace::World,
+            snapping: false,
+            translate_snap: 0.25,
+            rotate_snap: 15.0,
+            scale_snap: 0.1,
+            size: 96.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GizmoHandle {
+    Axis(usize),
+    Plane(usize),
+    Center,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct GizmoFrame {
+    origin: nalgebra_glm::Vec3,
+    axes: [nalgebra_glm::Vec3; 3],
+    scale: f32,
+}
+
+impl GizmoFrame {
+    fn basis(&self) -> nalgebra_glm::Mat4 {
+        let mut basis = nalgebra_glm::Mat4::identity();
+        for (column, axis) in self.axes.iter().enumerate() {
+            basis.set_column(column, &axis.push(0.0));
+        }
+        basis
+    }
+
+    fn plane_axes(&self, normal: usize) -> (nalgebra_glm::Vec3, nalgebra_glm::Vec3) {
+        (self.axes[(normal + 1) % 3], self.axes[(normal + 2) % 3])
+    }
+
+    fn plane_corners(&self, normal: usize) -> [nalgebra_glm::Vec3; 4] {
+        let (u, v) = self.plane_axes(normal);
+        let (near, far) = PLANE_EXTENT;
+        [(near, near), (far, near), (far, far), (near, far)]
+            .map(|(a, b)| self.origin + (u * a + v * b) * self.scale)
+    }
+
+    fn ring(&self, normal: usize) -> Vec<nalgebra_glm::Vec3> {
+        let (u, v) = self.plane_axes(normal);
+        (0..=RING_SEGMENTS)
+            .map(|segment| {
+                let angle = segment as f32 / RING_SEGMENTS as f32 * std::f32::consts::TAU;
+                self.origin + (u * angle.cos() + v * angle.sin()) * self.scale
+            })
+            .collect()
+    }
+}
+
+struct Viewport {
+    view_projection: nalgebra_glm::Mat4,
+    inverse_view_projection: nalgebra_glm::Mat4,
+    camera_position: nalgebra_glm::Vec3,
+    rect: egui::Rect,
+}
+
+impl Viewport {
+    fn new(scene: &Scene, rect: egui::Rect) -> Self {
+        let view_projection = scene.view_projection();
+        Self {
+            view_projection,
+            inverse_view_projection: nalgebra_glm::inverse(&view_projection),
+            camera_position: scene.camera.position,
+            rect,
+        }
+    }
+
+    fn project(&self, point: &nalgebra_glm::Vec3) -> Option<egui::Pos2> {
+        let clip = self.view_projection * point.push(1.0);
+        if clip.w <= 1e-4 {
+            return None;
+        }
+        let ndc = clip.xy() / clip.w;
+        Some(egui::pos2(
+            self.rect.min.x + (ndc.x + 1.0) * 0.5 * self.rect.width(),
+            self.rect.min.y + (1.0 - ndc.y) * 0.5 * self.rect.height(),
+        ))
+    }
+
+    fn ray(&self, position: egui::Pos2) -> Ray {
+        let ndc = nalgebra_glm::vec2(
+            (position.x - self.rect.min.x) / self.rect.width() * 2.0 - 1.0,
+            1.0 - (position.y - self.rect.min.y) / self.rect.height() * 2.0,
+        );
+        Ray::from_screen(&self.inverse_view_projection, ndc)
+    }
+
+    fn view_normal(&self, point: &nalgebra_glm::Vec3) -> nalgebra_glm::Vec3 {
+        nalgebra_glm::normalize(&(self.camera_position - point))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct GizmoDrag {
+    handle: GizmoHandle,
+    frame: GizmoFrame,
+    pointer: egui::Pos2,
+    start: nalgebra_glm::Vec3,
+    previous: nalgebra_glm::Vec3,
+    angle: f32,
+    transforms: Vec<(SceneObject, nalgebra_glm::Mat4)>,
+}
+
+#[derive(Debug, Default)]
+pub struct Gizmo {
+    pub settings: GizmoSettings,
+    hovered: Option<GizmoHandle>,
+    drag: Option<GizmoDrag>,
+}
+
+impl Gizmo {
+    pub fn is_dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+
+    pub fn wants_pointer(&self) -> bool {
+        self.hovered.is_some() || self.drag.is_some()
+    }
+
+    pub fn show(
+        &mut self,
+        ui: &egui::Ui,
+        response: &egui::Response,
+        scene: &mut Scene,
+        rect: egui::Rect,
+    ) -> Option<TransformEdit> {
+        if self.drag.is_none() && ui.memory(|memory| memory.focused().is_none()) {
+            for mode in GizmoMode::ALL {
+                if ui.input(|input| input.key_pressed(mode.shortcut())) {
+                    self.settings.mode = mode;
+                }
+            }
+        }
+
+        let viewport = Viewport::new(scene, rect);
+        let Some(frame) = self.frame(scene, &viewport) else {
+            self.hovered = None;
+            self.drag = None;
+            return None;
+        };
+
+        if self.drag.is_none() && !ui.input(|input| input.pointer.any_down()) {
+            self.hovered = response
+                .hover_pos()
+                .and_then(|position| self.hit_test(&frame, &viewport, position));
+        }
+
+        if response.drag_started()
+            && let Some(handle) = self.hovered
+            && let Some(pointer) = ui.input(|input| input.pointer.press_origin())
+        {
+            self.begin_drag(handle, frame, &viewport, pointer, scene);
+        }
+
+        let mut edit = None;
+        if self.drag.is_some() && ui.input(|input| input.key_pressed(egui::Key::Escape)) {
+            if let Some(drag) = self.drag.take() {
+                for (object, transform) in &drag.transforms {
+                    scene.set_object_transform(*object, transform);
+                }
+            }
+        } else if response.drag_stopped() {
+            edit = self.end_drag(scene);
+        } else if response.dragged()
+            && let Some(pointer) = response.interact_pointer_pos()
+        {
+            self.update_drag(&viewport, pointer, scene);
+        }
+
+        if let Some(frame) = self.frame(scene, &viewport) {
+            self.paint(ui.painter(), &frame, &viewport);
+        }
+        edit
+    }
+
+    fn frame(&self, scene: &Scene, viewport: &Viewport) -> Option<GizmoFrame> {
+        let transforms = scene
+            .selection
+            .iter()
+            .filter(|object| !scene.is_locked(**object))
+            .filter_map(|object| scene.object_transform(*object))
+            .collect::<Vec<_>>();
+        if transforms.is_empty() {
+            return None;
+        }
+        let origin = transforms
+            .iter()
+            .map(|transform| transform.column(3).xyz())
+            .sum::<nalgebra_glm::Vec3>()
+            / transforms.len() as f32;
+
+        let world_axes = [
+            nalgebra_glm::Vec3::x(),
+            nalgebra_glm::Vec3::y(),
+            nalgebra_glm::Vec3::z(),
+        ];
+        let axes = match (self.settings.space, scene.selection.primary()) {
+            (GizmoSpace::Local, Some(primary)) => {
+                let transform = scene.object_transform(primary)?;
+                let mut axes = world_axes;
+                for (column, axis) in axes.iter_mut().enumerate() {
+                    let local = transform.column(column).xyz();
+                    if nalgebra_glm::length(&local) > f32::EPSILON {
+                        *axis = nalgebra_glm::normalize(&local);
+                    }
+                }
+                axes
+            }
+            _ => world_axes,
+        };
+
+        let center = viewport.project(&origin)?;
+        let distance = nalgebra_glm::length(&(origin - viewport.camera_position));
+        let mut side =
+            nalgebra_glm::cross(&viewport.view_normal(&origin), &nalgebra_glm::Vec3::y());
+        if nalgebra_glm::length(&side) < 1e-3 {
+            side = nalgebra_glm::Vec3::x();
+        }
+        let step = distance * 0.01;
+        let offset = viewport.project(&(origin + nalgebra_glm::normalize(&side) * step))?;
+        let pixels = center.distance(offset);
+        if pixels < f32::EPSILON {
+            return None;
+        }
+
+        Some(GizmoFrame {
+            origin,
+            axes,
+            scale: self.settings.size * step / pixels,
+        })
+    }
+
+    fn hit_test(
+        &self,
+        frame: &GizmoFrame,
+        viewport: &Viewport,
+        position: egui::Pos2,
+    ) -> Option<GizmoHandle> {
+        let center = viewport.project(&frame.origin)?;
+        let nearest = |candidates: Vec<(GizmoHandle, f32)>| {
+            candidates
+                .into_iter()
+                .filter(|(_, distance)| *distance <= PICK_RADIUS)
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|(handle, _)| handle)
+        };
+
+        if self.settings.mode == GizmoMode::Rotate {
+            return nearest(
+                (0..3)
+                    .map(|axis| {
+                        let points = frame
+                            .ring(axis)
+                            .iter()
+                            .filter_map(|point| viewport.project(point))
+                            .collect::<Vec<_>>();
+                        (
+                            GizmoHandle::Axis(axis),
+                            polyline_distance(&points, position),
+                        )
+                    })
+                    .collect(),
+            );
+        }
+
+        if center.distance(position) <= PICK_RADIUS {
+            return Some(GizmoHandle::Center);
+        }
+        let axis = nearest(
+            (0..3)
+                .filter_map(|axis| {
+                    let tip = viewport.project(&(frame.origin + frame.axes[axis] * frame.scale))?;
+                    Some((
+                        GizmoHandle::Axis(axis),
+                        polyline_distance(&[center, tip], position),
+                    ))
+                })
+                .collect(),
+        );
+        axis.or_else(|| {
+            (0..3)
+                .find(|normal| {
+                    let corners = frame
+                        .plane_corners(*normal)
+                        .iter()
+                        .filter_map(|corner| viewport.project(corner))
+                        .collect::<Vec<_>>();
+                    corners.len() == 4 && polygon_contains(&corners, position)
+                })
+                .map(GizmoHandle::Plane)
+        })
+    }
+
+    fn constraint_point(
+        &self,
+        handle: GizmoHandle,
+        frame: &GizmoFrame,
+        viewport: &Viewport,
+        ray: &Ray,
+    ) -> Option<nalgebra_glm::Vec3> {
+        let on_plane = |normal: &nalgebra_glm::Vec3| {
+            ray.intersects_plane(&frame.origin, normal)
+                .map(|distance| ray.at(distance))
+        };
+        match (self.settings.mode, handle) {
+            (GizmoMode::Rotate, GizmoHandle::Axis(axis)) => on_plane(&frame.axes[axis]),
+            (GizmoMode::Rotate, _) => None,
+            (_, GizmoHandle::Axis(axis)) => ray
+                .closest_to_line(&frame.origin, &frame.axes[axis])
+                .map(|distance| frame.origin + frame.axes[axis] * distance),
+            (_, GizmoHandle::Plane(normal)) => on_plane(&frame.axes[normal]),
+            (GizmoMode::Translate, GizmoHandle::Center) => {
+                on_plane(&viewport.view_normal(&frame.origin))
+            }
+            (_, GizmoHandle::Center) => Some(frame.origin),
+        }
+    }
+
+    fn begin_drag(
+        &mut self,
+        handle: GizmoHandle,
+        frame: GizmoFrame,
+        viewport: &Viewport,
+        pointer: egui::Pos2,
+        scene: &Scene,
+    ) {
+        let Some(start) = self.constraint_point(handle, &frame, viewport, &viewport.ray(pointer))
+        else {
+            return;
+        };
+        self.drag = Some(GizmoDrag {
+            handle,
+            frame,
+            pointer,
+            start,
+            previous: start - frame.origin,
+            angle: 0.0,
+            transforms: scene
+                .with_descendants(scene.selection.iter().copied())
+                .into_iter()
+                .filter(|object| !scene.is_locked(*object))
+                .filter_map(|object| {
+                    scene
+                        .object_transform(object)
+                        .map(|transform| (object, transform))
+                })
+                .collect(),
+        });
+    }
+
+    fn update_drag(&mut self, viewport: &Viewport, pointer: egui::Pos2, scene: &mut Scene) {
+        let Some(drag) = self.drag.as_ref() else {
+            return;
+        };
+        let Some(point) =
+            self.constraint_point(drag.handle, &drag.frame, viewport, &viewport.ray(pointer))
+        else {
+            return;
+        };
+        let settings = self.settings;
+        let snap = |value: f32, step: f32| {
+            if settings.snapping && step > 0.0 {
+                (value / step).round() * step
+            } else {
+                value
+            }
+        };
+        let Some(drag) = self.drag.as_mut() else {
+            return;
+        };
+        let frame = drag.frame;
+        let pivot = nalgebra_glm::translation(&frame.origin);
+        let inverse_pivot = nalgebra_glm::translation(&-frame.origin);
+
+        let delta = match (settings.mode, drag.handle) {
+            (GizmoMode::Translate, _) => {
+                let offset = point - drag.start;
+                let translation = frame
+                    .axes
+                    .iter()
+                    .map(|axis| {
+                        axis * snap(nalgebra_glm::dot(&offset, axis), settings.translate_snap)
+                    })
+                    .sum::<nalgebra_glm::Vec3>();
+                nalgebra_glm::translation(&translation)
+            }
+            (GizmoMode::Rotate, GizmoHandle::Axis(axis)) => {
+                let direction = point - frame.origin;
+                if nalgebra_glm::length(&direction) < f32::EPSILON
+                    || nalgebra_glm::length(&drag.previous) < f32::EPSILON
+                {
+                    return;
+                }
+                let normal = frame.axes[axis];
+                drag.angle +=
+                    nalgebra_glm::dot(&nalgebra_glm::cross(&drag.previous, &direction), &normal)
+                        .atan2(nalgebra_glm::dot(&drag.previous, &direction));
+                drag.previous = direction;
+                let angle = snap(drag.angle, settings.rotate_snap.to_radians());
+                pivot * nalgebra_glm::rotation(angle, &normal) * inverse_pivot
+            }
+            (GizmoMode::Rotate, _) => return,
+            (GizmoMode::Scale, handle) => {
+                let mut factors = nalgebra_glm::vec3(1.0, 1.0, 1.0);
+                match handle {
+                    GizmoHandle::Axis(axis) => {
+                        let start =
+                            nalgebra_glm::dot(&(drag.start - frame.origin), &frame.axes[axis]);
+                        if start.abs() < f32::EPSILON {
+                            return;
+                        }
+                        factors[axis] =
+                            nalgebra_glm::dot(&(point - frame.origin), &frame.axes[axis]) / start;
+                    }
+                    GizmoHandle::Plane(normal) => {
+                        let start = nalgebra_glm::length(&(drag.start - frame.origin));
+                        if start < f32::EPSILON {
+                            return;
+                        }
+                        let factor = nalgebra_glm::length(&(point - frame.origin)) / start;
+                        factors[(normal + 1) % 3] = factor;
+                        factors[(normal + 2) % 3] = factor;
+                    }
+                    GizmoHandle::Center => {
+                        let offset = pointer - drag.pointer;
+                        let factor = 1.0 + (offset.x - offset.y) / settings.size;
+                        factors = nalgebra_glm::vec3(factor, factor, factor);
+                    }
+                }
+                let factors =
+                    factors.map(|factor| snap(factor, settings.scale_snap).max(MIN_SCALE));
+                let basis = frame.basis();
+                pivot
+                    * basis
+                    * nalgebra_glm::scaling(&factors)
+                    * nalgebra_glm::inverse(&basis)
+                    * inverse_pivot
+            }
+        };
+
+        for (object, transform) in &drag.transforms {
+            scene.set_object_transform(*object, &(delta * transform));
+        }
+    }
+
+    fn end_drag(&mut self, scene: &Scene) -> Option<TransformEdit> {
+        let drag = self.drag.take()?;
+        let transforms = drag
+            .transforms
+            .into_iter()
+            .filter_map(|(object, before)| {
+                scene
+                    .object_transform(object)
+                    .filter(|after| *after != before)
+                    .map(|after| (object, before, after))
+            })
+            .collect::<Vec<_>>();
+        (!transforms.is_empty()).then(|| TransformEdit {
+            label: self.settings.mode.label().to_string(),
+            transforms,
+        })
+    }
+
+    fn paint(&self, painter: &egui::Painter, frame: &GizmoFrame, viewport: &Viewport) {
+        let Some(center) = viewport.project(&frame.origin) else {
+            return;
+        };
+        let active = self.drag.as_ref().map(|drag| drag.handle).or(self.hovered);
+        let color = |handle: GizmoHandle, base: egui::Color32| {
+            if active == Some(handle) {
+                ACTIVE_COLOR
+            } else {
+                base
+            }
+        };
+
+        if self.settings.mode == GizmoMode::Rotate {
+            for (axis, axis_color) in AXIS_COLORS.into_iter().enumerate() {
+                let points = frame
+                    .ring(axis)
+                    .iter()
+                    .filter_map(|point| viewport.project(point))
+                    .collect::<Vec<_>>();
+                painter.add(egui::Shape::line(
+                    points,
+                    egui::Stroke::new(2.5_f32, color(GizmoHandle::Axis(axis), axis_color)),
+                ));
+            }
+            return;
+        }
+
+        for (normal, normal_color) in AXIS_COLORS.into_iter().enumerate() {
+            let corners = frame
+                .plane_corners(normal)
+                .iter()
+                .filter_map(|corner| viewport.project(corner))
+                .collect::<Vec<_>>();
+            if corners.len() == 4 {
+                let plane_color = color(GizmoHandle::Plane(normal), normal_color);
+                painter.add(egui::Shape::convex_polygon(
+                    corners,
+                    plane_color.gamma_multiply(0.35),
+                    egui::Stroke::new(1.0_f32, plane_color),
+                ));
+            }
+        }
+
+        for (axis, axis_color) in AXIS_COLORS.into_iter().enumerate() {
+            let Some(tip) = viewport.project(&(frame.origin + frame.axes[axis] * frame.scale))
+            else {
+                continue;
+            };
+            let axis_color = color(GizmoHandle::Axis(axis), axis_color);
+            let stroke = egui::Stroke::new(2.5_f32, axis_color);
+            match self.settings.mode {
+                GizmoMode::Translate => painter.arrow(center, tip - center, stroke),
+                _ => {
+                    painter.line_segment([center, tip], stroke);
+                    painter.rect_filled(
+                        egui::Rect::from_center_size(tip, egui::vec2(8.0, 8.0)),
+                        0.0,
+                        axis_color,
+                    );
+                }
+            }
+        }
+
+        let center_color = color(GizmoHandle::Center, CENTER_COLOR);
+        match self.settings.mode {
+            GizmoMode::Translate => {
+                painter.circle_stroke(center, 5.0, egui::Stroke::new(2.0_f32, center_color));
+            }
+            _ => {
+                painter.rect_filled(
+                    egui::Rect::from_center_size(center, egui::vec2(10.0, 10.0)),
+                    0.0,
+                    center_color,
+                );
+            }
+        }
+    }
+}
+
+fn polyline_distance(points: &[egui::Pos2], position: egui::Pos2) -> f32 {
+    points
+        .windows(2)
+        .map(|segment| {
+            let (start, end) = (segment[0], segment[1]);
+            let length = (end - start).length_sq();
+            let t = if length > f32::EPSILON {
+                ((position - start).dot(end - start) / length).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            position.distance(start + (end - start) * t)
+        })
+        .fold(f32::INFINITY, f32::min)
+}
+
+fn polygon_contains(points: &[egui::Pos2], position: egui::Pos2) -> bool {
+    let sides = points
+        .iter()
+        .zip(points.iter().cycle().skip(1))
+        .map(|(start, end)| {
+            (*end - *start).x * (position - *start).y - (*end - *start).y * (position - *start).x
+        })
+        .collect::<Vec<_>>();
+    sides.iter().all(|side| *side >= 0.0) || sides.iter().all(|side| *side <= 0.0)
+}
@@ -128,6 +128,11 @@ fn fragment_main(in: VertexOutput) -> @location(0) vec4<f32> {
         color = mix(color, vec4<f32>(0.24, 0.7, 0.29, 0.7), 0.5);
     }
 
+    let shadow_darkness = (1.0 - shadow_factor(in.world_pos, vec3<f32>(0.0, 1.0, 0.0))) * 0.5;
+    let shadowed_alpha = 1.0 - (1.0 - color.a) * (1.0 - shadow_darkness);
+    color = vec4<f32>(color.rgb * color.a / max(shadowed_alpha, 0.0001), shadowed_alpha);
+    color = vec4<f32>(shadow_debug_tint(color.rgb, in.world_pos), color.a);
+
     if (color.a < 0.02) {
         discard;
     }
@@ -0,0 +1,561 @@
+use crate::perf;
+use crate::post::LDR_FORMAT;
+use crate::resources::{ResourceTracker, Tracked};
+
+const OWNER: &str = "HDR";
+
+pub const HDR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+const LUMINANCE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R16Float;
+const LUMINANCE_SIZE: u32 = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Tonemapper {
+    Aces,
+    Agx,
+    Reinhard,
+}
+
+impl Tonemapper {
+    pub const ALL: [Tonemapper; 3] = [Tonemapper::Aces, Tonemapper::Agx, Tonemapper::Reinhard];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Tonemapper::Aces => "ACES",
+            Tonemapper::Agx => "AgX",
+            Tonemapper::Reinhard => "Reinhard",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum ExposureMode {
+    Manual,
+    Auto,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct TonemapSettings {
+    pub tonemapper: Tonemapper,
+    pub exposure_mode: ExposureMode,
+    pub exposure_ev: f32,
+    pub min_luminance_ev: f32,
+    pub max_luminance_ev: f32,
+    pub adaptation_speed: f32,
+}
+
+impl Default for TonemapSettings {
+    fn default() -> Self {
+        Self {
+            tonemapper: Tonemapper::Aces,
+            exposure_mode: ExposureMode::Manual,
+            exposure_ev: 0.0,
+            min_luminance_ev: -8.0,
+            max_luminance_ev: 8.0,
+            adaptation_speed: 1.5,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct TonemapUniform {
+    exposure: f32,
+    min_log_luminance: f32,
+    max_log_luminance: f32,
+    adaptation: f32,
+    tonemapper: u32,
+    auto_exposure: u32,
+    _padding: [u32; 2],
+}
+
+struct HdrTargets {
+    _color_texture: Tracked<wgpu::Texture>,
+    color_view: wgpu::TextureView,
+    luminance_bind_group: Tracked<wgpu::BindGroup>,
+    tonemap_bind_groups: [Tracked<wgpu::BindGroup>; 2],
+}
+
+pub struct HdrPipeline {
+    pub settings: TonemapSettings,
+    bind_group_layout: wgpu::BindGroupLayout,
+    sampler: Tracked<wgpu::Sampler>,
+    uniform_buffer: Tracked<wgpu::Buffer>,
+    _luminance_texture: Tracked<wgpu::Texture>,
+    luminance_views: Vec<wgpu::TextureView>,
+    _adapted_textures: [Tracked<wgpu::Texture>; 2],
+    adapted_views: [wgpu::TextureView; 2],
+    downsample_bind_groups: Vec<Tracked<wgpu::BindGroup>>,
+    adapt_bind_groups: [Tracked<wgpu::BindGroup>; 2],
+    luminance_pipeline: Tracked<wgpu::RenderPipeline>,
+    downsample_pipeline: Tracked<wgpu::RenderPipeline>,
+    adapt_pipeline: Tracked<wgpu::RenderPipeline>,
+    tonemap_pipeline: Tracked<wgpu::RenderPipeline>,
+    targets: HdrTargets,
+    current_adapted: usize,
+    reset_adaptation: bool,
+}
+
+impl HdrPipeline {
+    pub fn new(device: &wgpu::Device, width: u32, height: u32) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Tonemap Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let sampler =
+            ResourceTracker::new(device, OWNER).create_sampler(&wgpu::SamplerDescriptor {
+                label: Some("Tonemap Sampler"),
+                address_mode_u: wgpu::AddressMode::ClampToEdge,
+                address_mode_v: wgpu::AddressMode::ClampToEdge,
+                address_mode_w: wgpu::AddressMode::ClampToEdge,
+                mag_filter: wgpu::FilterMode::Linear,
+                min_filter: wgpu::FilterMode::Linear,
+                ..Default::default()
+            });
+
+        let uniform_buffer =
+            ResourceTracker::new(device, OWNER).create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Tonemap Uniform Buffer"),
+                size: std::mem::size_of::<TonemapUniform>() as u64,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+
+        let luminance_mip_count = LUMINANCE_SIZE.ilog2() + 1;
+        let luminance_texture =
+            ResourceTracker::new(device, OWNER).create_texture(&wgpu::TextureDescriptor {
+                label: Some("Luminance Texture"),
+                size: wgpu::Extent3d {
+                    width: LUMINANCE_SIZE,
+                    height: LUMINANCE_SIZE,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: luminance_mip_count,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: LUMINANCE_FORMAT,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            });
+        let luminance_views: Vec<wgpu::TextureView> = (0..luminance_mip_count)
+            .map(|mip_level| {
+                luminance_texture.create_view(&wgpu::TextureViewDescriptor {
+                    label: Some("Luminance Mip View"),
+                    base_mip_level: mip_level,
+                    mip_level_count: Some(1),
+                    ..Default::default()
+                })
+            })
+            .collect();
+
+        let adapted_textures = [0, 1].map(|_| {
+            ResourceTracker::new(device, OWNER).create_texture(&wgpu::TextureDescriptor {
+                label: Some("Adapted Luminance Texture"),
+                size: wgpu::Extent3d {
+                    width: 1,
+                    height: 1,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: LUMINANCE_FORMAT,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            })
+        });
+        let adapted_views = adapted_textures
+            .each_ref()
+            .map(|texture| texture.create_view(&wgpu::TextureViewDescriptor::default()));
+
+        let downsample_bind_groups = luminance_views
+            .windows(2)
+            .map(|views| {
+                Self::create_bind_group(
+                    device,
+                    &bind_group_layout,
+                    &views[0],
+                    &sampler,
+                    &uniform_buffer,
+                    &adapted_views[0],
+                )
+            })
+            .collect();
+        let last_luminance_view = &luminance_views[luminance_views.len() - 1];
+        let adapt_bind_groups = [0, 1].map(|index| {
+            Self::create_bind_group(
+                device,
+                &bind_group_layout,
+                last_luminance_view,
+                &sampler,
+                &uniform_buffer,
+                &adapted_views[index],
+            )
+        });
+
+        let shader_module = device.create_shader_module(wgpu::include_wgsl!("tonemap.wgsl"));
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Tonemap Pipeline Layout"),
+            bind_group_layouts: &[Some(&bind_group_layout)],
+            immediate_size: 0,
+        });
+        let luminance_pipeline = Self::create_pipeline(
+            device,
+            &pipeline_layout,
+            &shader_module,
+            "fs_luminance",
+            LUMINANCE_FORMAT,
+            1,
+        );
+        let downsample_pipeline = Self::create_pipeline(
+            device,
+            &pipeline_layout,
+            &shader_module,
+            "fs_downsample",
+            LUMINANCE_FORMAT,
+            1,
+        );
+        let adapt_pipeline = Self::create_pipeline(
+            device,
+            &pipeline_layout,
+            &shader_module,
+            "fs_adapt",
+            LUMINANCE_FORMAT,
+            1,
+        );
+        let tonemap_pipeline = Self::create_pipeline(
+            device,
+            &pipeline_layout,
+            &shader_module,
+            "fs_tonemap",
+            LDR_FORMAT,
+            1,
+        );
+
+        let targets = Self::create_targets(
+            device,
+            &bind_group_layout,
+            &sampler,
+            &uniform_buffer,
+            &adapted_views,
+            width,
+            height,
+        );
+
+        Self {
+            settings: TonemapSettings::default(),
+            bind_group_layout,
+            sampler,
+            uniform_buffer,
+            _luminance_texture: luminance_texture,
+            luminance_views,
+            _adapted_textures: adapted_textures,
+            adapted_views,
+            downsample_bind_groups,
+            adapt_bind_groups,
+            luminance_pipeline,
+            downsample_pipeline,
+            adapt_pipeline,
+            tonemap_pipeline,
+            targets,
+            current_adapted: 0,
+            reset_adaptation: true,
+        }
+    }
+
+    pub fn color_view(&self) -> &wgpu::TextureView {
+        &self.targets.color_view
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        self.targets = Self::create_targets(
+            device,
+            &self.bind_group_layout,
+            &self.sampler,
+            &self.uniform_buffer,
+            &self.adapted_views,
+            width,
+            height,
+        );
+    }
+
+    pub fn update(&mut self, queue: &wgpu::Queue, delta_time: f32) {
+        let settings = &self.settings;
+        let auto_exposure = settings.exposure_mode == ExposureMode::Auto;
+        if !auto_exposure {
+            self.reset_adaptation = true;
+        }
+        let adaptation = if self.reset_adaptation {
+            1.0
+        } else {
+            1.0 - (-delta_time * settings.adaptation_speed).exp()
+        };
+        if auto_exposure {
+            self.current_adapted = 1 - self.current_adapted;
+            self.reset_adaptation = false;
+        }
+        let uniform = TonemapUniform {
+            exposure: settings.exposure_ev.exp2(),
+            min_log_luminance: settings.min_luminance_ev,
+            max_log_luminance: settings.max_luminance_ev.max(settings.min_luminance_ev),
+            adaptation,
+            tonemapper: settings.tonemapper as u32,
+            auto_exposure: auto_exposure as u32,
+            _padding: [0; 2],
+        };
+        perf::write_buffer(
+            queue,
+            &self.uniform_buffer,
+            0,
+            bytemuck::cast_slice(&[uniform]),
+        );
+    }
+
+    pub fn auto_exposure(&self) -> bool {
+        self.settings.exposure_mode == ExposureMode::Auto
+    }
+
+    pub fn adapted_luminance_view(&self) -> &wgpu::TextureView {
+        &self.adapted_views[self.current_adapted]
+    }
+
+    pub fn render_exposure(&self, encoder: &mut wgpu::CommandEncoder) {
+        if self.settings.exposure_mode != ExposureMode::Auto {
+            return;
+        }
+
+        Self::draw_fullscreen(
+            encoder,
+            "Luminance Pass",
+            &self.luminance_views[0],
+            &self.luminance_pipeline,
+            &self.targets.luminance_bind_group,
+        );
+        for (view, bind_group) in self.luminance_views[1..]
+            .iter()
+            .zip(&self.downsample_bind_groups)
+        {
+            Self::draw_fullscreen(
+                encoder,
+                "Luminance Downsample Pass",
+                view,
+                &self.downsample_pipeline,
+                bind_group,
+            );
+        }
+
+        Self::draw_fullscreen(
+            encoder,
+            "Exposure Adaptation Pass",
+            &self.adapted_views[self.current_adapted],
+            &self.adapt_pipeline,
+            &self.adapt_bind_groups[1 - self.current_adapted],
+        );
+    }
+
+    pub fn render(&self, renderpass: &mut wgpu::RenderPass<'_>) {
+        renderpass.set_pipeline(&self.tonemap_pipeline);
+        renderpass.set_bind_group(
+            0,
+            &self.targets.tonemap_bind_groups[self.current_adapted],
+            &[],
+        );
+        renderpass.draw(0..3, 0..1);
+        perf::record_draw(1);
+    }
+
+    fn draw_fullscreen(
+        encoder: &mut wgpu::CommandEncoder,
+        label: &str,
+        target: &wgpu::TextureView,
+        pipeline: &wgpu::RenderPipeline,
+        bind_group: &wgpu::BindGroup,
+    ) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some(label),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+                depth_slice: None,
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+            multiview_mask: None,
+        });
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(0, bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+        perf::record_draw(1);
+    }
+
+    fn create_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        source: &wgpu::TextureView,
+        sampler: &wgpu::Sampler,
+        uniform_buffer: &wgpu::Buffer,
+        adapted_luminance: &wgpu::TextureView,
+    ) -> Tracked<wgpu::BindGroup> {
+        ResourceTracker::new(device, OWNER).create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Tonemap Bind Group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(source),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(adapted_luminance),
+                },
+            ],
+        })
+    }
+
+    fn create_targets(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        sampler: &wgpu::Sampler,
+        uniform_buffer: &wgpu::Buffer,
+        adapted_views: &[wgpu::TextureView; 2],
+        width: u32,
+        height: u32,
+    ) -> HdrTargets {
+        let color_texture =
+            ResourceTracker::new(device, OWNER).create_texture(&wgpu::TextureDescriptor {
+                label: Some("HDR Color Texture"),
+                size: wgpu::Extent3d {
+                    width: width.max(1),
+                    height: height.max(1),
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: HDR_FORMAT,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            });
+        let color_view = color_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let luminance_bind_group = Self::create_bind_group(
+            device,
+            layout,
+            &color_view,
+            sampler,
+            uniform_buffer,
+            &adapted_views[0],
+        );
+        let tonemap_bind_groups = [0, 1].map(|index| {
+            Self::create_bind_group(
+                device,
+                layout,
+                &color_view,
+                sampler,
+                uniform_buffer,
+                &adapted_views[index],
+            )
+        });
+
+        HdrTargets {
+            _color_texture: color_texture,
+            color_view,
+            luminance_bind_group,
+            tonemap_bind_groups,
+        }
+    }
+
+    fn create_pipeline(
+        device: &wgpu::Device,
+        layout: &wgpu::PipelineLayout,
+        shader_module: &wgpu::ShaderModule,
+        fragment_entry_point: &str,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> Tracked<wgpu::RenderPipeline> {
+        ResourceTracker::new(device, OWNER).create_render_pipeline(
+            &wgpu::RenderPipelineDescriptor {
+                label: Some(fragment_entry_point),
+                layout: Some(layout),
+                vertex: wgpu::VertexState {
+                    module: shader_module,
+                    entry_point: Some("vs_fullscreen"),
+                    buffers: &[],
+                    compilation_options: Default::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: shader_module,
+                    entry_point: Some(fragment_entry_point),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend: None,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                    compilation_options: Default::default(),
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState {
+                    count: sample_count,
+                    ..Default::default()
+                },
+                multiview_mask: None,
+                cache: None,
+            },
+        )
+    }
+}
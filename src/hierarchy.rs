@@ -0,0 +1,95 @@
+use crate::SceneObject;
+
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeInfo {
+    pub name: Option<String>,
+    pub parent: Option<SceneObject>,
+    pub visible: bool,
+    pub locked: bool,
+}
+
+impl Default for NodeInfo {
+    fn default() -> Self {
+        Self {
+            name: None,
+            parent: None,
+            visible: true,
+            locked: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Hierarchy {
+    nodes: HashMap<SceneObject, NodeInfo>,
+    children: HashMap<SceneObject, Vec<SceneObject>>,
+}
+
+impl Hierarchy {
+    pub fn info(&self, object: SceneObject) -> NodeInfo {
+        self.nodes.get(&object).cloned().unwrap_or_default()
+    }
+
+    pub fn name(&self, object: SceneObject) -> Option<&str> {
+        self.nodes.get(&object)?.name.as_deref()
+    }
+
+    pub fn parent(&self, object: SceneObject) -> Option<SceneObject> {
+        self.nodes.get(&object)?.parent
+    }
+
+    pub fn children(&self, object: SceneObject) -> &[SceneObject] {
+        self.children.get(&object).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn nodes(&self) -> impl Iterator<Item = (SceneObject, &NodeInfo)> {
+        self.nodes.iter().map(|(object, info)| (*object, info))
+    }
+
+    pub fn descendants(&self, object: SceneObject) -> Vec<SceneObject> {
+        let mut descendants = Vec::new();
+        let mut stack = self.children(object).to_vec();
+        while let Some(child) = stack.pop() {
+            descendants.push(child);
+            stack.extend_from_slice(self.children(child));
+        }
+        descendants
+    }
+
+    pub fn can_reparent(&self, object: SceneObject, parent: Option<SceneObject>) -> bool {
+        match parent {
+            Some(parent) => parent != object && !self.descendants(object).contains(&parent),
+            None => true,
+        }
+    }
+
+    pub fn set_info(&mut self, object: SceneObject, info: NodeInfo) {
+        let previous_parent = self.parent(object);
+        if previous_parent != info.parent {
+            if let Some(parent) = previous_parent
+                && let Some(siblings) = self.children.get_mut(&parent)
+            {
+                siblings.retain(|sibling| *sibling != object);
+                if siblings.is_empty() {
+                    self.children.remove(&parent);
+                }
+            }
+            if let Some(parent) = info.parent {
+                self.children.entry(parent).or_default().push(object);
+            }
+        }
+        if info == NodeInfo::default() {
+            self.nodes.remove(&object);
+        } else {
+            self.nodes.insert(object, info);
+        }
+    }
+
+    pub fn update(&mut self, object: SceneObject, edit: impl FnOnce(&mut NodeInfo)) {
+        let mut info = self.info(object);
+        edit(&mut info);
+        self.set_info(object, info);
+    }
+}
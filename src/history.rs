@@ -0,0 +1,712 @@
+use crate::components::{ComponentStore, ObjectComponents};
+use crate::hierarchy::{Hierarchy, NodeInfo};
+use crate::instancing::{InstanceBatch, InstanceData, InstanceId};
+use crate::shadows::DirectionalLight;
+use crate::{Camera, Material, Scene, SceneObject};
+
+use std::any::Any;
+
+pub const DEFAULT_MEMORY_LIMIT: usize = 64 * 1024 * 1024;
+
+pub const UNDO_SHORTCUT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Z);
+pub const REDO_SHORTCUT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Y);
+pub const REDO_ALT_SHORTCUT: egui::KeyboardShortcut = egui::KeyboardShortcut::new(
+    egui::Modifiers::COMMAND.plus(egui::Modifiers::SHIFT),
+    egui::Key::Z,
+);
+
+pub trait Command: Any {
+    fn label(&self) -> String;
+
+    fn apply(&mut self, scene: &mut Scene);
+
+    fn revert(&mut self, scene: &mut Scene);
+
+    fn memory_size(&self) -> usize {
+        std::mem::size_of_val(self)
+    }
+
+    fn merge(&mut self, _next: &dyn Command) -> bool {
+        false
+    }
+}
+
+pub struct History {
+    memory_limit: usize,
+    commands: Vec<Box<dyn Command>>,
+    cursor: usize,
+    merging: bool,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self {
+            memory_limit: DEFAULT_MEMORY_LIMIT,
+            commands: Vec::new(),
+            cursor: 0,
+            merging: false,
+        }
+    }
+}
+
+impl History {
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.cursor > 0
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.cursor < self.commands.len()
+    }
+
+    pub fn labels(&self) -> impl Iterator<Item = String> + '_ {
+        self.commands.iter().map(|command| command.label())
+    }
+
+    pub fn memory_limit(&self) -> usize {
+        self.memory_limit
+    }
+
+    pub fn set_memory_limit(&mut self, memory_limit: usize) {
+        self.memory_limit = memory_limit;
+        self.enforce_memory_limit();
+    }
+
+    pub fn memory_usage(&self) -> usize {
+        self.commands
+            .iter()
+            .map(|command| command.memory_size())
+            .sum()
+    }
+
+    pub fn execute(&mut self, mut command: impl Command, scene: &mut Scene) {
+        command.apply(scene);
+        self.push(command);
+    }
+
+    pub fn push(&mut self, command: impl Command) {
+        self.commands.truncate(self.cursor);
+        if self.merging
+            && let Some(last) = self.commands.last_mut()
+            && last.merge(&command)
+        {
+            self.enforce_memory_limit();
+            return;
+        }
+        self.commands.push(Box::new(command));
+        self.cursor = self.commands.len();
+        self.merging = true;
+        self.enforce_memory_limit();
+    }
+
+    pub fn end_merge(&mut self) {
+        self.merging = false;
+    }
+
+    pub fn undo(&mut self, scene: &mut Scene) -> bool {
+        let Some(command) = self.step_back() else {
+            return false;
+        };
+        command.revert(scene);
+        true
+    }
+
+    pub fn redo(&mut self, scene: &mut Scene) -> bool {
+        let Some(command) = self.step_forward() else {
+            return false;
+        };
+        command.apply(scene);
+        true
+    }
+
+    pub fn jump_to(&mut self, cursor: usize, scene: &mut Scene) {
+        let cursor = cursor.min(self.commands.len());
+        while self.cursor > cursor && self.undo(scene) {}
+        while self.cursor < cursor && self.redo(scene) {}
+    }
+
+    pub fn clear(&mut self) {
+        self.commands.clear();
+        self.cursor = 0;
+        self.merging = false;
+    }
+
+    fn step_back(&mut self) -> Option<&mut Box<dyn Command>> {
+        self.merging = false;
+        if !self.can_undo() {
+            return None;
+        }
+        self.cursor -= 1;
+        Some(&mut self.commands[self.cursor])
+    }
+
+    fn step_forward(&mut self) -> Option<&mut Box<dyn Command>> {
+        self.merging = false;
+        if !self.can_redo() {
+            return None;
+        }
+        self.cursor += 1;
+        Some(&mut self.commands[self.cursor - 1])
+    }
+
+    fn enforce_memory_limit(&mut self) {
+        let mut usage = self.memory_usage();
+        while usage > self.memory_limit && self.commands.len() > 1 {
+            usage -= self.commands.remove(0).memory_size();
+            self.cursor = self.cursor.saturating_sub(1);
+        }
+    }
+}
+
+pub struct CommandGroup {
+    label: String,
+    commands: Vec<Box<dyn Command>>,
+}
+
+impl CommandGroup {
+    pub fn new(label: impl Into<String>) -> Self {
+        Self {
+            label: label.into(),
+            commands: Vec::new(),
+        }
+    }
+
+    pub fn with(mut self, command: impl Command) -> Self {
+        self.push(command);
+        self
+    }
+
+    pub fn push(&mut self, command: impl Command) {
+        self.commands.push(Box::new(command));
+    }
+}
+
+impl Command for CommandGroup {
+    fn label(&self) -> String {
+        self.label.clone()
+    }
+
+    fn apply(&mut self, scene: &mut Scene) {
+        for command in &mut self.commands {
+            command.apply(scene);
+        }
+    }
+
+    fn revert(&mut self, scene: &mut Scene) {
+        for command in self.commands.iter_mut().rev() {
+            command.revert(scene);
+        }
+    }
+
+    fn memory_size(&self) -> usize {
+        std::mem::size_of_val(self)
+            + self
+                .commands
+                .iter()
+                .map(|command| command.memory_size())
+                .sum::<usize>()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransformEdit {
+    pub label: String,
+    pub transforms: Vec<(SceneObject, nalgebra_glm::Mat4, nalgebra_glm::Mat4)>,
+}
+
+impl Command for TransformEdit {
+    fn label(&self) -> String {
+        self.label.clone()
+    }
+
+    fn apply(&mut self, scene: &mut Scene) {
+        for (object, _, after) in &self.transforms {
+            scene.set_object_transform(*object, after);
+        }
+    }
+
+    fn revert(&mut self, scene: &mut Scene) {
+        for (object, before, _) in &self.transforms {
+            scene.set_object_transform(*object, before);
+        }
+    }
+
+    fn memory_size(&self) -> usize {
+        std::mem::size_of_val(self) + std::mem::size_of_val(self.transforms.as_slice())
+    }
+
+    fn merge(&mut self, next: &dyn Command) -> bool {
+        let Some(next) = (next as &dyn Any).downcast_ref::<Self>() else {
+            return false;
+        };
+        let same_objects = self.label == next.label
+            && self.transforms.len() == next.transforms.len()
+            && self
+                .transforms
+                .iter()
+                .zip(&next.transforms)
+                .all(|(current, next)| current.0 == next.0);
+        if same_objects {
+            for (current, next) in self.transforms.iter_mut().zip(&next.transforms) {
+                current.2 = next.2;
+            }
+        }
+        same_objects
+    }
+}
+
+pub struct PropertyEdit<T> {
+    label: String,
+    before: T,
+    after: T,
+    access: fn(&mut Scene) -> &mut T,
+}
+
+impl<T> PropertyEdit<T> {
+    pub fn new(
+        label: impl Into<String>,
+        before: T,
+        after: T,
+        access: fn(&mut Scene) -> &mut T,
+    ) -> Self {
+        Self {
+            label: label.into(),
+            before,
+            after,
+            access,
+        }
+    }
+}
+
+impl<T: Clone + 'static> Command for PropertyEdit<T> {
+    fn label(&self) -> String {
+        self.label.clone()
+    }
+
+    fn apply(&mut self, scene: &mut Scene) {
+        *(self.access)(scene) = self.after.clone();
+    }
+
+    fn revert(&mut self, scene: &mut Scene) {
+        *(self.access)(scene) = self.before.clone();
+    }
+
+    fn merge(&mut self, next: &dyn Command) -> bool {
+        match (next as &dyn Any).downcast_ref::<Self>() {
+            Some(next) if next.label == self.label => {
+                self.after = next.after.clone();
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+pub struct ComponentEdit<T> {
+    label: String,
+    object: Option<SceneObject>,
+    before: T,
+    after: T,
+    set: fn(&mut Scene, Option<SceneObject>, &T),
+}
+
+impl<T> ComponentEdit<T> {
+    pub fn new(
+        label: impl Into<String>,
+        object: Option<SceneObject>,
+        before: T,
+        after: T,
+        set: fn(&mut Scene, Option<SceneObject>, &T),
+    ) -> Self {
+        Self {
+            label: label.into(),
+            object,
+            before,
+            after,
+            set,
+        }
+    }
+}
+
+impl<T: Clone + 'static> Command for ComponentEdit<T> {
+    fn label(&self) -> String {
+        self.label.clone()
+    }
+
+    fn apply(&mut self, scene: &mut Scene) {
+        (self.set)(scene, self.object, &self.after);
+    }
+
+    fn revert(&mut self, scene: &mut Scene) {
+        (self.set)(scene, self.object, &self.before);
+    }
+
+    fn merge(&mut self, next: &dyn Command) -> bool {
+        match (next as &dyn Any).downcast_ref::<Self>() {
+            Some(next) if next.label == self.label && next.object == self.object => {
+                self.after = next.after.clone();
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+pub struct NodeEdit {
+    pub label: String,
+    pub object: SceneObject,
+    pub before: NodeInfo,
+    pub after: NodeInfo,
+}
+
+impl NodeEdit {
+    pub fn new(
+        label: impl Into<String>,
+        scene: &Scene,
+        object: SceneObject,
+        edit: impl FnOnce(&mut NodeInfo),
+    ) -> Self {
+        let before = scene.hierarchy.info(object);
+        let mut after = before.clone();
+        edit(&mut after);
+        Self {
+            label: label.into(),
+            object,
+            before,
+            after,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.before == self.after
+    }
+}
+
+impl Command for NodeEdit {
+    fn label(&self) -> String {
+        self.label.clone()
+    }
+
+    fn apply(&mut self, scene: &mut Scene) {
+        scene.hierarchy.set_info(self.object, self.after.clone());
+    }
+
+    fn revert(&mut self, scene: &mut Scene) {
+        scene.hierarchy.set_info(self.object, self.before.clone());
+    }
+}
+
+pub struct AddInstances {
+    batch: usize,
+    instances: Vec<InstanceData>,
+    ids: Vec<InstanceId>,
+    info: Option<NodeInfo>,
+}
+
+impl AddInstances {
+    pub fn new(batch: usize, instances: Vec<InstanceData>) -> Self {
+        Self {
+            batch,
+            instances,
+            ids: Vec::new(),
+            info: None,
+        }
+    }
+
+    pub fn with_info(mut self, info: NodeInfo) -> Self {
+        self.info = Some(info);
+        self
+    }
+
+    pub fn objects(&self) -> impl Iterator<Item = SceneObject> + '_ {
+        self.ids.iter().map(|instance| SceneObject::Instance {
+            batch: self.batch,
+            instance: *instance,
+        })
+    }
+}
+
+impl Command for AddInstances {
+    fn label(&self) -> String {
+        format!("Add {} Instances", self.instances.len())
+    }
+
+    fn apply(&mut self, scene: &mut Scene) {
+        let Some(batch) = scene.batches.get_mut(self.batch) else {
+            return;
+        };
+        if self.ids.is_empty() {
+            self.ids = batch.add(&self.instances);
+        } else {
+            for (id, instance) in self.ids.iter().zip(&self.instances) {
+                batch.restore(*id, instance);
+            }
+        }
+        if let Some(info) = &self.info {
+            for instance in &self.ids {
+                scene.hierarchy.set_info(
+                    SceneObject::Instance {
+                        batch: self.batch,
+                        instance: *instance,
+                    },
+                    info.clone(),
+                );
+            }
+        }
+    }
+
+    fn revert(&mut self, scene: &mut Scene) {
+        if let Some(batch) = scene.batches.get_mut(self.batch) {
+            batch.remove(&self.ids);
+        }
+    }
+
+    fn memory_size(&self) -> usize {
+        std::mem::size_of_val(self)
+            + std::mem::size_of_val(self.instances.as_slice())
+            + std::mem::size_of_val(self.ids.as_slice())
+    }
+}
+
+pub struct RemoveInstances {
+    instances: Vec<(usize, InstanceId, InstanceData)>,
+    components: Vec<(SceneObject, ObjectComponents)>,
+}
+
+impl RemoveInstances {
+    pub fn new(scene: &Scene, objects: impl IntoIterator<Item = SceneObject>) -> Self {
+        let instances = objects
+            .into_iter()
+            .filter_map(|object| match object {
+                SceneObject::Mesh => None,
+                SceneObject::Instance { batch, instance } => scene
+                    .batches
+                    .get(batch)?
+                    .get(instance)
+                    .map(|data| (batch, instance, *data)),
+            })
+            .collect();
+        Self {
+            instances,
+            components: Vec::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.instances.is_empty()
+    }
+}
+
+impl Command for RemoveInstances {
+    fn label(&self) -> String {
+        format!("Remove {} Instances", self.instances.len())
+    }
+
+    fn apply(&mut self, scene: &mut Scene) {
+        for (batch, instance, _) in &self.instances {
+            if let Some(instances) = scene.batches.get_mut(*batch) {
+                instances.remove(&[*instance]);
+            }
+            let object = SceneObject::Instance {
+                batch: *batch,
+                instance: *instance,
+            };
+            let components = scene.components.take_all(object);
+            if !components.is_empty() {
+                self.components.push((object, components));
+            }
+        }
+    }
+
+    fn revert(&mut self, scene: &mut Scene) {
+        for (batch, instance, data) in self.instances.iter().rev() {
+            if let Some(batch) = scene.batches.get_mut(*batch) {
+                batch.restore(*instance, data);
+            }
+        }
+        for (object, components) in self.components.drain(..) {
+            scene.components.restore_all(object, components);
+        }
+    }
+
+    fn memory_size(&self) -> usize {
+        std::mem::size_of_val(self) + std::mem::size_of_val(self.instances.as_slice())
+    }
+}
+
+#[derive(Default)]
+pub struct ClearScene {
+    batches: Vec<InstanceBatch>,
+    hierarchy: Hierarchy,
+    components: ComponentStore,
+}
+
+impl ClearScene {
+    fn swap(&mut self, scene: &mut Scene) {
+        std::mem::swap(&mut scene.batches, &mut self.batches);
+        std::mem::swap(&mut scene.hierarchy, &mut self.hierarchy);
+        std::mem::swap(&mut scene.components, &mut self.components);
+        let mesh = self.hierarchy.info(SceneObject::Mesh);
+        let current = scene.hierarchy.info(SceneObject::Mesh);
+        scene.hierarchy.set_info(SceneObject::Mesh, mesh);
+        self.hierarchy.set_info(SceneObject::Mesh, current);
+    }
+}
+
+impl Command for ClearScene {
+    fn label(&self) -> String {
+        "Clear Scene".to_string()
+    }
+
+    fn apply(&mut self, scene: &mut Scene) {
+        self.swap(scene);
+    }
+
+    fn revert(&mut self, scene: &mut Scene) {
+        self.swap(scene);
+    }
+
+    fn memory_size(&self) -> usize {
+        std::mem::size_of_val(self)
+            + self
+                .batches
+                .iter()
+                .map(|batch| std::mem::size_of_val(batch.instances()))
+                .sum::<usize>()
+    }
+}
+
+pub struct ResetScene {
+    model: nalgebra_glm::Mat4,
+    camera: Camera,
+    light: DirectionalLight,
+    material: Material,
+    components: ComponentStore,
+}
+
+impl Default for ResetScene {
+    fn default() -> Self {
+        Self {
+            model: nalgebra_glm::Mat4::identity(),
+            camera: Camera::default(),
+            light: DirectionalLight::default(),
+            material: Material::default(),
+            components: ComponentStore::default(),
+        }
+    }
+}
+
+impl ResetScene {
+    fn swap(&mut self, scene: &mut Scene) {
+        std::mem::swap(&mut scene.model, &mut self.model);
+        std::mem::swap(&mut scene.camera, &mut self.camera);
+        std::mem::swap(&mut scene.light, &mut self.light);
+        std::mem::swap(&mut scene.material, &mut self.material);
+        std::mem::swap(&mut scene.components, &mut self.components);
+    }
+}
+
+impl Command for ResetScene {
+    fn label(&self) -> String {
+        "Reset Scene".to_string()
+    }
+
+    fn apply(&mut self, scene: &mut Scene) {
+        self.swap(scene);
+    }
+
+    fn revert(&mut self, scene: &mut Scene) {
+        self.swap(scene);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn moved(label: &str, x: f32) -> TransformEdit {
+        TransformEdit {
+            label: label.to_string(),
+            transforms: vec![(
+                SceneObject::Mesh,
+                nalgebra_glm::Mat4::identity(),
+                nalgebra_glm::translation(&nalgebra_glm::vec3(x, 0.0, 0.0)),
+            )],
+        }
+    }
+
+    fn labels(history: &History) -> Vec<String> {
+        history.labels().collect()
+    }
+
+    #[test]
+    fn consecutive_drags_merge_into_one_entry() {
+        let mut history = History::default();
+        history.push(moved("Move", 1.0));
+        history.push(moved("Move", 2.0));
+        history.push(moved("Move", 3.0));
+        assert_eq!(history.len(), 1);
+        assert_eq!(history.cursor(), 1);
+        let merged = (history.commands[0].as_ref() as &dyn Any)
+            .downcast_ref::<TransformEdit>()
+            .unwrap();
+        assert_eq!(merged, &moved("Move", 3.0));
+
+        history.push(moved("Rotate", 4.0));
+        history.end_merge();
+        history.push(moved("Rotate", 5.0));
+        assert_eq!(labels(&history), ["Move", "Rotate", "Rotate"]);
+    }
+
+    #[test]
+    fn memory_limit_evicts_oldest_entries() {
+        let size = moved("Move", 0.0).memory_size();
+        let mut history = History::default();
+        history.set_memory_limit(size * 2);
+        for label in ["First", "Second", "Third"] {
+            history.push(moved(label, 1.0));
+        }
+        assert_eq!(labels(&history), ["Second", "Third"]);
+        assert_eq!(history.cursor(), 2);
+        assert!(history.memory_usage() <= history.memory_limit());
+
+        history.set_memory_limit(size);
+        assert_eq!(labels(&history), ["Third"]);
+        assert_eq!(history.cursor(), 1);
+
+        history.set_memory_limit(0);
+        assert_eq!(labels(&history), ["Third"]);
+    }
+
+    #[test]
+    fn push_after_undo_truncates_redo_tail() {
+        let mut history = History::default();
+        for label in ["First", "Second", "Third"] {
+            history.push(moved(label, 1.0));
+            history.end_merge();
+        }
+        assert!(history.step_back().is_some());
+        assert!(history.step_back().is_some());
+        assert_eq!(history.cursor(), 1);
+        assert!(history.can_redo());
+
+        history.push(moved("Second", 2.0));
+        assert_eq!(labels(&history), ["First", "Second"]);
+        assert_eq!(history.cursor(), 2);
+        assert!(!history.can_redo());
+        assert!(history.step_forward().is_none());
+    }
+}
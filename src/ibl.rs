@@ -0,0 +1,842 @@
+use crate::perf;
+use crate::resources::{ResourceTracker, Tracked};
+
+const OWNER: &str = "Environment";
+
+pub const ENVIRONMENT_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+pub const ENVIRONMENT_SHADER_SOURCE: &str = include_str!("environment.wgsl");
+pub const PBR_SHADER_SOURCE: &str = include_str!("pbr.wgsl");
+
+const ENVIRONMENT_SIZE: u32 = 512;
+const IRRADIANCE_SIZE: u32 = 32;
+const PREFILTERED_SIZE: u32 = 128;
+const PREFILTERED_MIP_COUNT: u32 = 5;
+const BRDF_LUT_SIZE: u32 = 256;
+const PREFILTER_SAMPLE_COUNT: u32 = 256;
+const WORKGROUP_SIZE: u32 = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkyMode {
+    Procedural,
+    Environment,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct EnvironmentUniform {
+    intensity: f32,
+    prefiltered_mip_count: f32,
+    sky_intensity: f32,
+    _padding: f32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct PrefilterParams {
+    roughness: f32,
+    environment_size: f32,
+    sample_count: u32,
+    _padding: u32,
+}
+
+struct EnvironmentMaps {
+    _textures: [Tracked<wgpu::Texture>; 3],
+    environment_view: wgpu::TextureView,
+    irradiance_view: wgpu::TextureView,
+    prefiltered_view: wgpu::TextureView,
+    prefiltered_mip_count: u32,
+}
+
+struct IblPipelines {
+    equirect_layout: wgpu::BindGroupLayout,
+    equirect_pipeline: Tracked<wgpu::ComputePipeline>,
+    downsample_layout: wgpu::BindGroupLayout,
+    downsample_pipeline: Tracked<wgpu::ComputePipeline>,
+    irradiance_layout: wgpu::BindGroupLayout,
+    irradiance_pipeline: Tracked<wgpu::ComputePipeline>,
+    prefilter_layout: wgpu::BindGroupLayout,
+    prefilter_pipeline: Tracked<wgpu::ComputePipeline>,
+    brdf_layout: wgpu::BindGroupLayout,
+    brdf_pipeline: Tracked<wgpu::ComputePipeline>,
+}
+
+pub struct Environment {
+    pub intensity: f32,
+    pub sky_intensity: f32,
+    pub bind_group_layout: wgpu::BindGroupLayout,
+    pub bind_group: Tracked<wgpu::BindGroup>,
+    maps: EnvironmentMaps,
+    _brdf_texture: Tracked<wgpu::Texture>,
+    brdf_view: wgpu::TextureView,
+    sampler: Tracked<wgpu::Sampler>,
+    uniform_buffer: Tracked<wgpu::Buffer>,
+    pipelines: Option<IblPipelines>,
+    loaded: bool,
+}
+
+impl Environment {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Environment Bind Group Layout"),
+            entries: &[
+                Self::cube_layout_entry(0),
+                Self::cube_layout_entry(1),
+                Self::cube_layout_entry(2),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 5,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let sampler =
+            ResourceTracker::new(device, OWNER).create_sampler(&wgpu::SamplerDescriptor {
+                label: Some("Environment Sampler"),
+                address_mode_u: wgpu::AddressMode::ClampToEdge,
+                address_mode_v: wgpu::AddressMode::ClampToEdge,
+                address_mode_w: wgpu::AddressMode::ClampToEdge,
+                mag_filter: wgpu::FilterMode::Linear,
+                min_filter: wgpu::FilterMode::Linear,
+                mipmap_filter: wgpu::MipmapFilterMode::Linear,
+                ..Default::default()
+            });
+
+        let uniform_buffer =
+            ResourceTracker::new(device, OWNER).create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Environment Uniform Buffer"),
+                size: std::mem::size_of::<EnvironmentUniform>() as u64,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+
+        let pipelines = Self::supports_baking(device).then(|| IblPipelines::new(device));
+
+        let (brdf_texture, brdf_view) = match pipelines.as_ref() {
+            Some(pipelines) => pipelines.compute_brdf_lut(device, queue),
+            None => Self::solid_texture(device, queue, [255, 0, 0, 255], false),
+        };
+
+        let ambient = [102, 112, 128, 255];
+        let [environment, irradiance, prefiltered] =
+            [0, 1, 2].map(|_| Self::solid_texture(device, queue, ambient, true));
+        let maps = EnvironmentMaps {
+            _textures: [environment.0, irradiance.0, prefiltered.0],
+            environment_view: environment.1,
+            irradiance_view: irradiance.1,
+            prefiltered_view: prefiltered.1,
+            prefiltered_mip_count: 1,
+        };
+
+        let bind_group = Self::create_bind_group(
+            device,
+            &bind_group_layout,
+            &maps,
+            &brdf_view,
+            &sampler,
+            &uniform_buffer,
+        );
+
+        let environment = Self {
+            intensity: 1.0,
+            sky_intensity: 1.0,
+            bind_group_layout,
+            bind_group,
+            maps,
+            _brdf_texture: brdf_texture,
+            brdf_view,
+            sampler,
+            uniform_buffer,
+            pipelines,
+            loaded: false,
+        };
+        environment.update(queue);
+        environment
+    }
+
+    pub fn is_loaded(&self) -> bool {
+        self.loaded
+    }
+
+    pub fn supports_baking(device: &wgpu::Device) -> bool {
+        device.limits().max_compute_workgroups_per_dimension > 0
+            && device.adapter_info().backend != wgpu::Backend::Gl
+    }
+
+    pub fn update(&self, queue: &wgpu::Queue) {
+        let uniform = EnvironmentUniform {
+            intensity: self.intensity,
+            prefiltered_mip_count: self.maps.prefiltered_mip_count as f32,
+            sky_intensity: self.sky_intensity,
+            _padding: 0.0,
+        };
+        perf::write_buffer(
+            queue,
+            &self.uniform_buffer,
+            0,
+            bytemuck::cast_slice(&[uniform]),
+        );
+    }
+
+    pub fn load_hdr(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        bytes: &[u8],
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(pipelines) = self.pipelines.as_ref() else {
+            return Err(
+                "Image-based lighting requires compute shaders and cube storage textures".into(),
+            );
+        };
+
+        let image = image::load_from_memory_with_format(bytes, image::ImageFormat::Hdr)?;
+        let image = image.to_rgba32f();
+        let (width, height) = image.dimensions();
+        log::info!("Loaded HDR environment ({width} x {height})");
+
+        self.maps = pipelines.bake(device, queue, width, height, bytemuck::cast_slice(&image))?;
+        self.bind_group = Self::create_bind_group(
+            device,
+            &self.bind_group_layout,
+            &self.maps,
+            &self.brdf_view,
+            &self.sampler,
+            &self.uniform_buffer,
+        );
+        self.loaded = true;
+        self.update(queue);
+        Ok(())
+    }
+
+    fn cube_layout_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+        wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::Cube,
+                multisampled: false,
+            },
+            count: None,
+        }
+    }
+
+    fn solid_texture(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        color: [u8; 4],
+        cube: bool,
+    ) -> (Tracked<wgpu::Texture>, wgpu::TextureView) {
+        let layer_count = if cube { 6 } else { 1 };
+        let texture =
+            ResourceTracker::new(device, OWNER).create_texture(&wgpu::TextureDescriptor {
+                label: Some("Environment Default Texture"),
+                size: wgpu::Extent3d {
+                    width: 1,
+                    height: 1,
+                    depth_or_array_layers: layer_count,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba8Unorm,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                view_formats: &[],
+            });
+        let data: Vec<u8> = (0..layer_count).flat_map(|_| color).collect();
+        queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            &data,
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(4),
+                rows_per_image: Some(1),
+            },
+            wgpu::Extent3d {
+                width: 1,
+                height: 1,
+                depth_or_array_layers: layer_count,
+            },
+        );
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("Environment Default View"),
+            dimension: Some(if cube {
+                wgpu::TextureViewDimension::Cube
+            } else {
+                wgpu::TextureViewDimension::D2
+            }),
+            ..Default::default()
+        });
+        (texture, view)
+    }
+
+    fn create_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        maps: &EnvironmentMaps,
+        brdf_view: &wgpu::TextureView,
+        sampler: &wgpu::Sampler,
+        uniform_buffer: &wgpu::Buffer,
+    ) -> Tracked<wgpu::BindGroup> {
+        ResourceTracker::new(device, OWNER).create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Environment Bind Group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&maps.environment_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&maps.irradiance_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&maps.prefiltered_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(brdf_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+            ],
+        })
+    }
+}
+
+impl IblPipelines {
+    fn new(device: &wgpu::Device) -> Self {
+        let shader = device.create_shader_module(wgpu::include_wgsl!("ibl.wgsl"));
+
+        let storage_cube = wgpu::BindGroupLayoutEntry {
+            binding: 1,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::StorageTexture {
+                access: wgpu::StorageTextureAccess::WriteOnly,
+                format: ENVIRONMENT_FORMAT,
+                view_dimension: wgpu::TextureViewDimension::D2Array,
+            },
+            count: None,
+        };
+        let environment_cube = wgpu::BindGroupLayoutEntry {
+            binding: 3,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::Cube,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let environment_sampler = wgpu::BindGroupLayoutEntry {
+            binding: 4,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+            count: None,
+        };
+
+        let equirect_layout = Self::create_layout(
+            device,
+            "Equirect To Cube Layout",
+            &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                storage_cube,
+            ],
+        );
+        let downsample_layout = Self::create_layout(
+            device,
+            "Downsample Cube Layout",
+            &[
+                storage_cube,
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2Array,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+            ],
+        );
+        let irradiance_layout = Self::create_layout(
+            device,
+            "Irradiance Layout",
+            &[storage_cube, environment_cube, environment_sampler],
+        );
+        let prefilter_layout = Self::create_layout(
+            device,
+            "Prefilter Layout",
+            &[
+                storage_cube,
+                environment_cube,
+                environment_sampler,
+                wgpu::BindGroupLayoutEntry {
+                    binding: 5,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        );
+        let brdf_layout = Self::create_layout(
+            device,
+            "BRDF LUT Layout",
+            &[wgpu::BindGroupLayoutEntry {
+                binding: 6,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::StorageTexture {
+                    access: wgpu::StorageTextureAccess::WriteOnly,
+                    format: ENVIRONMENT_FORMAT,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                },
+                count: None,
+            }],
+        );
+
+        Self {
+            equirect_pipeline: Self::create_pipeline(
+                device,
+                &shader,
+                &equirect_layout,
+                "equirect_to_cube",
+            ),
+            downsample_pipeline: Self::create_pipeline(
+                device,
+                &shader,
+                &downsample_layout,
+                "downsample_cube",
+            ),
+            irradiance_pipeline: Self::create_pipeline(
+                device,
+                &shader,
+                &irradiance_layout,
+                "irradiance",
+            ),
+            prefilter_pipeline: Self::create_pipeline(
+                device,
+                &shader,
+                &prefilter_layout,
+                "prefilter_specular",
+            ),
+            brdf_pipeline: Self::create_pipeline(device, &shader, &brdf_layout, "brdf_lut"),
+            equirect_layout,
+            downsample_layout,
+            irradiance_layout,
+            prefilter_layout,
+            brdf_layout,
+        }
+    }
+
+    fn create_layout(
+        device: &wgpu::Device,
+        label: &str,
+        entries: &[wgpu::BindGroupLayoutEntry],
+    ) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some(label),
+            entries,
+        })
+    }
+
+    fn create_pipeline(
+        device: &wgpu::Device,
+        shader: &wgpu::ShaderModule,
+        layout: &wgpu::BindGroupLayout,
+        entry_point: &str,
+    ) -> Tracked<wgpu::ComputePipeline> {
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some(entry_point),
+            bind_group_layouts: &[Some(layout)],
+            immediate_size: 0,
+        });
+        ResourceTracker::new(device, OWNER).create_compute_pipeline(
+            &wgpu::ComputePipelineDescriptor {
+                label: Some(entry_point),
+                layout: Some(&pipeline_layout),
+                module: shader,
+                entry_point: Some(entry_point),
+                compilation_options: Default::default(),
+                cache: None,
+            },
+        )
+    }
+
+    fn create_cube(
+        device: &wgpu::Device,
+        label: &str,
+        size: u32,
+        mip_level_count: u32,
+    ) -> Tracked<wgpu::Texture> {
+        ResourceTracker::new(device, OWNER).create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width: size,
+                height: size,
+                depth_or_array_layers: 6,
+            },
+            mip_level_count,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: ENVIRONMENT_FORMAT,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::STORAGE_BINDING,
+            view_formats: &[],
+        })
+    }
+
+    fn cube_view(texture: &wgpu::Texture) -> wgpu::TextureView {
+        texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("Environment Cube View"),
+            dimension: Some(wgpu::TextureViewDimension::Cube),
+            ..Default::default()
+        })
+    }
+
+    fn mip_view(texture: &wgpu::Texture, mip_level: u32) -> wgpu::TextureView {
+        texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("Environment Mip View"),
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            base_mip_level: mip_level,
+            mip_level_count: Some(1),
+            base_array_layer: 0,
+            array_layer_count: Some(6),
+            ..Default::default()
+        })
+    }
+
+    fn dispatch(
+        encoder: &mut wgpu::CommandEncoder,
+        label: &str,
+        pipeline: &wgpu::ComputePipeline,
+        bind_group: &wgpu::BindGroup,
+        size: u32,
+        layers: u32,
+    ) {
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some(label),
+            timestamp_writes: None,
+        });
+        compute_pass.set_pipeline(pipeline);
+        compute_pass.set_bind_group(0, bind_group, &[]);
+        let groups = size.div_ceil(WORKGROUP_SIZE);
+        compute_pass.dispatch_workgroups(groups, groups, layers);
+    }
+
+    fn compute_brdf_lut(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> (Tracked<wgpu::Texture>, wgpu::TextureView) {
+        let texture =
+            ResourceTracker::new(device, OWNER).create_texture(&wgpu::TextureDescriptor {
+                label: Some("BRDF LUT"),
+                size: wgpu::Extent3d {
+                    width: BRDF_LUT_SIZE,
+                    height: BRDF_LUT_SIZE,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: ENVIRONMENT_FORMAT,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::STORAGE_BINDING,
+                view_formats: &[],
+            });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let bind_group =
+            ResourceTracker::new(device, OWNER).create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("BRDF LUT Bind Group"),
+                layout: &self.brdf_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 6,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                }],
+            });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("BRDF LUT Encoder"),
+        });
+        Self::dispatch(
+            &mut encoder,
+            "BRDF LUT Pass",
+            &self.brdf_pipeline,
+            &bind_group,
+            BRDF_LUT_SIZE,
+            1,
+        );
+        queue.submit(std::iter::once(encoder.finish()));
+
+        (texture, view)
+    }
+
+    fn bake(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        width: u32,
+        height: u32,
+        pixels: &[u8],
+    ) -> Result<EnvironmentMaps, Box<dyn std::error::Error>> {
+        let max_dimension = device.limits().max_texture_dimension_2d;
+        if width > max_dimension || height > max_dimension {
+            return Err(format!(
+                "HDR image ({width} x {height}) exceeds the maximum texture size of {max_dimension}"
+            )
+            .into());
+        }
+
+        let equirect_texture =
+            ResourceTracker::new(device, OWNER).create_texture(&wgpu::TextureDescriptor {
+                label: Some("Equirectangular Environment"),
+                size: wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba32Float,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                view_formats: &[],
+            });
+        queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture: &equirect_texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            pixels,
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(width * 16),
+                rows_per_image: Some(height),
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+        let equirect_view = equirect_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let environment_mip_count = ENVIRONMENT_SIZE.ilog2() + 1;
+        let environment_texture = Self::create_cube(
+            device,
+            "Environment Cube",
+            ENVIRONMENT_SIZE,
+            environment_mip_count,
+        );
+        let irradiance_texture = Self::create_cube(device, "Irradiance Cube", IRRADIANCE_SIZE, 1);
+        let prefiltered_texture = Self::create_cube(
+            device,
+            "Prefiltered Cube",
+            PREFILTERED_SIZE,
+            PREFILTERED_MIP_COUNT,
+        );
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Environment Bake Encoder"),
+        });
+
+        let base_view = Self::mip_view(&environment_texture, 0);
+        let equirect_bind_group =
+            ResourceTracker::new(device, OWNER).create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Equirect To Cube Bind Group"),
+                layout: &self.equirect_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&equirect_view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(&base_view),
+                    },
+                ],
+            });
+        Self::dispatch(
+            &mut encoder,
+            "Equirect To Cube Pass",
+            &self.equirect_pipeline,
+            &equirect_bind_group,
+            ENVIRONMENT_SIZE,
+            6,
+        );
+
+        for mip_level in 1..environment_mip_count {
+            let source_view = Self::mip_view(&environment_texture, mip_level - 1);
+            let target_view = Self::mip_view(&environment_texture, mip_level);
+            let bind_group =
+                ResourceTracker::new(device, OWNER).create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("Downsample Cube Bind Group"),
+                    layout: &self.downsample_layout,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: wgpu::BindingResource::TextureView(&target_view),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 2,
+                            resource: wgpu::BindingResource::TextureView(&source_view),
+                        },
+                    ],
+                });
+            Self::dispatch(
+                &mut encoder,
+                "Downsample Cube Pass",
+                &self.downsample_pipeline,
+                &bind_group,
+                (ENVIRONMENT_SIZE >> mip_level).max(1),
+                6,
+            );
+        }
+
+        let environment_view = Self::cube_view(&environment_texture);
+        let sampler =
+            ResourceTracker::new(device, OWNER).create_sampler(&wgpu::SamplerDescriptor {
+                label: Some("Environment Bake Sampler"),
+                mag_filter: wgpu::FilterMode::Linear,
+                min_filter: wgpu::FilterMode::Linear,
+                mipmap_filter: wgpu::MipmapFilterMode::Linear,
+                ..Default::default()
+            });
+
+        let irradiance_target = Self::mip_view(&irradiance_texture, 0);
+        let irradiance_bind_group =
+            ResourceTracker::new(device, OWNER).create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Irradiance Bind Group"),
+                layout: &self.irradiance_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(&irradiance_target),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: wgpu::BindingResource::TextureView(&environment_view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 4,
+                        resource: wgpu::BindingResource::Sampler(&sampler),
+                    },
+                ],
+            });
+        Self::dispatch(
+            &mut encoder,
+            "Irradiance Pass",
+            &self.irradiance_pipeline,
+            &irradiance_bind_group,
+            IRRADIANCE_SIZE,
+            6,
+        );
+
+        for mip_level in 0..PREFILTERED_MIP_COUNT {
+            let params = PrefilterParams {
+                roughness: mip_level as f32 / (PREFILTERED_MIP_COUNT - 1) as f32,
+                environment_size: ENVIRONMENT_SIZE as f32,
+                sample_count: PREFILTER_SAMPLE_COUNT,
+                _padding: 0,
+            };
+            let params_buffer = ResourceTracker::new(device, OWNER).create_buffer_init(
+                &wgpu::util::BufferInitDescriptor {
+                    label: Some("Prefilter Params Buffer"),
+                    contents: bytemuck::cast_slice(&[params]),
+                    usage: wgpu::BufferUsages::UNIFORM,
+                },
+            );
+            let target_view = Self::mip_view(&prefiltered_texture, mip_level);
+            let bind_group =
+                ResourceTracker::new(device, OWNER).create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("Prefilter Bind Group"),
+                    layout: &self.prefilter_layout,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: wgpu::BindingResource::TextureView(&target_view),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 3,
+                            resource: wgpu::BindingResource::TextureView(&environment_view),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 4,
+                            resource: wgpu::BindingResource::Sampler(&sampler),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 5,
+                            resource: params_buffer.as_entire_binding(),
+                        },
+                    ],
+                });
+            Self::dispatch(
+                &mut encoder,
+                "Prefilter Specular Pass",
+                &self.prefilter_pipeline,
+                &bind_group,
+                (PREFILTERED_SIZE >> mip_level).max(1),
+                6,
+            );
+        }
+
+        queue.submit(std::iter::once(encoder.finish()));
+
+        Ok(EnvironmentMaps {
+            environment_view,
+            irradiance_view: Self::cube_view(&irradiance_texture),
+            prefiltered_view: Self::cube_view(&prefiltered_texture),
+            _textures: [environment_texture, irradiance_texture, prefiltered_texture],
+            prefiltered_mip_count: PREFILTERED_MIP_COUNT,
+        })
+    }
+}
@@ -0,0 +1,219 @@
+const PI: f32 = 3.14159265359;
+
+struct PrefilterParams {
+    roughness: f32,
+    environment_size: f32,
+    sample_count: u32,
+    _padding: u32,
+};
+
+@group(0) @binding(0)
+var equirect_map: texture_2d<f32>;
+
+@group(0) @binding(1)
+var output_cube: texture_storage_2d_array<rgba16float, write>;
+
+@group(0) @binding(2)
+var source_cube: texture_2d_array<f32>;
+
+@group(0) @binding(3)
+var environment_cube: texture_cube<f32>;
+
+@group(0) @binding(4)
+var environment_sampler: sampler;
+
+@group(0) @binding(5)
+var<uniform> prefilter: PrefilterParams;
+
+@group(0) @binding(6)
+var brdf_output: texture_storage_2d<rgba16float, write>;
+
+fn cube_direction(face: u32, uv: vec2<f32>) -> vec3<f32> {
+    let st = uv * 2.0 - 1.0;
+    switch face {
+        case 0u: { return normalize(vec3<f32>(1.0, -st.y, -st.x)); }
+        case 1u: { return normalize(vec3<f32>(-1.0, -st.y, st.x)); }
+        case 2u: { return normalize(vec3<f32>(st.x, 1.0, st.y)); }
+        case 3u: { return normalize(vec3<f32>(st.x, -1.0, -st.y)); }
+        case 4u: { return normalize(vec3<f32>(st.x, -st.y, 1.0)); }
+        default: { return normalize(vec3<f32>(-st.x, -st.y, -1.0)); }
+    }
+}
+
+fn tangent_to_world(tangent: vec3<f32>, normal: vec3<f32>) -> vec3<f32> {
+    let up = select(vec3<f32>(0.0, 1.0, 0.0), vec3<f32>(0.0, 0.0, 1.0), abs(normal.y) > 0.999);
+    let right = normalize(cross(up, normal));
+    let bitangent = cross(normal, right);
+    return normalize(tangent.x * right + tangent.y * bitangent + tangent.z * normal);
+}
+
+fn hammersley(index: u32, count: u32) -> vec2<f32> {
+    return vec2<f32>(f32(index) / f32(count), f32(reverseBits(index)) * 2.3283064365386963e-10);
+}
+
+fn importance_sample_ggx(xi: vec2<f32>, normal: vec3<f32>, roughness: f32) -> vec3<f32> {
+    let a = roughness * roughness;
+    let phi = 2.0 * PI * xi.x;
+    let cos_theta = sqrt((1.0 - xi.y) / (1.0 + (a * a - 1.0) * xi.y));
+    let sin_theta = sqrt(1.0 - cos_theta * cos_theta);
+    let half_vector = vec3<f32>(cos(phi) * sin_theta, sin(phi) * sin_theta, cos_theta);
+    return tangent_to_world(half_vector, normal);
+}
+
+fn distribution_ggx(n_dot_h: f32, roughness: f32) -> f32 {
+    let a = roughness * roughness;
+    let a2 = a * a;
+    let denominator = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    return a2 / (PI * denominator * denominator);
+}
+
+fn geometry_schlick_ggx(n_dot_v: f32, roughness: f32) -> f32 {
+    let k = roughness * roughness / 2.0;
+    return n_dot_v / (n_dot_v * (1.0 - k) + k);
+}
+
+fn load_equirect(x: i32, y: i32) -> vec3<f32> {
+    return textureLoad(equirect_map, vec2<i32>(x, y), 0).rgb;
+}
+
+fn sample_equirect(direction: vec3<f32>) -> vec3<f32> {
+    let dimensions = vec2<i32>(textureDimensions(equirect_map));
+    let uv = vec2<f32>(
+        atan2(direction.z, direction.x) / (2.0 * PI) + 0.5,
+        acos(clamp(direction.y, -1.0, 1.0)) / PI
+    );
+    let coords = uv * vec2<f32>(dimensions) - 0.5;
+    let base = floor(coords);
+    let blend = coords - base;
+    let x0 = ((i32(base.x) % dimensions.x) + dimensions.x) % dimensions.x;
+    let x1 = (x0 + 1) % dimensions.x;
+    let y0 = clamp(i32(base.y), 0, dimensions.y - 1);
+    let y1 = clamp(i32(base.y) + 1, 0, dimensions.y - 1);
+    let top = mix(load_equirect(x0, y0), load_equirect(x1, y0), blend.x);
+    let bottom = mix(load_equirect(x0, y1), load_equirect(x1, y1), blend.x);
+    return mix(top, bottom, blend.y);
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn equirect_to_cube(@builtin(global_invocation_id) id: vec3<u32>) {
+    let size = textureDimensions(output_cube);
+    if (id.x >= size.x || id.y >= size.y) {
+        return;
+    }
+    let uv = (vec2<f32>(id.xy) + 0.5) / vec2<f32>(size);
+    let color = sample_equirect(cube_direction(id.z, uv));
+    textureStore(output_cube, id.xy, id.z, vec4<f32>(color, 1.0));
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn downsample_cube(@builtin(global_invocation_id) id: vec3<u32>) {
+    let size = textureDimensions(output_cube);
+    if (id.x >= size.x || id.y >= size.y) {
+        return;
+    }
+    let source = vec2<i32>(id.xy) * 2;
+    let face = i32(id.z);
+    let color = (
+        textureLoad(source_cube, source, face, 0) +
+        textureLoad(source_cube, source + vec2<i32>(1, 0), face, 0) +
+        textureLoad(source_cube, source + vec2<i32>(0, 1), face, 0) +
+        textureLoad(source_cube, source + vec2<i32>(1, 1), face, 0)
+    ) * 0.25;
+    textureStore(output_cube, id.xy, id.z, color);
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn irradiance(@builtin(global_invocation_id) id: vec3<u32>) {
+    let size = textureDimensions(output_cube);
+    if (id.x >= size.x || id.y >= size.y) {
+        return;
+    }
+    let uv = (vec2<f32>(id.xy) + 0.5) / vec2<f32>(size);
+    let normal = cube_direction(id.z, uv);
+    let sample_mip = max(log2(f32(textureDimensions(environment_cube).x) / 64.0), 0.0);
+
+    var total = vec3<f32>(0.0);
+    var sample_count = 0.0;
+    let step = 0.05;
+    for (var phi = 0.0; phi < 2.0 * PI; phi += step) {
+        for (var theta = 0.0; theta < 0.5 * PI; theta += step) {
+            let tangent = vec3<f32>(sin(theta) * cos(phi), sin(theta) * sin(phi), cos(theta));
+            let direction = tangent_to_world(tangent, normal);
+            let radiance = textureSampleLevel(environment_cube, environment_sampler, direction, sample_mip).rgb;
+            total += radiance * cos(theta) * sin(theta);
+            sample_count += 1.0;
+        }
+    }
+
+    textureStore(output_cube, id.xy, id.z, vec4<f32>(PI * total / sample_count, 1.0));
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn prefilter_specular(@builtin(global_invocation_id) id: vec3<u32>) {
+    let size = textureDimensions(output_cube);
+    if (id.x >= size.x || id.y >= size.y) {
+        return;
+    }
+    let uv = (vec2<f32>(id.xy) + 0.5) / vec2<f32>(size);
+    let normal = cube_direction(id.z, uv);
+    let roughness = prefilter.roughness;
+
+    if (roughness <= 0.0) {
+        let color = textureSampleLevel(environment_cube, environment_sampler, normal, 0.0).rgb;
+        textureStore(output_cube, id.xy, id.z, vec4<f32>(color, 1.0));
+        return;
+    }
+
+    let texel_solid_angle = 4.0 * PI / (6.0 * prefilter.environment_size * prefilter.environment_size);
+    var total = vec3<f32>(0.0);
+    var total_weight = 0.0;
+    for (var index = 0u; index < prefilter.sample_count; index++) {
+        let xi = hammersley(index, prefilter.sample_count);
+        let half_vector = importance_sample_ggx(xi, normal, roughness);
+        let light = normalize(2.0 * dot(normal, half_vector) * half_vector - normal);
+        let n_dot_l = dot(normal, light);
+        if (n_dot_l > 0.0) {
+            let n_dot_h = max(dot(normal, half_vector), 0.0);
+            let pdf = distribution_ggx(n_dot_h, roughness) * 0.25 + 0.0001;
+            let sample_solid_angle = 1.0 / (f32(prefilter.sample_count) * pdf + 0.0001);
+            let mip = max(0.5 * log2(sample_solid_angle / texel_solid_angle), 0.0);
+            total += textureSampleLevel(environment_cube, environment_sampler, light, mip).rgb * n_dot_l;
+            total_weight += n_dot_l;
+        }
+    }
+
+    textureStore(output_cube, id.xy, id.z, vec4<f32>(total / max(total_weight, 0.0001), 1.0));
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn brdf_lut(@builtin(global_invocation_id) id: vec3<u32>) {
+    let size = textureDimensions(brdf_output);
+    if (id.x >= size.x || id.y >= size.y) {
+        return;
+    }
+    let n_dot_v = (f32(id.x) + 0.5) / f32(size.x);
+    let roughness = (f32(id.y) + 0.5) / f32(size.y);
+    let view = vec3<f32>(sqrt(1.0 - n_dot_v * n_dot_v), 0.0, n_dot_v);
+    let normal = vec3<f32>(0.0, 0.0, 1.0);
+
+    var scale = 0.0;
+    var bias = 0.0;
+    let sample_count = 512u;
+    for (var index = 0u; index < sample_count; index++) {
+        let xi = hammersley(index, sample_count);
+        let half_vector = importance_sample_ggx(xi, normal, roughness);
+        let light = normalize(2.0 * dot(view, half_vector) * half_vector - view);
+        let n_dot_l = max(light.z, 0.0);
+        let n_dot_h = max(half_vector.z, 0.0);
+        let v_dot_h = max(dot(view, half_vector), 0.0);
+        if (n_dot_l > 0.0) {
+            let geometry = geometry_schlick_ggx(n_dot_v, roughness) * geometry_schlick_ggx(n_dot_l, roughness);
+            let visibility = geometry * v_dot_h / (n_dot_h * n_dot_v);
+            let fresnel = pow(1.0 - v_dot_h, 5.0);
+            scale += (1.0 - fresnel) * visibility;
+            bias += fresnel * visibility;
+        }
+    }
+
+    textureStore(brdf_output, id.xy, vec4<f32>(scale / f32(sample_count), bias / f32(sample_count), 0.0, 1.0));
+}
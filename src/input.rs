@@ -0,0 +1,553 @@
+use std::collections::HashMap;
+use winit::event::{ElementState, MouseButton, MouseScrollDelta, TouchPhase, WindowEvent};
+use winit::keyboard::{KeyCode, PhysicalKey};
+
+pub const INPUT_BINDINGS_PATH: &str = "input.json";
+pub const BUTTON_THRESHOLD: f32 = 0.5;
+
+const PIXELS_PER_LINE: f32 = 40.0;
+const TOUCH_ROTATE_SCALE: f32 = -200.0;
+const TOUCH_PINCH_SCALE: f32 = 10.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActionKind {
+    Button,
+    Axis,
+    Vector2,
+}
+
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Axis {
+    #[default]
+    X,
+    Y,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GamepadButton {
+    South,
+    East,
+    North,
+    West,
+    LeftBumper,
+    RightBumper,
+    LeftTrigger,
+    RightTrigger,
+    Select,
+    Start,
+    LeftStick,
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GamepadAxis {
+    LeftStickX,
+    LeftStickY,
+    RightStickX,
+    RightStickY,
+    LeftTrigger,
+    RightTrigger,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TouchGesture {
+    Tap,
+    DoubleTap,
+    LongPress,
+    DragX,
+    DragY,
+    PanX,
+    PanY,
+    Pinch,
+    Rotate,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InputSource {
+    Key(KeyCode),
+    MouseButton(MouseButton),
+    MouseMotion(Axis),
+    MouseWheel(Axis),
+    GamepadButton(GamepadButton),
+    GamepadAxis(GamepadAxis),
+    Touch(TouchGesture),
+    Xr(String),
+}
+
+impl InputSource {
+    pub fn is_relative(&self) -> bool {
+        matches!(
+            self,
+            Self::MouseMotion(_) | Self::MouseWheel(_) | Self::Touch(_)
+        )
+    }
+
+    pub fn is_pointer(&self) -> bool {
+        matches!(
+            self,
+            Self::MouseButton(_) | Self::MouseMotion(_) | Self::MouseWheel(_) | Self::Touch(_)
+        )
+    }
+
+    pub fn is_keyboard(&self) -> bool {
+        matches!(self, Self::Key(_))
+    }
+}
+
+fn default_scale() -> f32 {
+    1.0
+}
+
+fn is_default_scale(scale: &f32) -> bool {
+    *scale == 1.0
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ActionBinding {
+    pub source: InputSource,
+    #[serde(default)]
+    pub axis: Axis,
+    #[serde(default = "default_scale", skip_serializing_if = "is_default_scale")]
+    pub scale: f32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modifier: Option<InputSource>,
+}
+
+impl ActionBinding {
+    pub fn new(source: InputSource) -> Self {
+        Self {
+            source,
+            axis: Axis::X,
+            scale: 1.0,
+            modifier: None,
+        }
+    }
+
+    pub fn axis(mut self, axis: Axis, scale: f32) -> Self {
+        self.axis = axis;
+        self.scale = scale;
+        self
+    }
+
+    pub fn modifier(mut self, modifier: InputSource) -> Self {
+        self.modifier = Some(modifier);
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ActionDefinition {
+    pub name: String,
+    pub kind: ActionKind,
+    #[serde(default)]
+    pub bindings: Vec<ActionBinding>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct InputMap {
+    pub actions: Vec<ActionDefinition>,
+}
+
+impl InputMap {
+    pub fn with_defaults() -> Self {
+        let key = |key_code| ActionBinding::new(InputSource::Key(key_code));
+        let xr = |name: &str| ActionBinding::new(InputSource::Xr(name.to_string()));
+        let stick = |axis| ActionBinding::new(InputSource::GamepadAxis(axis));
+        let touch = |gesture| ActionBinding::new(InputSource::Touch(gesture));
+        let orbit = InputSource::MouseButton(MouseButton::Right);
+        let mut map = Self::default();
+        map.define("exit", ActionKind::Button, vec![key(KeyCode::Escape)]);
+        map.define(
+            "move",
+            ActionKind::Vector2,
+            vec![
+                key(KeyCode::KeyD),
+                key(KeyCode::KeyA).axis(Axis::X, -1.0),
+                key(KeyCode::KeyW).axis(Axis::Y, 1.0),
+                key(KeyCode::KeyS).axis(Axis::Y, -1.0),
+                xr("move_x"),
+                xr("move_y").axis(Axis::Y, 1.0),
+                stick(GamepadAxis::LeftStickX),
+                stick(GamepadAxis::LeftStickY).axis(Axis::Y, 1.0),
+            ],
+        );
+        map.define(
+            "look",
+            ActionKind::Vector2,
+            vec![
+                ActionBinding::new(InputSource::MouseMotion(Axis::X)).modifier(orbit.clone()),
+                ActionBinding::new(InputSource::MouseMotion(Axis::Y))
+                    .axis(Axis::Y, 1.0)
+                    .modifier(orbit),
+                touch(TouchGesture::DragX),
+                touch(TouchGesture::DragY).axis(Axis::Y, 1.0),
+                touch(TouchGesture::Rotate).axis(Axis::X, TOUCH_ROTATE_SCALE),
+            ],
+        );
+        map.define(
+            "zoom",
+            ActionKind::Axis,
+            vec![
+                ActionBinding::new(InputSource::MouseWheel(Axis::Y)),
+                touch(TouchGesture::Pinch).axis(Axis::X, TOUCH_PINCH_SCALE),
+            ],
+        );
+        map.define(
+            "pan",
+            ActionKind::Vector2,
+            vec![
+                touch(TouchGesture::PanX),
+                touch(TouchGesture::PanY).axis(Axis::Y, 1.0),
+            ],
+        );
+        map.define(
+            "orbit",
+            ActionKind::Vector2,
+            vec![
+                stick(GamepadAxis::RightStickX),
+                stick(GamepadAxis::RightStickY).axis(Axis::Y, 1.0),
+            ],
+        );
+        map.define(
+            "dolly",
+            ActionKind::Axis,
+            vec![
+                stick(GamepadAxis::RightTrigger),
+                stick(GamepadAxis::LeftTrigger).axis(Axis::X, -1.0),
+            ],
+        );
+        map.define("left_trigger", ActionKind::Button, vec![xr("left_trigger")]);
+        map.define(
+            "right_trigger",
+            ActionKind::Button,
+            vec![xr("right_trigger")],
+        );
+        map
+    }
+
+    pub fn define(&mut self, name: &str, kind: ActionKind, bindings: Vec<ActionBinding>) {
+        match self.action_mut(name) {
+            Some(action) => {
+                action.kind = kind;
+                action.bindings = bindings;
+            }
+            None => self.actions.push(ActionDefinition {
+                name: name.to_string(),
+                kind,
+                bindings,
+            }),
+        }
+    }
+
+    pub fn action(&self, name: &str) -> Option<&ActionDefinition> {
+        self.actions.iter().find(|action| action.name == name)
+    }
+
+    pub fn action_mut(&mut self, name: &str) -> Option<&mut ActionDefinition> {
+        self.actions.iter_mut().find(|action| action.name == name)
+    }
+
+    pub fn bind(&mut self, name: &str, binding: ActionBinding) -> Result<(), String> {
+        let action = self
+            .action_mut(name)
+            .ok_or_else(|| format!("Unknown action '{name}'"))?;
+        if !action.bindings.contains(&binding) {
+            action.bindings.push(binding);
+        }
+        Ok(())
+    }
+
+    pub fn unbind(&mut self, name: &str, source: &InputSource) -> Result<(), String> {
+        let action = self
+            .action_mut(name)
+            .ok_or_else(|| format!("Unknown action '{name}'"))?;
+        action.bindings.retain(|binding| binding.source != *source);
+        Ok(())
+    }
+
+    pub fn merge(&mut self, other: Self) {
+        for action in other.actions {
+            self.define(&action.name, action.kind, action.bindings);
+        }
+    }
+
+    pub fn from_json(source: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(serde_json::from_str(source)?)
+    }
+
+    pub fn to_json(&self) -> Result<String, Box<dyn std::error::Error>> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut map = Self::with_defaults();
+        map.merge(Self::from_json(&std::fs::read_to_string(path)?)?);
+        log::info!("Loaded input bindings from {path}");
+        Ok(map)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        std::fs::write(path, self.to_json()?)?;
+        log::info!("Saved input bindings to {path}");
+        Ok(())
+    }
+
+    pub fn load_or_default() -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
+        if std::path::Path::new(INPUT_BINDINGS_PATH).exists() {
+            match Self::load(INPUT_BINDINGS_PATH) {
+                Ok(map) => return map,
+                Err(error) => log::error!("Failed to load {INPUT_BINDINGS_PATH}: {error}"),
+            }
+        }
+        Self::with_defaults()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InputEvent {
+    Key {
+        key: KeyCode,
+        pressed: bool,
+    },
+    MouseButton {
+        button: MouseButton,
+        pressed: bool,
+    },
+    CursorMoved {
+        x: f64,
+        y: f64,
+    },
+    CursorLeft,
+    MouseWheel {
+        x: f32,
+        y: f32,
+    },
+    Touch {
+        id: u64,
+        phase: TouchPhase,
+        x: f64,
+        y: f64,
+    },
+    Focused(bool),
+}
+
+impl InputEvent {
+    pub fn from_window_event(event: &WindowEvent) -> Option<Self> {
+        Some(match event {
+            WindowEvent::KeyboardInput { event, .. } => match event.physical_key {
+                PhysicalKey::Code(key) => Self::Key {
+                    key,
+                    pressed: event.state == ElementState::Pressed,
+                },
+                PhysicalKey::Unidentified(_) => return None,
+            },
+            WindowEvent::MouseInput { state, button, .. } => Self::MouseButton {
+                button: *button,
+                pressed: *state == ElementState::Pressed,
+            },
+            WindowEvent::CursorMoved { position, .. } => Self::CursorMoved {
+                x: position.x,
+                y: position.y,
+            },
+            WindowEvent::CursorLeft { .. } => Self::CursorLeft,
+            WindowEvent::MouseWheel { delta, .. } => {
+                let (x, y) = match *delta {
+                    MouseScrollDelta::LineDelta(x, y) => (x, y),
+                    MouseScrollDelta::PixelDelta(position) => (
+                        position.x as f32 / PIXELS_PER_LINE,
+                        position.y as f32 / PIXELS_PER_LINE,
+                    ),
+                };
+                Self::MouseWheel { x, y }
+            }
+            WindowEvent::Touch(touch) => Self::Touch {
+                id: touch.id,
+                phase: touch.phase,
+                x: touch.location.x,
+                y: touch.location.y,
+            },
+            WindowEvent::Focused(focused) => Self::Focused(*focused),
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ActionState {
+    pub value: nalgebra_glm::Vec2,
+    pub previous: nalgebra_glm::Vec2,
+}
+
+impl ActionState {
+    pub fn is_down(&self) -> bool {
+        self.value.x.abs() >= BUTTON_THRESHOLD
+    }
+
+    pub fn was_down(&self) -> bool {
+        self.previous.x.abs() >= BUTTON_THRESHOLD
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Actions {
+    map: InputMap,
+    sources: HashMap<InputSource, f32>,
+    deltas: HashMap<InputSource, f32>,
+    states: HashMap<String, ActionState>,
+    cursor: Option<(f64, f64)>,
+    pub pointer_enabled: bool,
+    pub keyboard_enabled: bool,
+}
+
+impl Actions {
+    pub fn new(map: InputMap) -> Self {
+        Self {
+            map,
+            pointer_enabled: true,
+            keyboard_enabled: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn map(&self) -> &InputMap {
+        &self.map
+    }
+
+    pub fn set_map(&mut self, map: InputMap) {
+        self.map = map;
+        self.states.clear();
+    }
+
+    pub fn set_source(&mut self, source: InputSource, value: f32) {
+        if source.is_relative() {
+            *self.deltas.entry(source).or_default() += value;
+        } else if value == 0.0 {
+            self.sources.remove(&source);
+        } else {
+            self.sources.insert(source, value);
+        }
+    }
+
+    pub fn source(&self, source: &InputSource) -> f32 {
+        let enabled = (self.pointer_enabled || !source.is_pointer())
+            && (self.keyboard_enabled || !source.is_keyboard());
+        if !enabled {
+            return 0.0;
+        }
+        self.sources
+            .get(source)
+            .or_else(|| self.deltas.get(source))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn release_all(&mut self) {
+        self.sources.clear();
+        self.deltas.clear();
+        self.cursor = None;
+    }
+
+    pub fn handle_window_event(&mut self, event: &WindowEvent) {
+        if let Some(event) = InputEvent::from_window_event(event) {
+            self.handle_input_event(&event);
+        }
+    }
+
+    pub fn handle_input_event(&mut self, event: &InputEvent) {
+        match *event {
+            InputEvent::Key { key, pressed } => {
+                self.set_source(InputSource::Key(key), f32::from(u8::from(pressed)));
+            }
+            InputEvent::MouseButton { button, pressed } => {
+                self.set_source(
+                    InputSource::MouseButton(button),
+                    f32::from(u8::from(pressed)),
+                );
+            }
+            InputEvent::CursorMoved { x, y } => {
+                if let Some((previous_x, previous_y)) = self.cursor {
+                    self.set_source(InputSource::MouseMotion(Axis::X), (x - previous_x) as f32);
+                    self.set_source(InputSource::MouseMotion(Axis::Y), (y - previous_y) as f32);
+                }
+                self.cursor = Some((x, y));
+            }
+            InputEvent::CursorLeft => self.cursor = None,
+            InputEvent::MouseWheel { x, y } => {
+                self.set_source(InputSource::MouseWheel(Axis::X), x);
+                self.set_source(InputSource::MouseWheel(Axis::Y), y);
+            }
+            InputEvent::Focused(false) => self.release_all(),
+            InputEvent::Touch { .. } | InputEvent::Focused(true) => (),
+        }
+    }
+
+    pub fn update(&mut self) {
+        for action in &self.map.actions {
+            let mut value = nalgebra_glm::Vec2::zeros();
+            for binding in &action.bindings {
+                if let Some(modifier) = &binding.modifier
+                    && self.source(modifier).abs() < BUTTON_THRESHOLD
+                {
+                    continue;
+                }
+                let input = self.source(&binding.source) * binding.scale;
+                match binding.axis {
+                    Axis::X => value.x += input,
+                    Axis::Y => value.y += input,
+                }
+            }
+            if action.kind != ActionKind::Vector2 {
+                value.y = 0.0;
+            }
+            if action.kind == ActionKind::Button {
+                value.x = value.x.clamp(-1.0, 1.0);
+            }
+            let state = self.states.entry(action.name.clone()).or_default();
+            state.previous = state.value;
+            state.value = value;
+        }
+        self.deltas.clear();
+    }
+
+    pub fn state(&self, name: &str) -> ActionState {
+        self.states.get(name).copied().unwrap_or_default()
+    }
+
+    pub fn down(&self, name: &str) -> bool {
+        self.state(name).is_down()
+    }
+
+    pub fn pressed(&self, name: &str) -> bool {
+        let state = self.state(name);
+        state.is_down() && !state.was_down()
+    }
+
+    pub fn released(&self, name: &str) -> bool {
+        let state = self.state(name);
+        !state.is_down() && state.was_down()
+    }
+
+    pub fn axis(&self, name: &str) -> f32 {
+        self.state(name).value.x
+    }
+
+    pub fn vector2(&self, name: &str) -> nalgebra_glm::Vec2 {
+        self.state(name).value
+    }
+}
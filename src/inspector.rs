@@ -0,0 +1,458 @@
+use crate::history::{ComponentEdit, History};
+use crate::instancing::InstanceData;
+use crate::shadows::DirectionalLight;
+use crate::{Camera, INDICES, Material, Scene, SceneObject};
+
+const DRAG_SPEED: f32 = 0.01;
+
+pub trait Inspect {
+    fn inspect(&mut self, ui: &mut egui::Ui) -> bool;
+}
+
+impl Inspect for f32 {
+    fn inspect(&mut self, ui: &mut egui::Ui) -> bool {
+        ui.add(egui::DragValue::new(self).speed(DRAG_SPEED))
+            .changed()
+    }
+}
+
+impl Inspect for bool {
+    fn inspect(&mut self, ui: &mut egui::Ui) -> bool {
+        ui.checkbox(self, "").changed()
+    }
+}
+
+impl Inspect for nalgebra_glm::Vec3 {
+    fn inspect(&mut self, ui: &mut egui::Ui) -> bool {
+        inspect_components(ui, self.as_mut_slice(), &["X", "Y", "Z"])
+    }
+}
+
+impl Inspect for nalgebra_glm::Vec4 {
+    fn inspect(&mut self, ui: &mut egui::Ui) -> bool {
+        inspect_components(ui, self.as_mut_slice(), &["X", "Y", "Z", "W"])
+    }
+}
+
+fn inspect_components(ui: &mut egui::Ui, values: &mut [f32], labels: &[&str]) -> bool {
+    ui.horizontal(|ui| {
+        let mut changed = false;
+        for (value, label) in values.iter_mut().zip(labels) {
+            changed |= ui
+                .add(
+                    egui::DragValue::new(value)
+                        .speed(DRAG_SPEED)
+                        .prefix(format!("{label} ")),
+                )
+                .changed();
+        }
+        changed
+    })
+    .inner
+}
+
+pub fn property(ui: &mut egui::Ui, label: &str, value: &mut impl Inspect) -> bool {
+    ui.label(label);
+    let changed = value.inspect(ui);
+    ui.end_row();
+    changed
+}
+
+pub fn slider_property(
+    ui: &mut egui::Ui,
+    label: &str,
+    value: &mut f32,
+    range: std::ops::RangeInclusive<f32>,
+) -> bool {
+    ui.label(label);
+    let changed = ui.add(egui::Slider::new(value, range)).changed();
+    ui.end_row();
+    changed
+}
+
+pub fn color_property(ui: &mut egui::Ui, label: &str, color: &mut nalgebra_glm::Vec4) -> bool {
+    ui.label(label);
+    let mut rgba = [color.x, color.y, color.z, color.w];
+    let changed = ui.color_edit_button_rgba_unmultiplied(&mut rgba).changed();
+    *color = nalgebra_glm::Vec4::from(rgba);
+    ui.end_row();
+    changed
+}
+
+pub fn read_only_property(ui: &mut egui::Ui, label: &str, value: impl Into<egui::WidgetText>) {
+    ui.label(label);
+    ui.label(value);
+    ui.end_row();
+}
+
+pub trait Component {
+    type Value: Clone + PartialEq + 'static;
+
+    fn name(&self) -> &str;
+
+    fn get(&self, scene: &Scene, object: Option<SceneObject>) -> Option<Self::Value>;
+
+    fn set(scene: &mut Scene, object: Option<SceneObject>, value: &Self::Value);
+
+    fn inspect(&mut self, ui: &mut egui::Ui, value: &mut Self::Value) -> bool;
+}
+
+trait ComponentPanel {
+    fn show(&mut self, ui: &mut egui::Ui, scene: &mut Scene, history: &mut History);
+}
+
+impl<C: Component> ComponentPanel for C {
+    fn show(&mut self, ui: &mut egui::Ui, scene: &mut Scene, history: &mut History) {
+        let object = scene.selection.primary();
+        let Some(mut value) = self.get(scene, object) else {
+            return;
+        };
+        let name = self.name().to_string();
+        let editable = !object.is_some_and(|object| scene.is_locked(object));
+        let before = value.clone();
+        let mut changed = false;
+        egui::CollapsingHeader::new(&name)
+            .id_salt(("Component", &name))
+            .default_open(true)
+            .show(ui, |ui| {
+                ui.add_enabled_ui(editable, |ui| {
+                    egui::Grid::new(("Component Grid", &name))
+                        .num_columns(2)
+                        .striped(true)
+                        .show(ui, |ui| {
+                            changed = self.inspect(ui, &mut value);
+                        });
+                });
+            });
+        if changed && value != before {
+            C::set(scene, object, &value);
+            history.push(ComponentEdit::new(
+                format!("Edit {name}"),
+                object,
+                before,
+                value,
+                C::set,
+            ));
+        }
+    }
+}
+
+pub struct Inspector {
+    components: Vec<Box<dyn ComponentPanel>>,
+}
+
+impl Default for Inspector {
+    fn default() -> Self {
+        let mut inspector = Self {
+            components: Vec::new(),
+        };
+        inspector.register(TransformComponent::default());
+        inspector.register(MeshComponent);
+        inspector.register(MaterialComponent);
+        inspector.register(LightComponent);
+        inspector.register(CameraComponent);
+        inspector
+    }
+}
+
+impl Inspector {
+    pub fn register(&mut self, component: impl Component + 'static) {
+        self.components.push(Box::new(component));
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui, scene: &mut Scene, history: &mut History) {
+        for component in &mut self.components {
+            component.show(ui, scene, history);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum RotationMode {
+    Euler,
+    Quaternion,
+}
+
+impl RotationMode {
+    pub const ALL: [Self; 2] = [Self::Euler, Self::Quaternion];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Euler => "Euler",
+            Self::Quaternion => "Quaternion",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub translation: nalgebra_glm::Vec3,
+    pub rotation: nalgebra_glm::Quat,
+    pub scale: nalgebra_glm::Vec3,
+}
+
+impl Transform {
+    pub fn from_matrix(matrix: &nalgebra_glm::Mat4) -> Self {
+        let scale = nalgebra_glm::vec3(
+            nalgebra_glm::length(&matrix.column(0).xyz()),
+            nalgebra_glm::length(&matrix.column(1).xyz()),
+            nalgebra_glm::length(&matrix.column(2).xyz()),
+        );
+        let mut rotation = nalgebra_glm::mat4_to_mat3(matrix);
+        for (mut column, scale) in rotation.column_iter_mut().zip(scale.iter()) {
+            if *scale > f32::EPSILON {
+                column /= *scale;
+            }
+        }
+        Self {
+            translation: matrix.column(3).xyz(),
+            rotation: nalgebra_glm::mat3_to_quat(&rotation),
+            scale,
+        }
+    }
+
+    pub fn to_matrix(&self) -> nalgebra_glm::Mat4 {
+        nalgebra_glm::translation(&self.translation)
+            * nalgebra_glm::quat_to_mat4(&self.rotation)
+            * nalgebra_glm::scaling(&self.scale)
+    }
+
+    pub fn euler_angles(&self) -> nalgebra_glm::Vec3 {
+        let angles = nalgebra_glm::quat_euler_angles(&self.rotation);
+        nalgebra_glm::vec3(angles.z, angles.y, angles.x)
+    }
+
+    pub fn set_euler_angles(&mut self, angles: &nalgebra_glm::Vec3) {
+        let identity = nalgebra_glm::quat_identity();
+        self.rotation = nalgebra_glm::quat_rotate(&identity, angles.z, &nalgebra_glm::Vec3::z())
+            * nalgebra_glm::quat_rotate(&identity, angles.y, &nalgebra_glm::Vec3::y())
+            * nalgebra_glm::quat_rotate(&identity, angles.x, &nalgebra_glm::Vec3::x());
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransformComponent {
+    pub rotation_mode: RotationMode,
+}
+
+impl Default for TransformComponent {
+    fn default() -> Self {
+        Self {
+            rotation_mode: RotationMode::Euler,
+        }
+    }
+}
+
+impl Component for TransformComponent {
+    type Value = Transform;
+
+    fn name(&self) -> &str {
+        "Transform"
+    }
+
+    fn get(&self, scene: &Scene, object: Option<SceneObject>) -> Option<Transform> {
+        scene
+            .object_transform(object?)
+            .map(|matrix| Transform::from_matrix(&matrix))
+    }
+
+    fn set(scene: &mut Scene, object: Option<SceneObject>, value: &Transform) {
+        if let Some(object) = object {
+            scene.set_object_transform(object, &value.to_matrix());
+        }
+    }
+
+    fn inspect(&mut self, ui: &mut egui::Ui, value: &mut Transform) -> bool {
+        let mut changed = property(ui, "Position", &mut value.translation);
+
+        ui.label("Rotation Mode");
+        ui.horizontal(|ui| {
+            for mode in RotationMode::ALL {
+                ui.selectable_value(&mut self.rotation_mode, mode, mode.label());
+            }
+        });
+        ui.end_row();
+
+        match self.rotation_mode {
+            RotationMode::Euler => {
+                let mut degrees = value.euler_angles().map(f32::to_degrees);
+                if property(ui, "Rotation", &mut degrees) {
+                    value.set_euler_angles(&degrees.map(f32::to_radians));
+                    changed = true;
+                }
+            }
+            RotationMode::Quaternion => {
+                let mut coords = value.rotation.coords;
+                if property(ui, "Rotation", &mut coords)
+                    && nalgebra_glm::length(&coords) > f32::EPSILON
+                {
+                    value.rotation =
+                        nalgebra_glm::quat_normalize(&nalgebra_glm::Quat::from(coords));
+                    changed = true;
+                }
+            }
+        }
+
+        changed |= property(ui, "Scale", &mut value.scale);
+        changed
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeshInfo {
+    pub source: String,
+    pub triangles: u32,
+    pub color: Option<nalgebra_glm::Vec4>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MeshComponent;
+
+impl Component for MeshComponent {
+    type Value = MeshInfo;
+
+    fn name(&self) -> &str {
+        "Mesh"
+    }
+
+    fn get(&self, scene: &Scene, object: Option<SceneObject>) -> Option<MeshInfo> {
+        match object? {
+            SceneObject::Mesh => Some(MeshInfo {
+                source: "Triangle".to_string(),
+                triangles: INDICES.len() as u32 / 3,
+                color: None,
+            }),
+            SceneObject::Instance { batch, instance } => {
+                let batch = scene.batches.get(batch)?;
+                let data = batch.get(instance)?;
+                Some(MeshInfo {
+                    source: batch.name.clone(),
+                    triangles: batch.index_count / 3,
+                    color: Some(nalgebra_glm::Vec4::from(data.color)),
+                })
+            }
+        }
+    }
+
+    fn set(scene: &mut Scene, object: Option<SceneObject>, value: &MeshInfo) {
+        if let (Some(SceneObject::Instance { batch, instance }), Some(color)) =
+            (object, value.color)
+            && let Some(batch) = scene.batches.get_mut(batch)
+            && let Some(data) = batch.get(instance)
+        {
+            let data = InstanceData {
+                color: color.into(),
+                ..*data
+            };
+            batch.update(&[(instance, data)]);
+        }
+    }
+
+    fn inspect(&mut self, ui: &mut egui::Ui, value: &mut MeshInfo) -> bool {
+        read_only_property(ui, "Source", &value.source);
+        read_only_property(ui, "Triangles", value.triangles.to_string());
+        match &mut value.color {
+            Some(color) => color_property(ui, "Color", color),
+            None => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MaterialComponent;
+
+impl Component for MaterialComponent {
+    type Value = Material;
+
+    fn name(&self) -> &str {
+        "Material"
+    }
+
+    fn get(&self, scene: &Scene, object: Option<SceneObject>) -> Option<Material> {
+        (object == Some(SceneObject::Mesh)).then_some(scene.material)
+    }
+
+    fn set(scene: &mut Scene, _object: Option<SceneObject>, value: &Material) {
+        scene.material = *value;
+    }
+
+    fn inspect(&mut self, ui: &mut egui::Ui, value: &mut Material) -> bool {
+        let mut changed = color_property(ui, "Base Color", &mut value.base_color);
+        changed |= slider_property(ui, "Metallic", &mut value.metallic, 0.0..=1.0);
+        changed |= slider_property(ui, "Roughness", &mut value.roughness, 0.0..=1.0);
+        changed
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LightComponent;
+
+impl Component for LightComponent {
+    type Value = DirectionalLight;
+
+    fn name(&self) -> &str {
+        "Light"
+    }
+
+    fn get(&self, scene: &Scene, _object: Option<SceneObject>) -> Option<DirectionalLight> {
+        Some(scene.light)
+    }
+
+    fn set(scene: &mut Scene, _object: Option<SceneObject>, value: &DirectionalLight) {
+        scene.light = *value;
+    }
+
+    fn inspect(&mut self, ui: &mut egui::Ui, value: &mut DirectionalLight) -> bool {
+        let mut changed = false;
+        let mut direction = value.direction;
+        if property(ui, "Direction", &mut direction)
+            && nalgebra_glm::length(&direction) > f32::EPSILON
+        {
+            value.direction = nalgebra_glm::normalize(&direction);
+            changed = true;
+        }
+        ui.label("Color");
+        let mut rgb = [value.color.x, value.color.y, value.color.z];
+        if ui.color_edit_button_rgb(&mut rgb).changed() {
+            value.color = nalgebra_glm::Vec3::from(rgb);
+            changed = true;
+        }
+        ui.end_row();
+        changed |= slider_property(ui, "Intensity", &mut value.intensity, 0.0..=10.0);
+        changed
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CameraComponent;
+
+impl Component for CameraComponent {
+    type Value = Camera;
+
+    fn name(&self) -> &str {
+        "Camera"
+    }
+
+    fn get(&self, scene: &Scene, _object: Option<SceneObject>) -> Option<Camera> {
+        Some(scene.camera)
+    }
+
+    fn set(scene: &mut Scene, _object: Option<SceneObject>, value: &Camera) {
+        scene.camera = *value;
+    }
+
+    fn inspect(&mut self, ui: &mut egui::Ui, value: &mut Camera) -> bool {
+        let mut changed = property(ui, "Position", &mut value.position);
+        changed |= property(ui, "Target", &mut value.target);
+        let mut fov = value.fov_y.to_degrees();
+        if slider_property(ui, "Field of View", &mut fov, 10.0..=150.0) {
+            value.fov_y = fov.to_radians();
+            changed = true;
+        }
+        changed |= property(ui, "Near", &mut value.near);
+        changed |= property(ui, "Far", &mut value.far);
+        value.near = value.near.max(0.001);
+        value.far = value.far.max(value.near + 0.001);
+        changed
+    }
+}
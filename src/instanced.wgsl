@@ -0,0 +1,51 @@
+struct Uniform {
+    mvp: mat4x4<f32>,
+    model: mat4x4<f32>,
+    view_projection: mat4x4<f32>,
+    camera_position: vec4<f32>,
+    base_color: vec4<f32>,
+    material: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> ubo: Uniform;
+
+struct VertexInput {
+    @location(0) position: vec4<f32>,
+    @location(1) color: vec4<f32>,
+};
+
+struct InstanceInput {
+    @location(2) model_0: vec4<f32>,
+    @location(3) model_1: vec4<f32>,
+    @location(4) model_2: vec4<f32>,
+    @location(5) model_3: vec4<f32>,
+    @location(6) color: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+    @location(1) world_pos: vec3<f32>,
+};
+
+@vertex
+fn vertex_main(vert: VertexInput, instance: InstanceInput) -> VertexOutput {
+    let model = mat4x4<f32>(instance.model_0, instance.model_1, instance.model_2, instance.model_3);
+    let world_position = model * vert.position;
+    var out: VertexOutput;
+    out.color = vert.color * instance.color;
+    out.position = ubo.view_projection * world_position;
+    out.world_pos = world_position.xyz;
+    return out;
+}
+
+@fragment
+fn fragment_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let view_dir = normalize(ubo.camera_position.xyz - in.world_pos);
+    let face_normal = normalize(cross(dpdx(in.world_pos), dpdy(in.world_pos)));
+    let normal = select(-face_normal, face_normal, dot(face_normal, view_dir) > 0.0);
+    let albedo = in.color.rgb * ubo.base_color.rgb;
+    let lit = pbr_lighting(albedo, ubo.material.x, ubo.material.y, normal, view_dir, in.world_pos);
+    return vec4<f32>(shadow_debug_tint(lit, in.world_pos), in.color.a * ubo.base_color.a);
+}
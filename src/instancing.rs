@@ -0,0 +1,485 @@
+use crate::bounds::{Aabb, Ray};
+use crate::perf;
+use crate::resources::{ResourceTracker, Tracked};
+
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+const OWNER: &str = "Instancing";
+
+pub const INSTANCED_SHADER_SOURCE: &str = include_str!("instanced.wgsl");
+
+pub const STRESS_TEST_INSTANCES: usize = 10_000;
+
+const INITIAL_CAPACITY: usize = 64;
+
+static NEXT_REVISION: AtomicU64 = AtomicU64::new(1);
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct InstanceData {
+    pub model: [[f32; 4]; 4],
+    pub color: [f32; 4],
+}
+
+impl Default for InstanceData {
+    fn default() -> Self {
+        Self::new(
+            &nalgebra_glm::Mat4::identity(),
+            nalgebra_glm::vec4(1.0, 1.0, 1.0, 1.0),
+        )
+    }
+}
+
+impl InstanceData {
+    pub fn new(model: &nalgebra_glm::Mat4, color: nalgebra_glm::Vec4) -> Self {
+        Self {
+            model: (*model).into(),
+            color: color.into(),
+        }
+    }
+
+    pub fn vertex_attributes() -> Vec<wgpu::VertexAttribute> {
+        wgpu::vertex_attr_array![
+            2 => Float32x4,
+            3 => Float32x4,
+            4 => Float32x4,
+            5 => Float32x4,
+            6 => Float32x4
+        ]
+        .to_vec()
+    }
+
+    pub fn description(attributes: &[wgpu::VertexAttribute]) -> wgpu::VertexBufferLayout<'_> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<InstanceData>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InstanceId {
+    index: u32,
+    generation: u32,
+}
+
+impl InstanceId {
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+}
+
+#[derive(Default, Clone, Copy)]
+struct InstanceSlot {
+    generation: u32,
+    dense_index: Option<usize>,
+    revision: u64,
+}
+
+pub struct InstanceBatch {
+    pub name: String,
+    pub vertex_buffer: Tracked<wgpu::Buffer>,
+    pub index_buffer: Tracked<wgpu::Buffer>,
+    pub index_count: u32,
+    pub instance_buffer: Tracked<wgpu::Buffer>,
+    pub visible_buffer: Tracked<wgpu::Buffer>,
+    pub indirect_buffer: Tracked<wgpu::Buffer>,
+    pub cull_bind_group: Option<Tracked<wgpu::BindGroup>>,
+    pub bounding_sphere: nalgebra_glm::Vec4,
+    pub local_bounds: Aabb,
+    positions: Vec<nalgebra_glm::Vec3>,
+    indices: Vec<u32>,
+    instances: Vec<InstanceData>,
+    owners: Vec<u32>,
+    slots: Vec<InstanceSlot>,
+    free_slots: Vec<u32>,
+    hidden: HashSet<u32>,
+    uploaded: usize,
+    capacity: usize,
+    dirty: bool,
+    revision: u64,
+    supports_culling: bool,
+}
+
+impl InstanceBatch {
+    pub fn new(
+        device: &wgpu::Device,
+        name: &str,
+        vertices: &[crate::Vertex],
+        indices: &[u32],
+    ) -> Self {
+        let vertex_buffer = ResourceTracker::new(device, OWNER).create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Instanced Vertex Buffer"),
+                contents: bytemuck::cast_slice(vertices),
+                usage: wgpu::BufferUsages::VERTEX,
+            },
+        );
+        let index_buffer = ResourceTracker::new(device, OWNER).create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Instanced Index Buffer"),
+                contents: bytemuck::cast_slice(indices),
+                usage: wgpu::BufferUsages::INDEX,
+            },
+        );
+        let positions = vertices
+            .iter()
+            .map(crate::Vertex::position)
+            .collect::<Vec<_>>();
+        let (local_bounds, bounding_sphere) = Self::local_bounds(&positions);
+        let supports_culling = crate::culling::FrustumCuller::is_supported(device);
+        let (instance_buffer, visible_buffer) =
+            Self::create_instance_buffers(device, INITIAL_CAPACITY, supports_culling);
+        let indirect_buffer =
+            ResourceTracker::new(device, OWNER).create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Indirect Draw Buffer"),
+                size: std::mem::size_of::<wgpu::util::DrawIndexedIndirectArgs>() as u64,
+                usage: if supports_culling {
+                    wgpu::BufferUsages::INDIRECT
+                        | wgpu::BufferUsages::STORAGE
+                        | wgpu::BufferUsages::COPY_SRC
+                        | wgpu::BufferUsages::COPY_DST
+                } else {
+                    wgpu::BufferUsages::COPY_DST
+                },
+                mapped_at_creation: false,
+            });
+
+        Self {
+            name: name.to_string(),
+            vertex_buffer,
+            index_buffer,
+            index_count: indices.len() as u32,
+            instance_buffer,
+            visible_buffer,
+            indirect_buffer,
+            cull_bind_group: None,
+            bounding_sphere,
+            local_bounds,
+            positions,
+            indices: indices.to_vec(),
+            instances: Vec::new(),
+            owners: Vec::new(),
+            slots: Vec::new(),
+            free_slots: Vec::new(),
+            hidden: HashSet::new(),
+            uploaded: 0,
+            capacity: INITIAL_CAPACITY,
+            dirty: false,
+            revision: NEXT_REVISION.fetch_add(1, Ordering::Relaxed),
+            supports_culling,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.instances.len()
+    }
+
+    pub fn uploaded_len(&self) -> usize {
+        self.uploaded
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.instances.is_empty()
+    }
+
+    pub fn instances(&self) -> &[InstanceData] {
+        &self.instances
+    }
+
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (InstanceId, &InstanceData)> {
+        self.owners
+            .iter()
+            .zip(&self.instances)
+            .map(|(&index, instance)| {
+                let id = InstanceId {
+                    index,
+                    generation: self.slots[index as usize].generation,
+                };
+                (id, instance)
+            })
+    }
+
+    pub fn world_bounds(&self, instance: &InstanceData) -> Aabb {
+        self.local_bounds
+            .transformed(&nalgebra_glm::Mat4::from(instance.model))
+    }
+
+    pub fn get(&self, id: InstanceId) -> Option<&InstanceData> {
+        self.dense_index(id).map(|index| &self.instances[index])
+    }
+
+    pub fn instance_revision(&self, id: InstanceId) -> Option<u64> {
+        self.dense_index(id)
+            .map(|_| self.slots[id.index as usize].revision)
+    }
+
+    pub fn id_at(&self, index: usize) -> Option<InstanceId> {
+        self.owners.get(index).map(|&slot| InstanceId {
+            index: slot,
+            generation: self.slots[slot as usize].generation,
+        })
+    }
+
+    pub fn raycast(&self, id: InstanceId, ray: &Ray, max_distance: f32) -> Option<f32> {
+        let instance = self.get(id)?;
+        ray.intersects_mesh(
+            &self.positions,
+            &self.indices,
+            &nalgebra_glm::Mat4::from(instance.model),
+            max_distance,
+        )
+    }
+
+    pub fn add(&mut self, instances: &[InstanceData]) -> Vec<InstanceId> {
+        self.instances.reserve(instances.len());
+        self.owners.reserve(instances.len());
+        let ids = instances
+            .iter()
+            .map(|instance| {
+                let index = self.free_slots.pop().unwrap_or_else(|| {
+                    self.slots.push(InstanceSlot::default());
+                    self.slots.len() as u32 - 1
+                });
+                let slot = &mut self.slots[index as usize];
+                slot.dense_index = Some(self.instances.len());
+                self.instances.push(*instance);
+                self.owners.push(index);
+                InstanceId {
+                    index,
+                    generation: slot.generation,
+                }
+            })
+            .collect::<Vec<_>>();
+        self.mark_changed();
+        for id in &ids {
+            self.slots[id.index as usize].revision = self.revision;
+        }
+        ids
+    }
+
+    pub fn remove(&mut self, ids: &[InstanceId]) -> usize {
+        let mut removed = 0;
+        for &id in ids {
+            let Some(dense_index) = self.dense_index(id) else {
+                continue;
+            };
+            self.instances.swap_remove(dense_index);
+            self.owners.swap_remove(dense_index);
+            if let Some(&moved) = self.owners.get(dense_index) {
+                self.slots[moved as usize].dense_index = Some(dense_index);
+            }
+            let slot = &mut self.slots[id.index as usize];
+            slot.dense_index = None;
+            slot.generation = slot.generation.wrapping_add(1);
+            self.free_slots.push(id.index);
+            removed += 1;
+        }
+        if removed > 0 {
+            self.mark_changed();
+        }
+        removed
+    }
+
+    pub fn restore(&mut self, id: InstanceId, instance: &InstanceData) -> bool {
+        let Some(slot) = self.slots.get_mut(id.index as usize) else {
+            return false;
+        };
+        if slot.dense_index.is_some() || slot.generation != id.generation.wrapping_add(1) {
+            return false;
+        }
+        slot.generation = id.generation;
+        slot.dense_index = Some(self.instances.len());
+        self.instances.push(*instance);
+        self.owners.push(id.index);
+        self.free_slots.retain(|&index| index != id.index);
+        self.mark_changed();
+        self.slots[id.index as usize].revision = self.revision;
+        true
+    }
+
+    pub fn is_hidden(&self, id: InstanceId) -> bool {
+        self.dense_index(id).is_some() && self.hidden.contains(&id.index)
+    }
+
+    pub fn set_hidden(&mut self, ids: &[InstanceId]) {
+        let hidden = ids
+            .iter()
+            .filter(|id| self.dense_index(**id).is_some())
+            .map(|id| id.index)
+            .collect::<HashSet<_>>();
+        if hidden != self.hidden {
+            self.hidden = hidden;
+            self.dirty = true;
+        }
+    }
+
+    pub fn update(&mut self, updates: &[(InstanceId, InstanceData)]) -> usize {
+        let mut updated = Vec::new();
+        for (id, instance) in updates {
+            if let Some(dense_index) = self.dense_index(*id) {
+                self.instances[dense_index] = *instance;
+                updated.push(id.index);
+            }
+        }
+        if !updated.is_empty() {
+            self.mark_changed();
+            for index in &updated {
+                self.slots[*index as usize].revision = self.revision;
+            }
+        }
+        updated.len()
+    }
+
+    pub fn clear(&mut self) {
+        for slot in &mut self.slots {
+            if slot.dense_index.take().is_some() {
+                slot.generation = slot.generation.wrapping_add(1);
+            }
+        }
+        self.free_slots = (0..self.slots.len() as u32).rev().collect();
+        self.instances.clear();
+        self.owners.clear();
+        self.mark_changed();
+    }
+
+    pub fn prepare(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if !self.dirty {
+            return;
+        }
+        if self.instances.len() > self.capacity {
+            self.capacity = self.instances.len().next_power_of_two();
+            (self.instance_buffer, self.visible_buffer) =
+                Self::create_instance_buffers(device, self.capacity, self.supports_culling);
+            self.cull_bind_group = None;
+        }
+        if self.hidden.is_empty() {
+            if !self.instances.is_empty() {
+                perf::write_buffer(
+                    queue,
+                    &self.instance_buffer,
+                    0,
+                    bytemuck::cast_slice(&self.instances),
+                );
+            }
+            self.uploaded = self.instances.len();
+        } else {
+            let instances = self
+                .instances
+                .iter()
+                .zip(&self.owners)
+                .filter(|(_, owner)| !self.hidden.contains(owner))
+                .map(|(instance, _)| *instance)
+                .collect::<Vec<_>>();
+            if !instances.is_empty() {
+                perf::write_buffer(
+                    queue,
+                    &self.instance_buffer,
+                    0,
+                    bytemuck::cast_slice(&instances),
+                );
+            }
+            self.uploaded = instances.len();
+        }
+        self.dirty = false;
+    }
+
+    pub fn render_culled(&self, renderpass: &mut wgpu::RenderPass<'_>) {
+        if self.uploaded == 0 {
+            return;
+        }
+        renderpass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        renderpass.set_vertex_buffer(1, self.visible_buffer.slice(..));
+        renderpass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        renderpass.draw_indexed_indirect(&self.indirect_buffer, 0);
+        perf::record_draw(u64::from(self.index_count / 3) * self.uploaded as u64);
+    }
+
+    pub fn render(&self, renderpass: &mut wgpu::RenderPass<'_>) {
+        if self.uploaded == 0 {
+            return;
+        }
+        renderpass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        renderpass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+        renderpass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        renderpass.draw_indexed(0..self.index_count, 0, 0..self.uploaded as u32);
+        perf::record_draw(u64::from(self.index_count / 3) * self.uploaded as u64);
+    }
+
+    fn mark_changed(&mut self) {
+        self.dirty = true;
+        self.revision = NEXT_REVISION.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn dense_index(&self, id: InstanceId) -> Option<usize> {
+        self.slots
+            .get(id.index as usize)
+            .filter(|slot| slot.generation == id.generation)
+            .and_then(|slot| slot.dense_index)
+    }
+
+    fn local_bounds(positions: &[nalgebra_glm::Vec3]) -> (Aabb, nalgebra_glm::Vec4) {
+        let bounds = Aabb::from_points(positions);
+        let center = bounds.center();
+        let radius = positions
+            .iter()
+            .map(|position| nalgebra_glm::distance(&center, position))
+            .fold(0.0, f32::max);
+        (
+            bounds,
+            nalgebra_glm::vec4(center.x, center.y, center.z, radius),
+        )
+    }
+
+    fn create_instance_buffers(
+        device: &wgpu::Device,
+        capacity: usize,
+        supports_culling: bool,
+    ) -> (Tracked<wgpu::Buffer>, Tracked<wgpu::Buffer>) {
+        let size = (capacity * std::mem::size_of::<InstanceData>()) as u64;
+        let storage = if supports_culling {
+            wgpu::BufferUsages::STORAGE
+        } else {
+            wgpu::BufferUsages::empty()
+        };
+        let instance_buffer =
+            ResourceTracker::new(device, OWNER).create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Instance Buffer"),
+                size,
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST | storage,
+                mapped_at_creation: false,
+            });
+        let visible_buffer =
+            ResourceTracker::new(device, OWNER).create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Visible Instance Buffer"),
+                size: if supports_culling { size } else { 0 },
+                usage: wgpu::BufferUsages::VERTEX | storage,
+                mapped_at_creation: false,
+            });
+        (instance_buffer, visible_buffer)
+    }
+}
+
+pub fn cube_grid(count: usize, spacing: f32) -> Vec<InstanceData> {
+    let side = (count as f32).sqrt().ceil().max(1.0) as usize;
+    let half_extent = (side - 1) as f32 * spacing * 0.5;
+    (0..count)
+        .map(|index| {
+            let (row, column) = (index / side, index % side);
+            let x = column as f32 * spacing - half_extent;
+            let z = row as f32 * spacing - half_extent;
+            let height = 0.05 + 0.5 * ((x * 0.35).sin() * (z * 0.35).cos() + 1.0);
+            let model = nalgebra_glm::translation(&nalgebra_glm::vec3(x, height, z))
+                * nalgebra_glm::rotation(x * 0.5 + z * 0.3, &nalgebra_glm::Vec3::y());
+            let u = column as f32 / side as f32;
+            let v = row as f32 / side as f32;
+            InstanceData::new(&model, nalgebra_glm::vec4(u, 0.4 + 0.6 * v, 1.0 - u, 1.0))
+        })
+        .collect()
+}
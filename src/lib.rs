@@ -12,6 +12,64 @@ pub mod xr;
 #[cfg(all(not(target_arch = "wasm32"), feature = "openxr"))]
 pub use xr::run_xr;
 
+pub mod bounds;
+pub mod bvh;
+pub mod components;
+pub mod console;
+pub mod culling;
+pub mod cvars;
+pub mod gamepad;
+pub mod gestures;
+pub mod gizmo;
+pub mod hdr;
+pub mod hierarchy;
+pub mod history;
+pub mod ibl;
+pub mod input;
+pub mod inspector;
+pub mod instancing;
+pub mod outline;
+pub mod perf;
+pub mod post;
+pub mod render_graph;
+pub mod replay;
+pub mod resources;
+pub mod scene_tree;
+pub mod selection;
+pub mod shadows;
+pub mod spin;
+pub mod trace;
+pub mod uniform_ring;
+
+use bounds::{Aabb, Frustum, Ray};
+use bvh::{Bvh, ProxyId};
+use components::ComponentStore;
+use console::{CommandRegistry, Console, ConsoleLogger};
+use culling::FrustumCuller;
+use cvars::{Cvar, CvarRegistry};
+use gamepad::{GamepadCvars, Gamepads};
+use gestures::GestureRecognizer;
+use gizmo::Gizmo;
+use hdr::HdrPipeline;
+use hierarchy::Hierarchy;
+use history::History;
+use ibl::{Environment, SkyMode};
+use input::{Actions, InputEvent, InputMap, TouchGesture};
+use inspector::Inspector;
+use instancing::{InstanceBatch, InstanceData, InstanceId};
+use outline::SelectionOutline;
+use perf::{FrameCounters, FrameSample, GpuTimer, PerfOverlay};
+use post::{PostEffect, PostProcessSettings, PostProcessor};
+use render_graph::{RenderGraph, TransientPool, TransientTexture};
+use replay::{InputRecorder, InputReplay, RecordingHeader, ReplayCvars};
+use resources::{ResourceInspector, ResourceTracker, Tracked};
+use scene_tree::SceneTree;
+use selection::{Selection, SelectionMode};
+use shadows::{CascadedShadowMap, DirectionalLight, ShadowCaster, ShadowSettings, ShadowView};
+use spin::SpinComponent;
+use uniform_ring::{UniformRing, UniformSlot};
+
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use web_time::{Duration, Instant};
 use winit::{
@@ -27,9 +85,12 @@ use winit::platform::android::EventLoopBuilderExtAndroid;
 #[cfg(all(target_os = "android", not(feature = "openxr")))]
 #[unsafe(no_mangle)]
 fn android_main(app: AndroidApp) {
-    android_logger::init_once(
+    let logger = android_logger::AndroidLogger::new(
         android_logger::Config::default().with_max_level(log::LevelFilter::Info),
     );
+    ConsoleLogger::new(Box::new(logger), log::LevelFilter::Info)
+        .init()
+        .expect("Failed to initialize logger!");
 
     let event_loop = winit::event_loop::EventLoop::builder()
         .with_android_app(app)
@@ -78,10 +139,17 @@ pub struct App {
     renderer_receiver: Option<futures::channel::oneshot::Receiver<Renderer>>,
     last_size: (u32, u32),
     initialized: bool,
+    #[cfg(not(target_arch = "wasm32"))]
+    environment_path: String,
+    console: Console<Renderer>,
 }
 
 impl ApplicationHandler for App {
     fn suspended(&mut self, _event_loop: &winit::event_loop::ActiveEventLoop) {
+        if let Some(renderer) = self.renderer.as_mut() {
+            renderer.save_cvars();
+            renderer.finish_recording();
+        }
         self.renderer = None;
         self.window = None;
     }
@@ -164,7 +232,12 @@ impl ApplicationHandler for App {
         #[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
         {
             if !self.initialized {
-                env_logger::init();
+                let logger = env_logger::Builder::from_default_env().build();
+                let level = logger.filter();
+                ConsoleLogger::new(Box::new(logger), level)
+                    .init()
+                    .expect("Failed to initialize logger!");
+                trace::log_status();
             }
             let renderer = pollster::block_on(async move {
                 Renderer::new(window_handle.clone(), width, height).await
@@ -186,7 +259,9 @@ impl ApplicationHandler for App {
             self.renderer_receiver = Some(receiver);
             if !self.initialized {
                 std::panic::set_hook(Box::new(console_error_panic_hook::hook));
-                console_log::init().expect("Failed to initialize logger!");
+                ConsoleLogger::new(Box::new(console::WebConsoleLogger), log::LevelFilter::Info)
+                    .init()
+                    .expect("Failed to initialize logger!");
             }
             log::info!("Canvas dimensions: ({canvas_width} x {canvas_height})");
             wasm_bindgen_futures::spawn_local(async move {
@@ -198,6 +273,10 @@ impl ApplicationHandler for App {
             });
         }
 
+        if !self.initialized {
+            register_console_commands(&mut self.console.commands);
+        }
+
         self.gui_state = Some(gui_state);
         self.last_render_time = Some(Instant::now());
         self.initialized = true;
@@ -235,23 +314,21 @@ impl ApplicationHandler for App {
             return;
         };
 
+        if let Some(input_event) = InputEvent::from_window_event(&event) {
+            if renderer.input_replay.is_some() {
+                return;
+            }
+            if let Some(recorder) = renderer.input_recorder.as_mut() {
+                recorder.record_event(input_event);
+            }
+            renderer.handle_input_event(&input_event);
+        }
+
         if gui_state.on_window_event(window, &event).consumed {
             return;
         }
 
         match event {
-            WindowEvent::KeyboardInput {
-                event:
-                    winit::event::KeyEvent {
-                        physical_key: winit::keyboard::PhysicalKey::Code(key_code),
-                        ..
-                    },
-                ..
-            } => {
-                if matches!(key_code, winit::keyboard::KeyCode::Escape) {
-                    event_loop.exit();
-                }
-            }
             WindowEvent::ScaleFactorChanged { .. } => {
                 #[cfg(not(target_arch = "wasm32"))]
                 {
@@ -284,6 +361,8 @@ impl ApplicationHandler for App {
             }
             WindowEvent::CloseRequested => {
                 log::info!("Close requested. Exiting...");
+                renderer.save_cvars();
+                renderer.finish_recording();
                 event_loop.exit();
             }
             WindowEvent::RedrawRequested => {
@@ -308,13 +387,52 @@ impl ApplicationHandler for App {
                 }
 
                 let now = Instant::now();
-                let delta_time = now - *last_render_time;
+                let mut delta_time = now - *last_render_time;
                 *last_render_time = now;
 
-                #[cfg(target_arch = "wasm32")]
+                renderer.apply_cvars();
+
+                let replay_frame = renderer.input_replay.as_mut().map(InputReplay::next_frame);
+                if let Some(None) = replay_frame {
+                    log::info!("Input replay finished");
+                    renderer.input_replay = None;
+                    if renderer
+                        .cvars
+                        .get(renderer.cvar_handles.replay.exit_on_finish)
+                    {
+                        renderer.finish_recording();
+                        event_loop.exit();
+                        return;
+                    }
+                }
+                let replay_frame = replay_frame.flatten();
+                if let Some(frame) = replay_frame.as_ref() {
+                    delta_time = frame.delta_time();
+                    for event in &frame.events {
+                        renderer.handle_input_event(event);
+                    }
+                    if let Some((width, height)) = frame.size
+                        && (width, height) != self.last_size
+                    {
+                        let _ = window.request_inner_size(PhysicalSize::new(width, height));
+                    }
+                }
+
+                renderer.gestures.update(&mut renderer.actions, delta_time);
+                let gamepad_events = match renderer.input_replay.as_ref() {
+                    Some(replay) => {
+                        renderer
+                            .gamepads
+                            .apply_state(&mut renderer.actions, replay.gamepad());
+                        Vec::new()
+                    }
+                    None => renderer.gamepads.poll(&mut renderer.actions),
+                };
+                if let Some(recorder) = renderer.input_recorder.as_mut() {
+                    recorder.record_frame(delta_time, renderer.gamepads.state(), self.last_size);
+                }
                 let mut gui_input = gui_state.take_egui_input(window);
-                #[cfg(not(target_arch = "wasm32"))]
-                let gui_input = gui_state.take_egui_input(window);
+                gui_input.events.extend(gamepad_events);
 
                 #[cfg(target_arch = "wasm32")]
                 {
@@ -335,6 +453,13 @@ impl ApplicationHandler for App {
                         Some(egui::Rect::from_min_size(egui::Pos2::ZERO, canvas_size));
                 }
 
+                if let Some(frame) = replay_frame.as_ref() {
+                    frame.gui.clone().unwrap_or_default().apply(&mut gui_input);
+                }
+                if let Some(recorder) = renderer.input_recorder.as_mut() {
+                    recorder.record_gui(&gui_input);
+                }
+
                 #[cfg(all(
                     not(target_arch = "wasm32"),
                     not(target_os = "android"),
@@ -353,6 +478,7 @@ impl ApplicationHandler for App {
                 #[cfg(feature = "android")]
                 let title = "Rust/Wgpu/Android";
 
+                let ui_scope = trace::scope("egui::run_ui");
                 let egui_winit::egui::FullOutput {
                     textures_delta,
                     shapes,
@@ -360,14 +486,44 @@ impl ApplicationHandler for App {
                     platform_output,
                     ..
                 } = gui_state.egui_ctx().run_ui(gui_input, |ui| {
+                    if !renderer.gizmo.is_dragging()
+                        && ui.memory(|memory| memory.focused().is_none())
+                    {
+                        let history = &mut renderer.history;
+                        let scene = &mut renderer.scene;
+                        if ui.input_mut(|input| {
+                            input.consume_shortcut(&history::REDO_SHORTCUT)
+                                || input.consume_shortcut(&history::REDO_ALT_SHORTCUT)
+                        }) {
+                            history.redo(scene);
+                        } else if ui
+                            .input_mut(|input| input.consume_shortcut(&history::UNDO_SHORTCUT))
+                        {
+                            history.undo(scene);
+                        }
+                        if ui.input(|input| input.key_pressed(egui::Key::Delete)) {
+                            delete_selection(history, scene);
+                        }
+                    }
+
                     egui::Panel::top("top").show_inside(ui, |ui| {
                         ui.horizontal(|ui| {
                             egui::MenuBar::new().ui(ui, |ui| {
                                 ui.menu_button("File", |ui| {
                                     if ui.button("Load").clicked() {
+                                        if let Err(error) = renderer.load_scene(SCENE_PATH) {
+                                            log::error!(
+                                                "Failed to load scene {SCENE_PATH}: {error}"
+                                            );
+                                        }
                                         ui.close();
                                     }
                                     if ui.button("Save").clicked() {
+                                        if let Err(error) = renderer.save_scene(SCENE_PATH) {
+                                            log::error!(
+                                                "Failed to save scene {SCENE_PATH}: {error}"
+                                            );
+                                        }
                                         ui.close();
                                     }
                                     ui.separator();
@@ -377,14 +533,51 @@ impl ApplicationHandler for App {
                                 });
 
                                 ui.menu_button("Edit", |ui| {
+                                    let history = &mut renderer.history;
+                                    let scene = &mut renderer.scene;
+                                    if ui
+                                        .add_enabled(
+                                            history.can_undo(),
+                                            egui::Button::new("Undo").shortcut_text(
+                                                ui.ctx().format_shortcut(&history::UNDO_SHORTCUT),
+                                            ),
+                                        )
+                                        .clicked()
+                                    {
+                                        history.undo(scene);
+                                        ui.close();
+                                    }
+                                    if ui
+                                        .add_enabled(
+                                            history.can_redo(),
+                                            egui::Button::new("Redo").shortcut_text(
+                                                ui.ctx().format_shortcut(&history::REDO_SHORTCUT),
+                                            ),
+                                        )
+                                        .clicked()
+                                    {
+                                        history.redo(scene);
+                                        ui.close();
+                                    }
+                                    ui.separator();
                                     if ui.button("Clear").clicked() {
+                                        history.execute(history::ClearScene::default(), scene);
                                         ui.close();
                                     }
                                     if ui.button("Reset").clicked() {
+                                        history.execute(history::ResetScene::default(), scene);
                                         ui.close();
                                     }
                                 });
 
+                                ui.menu_button("View", |ui| {
+                                    ui.checkbox(&mut renderer.perf.visible, "Performance");
+                                    ui.checkbox(
+                                        &mut renderer.resource_inspector.visible,
+                                        "GPU Resources",
+                                    );
+                                });
+
                                 ui.separator();
 
                                 ui.label(
@@ -406,20 +599,763 @@ impl ApplicationHandler for App {
 
                     egui::Panel::left("left").show_inside(ui, |ui| {
                         ui.heading("Scene Tree");
+                        renderer.scene_tree.show(
+                            ui,
+                            &mut renderer.scene,
+                            &mut renderer.history,
+                            &renderer.gpu.device,
+                        );
                     });
 
                     egui::Panel::right("right").show_inside(ui, |ui| {
                         ui.heading("Inspector");
+
+                        egui::CollapsingHeader::new("Selection")
+                            .default_open(true)
+                            .show(ui, |ui| {
+                                let scene = &mut renderer.scene;
+                                match scene.selection.primary() {
+                                    Some(primary) => {
+                                        ui.label(format!("{} selected", scene.selection.len()));
+                                        ui.label(format!(
+                                            "Active: {}",
+                                            scene.object_label(primary)
+                                        ));
+                                        if let Some(bounds) = scene.object_bounds(primary) {
+                                            let center = bounds.center();
+                                            ui.label(format!(
+                                                "Position: {:.2}, {:.2}, {:.2}",
+                                                center.x, center.y, center.z
+                                            ));
+                                        }
+                                    }
+                                    None => {
+                                        ui.label("Nothing selected");
+                                    }
+                                }
+                                ui.horizontal(|ui| {
+                                    let has_selection = !scene.selection.is_empty();
+                                    if ui
+                                        .add_enabled(
+                                            has_selection,
+                                            egui::Button::new("Frame Selection"),
+                                        )
+                                        .clicked()
+                                        && let Some(bounds) = scene.selection_bounds()
+                                    {
+                                        scene.camera.frame_bounds(&bounds);
+                                    }
+                                    if ui
+                                        .add_enabled(has_selection, egui::Button::new("Clear"))
+                                        .clicked()
+                                    {
+                                        scene.selection.clear();
+                                    }
+                                    if ui
+                                        .add_enabled(has_selection, egui::Button::new("Delete"))
+                                        .clicked()
+                                    {
+                                        delete_selection(&mut renderer.history, scene);
+                                    }
+                                });
+                                let settings = &mut renderer.outline.settings;
+                                ui.checkbox(&mut settings.enabled, "Outline");
+                                ui.add_enabled_ui(settings.enabled, |ui| {
+                                    ui.horizontal(|ui| {
+                                        ui.label("Selected");
+                                        ui.color_edit_button_rgba_unmultiplied(
+                                            &mut settings.selected_color,
+                                        );
+                                        ui.label("Hovered");
+                                        ui.color_edit_button_rgba_unmultiplied(
+                                            &mut settings.hovered_color,
+                                        );
+                                    });
+                                    ui.add(
+                                        egui::Slider::new(
+                                            &mut settings.width,
+                                            1.0..=outline::MAX_OUTLINE_WIDTH,
+                                        )
+                                        .text("Width"),
+                                    );
+                                });
+                            });
+
+                        renderer
+                            .inspector
+                            .show(ui, &mut renderer.scene, &mut renderer.history);
+
+                        egui::CollapsingHeader::new("Gizmo")
+                            .default_open(true)
+                            .show(ui, |ui| {
+                                let settings = &mut renderer.gizmo.settings;
+                                ui.horizontal(|ui| {
+                                    for mode in gizmo::GizmoMode::ALL {
+                                        ui.selectable_value(&mut settings.mode, mode, mode.label())
+                                            .on_hover_text(format!("{:?}", mode.shortcut()));
+                                    }
+                                });
+                                ui.horizontal(|ui| {
+                                    for space in gizmo::GizmoSpace::ALL {
+                                        ui.selectable_value(
+                                            &mut settings.space,
+                                            space,
+                                            space.label(),
+                                        );
+                                    }
+                                });
+                                ui.checkbox(&mut settings.snapping, "Snapping");
+                                ui.add_enabled_ui(settings.snapping, |ui| {
+                                    ui.add(
+                                        egui::DragValue::new(&mut settings.translate_snap)
+                                            .range(0.01..=10.0)
+                                            .speed(0.01)
+                                            .prefix("Move: "),
+                                    );
+                                    ui.add(
+                                        egui::DragValue::new(&mut settings.rotate_snap)
+                                            .range(1.0..=90.0)
+                                            .suffix("°")
+                                            .prefix("Rotate: "),
+                                    );
+                                    ui.add(
+                                        egui::DragValue::new(&mut settings.scale_snap)
+                                            .range(0.01..=1.0)
+                                            .speed(0.01)
+                                            .prefix("Scale: "),
+                                    );
+                                });
+                                ui.add(
+                                    egui::Slider::new(&mut settings.size, 48.0..=192.0)
+                                        .text("Size"),
+                                );
+                            });
+
+                        egui::CollapsingHeader::new("History")
+                            .default_open(true)
+                            .show(ui, |ui| {
+                                let history = &mut renderer.history;
+                                let scene = &mut renderer.scene;
+                                ui.horizontal(|ui| {
+                                    if ui
+                                        .add_enabled(history.can_undo(), egui::Button::new("Undo"))
+                                        .clicked()
+                                    {
+                                        history.undo(scene);
+                                    }
+                                    if ui
+                                        .add_enabled(history.can_redo(), egui::Button::new("Redo"))
+                                        .clicked()
+                                    {
+                                        history.redo(scene);
+                                    }
+                                    if ui
+                                        .add_enabled(
+                                            !history.is_empty(),
+                                            egui::Button::new("Clear"),
+                                        )
+                                        .clicked()
+                                    {
+                                        history.clear();
+                                    }
+                                });
+                                let mut target = None;
+                                egui::ScrollArea::vertical()
+                                    .max_height(160.0)
+                                    .stick_to_bottom(true)
+                                    .show(ui, |ui| {
+                                        let cursor = history.cursor();
+                                        if ui
+                                            .selectable_label(cursor == 0, "Initial State")
+                                            .clicked()
+                                        {
+                                            target = Some(0);
+                                        }
+                                        for (index, label) in history.labels().enumerate() {
+                                            let text = if index < cursor {
+                                                egui::RichText::new(label)
+                                            } else {
+                                                egui::RichText::new(label).weak()
+                                            };
+                                            if ui
+                                                .selectable_label(index + 1 == cursor, text)
+                                                .clicked()
+                                            {
+                                                target = Some(index + 1);
+                                            }
+                                        }
+                                    });
+                                if let Some(target) = target {
+                                    history.jump_to(target, scene);
+                                }
+                                const MEBIBYTE: usize = 1024 * 1024;
+                                let mut limit = history.memory_limit() / MEBIBYTE;
+                                let changed = ui.horizontal(|ui| {
+                                    ui.label(format!(
+                                        "Memory: {:.2} MiB /",
+                                        history.memory_usage() as f64 / MEBIBYTE as f64
+                                    ));
+                                    ui.add(
+                                        egui::DragValue::new(&mut limit)
+                                            .range(1..=1024)
+                                            .suffix(" MiB"),
+                                    )
+                                    .changed()
+                                });
+                                if changed.inner {
+                                    history.set_memory_limit(limit * MEBIBYTE);
+                                }
+                            });
+
+                        egui::CollapsingHeader::new("Shadows")
+                            .default_open(true)
+                            .show(ui, |ui| {
+                                let mut settings = renderer.scene.shadows.settings();
+                                ui.add(
+                                    egui::Slider::new(
+                                        &mut settings.cascade_count,
+                                        1..=shadows::MAX_CASCADES as u32,
+                                    )
+                                    .text("Cascades"),
+                                );
+                                egui::ComboBox::from_label("Resolution")
+                                    .selected_text(settings.resolution.to_string())
+                                    .show_ui(ui, |ui| {
+                                        for resolution in [512, 1024, 2048, 4096] {
+                                            ui.selectable_value(
+                                                &mut settings.resolution,
+                                                resolution,
+                                                resolution.to_string(),
+                                            );
+                                        }
+                                    });
+                                ui.add(
+                                    egui::Slider::new(&mut settings.split_lambda, 0.0..=1.0)
+                                        .text("Split Lambda"),
+                                );
+                                egui::ComboBox::from_label("Filter")
+                                    .selected_text(settings.filter.label())
+                                    .show_ui(ui, |ui| {
+                                        for filter in shadows::ShadowFilter::ALL {
+                                            ui.selectable_value(
+                                                &mut settings.filter,
+                                                filter,
+                                                filter.label(),
+                                            );
+                                        }
+                                    });
+                                ui.add(
+                                    egui::Slider::new(&mut settings.max_distance, 5.0..=200.0)
+                                        .text("Distance"),
+                                );
+                                ui.checkbox(&mut settings.debug_cascades, "Show Cascades");
+                                if settings != renderer.scene.shadows.settings() {
+                                    renderer
+                                        .scene
+                                        .shadows
+                                        .set_settings(&renderer.gpu.device, settings);
+                                }
+                            });
+
+                        egui::CollapsingHeader::new("Rendering")
+                            .default_open(true)
+                            .show(ui, |ui| {
+                                let mut sample_count = renderer.sample_count();
+                                egui::ComboBox::from_label("MSAA")
+                                    .selected_text(format!("{sample_count}x"))
+                                    .show_ui(ui, |ui| {
+                                        for &supported in renderer.supported_sample_counts() {
+                                            ui.selectable_value(
+                                                &mut sample_count,
+                                                supported,
+                                                format!("{supported}x"),
+                                            );
+                                        }
+                                    });
+                                if sample_count != renderer.sample_count() {
+                                    renderer.cvars.set(renderer.cvar_handles.msaa, sample_count);
+                                }
+
+                                let mut present_mode = renderer.gpu.surface_config.present_mode;
+                                egui::ComboBox::from_label("Present Mode")
+                                    .selected_text(present_mode_name(present_mode))
+                                    .show_ui(ui, |ui| {
+                                        for &supported in &renderer.gpu.present_modes {
+                                            ui.selectable_value(
+                                                &mut present_mode,
+                                                supported,
+                                                present_mode_name(supported),
+                                            );
+                                        }
+                                    });
+                                if present_mode != renderer.gpu.surface_config.present_mode {
+                                    renderer.cvars.set(
+                                        renderer.cvar_handles.present_mode,
+                                        present_mode_name(present_mode).to_string(),
+                                    );
+                                }
+
+                                let mut vsync = renderer.cvars.get(renderer.cvar_handles.vsync);
+                                if ui.checkbox(&mut vsync, "VSync").changed() {
+                                    renderer.cvars.set(renderer.cvar_handles.vsync, vsync);
+                                }
+
+                                let mut clear_color =
+                                    renderer.cvars.get(renderer.cvar_handles.clear_color);
+                                ui.horizontal(|ui| {
+                                    ui.label("Clear Color");
+                                    if ui
+                                        .color_edit_button_rgba_unmultiplied(&mut clear_color)
+                                        .changed()
+                                    {
+                                        renderer
+                                            .cvars
+                                            .set(renderer.cvar_handles.clear_color, clear_color);
+                                    }
+                                });
+                            });
+
+                        egui::CollapsingHeader::new("Exposure")
+                            .default_open(true)
+                            .show(ui, |ui| {
+                                if ui.button("Frame Scene").clicked()
+                                    && let Some(bounds) = renderer.scene.bvh.bounds()
+                                {
+                                    renderer.scene.camera.frame_bounds(&bounds);
+                                }
+                                let settings = &mut renderer.hdr.settings;
+                                egui::ComboBox::from_label("Tonemapper")
+                                    .selected_text(settings.tonemapper.label())
+                                    .show_ui(ui, |ui| {
+                                        for tonemapper in hdr::Tonemapper::ALL {
+                                            ui.selectable_value(
+                                                &mut settings.tonemapper,
+                                                tonemapper,
+                                                tonemapper.label(),
+                                            );
+                                        }
+                                    });
+                                ui.horizontal(|ui| {
+                                    ui.label("Exposure");
+                                    ui.selectable_value(
+                                        &mut settings.exposure_mode,
+                                        hdr::ExposureMode::Manual,
+                                        "Manual",
+                                    );
+                                    ui.selectable_value(
+                                        &mut settings.exposure_mode,
+                                        hdr::ExposureMode::Auto,
+                                        "Auto",
+                                    );
+                                });
+                                let exposure_label = match settings.exposure_mode {
+                                    hdr::ExposureMode::Manual => "Exposure (EV)",
+                                    hdr::ExposureMode::Auto => "Compensation (EV)",
+                                };
+                                ui.add(
+                                    egui::Slider::new(&mut settings.exposure_ev, -8.0..=8.0)
+                                        .text(exposure_label),
+                                );
+                                if settings.exposure_mode == hdr::ExposureMode::Auto {
+                                    ui.add(
+                                        egui::Slider::new(
+                                            &mut settings.min_luminance_ev,
+                                            -16.0..=0.0,
+                                        )
+                                        .text("Min Luminance (EV)"),
+                                    );
+                                    ui.add(
+                                        egui::Slider::new(
+                                            &mut settings.max_luminance_ev,
+                                            0.0..=16.0,
+                                        )
+                                        .text("Max Luminance (EV)"),
+                                    );
+                                    ui.add(
+                                        egui::Slider::new(
+                                            &mut settings.adaptation_speed,
+                                            0.1..=10.0,
+                                        )
+                                        .text("Adaptation Speed"),
+                                    );
+                                }
+                            });
+
+                        egui::CollapsingHeader::new("Post Processing")
+                            .default_open(true)
+                            .show(ui, |ui| {
+                                let effects = &mut renderer.post.settings.effects;
+                                let effect_count = effects.len();
+                                let mut swap = None;
+                                let mut load_lut = None;
+                                for (index, slot) in effects.iter_mut().enumerate() {
+                                    ui.push_id(index, |ui| {
+                                        ui.horizontal(|ui| {
+                                            ui.checkbox(&mut slot.enabled, slot.effect.label());
+                                            ui.with_layout(
+                                                egui::Layout::right_to_left(egui::Align::Center),
+                                                |ui| {
+                                                    if ui
+                                                        .add_enabled(
+                                                            index + 1 < effect_count,
+                                                            egui::Button::new("⬇"),
+                                                        )
+                                                        .clicked()
+                                                    {
+                                                        swap = Some((index, index + 1));
+                                                    }
+                                                    if ui
+                                                        .add_enabled(
+                                                            index > 0,
+                                                            egui::Button::new("⬆"),
+                                                        )
+                                                        .clicked()
+                                                    {
+                                                        swap = Some((index - 1, index));
+                                                    }
+                                                },
+                                            );
+                                        });
+                                        ui.add_enabled_ui(slot.enabled, |ui| {
+                                            match &mut slot.effect {
+                                                PostEffect::Bloom {
+                                                    threshold,
+                                                    knee,
+                                                    intensity,
+                                                    radius,
+                                                } => {
+                                                    ui.add(
+                                                        egui::Slider::new(threshold, 0.0..=10.0)
+                                                            .text("Threshold"),
+                                                    );
+                                                    ui.add(
+                                                        egui::Slider::new(knee, 0.0..=1.0)
+                                                            .text("Knee"),
+                                                    );
+                                                    ui.add(
+                                                        egui::Slider::new(intensity, 0.0..=1.0)
+                                                            .text("Intensity"),
+                                                    );
+                                                    ui.add(
+                                                        egui::Slider::new(radius, 0.5..=3.0)
+                                                            .text("Radius"),
+                                                    );
+                                                }
+                                                PostEffect::ChromaticAberration { amount } => {
+                                                    ui.add(
+                                                        egui::Slider::new(amount, 0.0..=0.05)
+                                                            .text("Amount"),
+                                                    );
+                                                }
+                                                PostEffect::Vignette {
+                                                    intensity,
+                                                    radius,
+                                                    smoothness,
+                                                } => {
+                                                    ui.add(
+                                                        egui::Slider::new(intensity, 0.0..=1.0)
+                                                            .text("Intensity"),
+                                                    );
+                                                    ui.add(
+                                                        egui::Slider::new(radius, 0.0..=1.5)
+                                                            .text("Radius"),
+                                                    );
+                                                    ui.add(
+                                                        egui::Slider::new(smoothness, 0.0..=1.0)
+                                                            .text("Smoothness"),
+                                                    );
+                                                }
+                                                PostEffect::ColorGrading {
+                                                    contribution,
+                                                    lut_path,
+                                                } => {
+                                                    ui.add(
+                                                        egui::Slider::new(contribution, 0.0..=1.0)
+                                                            .text("Contribution"),
+                                                    );
+                                                    ui.horizontal(|ui| {
+                                                        ui.text_edit_singleline(lut_path);
+                                                        if ui.button("Load LUT").clicked() {
+                                                            load_lut = Some(lut_path.clone());
+                                                        }
+                                                    });
+                                                }
+                                                PostEffect::Fxaa {
+                                                    span_max,
+                                                    reduce_mul,
+                                                    reduce_min,
+                                                } => {
+                                                    ui.add(
+                                                        egui::Slider::new(span_max, 1.0..=16.0)
+                                                            .text("Span"),
+                                                    );
+                                                    ui.add(
+                                                        egui::Slider::new(reduce_mul, 0.0..=0.5)
+                                                            .text("Reduce Mul"),
+                                                    );
+                                                    ui.add(
+                                                        egui::Slider::new(reduce_min, 0.0..=0.1)
+                                                            .text("Reduce Min"),
+                                                    );
+                                                }
+                                            }
+                                        });
+                                    });
+                                }
+                                if let Some((first, second)) = swap {
+                                    effects.swap(first, second);
+                                }
+                                if let Some(path) = load_lut
+                                    && let Err(error) = renderer.load_lut(&path)
+                                {
+                                    log::error!("Failed to load LUT {path}: {error}");
+                                }
+                            });
+
+                        egui::CollapsingHeader::new("Environment")
+                            .default_open(true)
+                            .show(ui, |ui| {
+                                #[cfg(not(target_arch = "wasm32"))]
+                                ui.horizontal(|ui| {
+                                    ui.text_edit_singleline(&mut self.environment_path);
+                                    if ui.button("Load HDR").clicked() {
+                                        let result = std::fs::read(&self.environment_path)
+                                            .map_err(Into::into)
+                                            .and_then(|bytes| {
+                                                renderer.scene.environment.load_hdr(
+                                                    &renderer.gpu.device,
+                                                    &renderer.gpu.queue,
+                                                    &bytes,
+                                                )
+                                            });
+                                        match result {
+                                            Ok(()) => {
+                                                renderer.scene.sky_mode = SkyMode::Environment
+                                            }
+                                            Err(error) => log::error!(
+                                                "Failed to load environment {}: {error}",
+                                                self.environment_path
+                                            ),
+                                        }
+                                    }
+                                });
+                                ui.add_enabled_ui(renderer.scene.environment.is_loaded(), |ui| {
+                                    ui.horizontal(|ui| {
+                                        ui.label("Sky");
+                                        ui.selectable_value(
+                                            &mut renderer.scene.sky_mode,
+                                            SkyMode::Procedural,
+                                            "Procedural",
+                                        );
+                                        ui.selectable_value(
+                                            &mut renderer.scene.sky_mode,
+                                            SkyMode::Environment,
+                                            "Environment",
+                                        );
+                                    });
+                                });
+                                ui.add(
+                                    egui::Slider::new(
+                                        &mut renderer.scene.environment.intensity,
+                                        0.0..=4.0,
+                                    )
+                                    .text("IBL Intensity"),
+                                );
+                                ui.add(
+                                    egui::Slider::new(
+                                        &mut renderer.scene.environment.sky_intensity,
+                                        0.0..=4.0,
+                                    )
+                                    .text("Sky Intensity"),
+                                );
+                            });
+
+                        egui::CollapsingHeader::new("Instancing")
+                            .default_open(true)
+                            .show(ui, |ui| {
+                                for batch in &renderer.scene.batches {
+                                    ui.label(format!("{}: {} instances", batch.name, batch.len()));
+                                }
+                                ui.add_enabled(
+                                    FrustumCuller::is_supported(&renderer.gpu.device),
+                                    egui::Checkbox::new(
+                                        &mut renderer.scene.culler.enabled,
+                                        "GPU Frustum Culling",
+                                    ),
+                                );
+                                let stats = renderer.scene.culler.stats();
+                                ui.label(format!(
+                                    "Tested: {}  Drawn: {}",
+                                    stats.tested, stats.drawn
+                                ));
+                                ui.horizontal(|ui| {
+                                    if ui.button("Spawn 10k Cubes").clicked() {
+                                        let scene = &mut renderer.scene;
+                                        let batch = scene.cube_batch(&renderer.gpu.device);
+                                        let existing = scene.batches[batch]
+                                            .iter()
+                                            .map(|(instance, _)| SceneObject::Instance {
+                                                batch,
+                                                instance,
+                                            })
+                                            .collect::<Vec<_>>();
+                                        let command = history::CommandGroup::new("Spawn Cubes")
+                                            .with(history::RemoveInstances::new(scene, existing))
+                                            .with(history::AddInstances::new(
+                                                batch,
+                                                instancing::cube_grid(
+                                                    instancing::STRESS_TEST_INSTANCES,
+                                                    0.25,
+                                                ),
+                                            ));
+                                        renderer.history.execute(command, scene);
+                                    }
+                                    if ui.button("Clear").clicked() {
+                                        renderer.history.execute(
+                                            history::ClearScene::default(),
+                                            &mut renderer.scene,
+                                        );
+                                    }
+                                });
+                            });
                     });
 
-                    egui::Panel::bottom("Console").show_inside(ui, |ui| {
-                        ui.heading("Console");
+                    egui::Panel::bottom("Console")
+                        .resizable(true)
+                        .default_size(180.0)
+                        .show_inside(ui, |ui| {
+                            self.console.show(ui, renderer);
+                        });
+
+                    renderer.perf.show(ui.ctx(), &mut renderer.gpu_timer);
+                    renderer.resource_inspector.show(ui.ctx());
+
+                    egui::CentralPanel::no_frame().show_inside(ui, |ui| {
+                        let response =
+                            ui.allocate_rect(ui.max_rect(), egui::Sense::click_and_drag());
+                        renderer.actions.pointer_enabled =
+                            response.contains_pointer() && !renderer.gizmo.wants_pointer();
+                        let screen_rect = ui.ctx().content_rect();
+                        let to_ndc = |position: egui::Pos2| {
+                            nalgebra_glm::vec2(
+                                (position.x - screen_rect.min.x) / screen_rect.width() * 2.0 - 1.0,
+                                1.0 - (position.y - screen_rect.min.y) / screen_rect.height() * 2.0,
+                            )
+                        };
+                        let pixels_per_point = ui.ctx().pixels_per_point();
+                        renderer.gestures.claim(|position| {
+                            let position = egui::pos2(position.x, position.y) / pixels_per_point;
+                            response.rect.contains(position)
+                                && !renderer.gizmo.wants_pointer()
+                                && ui
+                                    .ctx()
+                                    .layer_id_at(position)
+                                    .is_none_or(|layer| layer.order == egui::Order::Background)
+                        });
+                        let scene = &mut renderer.scene;
+                        let gizmo = &mut renderer.gizmo;
+                        if let Some(edit) = gizmo.show(ui, &response, scene, screen_rect) {
+                            renderer.history.push(edit);
+                        }
+                        if gizmo.wants_pointer() {
+                            scene.selection.hovered = None;
+                            return;
+                        }
+
+                        if renderer.gestures.is_touch_input() {
+                            scene.selection.hovered = None;
+                            for event in renderer.gestures.events() {
+                                let position = to_ndc(
+                                    egui::pos2(event.position.x, event.position.y)
+                                        / pixels_per_point,
+                                );
+                                match event.gesture {
+                                    TouchGesture::Tap => {
+                                        let mode = scene.selection.mode(&egui::Modifiers::NONE);
+                                        scene.selection.select(scene.pick(position), mode);
+                                    }
+                                    TouchGesture::LongPress => scene
+                                        .selection
+                                        .select(scene.pick(position), SelectionMode::Toggle),
+                                    TouchGesture::DoubleTap => {
+                                        if let Some(bounds) =
+                                            scene.selection_bounds().or_else(|| scene.bvh.bounds())
+                                        {
+                                            scene.camera.frame_bounds(&bounds);
+                                        }
+                                    }
+                                    _ => (),
+                                }
+                            }
+                            return;
+                        }
+
+                        let mode = scene.selection.mode(&ui.input(|input| input.modifiers));
+                        scene.selection.hovered = response
+                            .hover_pos()
+                            .and_then(|position| scene.pick(to_ndc(position)));
+
+                        if response.clicked()
+                            && let Some(position) = response.interact_pointer_pos()
+                        {
+                            let object = scene.pick(to_ndc(position));
+                            scene.selection.select(object, mode);
+                        }
+
+                        if response.drag_started()
+                            && let Some(position) = response.interact_pointer_pos()
+                        {
+                            ui.data_mut(|data| data.insert_temp(response.id, position));
+                        }
+                        let drag_origin = ui.data(|data| data.get_temp::<egui::Pos2>(response.id));
+                        if let (Some(origin), Some(position)) =
+                            (drag_origin, response.interact_pointer_pos())
+                        {
+                            let rect = egui::Rect::from_two_pos(origin, position);
+                            if response.drag_stopped() {
+                                ui.data_mut(|data| data.remove::<egui::Pos2>(response.id));
+                                let objects = scene.pick_rect(
+                                    to_ndc(rect.left_bottom()),
+                                    to_ndc(rect.right_top()),
+                                );
+                                scene.selection.select_all(objects, mode);
+                            } else if response.dragged() {
+                                let color = egui::Color32::from_rgb(80, 140, 255);
+                                ui.painter().rect(
+                                    rect,
+                                    0.0,
+                                    color.gamma_multiply(0.15),
+                                    egui::Stroke::new(1.0_f32, color),
+                                    egui::StrokeKind::Inside,
+                                );
+                            }
+                        }
                     });
+
+                    if !ui.input(|input| input.pointer.any_down()) {
+                        renderer.history.end_merge();
+                    }
                 });
 
+                drop(ui_scope);
+
+                renderer.actions.keyboard_enabled =
+                    !gui_state.egui_ctx().egui_wants_keyboard_input();
+                renderer.actions.update();
+                if renderer.actions.pressed("exit") {
+                    renderer.save_cvars();
+                    renderer.finish_recording();
+                    event_loop.exit();
+                    return;
+                }
+                renderer.update_camera(delta_time.as_secs_f32());
+
                 gui_state.handle_platform_output(window, platform_output);
 
-                let paint_jobs = gui_state.egui_ctx().tessellate(shapes, pixels_per_point);
+                let paint_jobs = {
+                    let _scope = trace::scope("egui::tessellate");
+                    gui_state.egui_ctx().tessellate(shapes, pixels_per_point)
+                };
 
                 let screen_descriptor = {
                     let (width, height) = self.last_size;
@@ -443,11 +1379,76 @@ impl ApplicationHandler for App {
 
 pub struct Renderer {
     gpu: Gpu,
-    depth_texture_view: wgpu::TextureView,
+    sample_count: u32,
+    transient_pool: TransientPool,
     egui_renderer: egui_wgpu::Renderer,
+    egui_textures: HashMap<egui::TextureId, egui::epaint::ImageDelta>,
+    hdr: HdrPipeline,
+    post: PostProcessor,
+    outline: SelectionOutline,
+    gizmo: Gizmo,
+    history: History,
+    scene_tree: SceneTree,
+    inspector: Inspector,
+    cvars: CvarRegistry,
+    cvar_handles: RendererCvars,
+    gpu_timer: GpuTimer,
+    perf: PerfOverlay,
+    resource_inspector: ResourceInspector,
+    actions: Actions,
+    gamepads: Gamepads,
+    gestures: GestureRecognizer,
+    camera_controls: CameraControls,
+    input_recorder: Option<InputRecorder>,
+    input_replay: Option<InputReplay>,
     scene: Scene,
 }
 
+#[derive(Clone, Copy)]
+struct RendererCvars {
+    present_mode: Cvar<String>,
+    vsync: Cvar<bool>,
+    msaa: Cvar<u32>,
+    clear_color: Cvar<[f32; 4]>,
+    scene: SceneCvars,
+    camera: CameraCvars,
+    gamepad: GamepadCvars,
+    replay: ReplayCvars,
+}
+
+impl RendererCvars {
+    fn declare(cvars: &mut CvarRegistry, present_mode: wgpu::PresentMode) -> Self {
+        let vsync = cvars.declare(
+            "render.vsync",
+            "Wait for vertical blank when presenting (switches the present mode)",
+            present_mode_is_vsync(present_mode),
+        );
+        cvars.alias("vsync", vsync);
+        Self {
+            present_mode: cvars.declare(
+                "render.present_mode",
+                "Surface present mode (fifo, fifo_relaxed, mailbox, immediate, auto_vsync, auto_no_vsync)",
+                present_mode_name(present_mode).to_string(),
+            ),
+            vsync,
+            msaa: cvars.declare(
+                "render.msaa",
+                "MSAA sample count",
+                DEFAULT_SAMPLE_COUNT,
+            ),
+            clear_color: cvars.declare(
+                "render.clear_color",
+                "Scene clear color (r g b [a])",
+                DEFAULT_CLEAR_COLOR,
+            ),
+            scene: SceneCvars::declare(cvars),
+            camera: CameraCvars::declare(cvars),
+            gamepad: GamepadCvars::declare(cvars),
+            replay: ReplayCvars::declare(cvars),
+        }
+    }
+}
+
 impl Renderer {
     const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
 
@@ -457,31 +1458,371 @@ impl Renderer {
         height: u32,
     ) -> Self {
         let gpu = Gpu::new_async(window, width, height).await;
-        let depth_texture_view = gpu.create_depth_texture(width, height);
 
-        let egui_renderer = egui_wgpu::Renderer::new(
+        let mut cvars = CvarRegistry::default();
+        let cvar_handles = RendererCvars::declare(&mut cvars, gpu.surface_config.present_mode);
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            if std::path::Path::new(CVARS_PATH).exists()
+                && let Err(error) = cvars.load(CVARS_PATH)
+            {
+                log::error!("Failed to load {CVARS_PATH}: {error}");
+            }
+            for error in cvars.apply_args(std::env::args().skip(1)) {
+                log::warn!("Command line: {error}");
+            }
+        }
+
+        let sample_count =
+            preferred_sample_count(&gpu.supported_sample_counts, cvars.get(cvar_handles.msaa));
+        let egui_renderer = Self::create_egui_renderer(&gpu, sample_count);
+
+        let hdr = HdrPipeline::new(&gpu.device, width, height);
+        let post = PostProcessor::new(
             &gpu.device,
-            gpu.surface_config.format,
-            egui_wgpu::RendererOptions {
-                depth_stencil_format: Some(Self::DEPTH_FORMAT),
-                msaa_samples: 1,
-                ..Default::default()
-            },
+            &gpu.queue,
+            gpu.surface_format,
+            sample_count,
+            width,
+            height,
+            hdr.color_view(),
         );
+        let outline = SelectionOutline::new(&gpu.device, post::LDR_FORMAT, 1, None, width, height);
+        let scene = Scene::new(&gpu.device, &gpu.queue, hdr::HDR_FORMAT, sample_count);
+        let gpu_timer = GpuTimer::new(&gpu.device, &gpu.queue);
 
-        let scene = Scene::new(&gpu.device, gpu.surface_format);
-
-        Self {
+        let mut renderer = Self {
             gpu,
-            depth_texture_view,
+            sample_count,
+            transient_pool: TransientPool::default(),
             egui_renderer,
+            egui_textures: HashMap::new(),
+            hdr,
+            post,
+            outline,
+            gizmo: Gizmo::default(),
+            history: History::default(),
+            scene_tree: SceneTree::default(),
+            inspector: Inspector::default(),
+            cvars,
+            cvar_handles,
+            gpu_timer,
+            perf: PerfOverlay::default(),
+            resource_inspector: ResourceInspector::default(),
+            actions: Actions::new(InputMap::load_or_default()),
+            gamepads: Gamepads::new(),
+            gestures: GestureRecognizer::default(),
+            camera_controls: CameraControls::default(),
+            input_recorder: None,
+            input_replay: None,
             scene,
+        };
+        renderer.inspector_mut().register(SpinComponent);
+        renderer
+    }
+
+    pub fn inspector(&self) -> &Inspector {
+        &self.inspector
+    }
+
+    pub fn inspector_mut(&mut self) -> &mut Inspector {
+        &mut self.inspector
+    }
+
+    pub fn actions(&self) -> &Actions {
+        &self.actions
+    }
+
+    pub fn actions_mut(&mut self) -> &mut Actions {
+        &mut self.actions
+    }
+
+    pub fn gamepads(&self) -> &Gamepads {
+        &self.gamepads
+    }
+
+    pub fn gamepads_mut(&mut self) -> &mut Gamepads {
+        &mut self.gamepads
+    }
+
+    pub fn gestures(&self) -> &GestureRecognizer {
+        &self.gestures
+    }
+
+    pub fn handle_input_event(&mut self, event: &InputEvent) {
+        self.actions.handle_input_event(event);
+        self.gestures.handle_input_event(event);
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.input_recorder.is_some()
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        self.input_replay.is_some()
+    }
+
+    pub fn start_recording(&mut self, path: &str) {
+        self.finish_recording();
+        let header = RecordingHeader {
+            version: replay::RECORDING_VERSION,
+            cvars: self.cvars.to_config(),
+            bindings: Some(self.actions.map().clone()),
+        };
+        self.input_recorder = Some(InputRecorder::new(path, header));
+        log::info!("Recording input to {path}");
+    }
+
+    pub fn finish_recording(&mut self) {
+        if let Some(recorder) = self.input_recorder.take() {
+            recorder.save();
+        }
+    }
+
+    pub fn start_replay(&mut self, replay: InputReplay) {
+        let header = replay.header();
+        for error in self.cvars.apply_config(&header.cvars) {
+            log::warn!("Replay cvars: {error}");
+        }
+        if let Some(bindings) = header.bindings.clone() {
+            self.actions.set_map(bindings);
+        }
+        self.actions.release_all();
+        self.gestures.cancel();
+        log::info!("Replaying {} recorded frames", replay.len());
+        self.input_replay = Some(replay);
+    }
+
+    pub fn update_camera(&mut self, delta_time: f32) {
+        let controls = self.camera_controls;
+        let camera = &mut self.scene.camera;
+        let movement = self.actions.vector2("move") * controls.move_speed * delta_time;
+        if movement != nalgebra_glm::Vec2::zeros() {
+            camera.translate(movement.x, movement.y);
+        }
+        let look = self.actions.vector2("look") * controls.look_speed;
+        if look != nalgebra_glm::Vec2::zeros() {
+            camera.orbit(-look.x, look.y);
+        }
+        let pan = self.actions.vector2("pan") * controls.pan_speed;
+        if pan != nalgebra_glm::Vec2::zeros() {
+            camera.translate(-pan.x, pan.y);
+        }
+        let orbit = self.actions.vector2("orbit") * controls.orbit_speed * delta_time;
+        if orbit != nalgebra_glm::Vec2::zeros() {
+            camera.orbit(-orbit.x, orbit.y);
+        }
+        let zoom = self.actions.axis("zoom") * controls.zoom_speed
+            + self.actions.axis("dolly") * controls.dolly_speed * delta_time;
+        if zoom != 0.0 {
+            camera.dolly(zoom);
+        }
+    }
+
+    pub fn cvars(&self) -> &CvarRegistry {
+        &self.cvars
+    }
+
+    pub fn cvars_mut(&mut self) -> &mut CvarRegistry {
+        &mut self.cvars
+    }
+
+    pub fn apply_cvars(&mut self) {
+        let handles = self.cvar_handles;
+        if self.cvars.take_changed(handles.replay.replay) {
+            let path = self.cvars.get(handles.replay.replay);
+            self.input_replay = None;
+            if !path.is_empty() {
+                #[cfg(not(target_arch = "wasm32"))]
+                match InputReplay::load(&path) {
+                    Ok(replay) => self.start_replay(replay),
+                    Err(error) => log::error!("input.replay: failed to load {path}: {error}"),
+                }
+                #[cfg(target_arch = "wasm32")]
+                log::error!("input.replay: loading recordings is not supported on the web");
+            }
+        }
+        if self.cvars.take_changed(handles.replay.record) {
+            let path = self.cvars.get(handles.replay.record);
+            if path.is_empty() {
+                self.finish_recording();
+            } else {
+                self.start_recording(&path);
+            }
+        }
+        if self.cvars.take_changed(handles.vsync) {
+            let vsync = self.cvars.get(handles.vsync);
+            let present_mode = parse_present_mode(&self.cvars.get(handles.present_mode))
+                .unwrap_or(self.gpu.surface_config.present_mode);
+            if present_mode_is_vsync(present_mode) != vsync {
+                let present_mode = if vsync {
+                    wgpu::PresentMode::AutoVsync
+                } else {
+                    wgpu::PresentMode::AutoNoVsync
+                };
+                self.cvars.set(
+                    handles.present_mode,
+                    present_mode_name(present_mode).to_string(),
+                );
+            }
+        }
+        if self.cvars.take_changed(handles.present_mode) {
+            let result = parse_present_mode(&self.cvars.get(handles.present_mode))
+                .and_then(|present_mode| self.gpu.set_present_mode(present_mode));
+            if let Err(error) = result {
+                log::error!("render.present_mode: {error}");
+                self.cvars.set(
+                    handles.present_mode,
+                    present_mode_name(self.gpu.surface_config.present_mode).to_string(),
+                );
+                self.cvars.take_changed(handles.present_mode);
+            }
+            self.cvars.set(
+                handles.vsync,
+                present_mode_is_vsync(self.gpu.surface_config.present_mode),
+            );
+            self.cvars.take_changed(handles.vsync);
+        }
+        if self.cvars.take_changed(handles.msaa) {
+            let sample_count = self.cvars.get(handles.msaa);
+            if self.gpu.supported_sample_counts.contains(&sample_count) {
+                self.set_sample_count(sample_count);
+            } else {
+                log::error!(
+                    "render.msaa: {sample_count}x is not supported (supported: {:?})",
+                    self.gpu.supported_sample_counts
+                );
+            }
+        }
+        handles.scene.apply(&mut self.cvars, &mut self.scene);
+        handles
+            .camera
+            .apply(&mut self.cvars, &mut self.camera_controls);
+        handles.gamepad.apply(&mut self.cvars, &mut self.gamepads);
+    }
+
+    pub fn save_cvars(&self) {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Err(error) = self.cvars.save(CVARS_PATH) {
+            log::error!("Failed to save {CVARS_PATH}: {error}");
+        }
+    }
+
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    pub fn supported_sample_counts(&self) -> &[u32] {
+        &self.gpu.supported_sample_counts
+    }
+
+    pub fn set_sample_count(&mut self, sample_count: u32) {
+        if sample_count == self.sample_count
+            || !self.gpu.supported_sample_counts.contains(&sample_count)
+        {
+            return;
+        }
+        log::info!("Switching MSAA to {sample_count}x");
+        self.sample_count = sample_count;
+
+        self.scene.set_sample_count(&self.gpu.device, sample_count);
+        self.post.set_sample_count(&self.gpu.device, sample_count);
+
+        self.egui_renderer = Self::create_egui_renderer(&self.gpu, sample_count);
+        for (id, image_delta) in &self.egui_textures {
+            self.egui_renderer
+                .update_texture(&self.gpu.device, &self.gpu.queue, *id, image_delta);
+        }
+    }
+
+    fn mirror_egui_texture(&mut self, id: egui::TextureId, image_delta: &egui::epaint::ImageDelta) {
+        let egui::ImageData::Color(patch) = &image_delta.image;
+        let Some([x, y]) = image_delta.pos else {
+            self.egui_textures.insert(id, image_delta.clone());
+            return;
+        };
+        let Some(mirror) = self.egui_textures.get_mut(&id) else {
+            return;
+        };
+        let egui::ImageData::Color(image) = &mut mirror.image;
+        let image = Arc::make_mut(image);
+        let width = image.width();
+        for (row, pixels) in patch.pixels.chunks_exact(patch.width()).enumerate() {
+            let start = (y + row) * width + x;
+            image.pixels[start..start + pixels.len()].copy_from_slice(pixels);
         }
+        mirror.options = image_delta.options;
+    }
+
+    fn create_egui_renderer(gpu: &Gpu, sample_count: u32) -> egui_wgpu::Renderer {
+        egui_wgpu::Renderer::new(
+            &gpu.device,
+            gpu.surface_config.format,
+            egui_wgpu::RendererOptions {
+                depth_stencil_format: None,
+                msaa_samples: sample_count,
+                ..Default::default()
+            },
+        )
     }
 
     pub fn resize(&mut self, width: u32, height: u32) {
         self.gpu.resize(width, height);
-        self.depth_texture_view = self.gpu.create_depth_texture(width, height);
+        self.hdr.resize(&self.gpu.device, width, height);
+        self.post
+            .resize(&self.gpu.device, width, height, self.hdr.color_view());
+        self.outline.resize(&self.gpu.device, width, height);
+    }
+
+    pub fn scene_file(&self) -> SceneFile {
+        SceneFile {
+            camera: self.scene.camera,
+            light: self.scene.light,
+            material: self.scene.material,
+            shadows: self.scene.shadows.settings(),
+            tonemap: self.hdr.settings,
+            post_process: self.post.settings.clone(),
+        }
+    }
+
+    pub fn apply_scene_file(&mut self, scene_file: SceneFile) {
+        self.scene.camera = scene_file.camera;
+        self.scene.light = scene_file.light;
+        self.scene.material = scene_file.material;
+        self.scene
+            .shadows
+            .set_settings(&self.gpu.device, scene_file.shadows);
+        self.hdr.settings = scene_file.tonemap;
+        self.post.settings = scene_file.post_process;
+
+        match self.post.settings.lut_path().map(str::to_owned) {
+            Some(path) => {
+                if let Err(error) = self.load_lut(&path) {
+                    log::error!("Failed to load LUT {path}: {error}");
+                    self.post.reset_lut(&self.gpu.device, &self.gpu.queue);
+                }
+            }
+            None => self.post.reset_lut(&self.gpu.device, &self.gpu.queue),
+        }
+    }
+
+    pub fn load_scene(&mut self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let scene_file = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        self.apply_scene_file(scene_file);
+        log::info!("Loaded scene from {path}");
+        Ok(())
+    }
+
+    pub fn save_scene(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        std::fs::write(path, serde_json::to_string_pretty(&self.scene_file())?)?;
+        log::info!("Saved scene to {path}");
+        Ok(())
+    }
+
+    pub fn load_lut(&mut self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let source = std::fs::read_to_string(path)?;
+        self.post
+            .load_lut(&self.gpu.device, &self.gpu.queue, &source)
     }
 
     pub fn render_frame(
@@ -491,18 +1832,36 @@ impl Renderer {
         textures_delta: egui::TexturesDelta,
         delta_time: crate::Duration,
     ) {
+        let _scope = trace::scope("Renderer::render_frame");
+        let cpu_start = Instant::now();
         let delta_time = delta_time.as_secs_f32();
 
+        self.gpu_timer.begin_frame(&self.gpu.device);
         self.scene
             .update(&self.gpu.queue, self.gpu.aspect_ratio(), delta_time);
+        self.hdr.update(&self.gpu.queue, delta_time);
+        self.post.update(&self.gpu.queue);
+        self.scene.prepare(&self.gpu.device, &self.gpu.queue);
+        self.outline.prepare(
+            &self.gpu.device,
+            &self.gpu.queue,
+            &self.scene,
+            &self.scene.world_model(),
+            &[self.scene.view_projection],
+        );
 
         for (id, image_delta) in &textures_delta.set {
+            perf::record_upload(
+                (image_delta.image.width() * image_delta.image.height() * 4) as u64,
+            );
             self.egui_renderer
                 .update_texture(&self.gpu.device, &self.gpu.queue, *id, image_delta);
+            self.mirror_egui_texture(*id, image_delta);
         }
 
         for id in &textures_delta.free {
             self.egui_renderer.free_texture(id);
+            self.egui_textures.remove(id);
         }
 
         let mut encoder = self
@@ -519,6 +1878,15 @@ impl Renderer {
             &paint_jobs,
             &screen_descriptor,
         );
+        for job in &paint_jobs {
+            if let egui::epaint::Primitive::Mesh(mesh) = &job.primitive {
+                perf::record_upload(
+                    (std::mem::size_of_val(mesh.vertices.as_slice())
+                        + std::mem::size_of_val(mesh.indices.as_slice()))
+                        as u64,
+                );
+            }
+        }
 
         let surface_texture = match self.gpu.surface.get_current_texture() {
             wgpu::CurrentSurfaceTexture::Success(frame)
@@ -553,48 +1921,149 @@ impl Renderer {
                     usage: None,
                 });
 
-        encoder.insert_debug_marker("Render scene");
+        let (width, height) = (
+            self.gpu.surface_config.width,
+            self.gpu.surface_config.height,
+        );
+        let sample_count = self.sample_count;
+        let clear_color = self.cvars.get(self.cvar_handles.clear_color);
+        let scene = &self.scene;
+        let hdr = &self.hdr;
+        let post = &self.post;
+        let outline = &self.outline;
+        let egui_renderer = &self.egui_renderer;
+
+        let mut graph = RenderGraph::new();
+        let shadow_map = graph.import_texture(scene.shadows.map_view());
+        let hdr_color = graph.import_texture(hdr.color_view());
+        let adapted_luminance = graph.import_texture(hdr.adapted_luminance_view());
+        let ldr_targets = post.import_targets(&mut graph);
+        let surface = graph.import_texture(&surface_texture_view);
+        let depth = graph.create_texture(TransientTexture::attachment(
+            "Depth Texture",
+            width,
+            height,
+            Self::DEPTH_FORMAT,
+            sample_count,
+        ));
+
+        graph
+            .add_pass("Shadow Pass")
+            .write_texture(shadow_map)
+            .encode(|encoder, _| scene.render_shadows(encoder));
+
+        let scene_color = if sample_count > 1 {
+            graph.create_texture(TransientTexture::attachment(
+                "Multisampled HDR Texture",
+                width,
+                height,
+                hdr::HDR_FORMAT,
+                sample_count,
+            ))
+        } else {
+            hdr_color
+        };
+        let culled_buffers = scene.culler.add_pass(&mut graph, &scene.batches);
+        let mut scene_pass = graph.add_pass("Scene Pass");
+        for buffer in &culled_buffers {
+            scene_pass = scene_pass.read_buffer(*buffer);
+        }
+        scene_pass
+            .read_texture(shadow_map)
+            .color_attachment(
+                scene_color,
+                (sample_count > 1).then_some(hdr_color),
+                wgpu::Color {
+                    r: clear_color[0].into(),
+                    g: clear_color[1].into(),
+                    b: clear_color[2].into(),
+                    a: clear_color[3].into(),
+                },
+            )
+            .depth_attachment(depth, 1.0)
+            .render(|render_pass, _| scene.render(render_pass));
+
+        post.add_hdr_passes(&mut graph, hdr_color);
+
+        if hdr.auto_exposure() {
+            graph
+                .add_pass("Exposure Pass")
+                .read_texture(hdr_color)
+                .write_texture(adapted_luminance)
+                .encode(|encoder, _| hdr.render_exposure(encoder));
+        }
 
-        {
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Render Pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &surface_texture_view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.19,
-                            g: 0.24,
-                            b: 0.42,
-                            a: 1.0,
-                        }),
-                        store: wgpu::StoreOp::Store,
-                    },
-                    depth_slice: None,
-                })],
-                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                    view: &self.depth_texture_view,
-                    depth_ops: Some(wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(1.0),
-                        store: wgpu::StoreOp::Store,
-                    }),
-                    stencil_ops: None,
-                }),
-                timestamp_writes: None,
-                occlusion_query_set: None,
-                multiview_mask: None,
+        graph
+            .add_pass("Tonemap Pass")
+            .read_texture(hdr_color)
+            .read_texture(adapted_luminance)
+            .color_attachment(ldr_targets[0], None, wgpu::Color::BLACK)
+            .render(|render_pass, _| hdr.render(render_pass));
+
+        let post_output = post.add_display_passes(&mut graph, ldr_targets);
+        if outline.is_active() {
+            outline.add_passes(&mut graph, scene, post_output);
+        }
+
+        let output_target = if sample_count > 1 {
+            graph.create_texture(TransientTexture::attachment(
+                "Multisampled Surface Texture",
+                width,
+                height,
+                self.gpu.surface_format,
+                sample_count,
+            ))
+        } else {
+            surface
+        };
+        graph
+            .add_pass("Output Pass")
+            .read_texture(post_output)
+            .color_attachment(
+                output_target,
+                (sample_count > 1).then_some(surface),
+                wgpu::Color::BLACK,
+            )
+            .render(|render_pass, _| {
+                post.render_output(render_pass);
+                egui_renderer.render(render_pass, &paint_jobs, &screen_descriptor);
+                for job in &paint_jobs {
+                    if let egui::epaint::Primitive::Mesh(mesh) = &job.primitive {
+                        perf::record_draw(mesh.indices.len() as u64 / 3);
+                    }
+                }
             });
-            self.scene.render(&mut render_pass);
 
-            self.egui_renderer.render(
-                &mut render_pass.forget_lifetime(),
-                &paint_jobs,
-                &screen_descriptor,
-            );
+        graph.set_timer(&mut self.gpu_timer);
+        {
+            let _scope = trace::scope("RenderGraph::execute");
+            graph.execute(&self.gpu.device, &mut encoder, &mut self.transient_pool);
         }
+        self.gpu_timer.resolve(&mut encoder);
 
-        self.gpu.queue.submit(std::iter::once(encoder.finish()));
-        surface_texture.present();
+        {
+            let _scope = trace::scope("Queue::submit");
+            self.gpu.queue.submit(std::iter::once(encoder.finish()));
+        }
+        self.scene.culler.finish_frame();
+        self.gpu_timer.finish_frame();
+        let gpu_completed = self.gpu_timer.take_completed();
+        self.perf.push(FrameSample {
+            frame_time: delta_time * 1000.0,
+            cpu_time: cpu_start.elapsed().as_secs_f32() * 1000.0,
+            gpu_time: self.gpu_timer.frame_time().filter(|_| gpu_completed),
+            counters: FrameCounters::take(),
+            passes: if gpu_completed {
+                self.gpu_timer.timings().to_vec()
+            } else {
+                Vec::new()
+            },
+        });
+        {
+            let _scope = trace::scope("Surface::present");
+            surface_texture.present();
+        }
+        trace::frame_mark();
     }
 }
 
@@ -604,6 +2073,8 @@ pub struct Gpu {
     pub queue: wgpu::Queue,
     pub surface_config: wgpu::SurfaceConfiguration,
     pub surface_format: wgpu::TextureFormat,
+    pub supported_sample_counts: Vec<u32>,
+    pub present_modes: Vec<wgpu::PresentMode>,
 }
 
 impl Gpu {
@@ -617,35 +2088,29 @@ impl Gpu {
         self.surface.configure(&self.device, &self.surface_config);
     }
 
-    pub fn create_depth_texture(&self, width: u32, height: u32) -> wgpu::TextureView {
-        let texture = self.device.create_texture(
-            &(wgpu::TextureDescriptor {
-                label: Some("Depth Texture"),
-                size: wgpu::Extent3d {
-                    width,
-                    height,
-                    depth_or_array_layers: 1,
-                },
-                mip_level_count: 1,
-                sample_count: 1,
-                dimension: wgpu::TextureDimension::D2,
-                format: wgpu::TextureFormat::Depth32Float,
-                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
-                    | wgpu::TextureUsages::TEXTURE_BINDING,
-                view_formats: &[],
-            }),
+    pub fn set_present_mode(&mut self, present_mode: wgpu::PresentMode) -> Result<(), String> {
+        if present_mode == self.surface_config.present_mode {
+            return Ok(());
+        }
+        let is_auto = matches!(
+            present_mode,
+            wgpu::PresentMode::AutoVsync | wgpu::PresentMode::AutoNoVsync
         );
-        texture.create_view(&wgpu::TextureViewDescriptor {
-            label: None,
-            format: Some(wgpu::TextureFormat::Depth32Float),
-            dimension: Some(wgpu::TextureViewDimension::D2),
-            aspect: wgpu::TextureAspect::All,
-            base_mip_level: 0,
-            base_array_layer: 0,
-            array_layer_count: None,
-            mip_level_count: None,
-            usage: None,
-        })
+        if !is_auto && !self.present_modes.contains(&present_mode) {
+            return Err(format!(
+                "{} is not supported (supported: {})",
+                present_mode_name(present_mode),
+                self.present_modes
+                    .iter()
+                    .map(|&mode| present_mode_name(mode))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+        self.surface_config.present_mode = present_mode;
+        self.surface.configure(&self.device, &self.surface_config);
+        log::info!("Present mode set to {}", present_mode_name(present_mode));
+        Ok(())
     }
 
     pub async fn new_async(
@@ -671,7 +2136,7 @@ impl Gpu {
                 .request_device(&wgpu::DeviceDescriptor {
                     label: Some("WGPU Device"),
                     memory_hints: wgpu::MemoryHints::default(),
-                    required_features: wgpu::Features::default(),
+                    required_features: GpuTimer::required_features(adapter.features()),
                     #[cfg(not(target_arch = "wasm32"))]
                     required_limits: wgpu::Limits::default().using_resolution(adapter.limits()),
                     #[cfg(all(target_arch = "wasm32", feature = "webgpu"))]
@@ -708,12 +2173,20 @@ impl Gpu {
 
         surface.configure(&device, &surface_config);
 
+        let supported_sample_counts = supported_sample_counts(
+            &adapter,
+            &[hdr::HDR_FORMAT, Renderer::DEPTH_FORMAT, surface_format],
+        );
+        log::info!("Supported MSAA sample counts: {supported_sample_counts:?}");
+
         Self {
             surface,
             device,
             queue,
             surface_config,
             surface_format,
+            supported_sample_counts,
+            present_modes: surface_capabilities.present_modes,
         }
     }
 
@@ -737,7 +2210,7 @@ impl Gpu {
                 .request_device(&wgpu::DeviceDescriptor {
                     label: Some("WGPU Device"),
                     memory_hints: wgpu::MemoryHints::default(),
-                    required_features: wgpu::Features::default(),
+                    required_features: GpuTimer::required_features(adapter.features()),
                     required_limits: wgpu::Limits::default().using_resolution(adapter.limits()),
                     experimental_features: wgpu::ExperimentalFeatures::disabled(),
                     trace: wgpu::Trace::Off,
@@ -772,144 +2245,1648 @@ impl Gpu {
                 .unwrap()
         };
 
+        let supported_sample_counts = supported_sample_counts(
+            &adapter,
+            &[hdr::HDR_FORMAT, Renderer::DEPTH_FORMAT, surface_format],
+        );
+
         Self {
             surface: dummy_surface,
             device,
             queue,
             surface_config,
             surface_format,
+            supported_sample_counts,
+            present_modes: vec![wgpu::PresentMode::Fifo],
+        }
+    }
+}
+
+pub const MSAA_SAMPLE_COUNTS: [u32; 4] = [1, 2, 4, 8];
+pub const SCENE_PATH: &str = "scene.json";
+pub const DEFAULT_SAMPLE_COUNT: u32 = 4;
+pub const CVARS_PATH: &str = "config.cfg";
+pub const DEFAULT_CLEAR_COLOR: [f32; 4] = [0.19, 0.24, 0.42, 1.0];
+pub const DEFAULT_GRID_SIZE: f32 = 100.0;
+pub const DEFAULT_GRID_CELL_SIZE: f32 = 0.025;
+pub const DEFAULT_SPIN_SPEED: f32 = 30.0;
+pub const DEFAULT_CAMERA_MOVE_SPEED: f32 = 3.0;
+pub const DEFAULT_CAMERA_LOOK_SPEED: f32 = 0.005;
+pub const DEFAULT_CAMERA_ZOOM_SPEED: f32 = 0.1;
+pub const DEFAULT_CAMERA_ORBIT_SPEED: f32 = 2.0;
+pub const DEFAULT_CAMERA_DOLLY_SPEED: f32 = 4.0;
+pub const DEFAULT_CAMERA_PAN_SPEED: f32 = 0.01;
+pub const PRESENT_MODES: [(wgpu::PresentMode, &str); 6] = [
+    (wgpu::PresentMode::Fifo, "fifo"),
+    (wgpu::PresentMode::FifoRelaxed, "fifo_relaxed"),
+    (wgpu::PresentMode::Mailbox, "mailbox"),
+    (wgpu::PresentMode::Immediate, "immediate"),
+    (wgpu::PresentMode::AutoVsync, "auto_vsync"),
+    (wgpu::PresentMode::AutoNoVsync, "auto_no_vsync"),
+];
+
+const SCENE_OWNER: &str = "Scene";
+const CAMERA_PITCH_LIMIT: f32 = 1.5;
+
+pub fn supported_sample_counts(
+    adapter: &wgpu::Adapter,
+    formats: &[wgpu::TextureFormat],
+) -> Vec<u32> {
+    MSAA_SAMPLE_COUNTS
+        .into_iter()
+        .filter(|&sample_count| {
+            formats.iter().all(|&format| {
+                let flags = adapter.get_texture_format_features(format).flags;
+                let resolvable = sample_count == 1
+                    || format.is_depth_stencil_format()
+                    || flags.contains(wgpu::TextureFormatFeatureFlags::MULTISAMPLE_RESOLVE);
+                flags.sample_count_supported(sample_count) && resolvable
+            })
+        })
+        .collect()
+}
+
+pub fn preferred_sample_count(supported_sample_counts: &[u32], requested: u32) -> u32 {
+    supported_sample_counts
+        .iter()
+        .copied()
+        .filter(|&sample_count| sample_count <= requested)
+        .max()
+        .unwrap_or(1)
+}
+
+fn register_console_commands(commands: &mut CommandRegistry<Renderer>) {
+    commands.register("load", "load <path>", |renderer, arguments| {
+        let [path] = arguments else {
+            return Err("Usage: load <path>".to_string());
+        };
+        renderer
+            .load_scene(path)
+            .map(|_| None)
+            .map_err(|error| format!("Failed to load scene {path}: {error}"))
+    });
+    commands.register("save", "save <path>", |renderer, arguments| {
+        let [path] = arguments else {
+            return Err("Usage: save <path>".to_string());
+        };
+        renderer
+            .save_scene(path)
+            .map(|_| None)
+            .map_err(|error| format!("Failed to save scene {path}: {error}"))
+    });
+    commands.register("set", "set <cvar> <value>", |renderer, arguments| {
+        let [name, value @ ..] = arguments else {
+            return Err("Usage: set <cvar> <value>".to_string());
+        };
+        if value.is_empty() {
+            return Err("Usage: set <cvar> <value>".to_string());
+        }
+        renderer.cvars.set_str(name, &value.join(" "))?;
+        Ok(None)
+    });
+    commands.register("get", "get <cvar>", |renderer, arguments| {
+        let [name] = arguments else {
+            return Err("Usage: get <cvar>".to_string());
+        };
+        let cvar = renderer
+            .cvars
+            .info(name)
+            .ok_or_else(|| format!("Unknown cvar '{name}'"))?;
+        Ok(Some(format!(
+            "{} = {} (default {})",
+            cvar.name, cvar.value, cvar.default
+        )))
+    });
+    commands.register("reset", "reset <cvar>", |renderer, arguments| {
+        let [name] = arguments else {
+            return Err("Usage: reset <cvar>".to_string());
+        };
+        renderer.cvars.reset(name)?;
+        Ok(None)
+    });
+    commands.register("cvars", "cvars [filter]", |renderer, arguments| {
+        let filter = arguments.first().map(String::as_str).unwrap_or_default();
+        let lines = renderer
+            .cvars
+            .iter()
+            .filter(|cvar| cvar.name.contains(filter))
+            .map(|cvar| {
+                format!(
+                    "  {} = {} [{}] {}",
+                    cvar.name,
+                    cvar.value,
+                    cvar.value.type_name(),
+                    cvar.description
+                )
+            })
+            .collect::<Vec<_>>();
+        if lines.is_empty() {
+            return Err(format!("No cvars match '{filter}'"));
+        }
+        Ok(Some(lines.join("\n")))
+    });
+    commands.register(
+        "actions",
+        "actions [save|reload]",
+        |renderer, arguments| match arguments {
+            [] => Ok(Some(
+                renderer
+                    .actions
+                    .map()
+                    .actions
+                    .iter()
+                    .map(|action| {
+                        let value = renderer.actions.vector2(&action.name);
+                        format!(
+                            "  {} [{:?}] = ({:.2}, {:.2}), {} bindings",
+                            action.name,
+                            action.kind,
+                            value.x,
+                            value.y,
+                            action.bindings.len()
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            )),
+            #[cfg(not(target_arch = "wasm32"))]
+            [command] if command == "save" => {
+                renderer
+                    .actions
+                    .map()
+                    .save(input::INPUT_BINDINGS_PATH)
+                    .map_err(|error| error.to_string())?;
+                Ok(Some(format!("Saved {}", input::INPUT_BINDINGS_PATH)))
+            }
+            [command] if command == "reload" => {
+                renderer.actions.set_map(InputMap::load_or_default());
+                Ok(Some("Reloaded input bindings".to_string()))
+            }
+            _ => Err("Usage: actions [save|reload]".to_string()),
+        },
+    );
+    commands.register("gamepads", "gamepads", |renderer, _| {
+        if !renderer.gamepads.is_supported() {
+            return Err("Gamepads are not available in this build".to_string());
+        }
+        let active = renderer.gamepads.active().map(|gamepad| gamepad.id);
+        let lines = renderer
+            .gamepads
+            .connected()
+            .iter()
+            .map(|gamepad| {
+                format!(
+                    "{} {}: {}{}",
+                    if Some(gamepad.id) == active { "*" } else { " " },
+                    gamepad.id,
+                    gamepad.name,
+                    if gamepad.rumble { " (rumble)" } else { "" }
+                )
+            })
+            .collect::<Vec<_>>();
+        if lines.is_empty() {
+            return Ok(Some("No gamepads connected".to_string()));
+        }
+        Ok(Some(lines.join("\n")))
+    });
+    commands.register(
+        "rumble",
+        "rumble [strength] [milliseconds]",
+        |renderer, arguments| {
+            if arguments.len() > 2 {
+                return Err("Usage: rumble [strength] [milliseconds]".to_string());
+            }
+            let strength = match arguments.first() {
+                Some(strength) => strength
+                    .parse::<f32>()
+                    .map_err(|_| format!("Invalid strength '{strength}'"))?,
+                None => 1.0,
+            };
+            let milliseconds = match arguments.get(1) {
+                Some(milliseconds) => milliseconds
+                    .parse::<u64>()
+                    .map_err(|_| format!("Invalid duration '{milliseconds}'"))?,
+                None => 250,
+            };
+            renderer
+                .gamepads
+                .rumble(strength, Duration::from_millis(milliseconds))?;
+            Ok(None)
+        },
+    );
+    commands.register(
+        "trace",
+        "trace <start|stop> [path]",
+        |_, arguments| match arguments {
+            [action] if action == "start" => {
+                trace::start_capture();
+                Ok(Some("Trace capture started".to_string()))
+            }
+            [action, path @ ..] if action == "stop" && path.len() <= 1 => {
+                if !trace::is_capturing() {
+                    return Err("No trace capture is running".to_string());
+                }
+                let capture = trace::stop_capture();
+                #[cfg(not(target_arch = "wasm32"))]
+                {
+                    let path = path.first().map_or(trace::TRACE_PATH, String::as_str);
+                    capture
+                        .write_chrome_trace(path)
+                        .map_err(|error| format!("Failed to write trace {path}: {error}"))?;
+                    Ok(Some(format!(
+                        "Wrote {} events to {path}",
+                        capture.events.len()
+                    )))
+                }
+                #[cfg(target_arch = "wasm32")]
+                {
+                    log::info!("{}", capture.to_chrome_json());
+                    Ok(Some(format!(
+                        "Logged {} events as Chrome trace JSON",
+                        capture.events.len()
+                    )))
+                }
+            }
+            _ => Err("Usage: trace <start|stop> [path]".to_string()),
+        },
+    );
+    commands.register("undo", "undo", |renderer, _| {
+        renderer.history.undo(&mut renderer.scene);
+        Ok(None)
+    });
+    commands.register("redo", "redo", |renderer, _| {
+        renderer.history.redo(&mut renderer.scene);
+        Ok(None)
+    });
+    commands.register("frame", "frame", |renderer, _| {
+        let scene = &mut renderer.scene;
+        let bounds = scene.selection_bounds().or_else(|| scene.bvh.bounds());
+        if let Some(bounds) = bounds {
+            scene.camera.frame_bounds(&bounds);
+        }
+        Ok(None)
+    });
+}
+
+pub fn present_mode_name(present_mode: wgpu::PresentMode) -> &'static str {
+    PRESENT_MODES
+        .iter()
+        .find(|(mode, _)| *mode == present_mode)
+        .map_or("unknown", |(_, name)| name)
+}
+
+pub fn present_mode_is_vsync(present_mode: wgpu::PresentMode) -> bool {
+    matches!(
+        present_mode,
+        wgpu::PresentMode::Fifo | wgpu::PresentMode::FifoRelaxed | wgpu::PresentMode::AutoVsync
+    )
+}
+
+pub fn parse_present_mode(name: &str) -> Result<wgpu::PresentMode, String> {
+    PRESENT_MODES
+        .iter()
+        .find(|(_, mode_name)| *mode_name == name)
+        .map(|(mode, _)| *mode)
+        .ok_or_else(|| format!("Unknown present mode '{name}'"))
+}
+
+fn delete_selection(history: &mut History, scene: &mut Scene) {
+    let objects = scene.with_descendants(scene.selection.iter().copied());
+    let command = history::RemoveInstances::new(scene, objects);
+    if !command.is_empty() {
+        history.execute(command, scene);
+    }
+}
+
+pub fn single_view_shader(source: &str) -> String {
+    format!(
+        "const view: u32 = 0u;\n{}",
+        source.replace(", @builtin(view_index) view: u32", "")
+    )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CameraControls {
+    pub move_speed: f32,
+    pub look_speed: f32,
+    pub zoom_speed: f32,
+    pub orbit_speed: f32,
+    pub dolly_speed: f32,
+    pub pan_speed: f32,
+}
+
+impl Default for CameraControls {
+    fn default() -> Self {
+        Self {
+            move_speed: DEFAULT_CAMERA_MOVE_SPEED,
+            look_speed: DEFAULT_CAMERA_LOOK_SPEED,
+            zoom_speed: DEFAULT_CAMERA_ZOOM_SPEED,
+            orbit_speed: DEFAULT_CAMERA_ORBIT_SPEED,
+            dolly_speed: DEFAULT_CAMERA_DOLLY_SPEED,
+            pan_speed: DEFAULT_CAMERA_PAN_SPEED,
         }
     }
 }
 
+#[derive(Clone, Copy)]
+pub struct CameraCvars {
+    pub move_speed: Cvar<f32>,
+    pub look_speed: Cvar<f32>,
+    pub zoom_speed: Cvar<f32>,
+    pub orbit_speed: Cvar<f32>,
+    pub dolly_speed: Cvar<f32>,
+    pub pan_speed: Cvar<f32>,
+}
+
+impl CameraCvars {
+    pub fn declare(cvars: &mut CvarRegistry) -> Self {
+        Self {
+            move_speed: cvars.declare(
+                "camera.move_speed",
+                "Camera movement speed in world units per second",
+                DEFAULT_CAMERA_MOVE_SPEED,
+            ),
+            look_speed: cvars.declare(
+                "camera.look_speed",
+                "Camera look sensitivity in radians per pixel",
+                DEFAULT_CAMERA_LOOK_SPEED,
+            ),
+            zoom_speed: cvars.declare(
+                "camera.zoom_speed",
+                "Camera zoom distance per scroll step",
+                DEFAULT_CAMERA_ZOOM_SPEED,
+            ),
+            orbit_speed: cvars.declare(
+                "camera.orbit_speed",
+                "Camera orbit speed in radians per second",
+                DEFAULT_CAMERA_ORBIT_SPEED,
+            ),
+            dolly_speed: cvars.declare(
+                "camera.dolly_speed",
+                "Camera dolly speed in world units per second",
+                DEFAULT_CAMERA_DOLLY_SPEED,
+            ),
+            pan_speed: cvars.declare(
+                "camera.pan_speed",
+                "Camera pan distance per pixel",
+                DEFAULT_CAMERA_PAN_SPEED,
+            ),
+        }
+    }
+
+    pub fn apply(&self, cvars: &mut CvarRegistry, controls: &mut CameraControls) {
+        for (cvar, speed) in [
+            (self.move_speed, &mut controls.move_speed),
+            (self.look_speed, &mut controls.look_speed),
+            (self.zoom_speed, &mut controls.zoom_speed),
+            (self.orbit_speed, &mut controls.orbit_speed),
+            (self.dolly_speed, &mut controls.dolly_speed),
+            (self.pan_speed, &mut controls.pan_speed),
+        ] {
+            if cvars.take_changed(cvar) {
+                *speed = cvars.get(cvar);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Camera {
+    pub position: nalgebra_glm::Vec3,
+    pub target: nalgebra_glm::Vec3,
+    pub fov_y: f32,
+    pub near: f32,
+    pub far: f32,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self {
+            position: nalgebra_glm::vec3(0.0, 2.0, 3.5),
+            target: nalgebra_glm::vec3(0.0, 1.0, 0.0),
+            fov_y: 80_f32.to_radians(),
+            near: 0.1,
+            far: 1000.0,
+        }
+    }
+}
+
+impl Camera {
+    pub fn view_matrix(&self) -> nalgebra_glm::Mat4 {
+        nalgebra_glm::look_at_lh(&self.position, &self.target, &nalgebra_glm::Vec3::y())
+    }
+
+    pub fn projection_matrix(&self, aspect_ratio: f32) -> nalgebra_glm::Mat4 {
+        nalgebra_glm::perspective_lh_zo(aspect_ratio, self.fov_y, self.near, self.far)
+    }
+
+    pub fn frame_bounds(&mut self, bounds: &Aabb) {
+        let center = bounds.center();
+        let radius = nalgebra_glm::length(&bounds.half_extents()).max(0.01);
+        let offset = self.position - self.target;
+        let direction = if nalgebra_glm::length(&offset) > f32::EPSILON {
+            nalgebra_glm::normalize(&offset)
+        } else {
+            nalgebra_glm::vec3(0.0, 0.0, -1.0)
+        };
+        let distance = radius / (self.fov_y * 0.5).sin();
+        self.target = center;
+        self.position = center + direction * distance;
+        self.far = self.far.max(distance + radius * 2.0);
+    }
+
+    pub fn orbit(&mut self, yaw: f32, pitch: f32) {
+        let offset = self.position - self.target;
+        let distance = nalgebra_glm::length(&offset).max(f32::EPSILON);
+        let current_pitch = (offset.y / distance).clamp(-1.0, 1.0).asin();
+        let pitch = (current_pitch + pitch).clamp(-CAMERA_PITCH_LIMIT, CAMERA_PITCH_LIMIT);
+        let current_yaw = offset.z.atan2(offset.x);
+        let yaw = current_yaw + yaw;
+        self.position = self.target
+            + nalgebra_glm::vec3(
+                pitch.cos() * yaw.cos(),
+                pitch.sin(),
+                pitch.cos() * yaw.sin(),
+            ) * distance;
+    }
+
+    pub fn dolly(&mut self, amount: f32) {
+        let offset = self.position - self.target;
+        let distance = nalgebra_glm::length(&offset);
+        if distance <= f32::EPSILON {
+            return;
+        }
+        let new_distance = (distance * (1.0 - amount)).clamp(self.near * 2.0, self.far * 0.5);
+        self.position = self.target + offset * (new_distance / distance);
+    }
+
+    pub fn translate(&mut self, right: f32, forward: f32) {
+        let view_direction = self.target - self.position;
+        let forward_direction = nalgebra_glm::vec3(view_direction.x, 0.0, view_direction.z);
+        if nalgebra_glm::length(&forward_direction) <= f32::EPSILON {
+            return;
+        }
+        let forward_direction = nalgebra_glm::normalize(&forward_direction);
+        let right_direction = nalgebra_glm::cross(&nalgebra_glm::Vec3::y(), &forward_direction);
+        let offset = right_direction * right + forward_direction * forward;
+        self.position += offset;
+        self.target += offset;
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct GridUniform {
+    pub view_proj: [[[f32; 4]; 4]; 2],
+    pub camera_world_pos: [[f32; 4]; 2],
+    pub grid_size: f32,
+    pub grid_min_pixels: f32,
+    pub grid_cell_size: f32,
+    pub orthographic_scale: f32,
+    pub is_orthographic: f32,
+    pub _padding: [f32; 3],
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct SkyUniform {
+    pub proj_inv: [[[f32; 4]; 4]; 2],
+    pub view: [[[f32; 4]; 4]; 2],
+    pub sun_direction: [f32; 4],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Material {
+    pub base_color: nalgebra_glm::Vec4,
+    pub metallic: f32,
+    pub roughness: f32,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Self {
+            base_color: nalgebra_glm::vec4(1.0, 1.0, 1.0, 1.0),
+            metallic: 0.0,
+            roughness: 0.5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct SceneFile {
+    pub camera: Camera,
+    pub light: DirectionalLight,
+    pub material: Material,
+    pub shadows: ShadowSettings,
+    pub tonemap: hdr::TonemapSettings,
+    pub post_process: PostProcessSettings,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SceneObject {
+    Mesh,
+    Instance { batch: usize, instance: InstanceId },
+}
+
+#[derive(Clone, Copy)]
+pub struct SceneCvars {
+    pub grid_size: Cvar<f32>,
+    pub grid_cell_size: Cvar<f32>,
+    pub spin_speed: Cvar<f32>,
+}
+
+impl SceneCvars {
+    pub fn declare(cvars: &mut CvarRegistry) -> Self {
+        Self {
+            grid_size: cvars.declare(
+                "scene.grid_size",
+                "Extent of the ground grid in world units",
+                DEFAULT_GRID_SIZE,
+            ),
+            grid_cell_size: cvars.declare(
+                "scene.grid_cell_size",
+                "Size of a ground grid cell relative to the grid extent",
+                DEFAULT_GRID_CELL_SIZE,
+            ),
+            spin_speed: cvars.declare(
+                "scene.spin_speed",
+                "Rotation speed of the scene mesh in degrees per second",
+                DEFAULT_SPIN_SPEED,
+            ),
+        }
+    }
+
+    pub fn apply(&self, cvars: &mut CvarRegistry, scene: &mut Scene) {
+        if cvars.take_changed(self.grid_size) {
+            scene.grid_size = cvars.get(self.grid_size);
+        }
+        if cvars.take_changed(self.grid_cell_size) {
+            scene.grid_cell_size = cvars.get(self.grid_cell_size);
+        }
+        if cvars.take_changed(self.spin_speed) {
+            scene.spin_speed = cvars.get(self.spin_speed);
+        }
+    }
+}
+
+#[derive(Default)]
+struct BatchProxies {
+    revision: u64,
+    proxies: HashMap<InstanceId, (ProxyId, u64)>,
+}
+
 pub struct Scene {
     pub model: nalgebra_glm::Mat4,
-    pub vertex_buffer: wgpu::Buffer,
-    pub index_buffer: wgpu::Buffer,
-    pub uniform: UniformBinding,
-    pub pipeline: wgpu::RenderPipeline,
+    pub camera: Camera,
+    pub light: DirectionalLight,
+    pub material: Material,
+    pub sky_mode: SkyMode,
+    pub grid_size: f32,
+    pub grid_cell_size: f32,
+    pub spin_speed: f32,
+    pub vertex_buffer: Tracked<wgpu::Buffer>,
+    pub index_buffer: Tracked<wgpu::Buffer>,
+    pub uniform: UniformRing<UniformBuffer>,
+    mesh_slot: UniformSlot,
+    pub pipeline: Tracked<wgpu::RenderPipeline>,
+    pub instanced_pipeline: Tracked<wgpu::RenderPipeline>,
+    pub batches: Vec<InstanceBatch>,
+    pub culler: FrustumCuller,
+    view_projection: nalgebra_glm::Mat4,
+    pub bvh: Bvh<SceneObject>,
+    pub mesh_bounds: Aabb,
+    mesh_proxy: ProxyId,
+    batch_proxies: Vec<BatchProxies>,
+    pub selection: Selection,
+    pub hierarchy: Hierarchy,
+    pub components: ComponentStore,
+    pub shadows: CascadedShadowMap,
+    pub environment: Environment,
+    color_format: wgpu::TextureFormat,
+    sample_count: u32,
+    sky_uniform_buffer: Tracked<wgpu::Buffer>,
+    sky_bind_group_layout: wgpu::BindGroupLayout,
+    sky_bind_group: Tracked<wgpu::BindGroup>,
+    sky_pipeline: Tracked<wgpu::RenderPipeline>,
+    environment_sky_pipeline: Tracked<wgpu::RenderPipeline>,
+    grid_uniform_buffer: Tracked<wgpu::Buffer>,
+    grid_bind_group_layout: wgpu::BindGroupLayout,
+    grid_bind_group: Tracked<wgpu::BindGroup>,
+    grid_pipeline: Tracked<wgpu::RenderPipeline>,
 }
 
 impl Scene {
-    pub fn new(device: &wgpu::Device, surface_format: wgpu::TextureFormat) -> Self {
-        let vertex_buffer = wgpu::util::DeviceExt::create_buffer_init(
-            device,
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        color_format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> Self {
+        let vertex_buffer = ResourceTracker::new(device, SCENE_OWNER).create_buffer_init(
             &wgpu::util::BufferInitDescriptor {
                 label: Some("Vertex Buffer"),
                 contents: bytemuck::cast_slice(&VERTICES),
                 usage: wgpu::BufferUsages::VERTEX,
             },
         );
-        let index_buffer = wgpu::util::DeviceExt::create_buffer_init(
-            device,
+        let index_buffer = ResourceTracker::new(device, SCENE_OWNER).create_buffer_init(
             &wgpu::util::BufferInitDescriptor {
                 label: Some("index Buffer"),
                 contents: bytemuck::cast_slice(&INDICES),
                 usage: wgpu::BufferUsages::INDEX,
             },
         );
-        let uniform = UniformBinding::new(device);
-        let pipeline = Self::create_pipeline(device, surface_format, &uniform);
+        let mesh_positions = VERTICES.iter().map(Vertex::position).collect::<Vec<_>>();
+        let mesh_bounds = Aabb::from_points(&mesh_positions);
+        let mut bvh = Bvh::default();
+        let mesh_proxy = bvh.insert(mesh_bounds, SceneObject::Mesh);
+
+        let mut uniform = UniformRing::new(
+            device,
+            "Mesh Uniform Ring",
+            wgpu::ShaderStages::VERTEX_FRAGMENT,
+        );
+        let mesh_slot = uniform.push(&UniformBuffer::default());
+        let shadows = CascadedShadowMap::new(device, ShadowSettings::default());
+        let environment = Environment::new(device, queue);
+        let pipeline = Self::create_pipeline(
+            device,
+            color_format,
+            sample_count,
+            &uniform,
+            &shadows,
+            &environment,
+            false,
+        );
+        let instanced_pipeline = Self::create_pipeline(
+            device,
+            color_format,
+            sample_count,
+            &uniform,
+            &shadows,
+            &environment,
+            true,
+        );
+
+        let sky_uniform_buffer =
+            ResourceTracker::new(device, SCENE_OWNER).create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Sky Uniform Buffer"),
+                size: std::mem::size_of::<SkyUniform>() as u64,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+        let sky_bind_group_layout = Self::create_uniform_layout(device, "Sky Bind Group Layout");
+        let sky_bind_group = ResourceTracker::new(device, SCENE_OWNER).create_bind_group(
+            &wgpu::BindGroupDescriptor {
+                layout: &sky_bind_group_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: sky_uniform_buffer.as_entire_binding(),
+                }],
+                label: Some("Sky Bind Group"),
+            },
+        );
+        let sky_pipeline =
+            Self::create_sky_pipeline(device, color_format, sample_count, &sky_bind_group_layout);
+        let environment_sky_pipeline = Self::create_environment_sky_pipeline(
+            device,
+            color_format,
+            sample_count,
+            &sky_bind_group_layout,
+            &environment,
+        );
+
+        let grid_uniform_buffer =
+            ResourceTracker::new(device, SCENE_OWNER).create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Grid Uniform Buffer"),
+                size: std::mem::size_of::<GridUniform>() as u64,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+        let grid_bind_group_layout = Self::create_uniform_layout(device, "Grid Bind Group Layout");
+        let grid_bind_group = ResourceTracker::new(device, SCENE_OWNER).create_bind_group(
+            &wgpu::BindGroupDescriptor {
+                layout: &grid_bind_group_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: grid_uniform_buffer.as_entire_binding(),
+                }],
+                label: Some("Grid Bind Group"),
+            },
+        );
+        let grid_pipeline = Self::create_grid_pipeline(
+            device,
+            color_format,
+            sample_count,
+            &grid_bind_group_layout,
+            &shadows,
+        );
+
         Self {
             model: nalgebra_glm::Mat4::identity(),
+            camera: Camera::default(),
+            light: DirectionalLight::default(),
+            material: Material::default(),
+            sky_mode: SkyMode::Procedural,
+            grid_size: DEFAULT_GRID_SIZE,
+            grid_cell_size: DEFAULT_GRID_CELL_SIZE,
+            spin_speed: DEFAULT_SPIN_SPEED,
             uniform,
+            mesh_slot,
             pipeline,
+            instanced_pipeline,
+            batches: Vec::new(),
+            culler: FrustumCuller::new(device),
+            view_projection: nalgebra_glm::Mat4::identity(),
+            bvh,
+            mesh_bounds,
+            mesh_proxy,
+            batch_proxies: Vec::new(),
+            selection: Selection::default(),
+            hierarchy: Hierarchy::default(),
+            components: ComponentStore::default(),
             vertex_buffer,
             index_buffer,
+            shadows,
+            environment,
+            color_format,
+            sample_count,
+            sky_uniform_buffer,
+            sky_bind_group_layout,
+            sky_bind_group,
+            sky_pipeline,
+            environment_sky_pipeline,
+            grid_uniform_buffer,
+            grid_bind_group_layout,
+            grid_bind_group,
+            grid_pipeline,
         }
     }
 
-    pub fn render<'rpass>(&'rpass self, renderpass: &mut wgpu::RenderPass<'rpass>) {
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    pub fn set_sample_count(&mut self, device: &wgpu::Device, sample_count: u32) {
+        self.sample_count = sample_count;
+        self.pipeline = Self::create_pipeline(
+            device,
+            self.color_format,
+            sample_count,
+            &self.uniform,
+            &self.shadows,
+            &self.environment,
+            false,
+        );
+        self.instanced_pipeline = Self::create_pipeline(
+            device,
+            self.color_format,
+            sample_count,
+            &self.uniform,
+            &self.shadows,
+            &self.environment,
+            true,
+        );
+        self.sky_pipeline = Self::create_sky_pipeline(
+            device,
+            self.color_format,
+            sample_count,
+            &self.sky_bind_group_layout,
+        );
+        self.environment_sky_pipeline = Self::create_environment_sky_pipeline(
+            device,
+            self.color_format,
+            sample_count,
+            &self.sky_bind_group_layout,
+            &self.environment,
+        );
+        self.grid_pipeline = Self::create_grid_pipeline(
+            device,
+            self.color_format,
+            sample_count,
+            &self.grid_bind_group_layout,
+            &self.shadows,
+        );
+    }
+
+    pub fn render(&self, renderpass: &mut wgpu::RenderPass<'_>) {
+        match self.sky_mode {
+            SkyMode::Procedural => renderpass.set_pipeline(&self.sky_pipeline),
+            SkyMode::Environment => {
+                renderpass.set_pipeline(&self.environment_sky_pipeline);
+                renderpass.set_bind_group(2, &self.environment.bind_group, &[]);
+            }
+        }
+        renderpass.set_bind_group(0, &self.sky_bind_group, &[]);
+        renderpass.draw(0..3, 0..1);
+        perf::record_draw(1);
+
         renderpass.set_pipeline(&self.pipeline);
-        renderpass.set_bind_group(0, &self.uniform.bind_group, &[]);
+        renderpass.set_bind_group(
+            0,
+            self.uniform.bind_group(),
+            &[self.uniform.offset(self.mesh_slot)],
+        );
+        renderpass.set_bind_group(1, &self.shadows.bind_group, &[]);
+        renderpass.set_bind_group(2, &self.environment.bind_group, &[]);
+
+        if self.is_visible(SceneObject::Mesh) {
+            renderpass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            renderpass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+            renderpass.draw_indexed(0..(INDICES.len() as _), 0, 0..1);
+            perf::record_draw(INDICES.len() as u64 / 3);
+        }
 
-        renderpass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-        renderpass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        renderpass.set_pipeline(&self.instanced_pipeline);
+        self.render_instances(renderpass);
 
-        renderpass.draw_indexed(0..(INDICES.len() as _), 0, 0..1);
+        renderpass.set_pipeline(&self.grid_pipeline);
+        renderpass.set_bind_group(0, &self.grid_bind_group, &[]);
+        renderpass.set_bind_group(1, &self.shadows.bind_group, &[]);
+        renderpass.draw(0..6, 0..1);
+        perf::record_draw(2);
     }
 
-    pub fn update(&mut self, queue: &wgpu::Queue, aspect_ratio: f32, delta_time: f32) {
-        let projection =
-            nalgebra_glm::perspective_lh_zo(aspect_ratio, 80_f32.to_radians(), 0.1, 1000.0);
-        let view = nalgebra_glm::look_at_lh(
-            &nalgebra_glm::vec3(0.0, 0.0, 3.0),
-            &nalgebra_glm::vec3(0.0, 0.0, 0.0),
-            &nalgebra_glm::Vec3::y(),
+    pub fn render_instances(&self, renderpass: &mut wgpu::RenderPass<'_>) {
+        for batch in &self.batches {
+            if self.culler.is_active() {
+                batch.render_culled(renderpass);
+            } else {
+                batch.render(renderpass);
+            }
+        }
+    }
+
+    pub fn prepare(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        self.uniform.upload(device, queue);
+        self.prepare_instances(device, queue, &[self.view_projection]);
+        let casters = Self::shadow_casters(
+            &self.vertex_buffer,
+            &self.index_buffer,
+            self.is_visible(SceneObject::Mesh)
+                .then(|| self.world_model()),
+            &self.batches,
+        );
+        self.shadows.prepare(device, queue, &casters);
+    }
+
+    pub fn prepare_instances(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        view_projections: &[nalgebra_glm::Mat4],
+    ) {
+        for batch in &mut self.batches {
+            batch.prepare(device, queue);
+        }
+        self.culler
+            .prepare(device, queue, &mut self.batches, view_projections);
+    }
+
+    pub fn render_shadows(&self, encoder: &mut wgpu::CommandEncoder) {
+        let casters = Self::shadow_casters(
+            &self.vertex_buffer,
+            &self.index_buffer,
+            self.is_visible(SceneObject::Mesh)
+                .then(|| self.world_model()),
+            &self.batches,
+        );
+        self.shadows.render(encoder, &casters);
+    }
+
+    pub fn shadow_casters<'a>(
+        vertex_buffer: &'a wgpu::Buffer,
+        index_buffer: &'a wgpu::Buffer,
+        model: Option<nalgebra_glm::Mat4>,
+        batches: &'a [InstanceBatch],
+    ) -> Vec<ShadowCaster<'a>> {
+        let mut casters = model
+            .map(|model| ShadowCaster {
+                vertex_buffer,
+                index_buffer,
+                index_count: INDICES.len() as u32,
+                model,
+                instances: None,
+            })
+            .into_iter()
+            .collect::<Vec<_>>();
+        casters.extend(
+            batches
+                .iter()
+                .filter(|batch| batch.uploaded_len() > 0)
+                .map(|batch| ShadowCaster {
+                    vertex_buffer: &batch.vertex_buffer,
+                    index_buffer: &batch.index_buffer,
+                    index_count: batch.index_count,
+                    model: nalgebra_glm::Mat4::identity(),
+                    instances: Some((&batch.instance_buffer, batch.uploaded_len() as u32)),
+                }),
         );
+        casters
+    }
+
+    pub fn cube_batch(&mut self, device: &wgpu::Device) -> usize {
+        match self.batches.iter().position(|batch| batch.name == "Cubes") {
+            Some(index) => index,
+            None => {
+                self.batches.push(InstanceBatch::new(
+                    device,
+                    "Cubes",
+                    &CUBE_VERTICES,
+                    &CUBE_INDICES,
+                ));
+                self.batches.len() - 1
+            }
+        }
+    }
+
+    pub fn spawn_cube_grid(&mut self, device: &wgpu::Device, count: usize) {
+        let index = self.cube_batch(device);
+        let batch = &mut self.batches[index];
+        batch.clear();
+        batch.add(&instancing::cube_grid(count, 0.25));
+    }
+
+    pub fn update_bounds(&mut self) {
+        self.bvh.update(
+            self.mesh_proxy,
+            self.mesh_bounds.transformed(&self.world_model()),
+        );
+
+        for proxies in self
+            .batch_proxies
+            .drain(self.batches.len().min(self.batch_proxies.len())..)
+        {
+            for (proxy, _) in proxies.proxies.into_values() {
+                self.bvh.remove(proxy);
+            }
+        }
+        for (batch_index, batch) in self.batches.iter().enumerate() {
+            if self.batch_proxies.len() <= batch_index {
+                self.batch_proxies.push(BatchProxies::default());
+            }
+            let proxies = &mut self.batch_proxies[batch_index];
+            if proxies.revision == batch.revision() {
+                continue;
+            }
+            let bvh = &mut self.bvh;
+            proxies.proxies.retain(|instance, (proxy, _)| {
+                let exists = batch.get(*instance).is_some();
+                if !exists {
+                    bvh.remove(*proxy);
+                }
+                exists
+            });
+            for (instance, data) in batch.iter() {
+                let revision = batch.instance_revision(instance).unwrap_or_default();
+                match proxies.proxies.get_mut(&instance) {
+                    Some((_, known)) if *known == revision => {}
+                    Some((proxy, known)) => {
+                        bvh.update(*proxy, batch.world_bounds(data));
+                        *known = revision;
+                    }
+                    None => {
+                        let proxy = bvh.insert(
+                            batch.world_bounds(data),
+                            SceneObject::Instance {
+                                batch: batch_index,
+                                instance,
+                            },
+                        );
+                        proxies.proxies.insert(instance, (proxy, revision));
+                    }
+                }
+            }
+            proxies.revision = batch.revision();
+        }
+
+        let batches = &self.batches;
+        self.selection.retain(|object| match *object {
+            SceneObject::Mesh => true,
+            SceneObject::Instance { batch, instance } => batches
+                .get(batch)
+                .is_some_and(|batch| batch.get(instance).is_some()),
+        });
+    }
+
+    pub fn exists(&self, object: SceneObject) -> bool {
+        match object {
+            SceneObject::Mesh => true,
+            SceneObject::Instance { batch, instance } => self
+                .batches
+                .get(batch)
+                .is_some_and(|batch| batch.get(instance).is_some()),
+        }
+    }
+
+    pub fn parent(&self, object: SceneObject) -> Option<SceneObject> {
+        self.hierarchy
+            .parent(object)
+            .filter(|parent| self.exists(*parent))
+    }
+
+    pub fn children(&self, object: SceneObject) -> Vec<SceneObject> {
+        self.hierarchy
+            .children(object)
+            .iter()
+            .copied()
+            .filter(|child| self.exists(*child))
+            .collect()
+    }
+
+    pub fn descendants(&self, object: SceneObject) -> Vec<SceneObject> {
+        let mut descendants = Vec::new();
+        let mut stack = self.children(object);
+        while let Some(child) = stack.pop() {
+            descendants.push(child);
+            stack.extend(self.children(child));
+        }
+        descendants
+    }
+
+    pub fn with_descendants(
+        &self,
+        objects: impl IntoIterator<Item = SceneObject>,
+    ) -> Vec<SceneObject> {
+        let mut result = Vec::new();
+        let mut seen = HashSet::new();
+        for object in objects {
+            for object in std::iter::once(object).chain(self.descendants(object)) {
+                if seen.insert(object) {
+                    result.push(object);
+                }
+            }
+        }
+        result
+    }
+
+    pub fn ancestors(&self, object: SceneObject) -> Vec<SceneObject> {
+        let mut ancestors = Vec::new();
+        let mut current = self.parent(object);
+        while let Some(parent) = current {
+            if ancestors.contains(&parent) {
+                break;
+            }
+            ancestors.push(parent);
+            current = self.parent(parent);
+        }
+        ancestors
+    }
+
+    pub fn is_visible(&self, object: SceneObject) -> bool {
+        std::iter::once(object)
+            .chain(self.ancestors(object))
+            .all(|object| self.hierarchy.info(object).visible)
+    }
+
+    pub fn is_locked(&self, object: SceneObject) -> bool {
+        std::iter::once(object)
+            .chain(self.ancestors(object))
+            .any(|object| self.hierarchy.info(object).locked)
+    }
+
+    pub fn is_pickable(&self, object: SceneObject) -> bool {
+        self.is_visible(object) && !self.is_locked(object)
+    }
+
+    fn sync_visibility(&mut self) {
+        let mut hidden = vec![Vec::new(); self.batches.len()];
+        for (object, info) in self.hierarchy.nodes() {
+            if info.visible || !self.exists(object) {
+                continue;
+            }
+            for object in std::iter::once(object).chain(self.descendants(object)) {
+                if let SceneObject::Instance { batch, instance } = object {
+                    hidden[batch].push(instance);
+                }
+            }
+        }
+        for (batch, hidden) in self.batches.iter_mut().zip(&hidden) {
+            batch.set_hidden(hidden);
+        }
+    }
+
+    pub fn object_label(&self, object: SceneObject) -> String {
+        if let Some(name) = self.hierarchy.name(object) {
+            return name.to_string();
+        }
+        match object {
+            SceneObject::Mesh => "Triangle".to_string(),
+            SceneObject::Instance { batch, instance } => {
+                let name = self
+                    .batches
+                    .get(batch)
+                    .map_or("Batch", |batch| batch.name.as_str());
+                format!("{name} #{}", instance.index())
+            }
+        }
+    }
+
+    pub fn object_bounds(&self, object: SceneObject) -> Option<Aabb> {
+        match object {
+            SceneObject::Mesh => Some(self.mesh_bounds.transformed(&self.world_model())),
+            SceneObject::Instance { batch, instance } => {
+                let batch = self.batches.get(batch)?;
+                batch
+                    .get(instance)
+                    .map(|instance| batch.world_bounds(instance))
+            }
+        }
+    }
+
+    pub fn object_transform(&self, object: SceneObject) -> Option<nalgebra_glm::Mat4> {
+        match object {
+            SceneObject::Mesh => Some(self.world_model()),
+            SceneObject::Instance { batch, instance } => self
+                .batches
+                .get(batch)?
+                .get(instance)
+                .map(|instance| nalgebra_glm::Mat4::from(instance.model)),
+        }
+    }
+
+    pub fn set_object_transform(&mut self, object: SceneObject, transform: &nalgebra_glm::Mat4) {
+        match object {
+            SceneObject::Mesh => {
+                self.model =
+                    nalgebra_glm::translation(&nalgebra_glm::vec3(0.0, -1.0, 0.0)) * transform;
+            }
+            SceneObject::Instance { batch, instance } => {
+                let Some(batch) = self.batches.get_mut(batch) else {
+                    return;
+                };
+                if let Some(data) = batch.get(instance) {
+                    let data = InstanceData {
+                        model: (*transform).into(),
+                        ..*data
+                    };
+                    batch.update(&[(instance, data)]);
+                }
+            }
+        }
+    }
+
+    pub fn view_projection(&self) -> nalgebra_glm::Mat4 {
+        self.view_projection
+    }
+
+    pub fn selection_bounds(&self) -> Option<Aabb> {
+        self.selection
+            .iter()
+            .filter_map(|object| self.object_bounds(*object))
+            .reduce(|bounds, other| bounds.union(&other))
+    }
+
+    pub fn raycast(&self, ray: &Ray, max_distance: f32) -> Option<(SceneObject, f32)> {
+        let mut closest: Option<(SceneObject, f32)> = None;
+        for (object, bounds_distance) in self.bvh.query_ray(ray, max_distance) {
+            let limit = closest.map_or(max_distance, |(_, distance)| distance);
+            if bounds_distance > limit {
+                break;
+            }
+            if !self.is_pickable(object) {
+                continue;
+            }
+            let distance = match object {
+                SceneObject::Mesh => {
+                    let positions = VERTICES.iter().map(Vertex::position).collect::<Vec<_>>();
+                    ray.intersects_mesh(&positions, &INDICES, &self.world_model(), limit)
+                }
+                SceneObject::Instance { batch, instance } => self
+                    .batches
+                    .get(batch)
+                    .and_then(|batch| batch.raycast(instance, ray, limit)),
+            };
+            if let Some(distance) = distance {
+                closest = Some((object, distance));
+            }
+        }
+        closest
+    }
+
+    pub fn pick(&self, ndc: nalgebra_glm::Vec2) -> Option<SceneObject> {
+        let ray = Ray::from_screen(&nalgebra_glm::inverse(&self.view_projection), ndc);
+        self.raycast(&ray, self.camera.far)
+            .map(|(object, _)| object)
+    }
+
+    pub fn pick_rect(&self, min: nalgebra_glm::Vec2, max: nalgebra_glm::Vec2) -> Vec<SceneObject> {
+        let size = nalgebra_glm::max(&(max - min), f32::EPSILON);
+        let center = (min + max) * 0.5;
+        let mut crop = nalgebra_glm::Mat4::identity();
+        crop[(0, 0)] = 2.0 / size.x;
+        crop[(1, 1)] = 2.0 / size.y;
+        crop[(0, 3)] = -2.0 * center.x / size.x;
+        crop[(1, 3)] = -2.0 * center.y / size.y;
+        self.bvh
+            .query_frustum(&Frustum::from_view_projection(
+                &(crop * self.view_projection),
+            ))
+            .into_iter()
+            .filter(|object| self.is_pickable(*object))
+            .collect()
+    }
+
+    pub fn world_model(&self) -> nalgebra_glm::Mat4 {
+        nalgebra_glm::translation(&nalgebra_glm::vec3(0.0, 1.0, 0.0)) * self.model
+    }
+
+    pub fn update(&mut self, queue: &wgpu::Queue, aspect_ratio: f32, delta_time: f32) {
+        let _scope = trace::scope("Scene::update");
+        let projection = self.camera.projection_matrix(aspect_ratio);
+        let view = self.camera.view_matrix();
         self.model = nalgebra_glm::rotate(
             &self.model,
-            30_f32.to_radians() * delta_time,
+            self.spin_speed.to_radians() * delta_time,
             &nalgebra_glm::Vec3::y(),
         );
-        self.uniform.update_buffer(
+        spin::update(self, delta_time);
+        self.update_bounds();
+        self.sync_visibility();
+        let model = self.world_model();
+        let camera_position = self.camera.position;
+        self.uniform.begin_frame();
+        self.mesh_slot = self.uniform.push(&UniformBuffer {
+            mvp: projection * view * model,
+            model,
+            view_projection: projection * view,
+            camera_position: nalgebra_glm::vec4(
+                camera_position.x,
+                camera_position.y,
+                camera_position.z,
+                1.0,
+            ),
+            base_color: self.material.base_color,
+            material: nalgebra_glm::vec4(self.material.metallic, self.material.roughness, 0.0, 0.0),
+        });
+        self.environment.update(queue);
+
+        let view_projection = projection * view;
+        self.view_projection = view_projection;
+        self.shadows.update(
+            queue,
+            &self.light,
+            &[ShadowView::new(
+                &view_projection,
+                self.camera.near,
+                self.camera.far,
+            )],
+        );
+
+        let sun_direction = self.light.sun_direction();
+        let sky_uniform = SkyUniform {
+            proj_inv: [nalgebra_glm::inverse(&projection).into(); 2],
+            view: [view.into(); 2],
+            sun_direction: [sun_direction.x, sun_direction.y, sun_direction.z, 0.0],
+        };
+        perf::write_buffer(
             queue,
+            &self.sky_uniform_buffer,
             0,
-            UniformBuffer {
-                mvp: projection * view * self.model,
-            },
+            bytemuck::cast_slice(&[sky_uniform]),
+        );
+
+        let grid_uniform = GridUniform {
+            view_proj: [view_projection.into(); 2],
+            camera_world_pos: [[camera_position.x, camera_position.y, camera_position.z, 1.0]; 2],
+            grid_size: self.grid_size,
+            grid_min_pixels: 2.0,
+            grid_cell_size: self.grid_cell_size,
+            orthographic_scale: 1.0,
+            is_orthographic: 0.0,
+            _padding: [0.0; 3],
+        };
+        perf::write_buffer(
+            queue,
+            &self.grid_uniform_buffer,
+            0,
+            bytemuck::cast_slice(&[grid_uniform]),
         );
     }
 
+    fn create_uniform_layout(device: &wgpu::Device, label: &str) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+            label: Some(label),
+        })
+    }
+
+    fn create_sky_pipeline(
+        device: &wgpu::Device,
+        color_format: wgpu::TextureFormat,
+        sample_count: u32,
+        bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Tracked<wgpu::RenderPipeline> {
+        let shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Sky Shader"),
+            source: wgpu::ShaderSource::Wgsl(single_view_shader(include_str!("sky.wgsl")).into()),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Sky Pipeline Layout"),
+            bind_group_layouts: &[Some(bind_group_layout)],
+            immediate_size: 0,
+        });
+
+        ResourceTracker::new(device, SCENE_OWNER).create_render_pipeline(
+            &wgpu::RenderPipelineDescriptor {
+                label: Some("Sky Pipeline"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader_module,
+                    entry_point: Some("vs_sky"),
+                    buffers: &[],
+                    compilation_options: Default::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader_module,
+                    entry_point: Some("fs_sky"),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: color_format,
+                        blend: Some(wgpu::BlendState::REPLACE),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                    compilation_options: Default::default(),
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: None,
+                    ..Default::default()
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: Renderer::DEPTH_FORMAT,
+                    depth_write_enabled: Some(false),
+                    depth_compare: Some(wgpu::CompareFunction::LessEqual),
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState {
+                    count: sample_count,
+                    ..Default::default()
+                },
+                multiview_mask: None,
+                cache: None,
+            },
+        )
+    }
+
+    fn create_environment_sky_pipeline(
+        device: &wgpu::Device,
+        color_format: wgpu::TextureFormat,
+        sample_count: u32,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        environment: &Environment,
+    ) -> Tracked<wgpu::RenderPipeline> {
+        let shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Environment Sky Shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                single_view_shader(concat!(
+                    include_str!("environment.wgsl"),
+                    include_str!("sky.wgsl"),
+                    include_str!("skybox.wgsl")
+                ))
+                .into(),
+            ),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Environment Sky Pipeline Layout"),
+            bind_group_layouts: &[
+                Some(bind_group_layout),
+                None,
+                Some(&environment.bind_group_layout),
+            ],
+            immediate_size: 0,
+        });
+
+        ResourceTracker::new(device, SCENE_OWNER).create_render_pipeline(
+            &wgpu::RenderPipelineDescriptor {
+                label: Some("Environment Sky Pipeline"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader_module,
+                    entry_point: Some("vs_sky"),
+                    buffers: &[],
+                    compilation_options: Default::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader_module,
+                    entry_point: Some("fs_environment"),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: color_format,
+                        blend: Some(wgpu::BlendState::REPLACE),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                    compilation_options: Default::default(),
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: None,
+                    ..Default::default()
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: Renderer::DEPTH_FORMAT,
+                    depth_write_enabled: Some(false),
+                    depth_compare: Some(wgpu::CompareFunction::LessEqual),
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState {
+                    count: sample_count,
+                    ..Default::default()
+                },
+                multiview_mask: None,
+                cache: None,
+            },
+        )
+    }
+
+    fn create_grid_pipeline(
+        device: &wgpu::Device,
+        color_format: wgpu::TextureFormat,
+        sample_count: u32,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        shadows: &CascadedShadowMap,
+    ) -> Tracked<wgpu::RenderPipeline> {
+        let shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Grid Shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                single_view_shader(concat!(
+                    include_str!("shadow.wgsl"),
+                    include_str!("grid.wgsl")
+                ))
+                .into(),
+            ),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Grid Pipeline Layout"),
+            bind_group_layouts: &[Some(bind_group_layout), Some(&shadows.bind_group_layout)],
+            immediate_size: 0,
+        });
+
+        ResourceTracker::new(device, SCENE_OWNER).create_render_pipeline(
+            &wgpu::RenderPipelineDescriptor {
+                label: Some("Grid Pipeline"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader_module,
+                    entry_point: Some("vertex_main"),
+                    buffers: &[],
+                    compilation_options: Default::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader_module,
+                    entry_point: Some("fragment_main"),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: color_format,
+                        blend: Some(wgpu::BlendState {
+                            color: wgpu::BlendComponent {
+                                src_factor: wgpu::BlendFactor::SrcAlpha,
+                                dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                                operation: wgpu::BlendOperation::Add,
+                            },
+                            alpha: wgpu::BlendComponent::OVER,
+                        }),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                    compilation_options: Default::default(),
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    strip_index_format: None,
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: None,
+                    unclipped_depth: false,
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    conservative: false,
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: Renderer::DEPTH_FORMAT,
+                    depth_write_enabled: Some(false),
+                    depth_compare: Some(wgpu::CompareFunction::LessEqual),
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState {
+                        constant: 2,
+                        slope_scale: 2.0,
+                        clamp: 0.0,
+                    },
+                }),
+                multisample: wgpu::MultisampleState {
+                    count: sample_count,
+                    ..Default::default()
+                },
+                multiview_mask: None,
+                cache: None,
+            },
+        )
+    }
+
     fn create_pipeline(
         device: &wgpu::Device,
-        surface_format: wgpu::TextureFormat,
-        uniform: &UniformBinding,
-    ) -> wgpu::RenderPipeline {
+        color_format: wgpu::TextureFormat,
+        sample_count: u32,
+        uniform: &UniformRing<UniformBuffer>,
+        shadows: &CascadedShadowMap,
+        environment: &Environment,
+        instanced: bool,
+    ) -> Tracked<wgpu::RenderPipeline> {
         let shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: None,
-            source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(SHADER_SOURCE)),
+            source: wgpu::ShaderSource::Wgsl(
+                format!(
+                    "{}{}{}{}",
+                    shadows::SHADOW_SHADER_SOURCE,
+                    ibl::ENVIRONMENT_SHADER_SOURCE,
+                    ibl::PBR_SHADER_SOURCE,
+                    if instanced {
+                        instancing::INSTANCED_SHADER_SOURCE
+                    } else {
+                        SHADER_SOURCE
+                    }
+                )
+                .into(),
+            ),
         });
 
+        let vertex_attributes = Vertex::vertex_attributes();
+        let instance_attributes = instancing::InstanceData::vertex_attributes();
+        let mut buffers = vec![Vertex::description(&vertex_attributes)];
+        if instanced {
+            buffers.push(instancing::InstanceData::description(&instance_attributes));
+        }
+
         let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: None,
-            bind_group_layouts: &[Some(&uniform.bind_group_layout)],
+            bind_group_layouts: &[
+                Some(&uniform.bind_group_layout),
+                Some(&shadows.bind_group_layout),
+                Some(&environment.bind_group_layout),
+            ],
             immediate_size: 0,
         });
 
-        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: None,
-            layout: Some(&pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader_module,
-                entry_point: Some("vertex_main"),
-                buffers: &[Vertex::description(&Vertex::vertex_attributes())],
-                compilation_options: Default::default(),
-            },
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleStrip,
-                strip_index_format: Some(wgpu::IndexFormat::Uint32),
-                front_face: wgpu::FrontFace::Cw,
-                cull_mode: None,
-                polygon_mode: wgpu::PolygonMode::Fill,
-                conservative: false,
-                unclipped_depth: false,
-            },
-            depth_stencil: Some(wgpu::DepthStencilState {
-                format: Renderer::DEPTH_FORMAT,
-                depth_write_enabled: Some(true),
-                depth_compare: Some(wgpu::CompareFunction::Less),
-                stencil: wgpu::StencilState::default(),
-                bias: wgpu::DepthBiasState::default(),
-            }),
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
+        ResourceTracker::new(device, SCENE_OWNER).create_render_pipeline(
+            &wgpu::RenderPipelineDescriptor {
+                label: None,
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader_module,
+                    entry_point: Some("vertex_main"),
+                    buffers: &buffers,
+                    compilation_options: Default::default(),
+                },
+                primitive: wgpu::PrimitiveState {
+                    topology: if instanced {
+                        wgpu::PrimitiveTopology::TriangleList
+                    } else {
+                        wgpu::PrimitiveTopology::TriangleStrip
+                    },
+                    strip_index_format: (!instanced).then_some(wgpu::IndexFormat::Uint32),
+                    front_face: wgpu::FrontFace::Cw,
+                    cull_mode: None,
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    conservative: false,
+                    unclipped_depth: false,
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: Renderer::DEPTH_FORMAT,
+                    depth_write_enabled: Some(true),
+                    depth_compare: Some(wgpu::CompareFunction::Less),
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState {
+                    count: sample_count,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader_module,
+                    entry_point: Some("fragment_main"),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: color_format,
+                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                    compilation_options: Default::default(),
+                }),
+                multiview_mask: None,
+                cache: None,
             },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader_module,
-                entry_point: Some("fragment_main"),
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: surface_format,
-                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-                compilation_options: Default::default(),
-            }),
-            multiview_mask: None,
-            cache: None,
-        })
+        )
     }
 }
 
@@ -921,6 +3898,10 @@ pub struct Vertex {
 }
 
 impl Vertex {
+    pub fn position(&self) -> nalgebra_glm::Vec3 {
+        nalgebra_glm::vec3(self.position[0], self.position[1], self.position[2])
+    }
+
     pub fn vertex_attributes() -> Vec<wgpu::VertexAttribute> {
         wgpu::vertex_attr_array![0 => Float32x4, 1 => Float32x4].to_vec()
     }
@@ -938,67 +3919,11 @@ impl Vertex {
 #[derive(Default, Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct UniformBuffer {
     mvp: nalgebra_glm::Mat4,
-}
-
-pub struct UniformBinding {
-    pub buffer: wgpu::Buffer,
-    pub bind_group: wgpu::BindGroup,
-    pub bind_group_layout: wgpu::BindGroupLayout,
-}
-
-impl UniformBinding {
-    pub fn new(device: &wgpu::Device) -> Self {
-        let buffer = wgpu::util::DeviceExt::create_buffer_init(
-            device,
-            &wgpu::util::BufferInitDescriptor {
-                label: Some("Uniform Buffer"),
-                contents: bytemuck::cast_slice(&[UniformBuffer::default()]),
-                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-            },
-        );
-
-        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            entries: &[wgpu::BindGroupLayoutEntry {
-                binding: 0,
-                visibility: wgpu::ShaderStages::VERTEX,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
-                },
-                count: None,
-            }],
-            label: Some("uniform_bind_group_layout"),
-        });
-
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: buffer.as_entire_binding(),
-            }],
-            label: Some("uniform_bind_group"),
-        });
-
-        Self {
-            buffer,
-            bind_group,
-            bind_group_layout,
-        }
-    }
-
-    pub fn update_buffer(
-        &mut self,
-        queue: &wgpu::Queue,
-        offset: wgpu::BufferAddress,
-        uniform_buffer: UniformBuffer,
-    ) {
-        queue.write_buffer(
-            &self.buffer,
-            offset,
-            bytemuck::cast_slice(&[uniform_buffer]),
-        )
-    }
+    model: nalgebra_glm::Mat4,
+    view_projection: nalgebra_glm::Mat4,
+    camera_position: nalgebra_glm::Vec4,
+    base_color: nalgebra_glm::Vec4,
+    material: nalgebra_glm::Vec4,
 }
 
 const VERTICES: [Vertex; 3] = [
@@ -1096,6 +4021,11 @@ pub const GREEN_CUBE_VERTICES: [Vertex; 8] = [
 const SHADER_SOURCE: &str = "
 struct Uniform {
     mvp: mat4x4<f32>,
+    model: mat4x4<f32>,
+    view_projection: mat4x4<f32>,
+    camera_position: vec4<f32>,
+    base_color: vec4<f32>,
+    material: vec4<f32>,
 };
 
 @group(0) @binding(0)
@@ -1108,6 +4038,7 @@ struct VertexInput {
 struct VertexOutput {
     @builtin(position) position: vec4<f32>,
     @location(0) color: vec4<f32>,
+    @location(1) world_pos: vec3<f32>,
 };
 
 @vertex
@@ -1115,11 +4046,17 @@ fn vertex_main(vert: VertexInput) -> VertexOutput {
     var out: VertexOutput;
     out.color = vert.color;
     out.position = ubo.mvp * vert.position;
+    out.world_pos = (ubo.model * vert.position).xyz;
     return out;
 };
 
 @fragment
 fn fragment_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return vec4<f32>(in.color);
+    let view_dir = normalize(ubo.camera_position.xyz - in.world_pos);
+    let face_normal = normalize(cross(dpdx(in.world_pos), dpdy(in.world_pos)));
+    let normal = select(-face_normal, face_normal, dot(face_normal, view_dir) > 0.0);
+    let albedo = in.color.rgb * ubo.base_color.rgb;
+    let lit = pbr_lighting(albedo, ubo.material.x, ubo.material.y, normal, view_dir, in.world_pos);
+    return vec4<f32>(shadow_debug_tint(lit, in.world_pos), in.color.a * ubo.base_color.a);
 }
 ";
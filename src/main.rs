@@ -1,7 +1,13 @@
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    app_core::trace::init();
+    app_core::trace::capture_from_args(std::env::args().skip(1));
+
     #[cfg(feature = "openxr")]
     {
-        app_core::run_xr()
+        let result = app_core::run_xr();
+        app_core::trace::finish_capture(app_core::trace::TRACE_PATH);
+        app_core::resources::log_leak_report();
+        result
     }
 
     #[cfg(not(feature = "openxr"))]
@@ -10,6 +16,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         event_loop.set_control_flow(winit::event_loop::ControlFlow::Poll);
         let mut application = app_core::App::default();
         event_loop.run_app(&mut application)?;
+        app_core::trace::finish_capture(app_core::trace::TRACE_PATH);
+        drop(application);
+        app_core::resources::log_leak_report();
         Ok(())
     }
 }
@@ -1,5 +1,6 @@
 struct Uniform {
     mvp: array<mat4x4<f32>, 2>,
+    model: mat4x4<f32>,
 };
 
 @group(0) @binding(0)
@@ -13,6 +14,7 @@ struct VertexInput {
 struct VertexOutput {
     @builtin(position) position: vec4<f32>,
     @location(0) color: vec4<f32>,
+    @location(1) world_pos: vec3<f32>,
 };
 
 @vertex
@@ -20,10 +22,37 @@ fn vertex_main(vert: VertexInput, @builtin(view_index) view: u32) -> VertexOutpu
     var out: VertexOutput;
     out.color = vert.color;
     out.position = ubo.mvp[view] * vert.position;
+    out.world_pos = (ubo.model * vert.position).xyz;
     return out;
 }
 
 @fragment
 fn fragment_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return vec4<f32>(in.color);
+    let normal = normalize(cross(dpdx(in.world_pos), dpdy(in.world_pos)));
+    let shadow = shadow_factor(in.world_pos, normal);
+    let lit = in.color.rgb * mix(0.35, 1.0, shadow);
+    return vec4<f32>(shadow_debug_tint(lit, in.world_pos), in.color.a);
+}
+
+struct InstanceInput {
+    @location(2) model_0: vec4<f32>,
+    @location(3) model_1: vec4<f32>,
+    @location(4) model_2: vec4<f32>,
+    @location(5) model_3: vec4<f32>,
+    @location(6) color: vec4<f32>,
+};
+
+@vertex
+fn vertex_instanced(
+    vert: VertexInput,
+    instance: InstanceInput,
+    @builtin(view_index) view: u32,
+) -> VertexOutput {
+    let model = mat4x4<f32>(instance.model_0, instance.model_1, instance.model_2, instance.model_3);
+    let world_position = ubo.model * model * vert.position;
+    var out: VertexOutput;
+    out.color = vert.color * instance.color;
+    out.position = ubo.mvp[view] * model * vert.position;
+    out.world_pos = world_position.xyz;
+    return out;
 }
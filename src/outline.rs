@@ -0,0 +1,739 @@
+use crate::instancing::InstanceData;
+use crate::perf;
+use crate::render_graph::{RenderGraph, TextureHandle};
+use crate::resources::{ResourceTracker, Tracked};
+use crate::uniform_ring::{UniformRing, UniformSlot};
+use crate::{Scene, SceneObject};
+
+use std::num::NonZeroU32;
+use std::ops::Range;
+
+const OWNER: &str = "Outline";
+
+pub const MASK_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+pub const MAX_OUTLINE_WIDTH: f32 = 32.0;
+
+const SEED_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Uint;
+const INITIAL_INSTANCE_CAPACITY: usize = 64;
+
+const SINGLE_VIEW_BINDINGS: &str = r#"
+@group(0) @binding(1)
+var mask_texture: texture_2d<f32>;
+
+@group(0) @binding(2)
+var seed_texture: texture_2d<u32>;
+
+fn load_mask(coord: vec2<i32>, view: u32) -> vec4<f32> {
+    return textureLoad(mask_texture, coord, 0);
+}
+
+fn load_seed(coord: vec2<i32>, view: u32) -> vec4<u32> {
+    return textureLoad(seed_texture, coord, 0);
+}
+"#;
+
+const MULTIVIEW_BINDINGS: &str = r#"
+@group(0) @binding(1)
+var mask_texture: texture_2d_array<f32>;
+
+@group(0) @binding(2)
+var seed_texture: texture_2d_array<u32>;
+
+fn load_mask(coord: vec2<i32>, view: u32) -> vec4<f32> {
+    return textureLoad(mask_texture, coord, i32(view), 0);
+}
+
+fn load_seed(coord: vec2<i32>, view: u32) -> vec4<u32> {
+    return textureLoad(seed_texture, coord, i32(view), 0);
+}
+"#;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct MaskUniform {
+    view_projection: [[[f32; 4]; 4]; 2],
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct OutlineUniform {
+    selected_color: [f32; 4],
+    hovered_color: [f32; 4],
+    width: f32,
+    encode_srgb: f32,
+    _padding: [f32; 2],
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct FloodUniform {
+    step: i32,
+    _padding: [i32; 3],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct OutlineSettings {
+    pub enabled: bool,
+    pub selected_color: [f32; 4],
+    pub hovered_color: [f32; 4],
+    pub width: f32,
+}
+
+impl Default for OutlineSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            selected_color: [1.0, 0.45, 0.05, 1.0],
+            hovered_color: [0.3, 0.7, 1.0, 1.0],
+            width: 3.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MaskSource {
+    Mesh,
+    Batch(usize),
+}
+
+struct OutlineTargets {
+    _mask_texture: Tracked<wgpu::Texture>,
+    _seed_textures: [Tracked<wgpu::Texture>; 2],
+    mask_view: wgpu::TextureView,
+    seed_views: [wgpu::TextureView; 2],
+    source_bind_groups: [Tracked<wgpu::BindGroup>; 2],
+}
+
+pub struct SelectionOutline {
+    pub settings: OutlineSettings,
+    layers: u32,
+    multiview_mask: Option<NonZeroU32>,
+    color_format: wgpu::TextureFormat,
+    shader_module: wgpu::ShaderModule,
+    source_bind_group_layout: wgpu::BindGroupLayout,
+    fullscreen_pipeline_layout: wgpu::PipelineLayout,
+    mask_uniform_buffer: Tracked<wgpu::Buffer>,
+    mask_bind_group: Tracked<wgpu::BindGroup>,
+    outline_uniform_buffer: Tracked<wgpu::Buffer>,
+    flood_uniforms: UniformRing<FloodUniform>,
+    flood_slots: Vec<UniformSlot>,
+    mask_pipeline: Tracked<wgpu::RenderPipeline>,
+    seed_pipeline: Tracked<wgpu::RenderPipeline>,
+    flood_pipeline: Tracked<wgpu::RenderPipeline>,
+    composite_pipeline: Tracked<wgpu::RenderPipeline>,
+    instance_buffer: Tracked<wgpu::Buffer>,
+    instance_capacity: usize,
+    draws: Vec<(MaskSource, Range<u32>)>,
+    targets: OutlineTargets,
+}
+
+impl SelectionOutline {
+    pub fn new(
+        device: &wgpu::Device,
+        color_format: wgpu::TextureFormat,
+        sample_count: u32,
+        multiview_mask: Option<NonZeroU32>,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let layers = multiview_mask.map_or(1, |mask| 32 - mask.get().leading_zeros());
+        let source = include_str!("outline.wgsl");
+        let shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Outline Shader"),
+            source: wgpu::ShaderSource::Wgsl(if multiview_mask.is_some() {
+                format!("{source}{MULTIVIEW_BINDINGS}").into()
+            } else {
+                crate::single_view_shader(&format!("{source}{SINGLE_VIEW_BINDINGS}")).into()
+            }),
+        });
+        let view_dimension = texture_view_dimension(layers);
+
+        let mask_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Outline Mask Bind Group Layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+            });
+        let texture_entry = |binding, sample_type| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type,
+                view_dimension,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let source_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Outline Source Bind Group Layout"),
+                entries: &[
+                    texture_entry(1, wgpu::TextureSampleType::Float { filterable: false }),
+                    texture_entry(2, wgpu::TextureSampleType::Uint),
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+
+        let mask_uniform_buffer =
+            ResourceTracker::new(device, OWNER).create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Outline Mask Uniform Buffer"),
+                size: std::mem::size_of::<MaskUniform>() as u64,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+        let mask_bind_group =
+            ResourceTracker::new(device, OWNER).create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Outline Mask Bind Group"),
+                layout: &mask_bind_group_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: mask_uniform_buffer.as_entire_binding(),
+                }],
+            });
+        let outline_uniform_buffer =
+            ResourceTracker::new(device, OWNER).create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Outline Uniform Buffer"),
+                size: std::mem::size_of::<OutlineUniform>() as u64,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+        let flood_uniforms = UniformRing::new(
+            device,
+            "Outline Flood Uniform Ring",
+            wgpu::ShaderStages::FRAGMENT,
+        );
+
+        let mask_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Outline Mask Pipeline Layout"),
+            bind_group_layouts: &[Some(&mask_bind_group_layout)],
+            immediate_size: 0,
+        });
+        let fullscreen_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Outline Pipeline Layout"),
+                bind_group_layouts: &[
+                    Some(&source_bind_group_layout),
+                    Some(&flood_uniforms.bind_group_layout),
+                ],
+                immediate_size: 0,
+            });
+
+        let vertex_attributes = crate::Vertex::vertex_attributes();
+        let instance_attributes = InstanceData::vertex_attributes();
+        let mask_pipeline = ResourceTracker::new(device, OWNER).create_render_pipeline(
+            &wgpu::RenderPipelineDescriptor {
+                label: Some("Outline Mask Pipeline"),
+                layout: Some(&mask_pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader_module,
+                    entry_point: Some("vs_mask"),
+                    buffers: &[
+                        crate::Vertex::description(&vertex_attributes),
+                        InstanceData::description(&instance_attributes),
+                    ],
+                    compilation_options: Default::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader_module,
+                    entry_point: Some("fs_mask"),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: MASK_FORMAT,
+                        blend: Some(wgpu::BlendState {
+                            color: wgpu::BlendComponent {
+                                src_factor: wgpu::BlendFactor::One,
+                                dst_factor: wgpu::BlendFactor::One,
+                                operation: wgpu::BlendOperation::Max,
+                            },
+                            alpha: wgpu::BlendComponent::REPLACE,
+                        }),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                    compilation_options: Default::default(),
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    cull_mode: None,
+                    ..Default::default()
+                },
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview_mask,
+                cache: None,
+            },
+        );
+        let seed_pipeline = Self::create_fullscreen_pipeline(
+            device,
+            &fullscreen_pipeline_layout,
+            &shader_module,
+            "fs_seed",
+            wgpu::ColorTargetState {
+                format: SEED_FORMAT,
+                blend: None,
+                write_mask: wgpu::ColorWrites::ALL,
+            },
+            1,
+            multiview_mask,
+        );
+        let flood_pipeline = Self::create_fullscreen_pipeline(
+            device,
+            &fullscreen_pipeline_layout,
+            &shader_module,
+            "fs_flood",
+            wgpu::ColorTargetState {
+                format: SEED_FORMAT,
+                blend: None,
+                write_mask: wgpu::ColorWrites::ALL,
+            },
+            1,
+            multiview_mask,
+        );
+        let composite_pipeline = Self::create_fullscreen_pipeline(
+            device,
+            &fullscreen_pipeline_layout,
+            &shader_module,
+            "fs_composite",
+            wgpu::ColorTargetState {
+                format: color_format,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            },
+            sample_count,
+            multiview_mask,
+        );
+
+        let instance_buffer = Self::create_instance_buffer(device, INITIAL_INSTANCE_CAPACITY);
+        let targets = Self::create_targets(
+            device,
+            &source_bind_group_layout,
+            &outline_uniform_buffer,
+            layers,
+            width,
+            height,
+        );
+
+        Self {
+            settings: OutlineSettings::default(),
+            layers,
+            multiview_mask,
+            color_format,
+            shader_module,
+            source_bind_group_layout,
+            fullscreen_pipeline_layout,
+            mask_uniform_buffer,
+            mask_bind_group,
+            outline_uniform_buffer,
+            flood_uniforms,
+            flood_slots: Vec::new(),
+            mask_pipeline,
+            seed_pipeline,
+            flood_pipeline,
+            composite_pipeline,
+            instance_buffer,
+            instance_capacity: INITIAL_INSTANCE_CAPACITY,
+            draws: Vec::new(),
+            targets,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.settings.enabled && !self.draws.is_empty()
+    }
+
+    pub fn set_sample_count(&mut self, device: &wgpu::Device, sample_count: u32) {
+        self.composite_pipeline = Self::create_fullscreen_pipeline(
+            device,
+            &self.fullscreen_pipeline_layout,
+            &self.shader_module,
+            "fs_composite",
+            wgpu::ColorTargetState {
+                format: self.color_format,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            },
+            sample_count,
+            self.multiview_mask,
+        );
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        self.targets = Self::create_targets(
+            device,
+            &self.source_bind_group_layout,
+            &self.outline_uniform_buffer,
+            self.layers,
+            width,
+            height,
+        );
+    }
+
+    pub fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        scene: &Scene,
+        mesh_model: &nalgebra_glm::Mat4,
+        view_projections: &[nalgebra_glm::Mat4],
+    ) {
+        self.draws.clear();
+        if !self.settings.enabled {
+            return;
+        }
+
+        let selection = &scene.selection;
+        let hovered = selection
+            .hovered
+            .filter(|hovered| !selection.contains(hovered));
+        let mut groups: Vec<(MaskSource, Vec<InstanceData>)> = Vec::new();
+        for (object, is_selected) in selection
+            .iter()
+            .map(|object| (*object, true))
+            .chain(hovered.map(|object| (object, false)))
+        {
+            if !scene.is_visible(object) {
+                continue;
+            }
+            let is_hovered = selection.hovered == Some(object);
+            let flags = [
+                if is_selected { 1.0 } else { 0.0 },
+                if is_hovered { 1.0 } else { 0.0 },
+                0.0,
+                1.0,
+            ];
+            let (source, model) = match object {
+                SceneObject::Mesh => (MaskSource::Mesh, (*mesh_model).into()),
+                SceneObject::Instance { batch, instance } => {
+                    let Some(data) = scene
+                        .batches
+                        .get(batch)
+                        .and_then(|batch| batch.get(instance))
+                    else {
+                        continue;
+                    };
+                    (MaskSource::Batch(batch), data.model)
+                }
+            };
+            let instance = InstanceData {
+                model,
+                color: flags,
+            };
+            match groups.iter_mut().find(|(group, _)| *group == source) {
+                Some((_, instances)) => instances.push(instance),
+                None => groups.push((source, vec![instance])),
+            }
+        }
+        if groups.is_empty() {
+            return;
+        }
+
+        let mut instances = Vec::new();
+        for (source, group) in groups {
+            let start = instances.len() as u32;
+            instances.extend(group);
+            self.draws.push((source, start..instances.len() as u32));
+        }
+        if instances.len() > self.instance_capacity {
+            self.instance_capacity = instances.len().next_power_of_two();
+            self.instance_buffer = Self::create_instance_buffer(device, self.instance_capacity);
+        }
+        perf::write_buffer(
+            queue,
+            &self.instance_buffer,
+            0,
+            bytemuck::cast_slice(&instances),
+        );
+
+        let first = view_projections
+            .first()
+            .copied()
+            .unwrap_or_else(nalgebra_glm::Mat4::identity);
+        let second = view_projections.get(1).copied().unwrap_or(first);
+        perf::write_buffer(
+            queue,
+            &self.mask_uniform_buffer,
+            0,
+            bytemuck::cast_slice(&[MaskUniform {
+                view_projection: [first.into(), second.into()],
+            }]),
+        );
+
+        let width = self.settings.width.clamp(0.5, MAX_OUTLINE_WIDTH);
+        perf::write_buffer(
+            queue,
+            &self.outline_uniform_buffer,
+            0,
+            bytemuck::cast_slice(&[OutlineUniform {
+                selected_color: self.settings.selected_color,
+                hovered_color: self.settings.hovered_color,
+                width,
+                encode_srgb: if self.color_format.is_srgb() {
+                    0.0
+                } else {
+                    1.0
+                },
+                _padding: [0.0; 2],
+            }]),
+        );
+
+        self.flood_uniforms.begin_frame();
+        self.flood_slots.clear();
+        let mut step = (width.ceil() as u32 + 1).next_power_of_two() / 2;
+        while step > 0 {
+            self.flood_slots
+                .push(self.flood_uniforms.push(&FloodUniform {
+                    step: step as i32,
+                    _padding: [0; 3],
+                }));
+            step /= 2;
+        }
+        self.flood_slots
+            .push(self.flood_uniforms.push(&FloodUniform {
+                step: 1,
+                _padding: [0; 3],
+            }));
+        self.flood_uniforms.upload(device, queue);
+    }
+
+    pub fn add_passes<'a>(
+        &'a self,
+        graph: &mut RenderGraph<'a>,
+        scene: &'a Scene,
+        target: TextureHandle,
+    ) {
+        let mask = graph.import_texture(&self.targets.mask_view);
+        let seeds = [
+            graph.import_texture(&self.targets.seed_views[0]),
+            graph.import_texture(&self.targets.seed_views[1]),
+        ];
+
+        graph
+            .add_pass("Outline Mask Pass")
+            .color_attachment(mask, None, wgpu::Color::TRANSPARENT)
+            .multiview_mask(self.multiview_mask)
+            .render(move |render_pass, _| self.render_mask(render_pass, scene));
+
+        graph
+            .add_pass("Outline Seed Pass")
+            .read_texture(mask)
+            .color_attachment(seeds[0], None, wgpu::Color::TRANSPARENT)
+            .multiview_mask(self.multiview_mask)
+            .render(move |render_pass, _| {
+                render_pass.set_pipeline(&self.seed_pipeline);
+                render_pass.set_bind_group(0, &self.targets.source_bind_groups[1], &[]);
+                render_pass.set_bind_group(
+                    1,
+                    self.flood_uniforms.bind_group(),
+                    &[self.flood_uniforms.offset(self.flood_slots[0])],
+                );
+                render_pass.draw(0..3, 0..1);
+                perf::record_draw(1);
+            });
+
+        let mut current = 0;
+        for slot in &self.flood_slots {
+            let next = 1 - current;
+            let bind_group = &self.targets.source_bind_groups[current];
+            graph
+                .add_pass("Outline Flood Pass")
+                .read_texture(seeds[current])
+                .color_attachment(seeds[next], None, wgpu::Color::TRANSPARENT)
+                .multiview_mask(self.multiview_mask)
+                .render(move |render_pass, _| {
+                    render_pass.set_pipeline(&self.flood_pipeline);
+                    render_pass.set_bind_group(0, bind_group, &[]);
+                    render_pass.set_bind_group(
+                        1,
+                        self.flood_uniforms.bind_group(),
+                        &[self.flood_uniforms.offset(*slot)],
+                    );
+                    render_pass.draw(0..3, 0..1);
+                    perf::record_draw(1);
+                });
+            current = next;
+        }
+
+        let bind_group = &self.targets.source_bind_groups[current];
+        let slot = self.flood_slots[0];
+        graph
+            .add_pass("Outline Composite Pass")
+            .read_texture(mask)
+            .read_texture(seeds[current])
+            .color_attachment(target, None, wgpu::Color::BLACK)
+            .multiview_mask(self.multiview_mask)
+            .render(move |render_pass, _| {
+                render_pass.set_pipeline(&self.composite_pipeline);
+                render_pass.set_bind_group(0, bind_group, &[]);
+                render_pass.set_bind_group(
+                    1,
+                    self.flood_uniforms.bind_group(),
+                    &[self.flood_uniforms.offset(slot)],
+                );
+                render_pass.draw(0..3, 0..1);
+                perf::record_draw(1);
+            });
+    }
+
+    fn render_mask(&self, render_pass: &mut wgpu::RenderPass<'_>, scene: &Scene) {
+        render_pass.set_pipeline(&self.mask_pipeline);
+        render_pass.set_bind_group(0, &self.mask_bind_group, &[]);
+        render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+        for (source, instances) in &self.draws {
+            let (vertex_buffer, index_buffer, index_count) = match source {
+                MaskSource::Mesh => (
+                    &scene.vertex_buffer,
+                    &scene.index_buffer,
+                    crate::INDICES.len() as u32,
+                ),
+                MaskSource::Batch(batch) => {
+                    let Some(batch) = scene.batches.get(*batch) else {
+                        continue;
+                    };
+                    (&batch.vertex_buffer, &batch.index_buffer, batch.index_count)
+                }
+            };
+            render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+            render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+            render_pass.draw_indexed(0..index_count, 0, instances.clone());
+            perf::record_draw(u64::from(index_count / 3) * instances.len() as u64);
+        }
+    }
+
+    fn create_instance_buffer(device: &wgpu::Device, capacity: usize) -> Tracked<wgpu::Buffer> {
+        ResourceTracker::new(device, OWNER).create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Outline Instance Buffer"),
+            size: (capacity * std::mem::size_of::<InstanceData>()) as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    fn create_fullscreen_pipeline(
+        device: &wgpu::Device,
+        layout: &wgpu::PipelineLayout,
+        shader_module: &wgpu::ShaderModule,
+        entry_point: &str,
+        target: wgpu::ColorTargetState,
+        sample_count: u32,
+        multiview_mask: Option<NonZeroU32>,
+    ) -> Tracked<wgpu::RenderPipeline> {
+        ResourceTracker::new(device, OWNER).create_render_pipeline(
+            &wgpu::RenderPipelineDescriptor {
+                label: Some(entry_point),
+                layout: Some(layout),
+                vertex: wgpu::VertexState {
+                    module: shader_module,
+                    entry_point: Some("vs_fullscreen"),
+                    buffers: &[],
+                    compilation_options: Default::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: shader_module,
+                    entry_point: Some(entry_point),
+                    targets: &[Some(target)],
+                    compilation_options: Default::default(),
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState {
+                    count: sample_count,
+                    ..Default::default()
+                },
+                multiview_mask,
+                cache: None,
+            },
+        )
+    }
+
+    fn create_targets(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        uniform_buffer: &wgpu::Buffer,
+        layers: u32,
+        width: u32,
+        height: u32,
+    ) -> OutlineTargets {
+        let create_texture = |label, format| {
+            ResourceTracker::new(device, OWNER).create_texture(&wgpu::TextureDescriptor {
+                label: Some(label),
+                size: wgpu::Extent3d {
+                    width: width.max(1),
+                    height: height.max(1),
+                    depth_or_array_layers: layers,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            })
+        };
+        let create_view = |texture: &Tracked<wgpu::Texture>| {
+            texture.create_view(&wgpu::TextureViewDescriptor {
+                dimension: Some(texture_view_dimension(layers)),
+                ..Default::default()
+            })
+        };
+
+        let mask_texture = create_texture("Outline Mask Texture", MASK_FORMAT);
+        let seed_textures = [
+            create_texture("Outline Seed Texture", SEED_FORMAT),
+            create_texture("Outline Seed Texture", SEED_FORMAT),
+        ];
+        let mask_view = create_view(&mask_texture);
+        let seed_views = seed_textures.each_ref().map(create_view);
+        let source_bind_groups = [0, 1].map(|index| {
+            ResourceTracker::new(device, OWNER).create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Outline Source Bind Group"),
+                layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(&mask_view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::TextureView(&seed_views[index]),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: uniform_buffer.as_entire_binding(),
+                    },
+                ],
+            })
+        });
+
+        OutlineTargets {
+            _mask_texture: mask_texture,
+            _seed_textures: seed_textures,
+            mask_view,
+            seed_views,
+            source_bind_groups,
+        }
+    }
+}
+
+fn texture_view_dimension(layers: u32) -> wgpu::TextureViewDimension {
+    if layers > 1 {
+        wgpu::TextureViewDimension::D2Array
+    } else {
+        wgpu::TextureViewDimension::D2
+    }
+}
@@ -0,0 +1,149 @@
+struct MaskUniform {
+    view_projection: array<mat4x4<f32>, 2>,
+};
+
+struct OutlineUniform {
+    selected_color: vec4<f32>,
+    hovered_color: vec4<f32>,
+    width: f32,
+    encode_srgb: f32,
+    _padding: vec2<f32>,
+};
+
+struct FloodUniform {
+    step: i32,
+    _padding0: i32,
+    _padding1: i32,
+    _padding2: i32,
+};
+
+@group(0) @binding(0)
+var<uniform> mask_uniform: MaskUniform;
+
+@group(0) @binding(3)
+var<uniform> outline: OutlineUniform;
+
+@group(1) @binding(0)
+var<uniform> flood: FloodUniform;
+
+struct MaskVertexInput {
+    @location(0) position: vec4<f32>,
+    @location(1) color: vec4<f32>,
+};
+
+struct MaskInstanceInput {
+    @location(2) model_0: vec4<f32>,
+    @location(3) model_1: vec4<f32>,
+    @location(4) model_2: vec4<f32>,
+    @location(5) model_3: vec4<f32>,
+    @location(6) flags: vec4<f32>,
+};
+
+struct MaskVertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) flags: vec4<f32>,
+};
+
+@vertex
+fn vs_mask(vert: MaskVertexInput, instance: MaskInstanceInput, @builtin(view_index) view: u32) -> MaskVertexOutput {
+    let model = mat4x4<f32>(instance.model_0, instance.model_1, instance.model_2, instance.model_3);
+    var out: MaskVertexOutput;
+    out.position = mask_uniform.view_projection[view] * model * vec4<f32>(vert.position.xyz, 1.0);
+    out.flags = instance.flags;
+    return out;
+}
+
+@fragment
+fn fs_mask(in: MaskVertexOutput) -> @location(0) vec4<f32> {
+    return in.flags;
+}
+
+struct FullscreenOutput {
+    @builtin(position) position: vec4<f32>,
+};
+
+@vertex
+fn vs_fullscreen(@builtin(vertex_index) vertex_index: u32) -> FullscreenOutput {
+    let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    var out: FullscreenOutput;
+    out.position = vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+    return out;
+}
+
+fn mask_kind(coord: vec2<i32>, view: u32) -> u32 {
+    let mask = load_mask(coord, view);
+    return select(0u, 1u, mask.r > 0.5) | select(0u, 2u, mask.g > 0.5);
+}
+
+@fragment
+fn fs_seed(in: FullscreenOutput, @builtin(view_index) view: u32) -> @location(0) vec4<u32> {
+    let coord = vec2<i32>(in.position.xy);
+    let kind = mask_kind(coord, view);
+    if (kind == 0u) {
+        return vec4<u32>(0u);
+    }
+    return vec4<u32>(vec2<u32>(coord), kind, 1u);
+}
+
+@fragment
+fn fs_flood(in: FullscreenOutput, @builtin(view_index) view: u32) -> @location(0) vec4<u32> {
+    let coord = vec2<i32>(in.position.xy);
+    let size = vec2<i32>(textureDimensions(seed_texture));
+    var best = vec4<u32>(0u);
+    var best_distance = 3.4e38;
+    for (var y = -1; y <= 1; y++) {
+        for (var x = -1; x <= 1; x++) {
+            let sample_coord = clamp(coord + vec2<i32>(x, y) * flood.step, vec2<i32>(0), size - 1);
+            let seed = load_seed(sample_coord, view);
+            if (seed.w == 0u) {
+                continue;
+            }
+            let offset = vec2<f32>(vec2<i32>(seed.xy) - coord);
+            let distance = dot(offset, offset);
+            if (distance < best_distance) {
+                best_distance = distance;
+                best = seed;
+            }
+        }
+    }
+    return best;
+}
+
+fn linear_to_srgb(color: vec3<f32>) -> vec3<f32> {
+    let high = 1.055 * pow(color, vec3<f32>(1.0 / 2.4)) - 0.055;
+    let low = color * 12.92;
+    return select(high, low, color <= vec3<f32>(0.0031308));
+}
+
+fn encode_color(color: vec4<f32>) -> vec4<f32> {
+    var rgb = color.rgb;
+    if (outline.encode_srgb != 0.0) {
+        rgb = linear_to_srgb(rgb);
+    }
+    return vec4<f32>(rgb, color.a);
+}
+
+@fragment
+fn fs_composite(in: FullscreenOutput, @builtin(view_index) view: u32) -> @location(0) vec4<f32> {
+    let coord = vec2<i32>(in.position.xy);
+    let kind = mask_kind(coord, view);
+    if (kind != 0u) {
+        if ((kind & 2u) != 0u) {
+            let highlight = outline.hovered_color;
+            return encode_color(vec4<f32>(highlight.rgb, highlight.a * 0.25));
+        }
+        return vec4<f32>(0.0);
+    }
+
+    let seed = load_seed(coord, view);
+    if (seed.w == 0u) {
+        return vec4<f32>(0.0);
+    }
+    let distance = length(vec2<f32>(vec2<i32>(seed.xy) - coord));
+    let coverage = clamp(outline.width + 0.5 - distance, 0.0, 1.0);
+    var color = outline.hovered_color;
+    if ((seed.z & 1u) != 0u) {
+        color = outline.selected_color;
+    }
+    return encode_color(vec4<f32>(color.rgb, color.a * coverage));
+}
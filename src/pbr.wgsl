@@ -0,0 +1,65 @@
+const PI: f32 = 3.14159265359;
+
+fn distribution_ggx(n_dot_h: f32, roughness: f32) -> f32 {
+    let a = roughness * roughness;
+    let a2 = a * a;
+    let denominator = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    return a2 / (PI * denominator * denominator);
+}
+
+fn geometry_smith(n_dot_v: f32, n_dot_l: f32, roughness: f32) -> f32 {
+    let r = roughness + 1.0;
+    let k = r * r / 8.0;
+    let ggx_v = n_dot_v / (n_dot_v * (1.0 - k) + k);
+    let ggx_l = n_dot_l / (n_dot_l * (1.0 - k) + k);
+    return ggx_v * ggx_l;
+}
+
+fn fresnel_schlick(cos_theta: f32, f0: vec3<f32>) -> vec3<f32> {
+    return f0 + (1.0 - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+fn fresnel_schlick_roughness(cos_theta: f32, f0: vec3<f32>, roughness: f32) -> vec3<f32> {
+    return f0 + (max(vec3<f32>(1.0 - roughness), f0) - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+fn pbr_lighting(
+    albedo: vec3<f32>,
+    metallic: f32,
+    roughness: f32,
+    normal: vec3<f32>,
+    view_dir: vec3<f32>,
+    world_pos: vec3<f32>,
+) -> vec3<f32> {
+    let roughness_clamped = clamp(roughness, 0.04, 1.0);
+    let light_dir = -normalize(shadow.light_direction.xyz);
+    let half_dir = normalize(view_dir + light_dir);
+    let n_dot_v = max(dot(normal, view_dir), 0.0001);
+    let n_dot_l = max(dot(normal, light_dir), 0.0);
+    let n_dot_h = max(dot(normal, half_dir), 0.0);
+    let f0 = mix(vec3<f32>(0.04), albedo, metallic);
+
+    let radiance = shadow.light_color.rgb * shadow.light_color.a;
+    let fresnel = fresnel_schlick(max(dot(half_dir, view_dir), 0.0), f0);
+    let specular = distribution_ggx(n_dot_h, roughness_clamped)
+        * geometry_smith(n_dot_v, n_dot_l, roughness_clamped)
+        * fresnel
+        / (4.0 * n_dot_v * n_dot_l + 0.0001);
+    let diffuse_weight = (vec3<f32>(1.0) - fresnel) * (1.0 - metallic);
+    let direct = (diffuse_weight * albedo / PI + specular)
+        * radiance
+        * n_dot_l
+        * shadow_factor(world_pos, normal);
+
+    let ambient_fresnel = fresnel_schlick_roughness(n_dot_v, f0, roughness_clamped);
+    let ambient_diffuse_weight = (vec3<f32>(1.0) - ambient_fresnel) * (1.0 - metallic);
+    let irradiance = textureSampleLevel(irradiance_map, environment_sampler, normal, 0.0).rgb;
+    let reflection = reflect(-view_dir, normal);
+    let prefiltered_mip = roughness_clamped * (environment.prefiltered_mip_count - 1.0);
+    let prefiltered = textureSampleLevel(prefiltered_map, environment_sampler, reflection, prefiltered_mip).rgb;
+    let brdf = textureSampleLevel(brdf_lut, environment_sampler, vec2<f32>(n_dot_v, roughness_clamped), 0.0).rg;
+    let ambient = (ambient_diffuse_weight * irradiance * albedo
+        + prefiltered * (ambient_fresnel * brdf.x + brdf.y)) * environment.intensity;
+
+    return direct + ambient;
+}
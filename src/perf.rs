@@ -0,0 +1,642 @@
+use crate::resources::{ResourceTracker, Tracked};
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+pub const MAX_TIMED_PASSES: u32 = 32;
+pub const DEFAULT_STATS_WINDOW: usize = 240;
+pub const MAX_STATS_WINDOW: usize = 4096;
+pub const PERF_CSV_PATH: &str = "perf.csv";
+
+const TIMESTAMP_SIZE: u64 = std::mem::size_of::<u64>() as u64;
+const GRAPH_HEIGHT: f32 = 60.0;
+const TARGET_FRAME_TIME: f32 = 1000.0 / 60.0;
+
+type SampleValue = fn(&FrameSample) -> Option<f32>;
+
+static DRAW_CALLS: AtomicU64 = AtomicU64::new(0);
+static TRIANGLES: AtomicU64 = AtomicU64::new(0);
+static UPLOAD_BYTES: AtomicU64 = AtomicU64::new(0);
+
+pub fn record_draw(triangles: u64) {
+    DRAW_CALLS.fetch_add(1, Ordering::Relaxed);
+    TRIANGLES.fetch_add(triangles, Ordering::Relaxed);
+}
+
+pub fn record_upload(bytes: u64) {
+    UPLOAD_BYTES.fetch_add(bytes, Ordering::Relaxed);
+}
+
+pub fn write_buffer(
+    queue: &wgpu::Queue,
+    buffer: &wgpu::Buffer,
+    offset: wgpu::BufferAddress,
+    data: &[u8],
+) {
+    record_upload(data.len() as u64);
+    queue.write_buffer(buffer, offset, data);
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FrameCounters {
+    pub draw_calls: u64,
+    pub triangles: u64,
+    pub upload_bytes: u64,
+}
+
+impl FrameCounters {
+    pub fn take() -> Self {
+        Self {
+            draw_calls: DRAW_CALLS.swap(0, Ordering::Relaxed),
+            triangles: TRIANGLES.swap(0, Ordering::Relaxed),
+            upload_bytes: UPLOAD_BYTES.swap(0, Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PassTiming {
+    pub label: &'static str,
+    pub milliseconds: f32,
+}
+
+struct TimerQueries {
+    query_set: Tracked<wgpu::QuerySet>,
+    resolve_buffer: Tracked<wgpu::Buffer>,
+    readback_buffer: Tracked<wgpu::Buffer>,
+    inside_encoders: bool,
+    period: f32,
+}
+
+pub struct GpuTimer {
+    pub enabled: bool,
+    queries: Option<TimerQueries>,
+    labels: Vec<&'static str>,
+    pending_labels: Vec<&'static str>,
+    readback_ready: Arc<AtomicBool>,
+    readback_pending: bool,
+    resolved: bool,
+    completed: bool,
+    timings: Vec<PassTiming>,
+    frame_time: Option<f32>,
+}
+
+impl GpuTimer {
+    pub fn required_features(adapter_features: wgpu::Features) -> wgpu::Features {
+        adapter_features
+            & (wgpu::Features::TIMESTAMP_QUERY | wgpu::Features::TIMESTAMP_QUERY_INSIDE_ENCODERS)
+    }
+
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        let features = device.features();
+        let resources = ResourceTracker::new(device, "GPU Timer");
+        let queries = features.contains(wgpu::Features::TIMESTAMP_QUERY).then(|| {
+            let count = MAX_TIMED_PASSES * 2;
+            let size = count as u64 * TIMESTAMP_SIZE;
+            TimerQueries {
+                query_set: resources.create_query_set(&wgpu::QuerySetDescriptor {
+                    label: Some("GPU Timer Query Set"),
+                    ty: wgpu::QueryType::Timestamp,
+                    count,
+                }),
+                resolve_buffer: resources.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("GPU Timer Resolve Buffer"),
+                    size,
+                    usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+                    mapped_at_creation: false,
+                }),
+                readback_buffer: resources.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("GPU Timer Readback Buffer"),
+                    size,
+                    usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                    mapped_at_creation: false,
+                }),
+                inside_encoders: features.contains(wgpu::Features::TIMESTAMP_QUERY_INSIDE_ENCODERS),
+                period: queue.get_timestamp_period(),
+            }
+        });
+        Self {
+            enabled: true,
+            queries,
+            labels: Vec::new(),
+            pending_labels: Vec::new(),
+            readback_ready: Arc::new(AtomicBool::new(false)),
+            readback_pending: false,
+            resolved: false,
+            completed: false,
+            timings: Vec::new(),
+            frame_time: None,
+        }
+    }
+
+    pub fn is_supported(&self) -> bool {
+        self.queries.is_some()
+    }
+
+    pub fn timings(&self) -> &[PassTiming] {
+        &self.timings
+    }
+
+    pub fn frame_time(&self) -> Option<f32> {
+        self.frame_time
+    }
+
+    pub fn take_completed(&mut self) -> bool {
+        std::mem::take(&mut self.completed)
+    }
+
+    pub fn begin_frame(&mut self, device: &wgpu::Device) {
+        self.completed = false;
+        self.read_timings(device);
+        self.labels.clear();
+        self.resolved = false;
+        if !self.enabled {
+            self.timings.clear();
+            self.frame_time = None;
+        }
+    }
+
+    fn is_recording(&self) -> bool {
+        self.enabled && !self.readback_pending && !self.resolved
+    }
+
+    fn allocate(&mut self, label: &'static str) -> Option<u32> {
+        if !self.is_recording() || self.labels.len() as u32 >= MAX_TIMED_PASSES {
+            return None;
+        }
+        self.labels.push(label);
+        Some((self.labels.len() as u32 - 1) * 2)
+    }
+
+    pub fn render_pass_writes(
+        &mut self,
+        label: &'static str,
+    ) -> Option<wgpu::RenderPassTimestampWrites<'_>> {
+        self.queries.as_ref()?;
+        let index = self.allocate(label)?;
+        self.queries
+            .as_ref()
+            .map(|queries| wgpu::RenderPassTimestampWrites {
+                query_set: &queries.query_set,
+                beginning_of_pass_write_index: Some(index),
+                end_of_pass_write_index: Some(index + 1),
+            })
+    }
+
+    pub fn begin_encoder_pass(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        label: &'static str,
+    ) -> Option<u32> {
+        if !self.queries.as_ref()?.inside_encoders {
+            return None;
+        }
+        let index = self.allocate(label)?;
+        let queries = self.queries.as_ref()?;
+        encoder.write_timestamp(&queries.query_set, index);
+        Some(index + 1)
+    }
+
+    pub fn end_encoder_pass(&self, encoder: &mut wgpu::CommandEncoder, index: Option<u32>) {
+        if let (Some(queries), Some(index)) = (self.queries.as_ref(), index) {
+            encoder.write_timestamp(&queries.query_set, index);
+        }
+    }
+
+    pub fn resolve(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        let Some(queries) = self.queries.as_ref() else {
+            return;
+        };
+        if !self.is_recording() || self.labels.is_empty() {
+            return;
+        }
+        let count = self.labels.len() as u32 * 2;
+        encoder.resolve_query_set(&queries.query_set, 0..count, &queries.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(
+            &queries.resolve_buffer,
+            0,
+            &queries.readback_buffer,
+            0,
+            count as u64 * TIMESTAMP_SIZE,
+        );
+        self.resolved = true;
+    }
+
+    pub fn finish_frame(&mut self) {
+        if !self.resolved {
+            return;
+        }
+        let Some(queries) = self.queries.as_ref() else {
+            return;
+        };
+        let ready = self.readback_ready.clone();
+        queries
+            .readback_buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                ready.store(result.is_ok(), Ordering::Release);
+            });
+        self.pending_labels = std::mem::take(&mut self.labels);
+        self.readback_pending = true;
+    }
+
+    fn read_timings(&mut self, device: &wgpu::Device) {
+        if !self.readback_pending {
+            return;
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        let _ = device.poll(wgpu::PollType::Poll);
+        #[cfg(target_arch = "wasm32")]
+        let _ = device;
+        if !self.readback_ready.swap(false, Ordering::Acquire) {
+            return;
+        }
+        let Some(queries) = self.queries.as_ref() else {
+            return;
+        };
+        {
+            let data = queries.readback_buffer.slice(..).get_mapped_range();
+            let timestamp = |index: usize| {
+                let offset = index * TIMESTAMP_SIZE as usize;
+                u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap_or([0; 8]))
+            };
+            let to_milliseconds =
+                |ticks: u64| (ticks as f64 * queries.period as f64 / 1_000_000.0) as f32;
+            self.timings = self
+                .pending_labels
+                .iter()
+                .enumerate()
+                .map(|(pass, &label)| PassTiming {
+                    label,
+                    milliseconds: to_milliseconds(
+                        timestamp(pass * 2 + 1).saturating_sub(timestamp(pass * 2)),
+                    ),
+                })
+                .collect();
+            let begin = (0..self.pending_labels.len())
+                .map(|pass| timestamp(pass * 2))
+                .min();
+            let end = (0..self.pending_labels.len())
+                .map(|pass| timestamp(pass * 2 + 1))
+                .max();
+            self.frame_time = begin
+                .zip(end)
+                .map(|(begin, end)| to_milliseconds(end.saturating_sub(begin)));
+        }
+        queries.readback_buffer.unmap();
+        self.readback_pending = false;
+        self.completed = true;
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FrameSample {
+    pub frame_time: f32,
+    pub cpu_time: f32,
+    pub gpu_time: Option<f32>,
+    pub counters: FrameCounters,
+    pub passes: Vec<PassTiming>,
+}
+
+impl FrameSample {
+    pub fn pass_time(&self, label: &str) -> Option<f32> {
+        self.passes
+            .iter()
+            .filter(|pass| pass.label == label)
+            .map(|pass| pass.milliseconds)
+            .reduce(|total, milliseconds| total + milliseconds)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Summary {
+    pub min: f32,
+    pub avg: f32,
+    pub max: f32,
+}
+
+impl Summary {
+    pub fn from_values(values: impl IntoIterator<Item = f32>) -> Option<Self> {
+        let mut count = 0;
+        let mut summary = Self {
+            min: f32::MAX,
+            avg: 0.0,
+            max: f32::MIN,
+        };
+        for value in values {
+            summary.min = summary.min.min(value);
+            summary.max = summary.max.max(value);
+            summary.avg += value;
+            count += 1;
+        }
+        (count > 0).then(|| Self {
+            avg: summary.avg / count as f32,
+            ..summary
+        })
+    }
+}
+
+pub struct PerfOverlay {
+    pub visible: bool,
+    pub paused: bool,
+    pub window: usize,
+    pub export_path: String,
+    samples: VecDeque<FrameSample>,
+    frame_index: u64,
+}
+
+impl Default for PerfOverlay {
+    fn default() -> Self {
+        Self {
+            visible: false,
+            paused: false,
+            window: DEFAULT_STATS_WINDOW,
+            export_path: PERF_CSV_PATH.to_string(),
+            samples: VecDeque::new(),
+            frame_index: 0,
+        }
+    }
+}
+
+impl PerfOverlay {
+    pub fn push(&mut self, sample: FrameSample) {
+        self.frame_index += 1;
+        if self.paused {
+            return;
+        }
+        self.samples.push_back(sample);
+        while self.samples.len() > self.window.max(1) {
+            self.samples.pop_front();
+        }
+    }
+
+    pub fn samples(&self) -> impl Iterator<Item = &FrameSample> {
+        self.samples.iter()
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    pub fn summary(&self, value: impl Fn(&FrameSample) -> Option<f32>) -> Option<Summary> {
+        Summary::from_values(self.samples.iter().filter_map(value))
+    }
+
+    pub fn fps(&self) -> f32 {
+        self.summary(|sample| Some(sample.frame_time))
+            .filter(|summary| summary.avg > 0.0)
+            .map_or(0.0, |summary| 1000.0 / summary.avg)
+    }
+
+    pub fn pass_labels(&self) -> Vec<&'static str> {
+        let mut labels = Vec::new();
+        for pass in self.samples.iter().flat_map(|sample| &sample.passes) {
+            if !labels.contains(&pass.label) {
+                labels.push(pass.label);
+            }
+        }
+        labels
+    }
+
+    pub fn pass_summary(&self, label: &str) -> Option<Summary> {
+        self.summary(|sample| sample.pass_time(label))
+    }
+
+    pub fn to_csv(&self) -> String {
+        let labels = self.pass_labels();
+        let mut csv =
+            String::from("frame,frame_ms,cpu_ms,gpu_ms,draw_calls,triangles,upload_bytes");
+        for label in &labels {
+            csv.push(',');
+            csv.push_str(&label.replace(',', " "));
+        }
+        csv.push('\n');
+        let first_frame = self.frame_index - self.samples.len() as u64;
+        for (index, sample) in self.samples.iter().enumerate() {
+            csv.push_str(&format!(
+                "{},{:.4},{:.4},{},{},{},{}",
+                first_frame + index as u64,
+                sample.frame_time,
+                sample.cpu_time,
+                sample
+                    .gpu_time
+                    .map(|time| format!("{time:.4}"))
+                    .unwrap_or_default(),
+                sample.counters.draw_calls,
+                sample.counters.triangles,
+                sample.counters.upload_bytes,
+            ));
+            for label in &labels {
+                csv.push(',');
+                if let Some(milliseconds) = sample.pass_time(label) {
+                    csv.push_str(&format!("{milliseconds:.4}"));
+                }
+            }
+            csv.push('\n');
+        }
+        csv
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn export_csv(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        std::fs::write(path, self.to_csv())?;
+        log::info!("Exported {} frames to {path}", self.samples.len());
+        Ok(())
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, gpu_timer: &mut GpuTimer) {
+        let mut visible = self.visible;
+        egui::Window::new("Performance")
+            .open(&mut visible)
+            .default_pos(ctx.content_rect().right_top() + egui::vec2(-360.0, 40.0))
+            .default_width(320.0)
+            .resizable(false)
+            .show(ctx, |ui| self.ui(ui, gpu_timer));
+        self.visible = visible;
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui, gpu_timer: &mut GpuTimer) {
+        ui.label(
+            egui::RichText::new(format!("{:.0} FPS", self.fps()))
+                .heading()
+                .color(egui::Color32::LIGHT_GREEN),
+        );
+        self.frame_graph(ui);
+
+        let milliseconds = |summary: Option<Summary>| {
+            summary.map_or(
+                ["-".to_string(), "-".to_string(), "-".to_string()],
+                |summary| {
+                    [summary.min, summary.avg, summary.max].map(|value| format!("{value:.2} ms"))
+                },
+            )
+        };
+        let count = |summary: Option<Summary>| {
+            summary.map_or(
+                ["-".to_string(), "-".to_string(), "-".to_string()],
+                |summary| {
+                    [summary.min, summary.avg, summary.max].map(|value| format!("{value:.0}"))
+                },
+            )
+        };
+        let bytes = |summary: Option<Summary>| {
+            summary.map_or(
+                ["-".to_string(), "-".to_string(), "-".to_string()],
+                |summary| {
+                    [summary.min, summary.avg, summary.max]
+                        .map(|value| format!("{:.1} KiB", value / 1024.0))
+                },
+            )
+        };
+        let rows = [
+            (
+                "Frame",
+                milliseconds(self.summary(|sample| Some(sample.frame_time))),
+            ),
+            (
+                "CPU",
+                milliseconds(self.summary(|sample| Some(sample.cpu_time))),
+            ),
+            ("GPU", milliseconds(self.summary(|sample| sample.gpu_time))),
+            (
+                "Draw Calls",
+                count(self.summary(|sample| Some(sample.counters.draw_calls as f32))),
+            ),
+            (
+                "Triangles",
+                count(self.summary(|sample| Some(sample.counters.triangles as f32))),
+            ),
+            (
+                "Uploads",
+                bytes(self.summary(|sample| Some(sample.counters.upload_bytes as f32))),
+            ),
+        ];
+        egui::Grid::new("perf_summary")
+            .num_columns(4)
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("");
+                ui.strong("Min");
+                ui.strong("Avg");
+                ui.strong("Max");
+                ui.end_row();
+                for (label, values) in rows {
+                    ui.label(label);
+                    for value in values {
+                        ui.monospace(value);
+                    }
+                    ui.end_row();
+                }
+            });
+
+        egui::CollapsingHeader::new("GPU Passes")
+            .default_open(true)
+            .show(ui, |ui| {
+                ui.checkbox(&mut gpu_timer.enabled, "Timestamp Queries");
+                if !gpu_timer.is_supported() {
+                    ui.label("Timestamp queries are not supported on this device");
+                    return;
+                }
+                egui::Grid::new("perf_passes")
+                    .num_columns(4)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for label in self.pass_labels() {
+                            ui.label(label);
+                            for value in milliseconds(self.pass_summary(label)) {
+                                ui.monospace(value);
+                            }
+                            ui.end_row();
+                        }
+                    });
+            });
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.label("Window");
+            ui.add(egui::DragValue::new(&mut self.window).range(1..=MAX_STATS_WINDOW));
+            ui.checkbox(&mut self.paused, "Pause");
+            if ui.button("Clear").clicked() {
+                self.clear();
+            }
+        });
+        #[cfg(not(target_arch = "wasm32"))]
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut self.export_path);
+            if ui.button("Export CSV").clicked()
+                && let Err(error) = self.export_csv(&self.export_path)
+            {
+                log::error!("Failed to export {}: {error}", self.export_path);
+            }
+        });
+    }
+
+    fn frame_graph(&self, ui: &mut egui::Ui) {
+        let (rect, _) = ui.allocate_exact_size(
+            egui::vec2(ui.available_width(), GRAPH_HEIGHT),
+            egui::Sense::hover(),
+        );
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+
+        let max_time = self
+            .summary(|sample| Some(sample.frame_time))
+            .map_or(TARGET_FRAME_TIME, |summary| summary.max)
+            .max(TARGET_FRAME_TIME * 1.25);
+        let to_y = |milliseconds: f32| rect.bottom() - milliseconds / max_time * rect.height();
+        let step = rect.width() / self.window.max(2) as f32;
+        let offset = self.window.saturating_sub(self.samples.len()) as f32 * step;
+        let line = |value: SampleValue| {
+            self.samples
+                .iter()
+                .enumerate()
+                .filter_map(|(index, sample)| {
+                    value(sample).map(|value| {
+                        egui::pos2(rect.left() + offset + index as f32 * step, to_y(value))
+                    })
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let target = to_y(TARGET_FRAME_TIME);
+        painter.line_segment(
+            [
+                egui::pos2(rect.left(), target),
+                egui::pos2(rect.right(), target),
+            ],
+            egui::Stroke::new(1.0_f32, egui::Color32::DARK_GRAY),
+        );
+        let series: [(&str, SampleValue, egui::Color32); 3] = [
+            (
+                "Frame",
+                |sample| Some(sample.frame_time),
+                egui::Color32::LIGHT_GREEN,
+            ),
+            (
+                "CPU",
+                |sample| Some(sample.cpu_time),
+                egui::Color32::from_rgb(80, 140, 255),
+            ),
+            ("GPU", |sample| sample.gpu_time, egui::Color32::ORANGE),
+        ];
+        for (_, value, color) in series {
+            painter.add(egui::Shape::line(
+                line(value),
+                egui::Stroke::new(1.0_f32, color),
+            ));
+        }
+        painter.text(
+            rect.left_top() + egui::vec2(4.0, 2.0),
+            egui::Align2::LEFT_TOP,
+            format!("{max_time:.1} ms"),
+            egui::FontId::monospace(10.0),
+            ui.visuals().weak_text_color(),
+        );
+        ui.horizontal(|ui| {
+            for (label, _, color) in series {
+                ui.colored_label(color, label);
+            }
+        });
+    }
+}
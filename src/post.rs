@@ -0,0 +1,898 @@
+use crate::perf;
+use crate::render_graph::{RenderGraph, TextureHandle};
+use crate::resources::{ResourceTracker, Tracked};
+
+const OWNER: &str = "Post Processing";
+
+pub const LDR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+
+const LUT_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+const IDENTITY_LUT_SIZE: u32 = 16;
+const MAX_LUT_SIZE: u32 = 128;
+const MAX_BLOOM_MIPS: u32 = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PostStage {
+    Hdr,
+    Display,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum PostEffect {
+    Bloom {
+        threshold: f32,
+        knee: f32,
+        intensity: f32,
+        radius: f32,
+    },
+    ChromaticAberration {
+        amount: f32,
+    },
+    Vignette {
+        intensity: f32,
+        radius: f32,
+        smoothness: f32,
+    },
+    ColorGrading {
+        contribution: f32,
+        lut_path: String,
+    },
+    Fxaa {
+        span_max: f32,
+        reduce_mul: f32,
+        reduce_min: f32,
+    },
+}
+
+impl PostEffect {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Bloom { .. } => "Bloom",
+            Self::ChromaticAberration { .. } => "Chromatic Aberration",
+            Self::Vignette { .. } => "Vignette",
+            Self::ColorGrading { .. } => "Color Grading",
+            Self::Fxaa { .. } => "FXAA",
+        }
+    }
+
+    pub fn stage(&self) -> PostStage {
+        match self {
+            Self::Bloom { .. } => PostStage::Hdr,
+            _ => PostStage::Display,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct PostEffectSlot {
+    pub enabled: bool,
+    pub effect: PostEffect,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct PostProcessSettings {
+    pub effects: Vec<PostEffectSlot>,
+}
+
+impl PostProcessSettings {
+    pub fn lut_path(&self) -> Option<&str> {
+        self.effects.iter().find_map(|slot| match &slot.effect {
+            PostEffect::ColorGrading { lut_path, .. } if !lut_path.is_empty() => {
+                Some(lut_path.as_str())
+            }
+            _ => None,
+        })
+    }
+}
+
+impl Default for PostProcessSettings {
+    fn default() -> Self {
+        Self {
+            effects: vec![
+                PostEffectSlot {
+                    enabled: true,
+                    effect: PostEffect::Bloom {
+                        threshold: 1.0,
+                        knee: 0.5,
+                        intensity: 0.05,
+                        radius: 1.0,
+                    },
+                },
+                PostEffectSlot {
+                    enabled: false,
+                    effect: PostEffect::ChromaticAberration { amount: 0.005 },
+                },
+                PostEffectSlot {
+                    enabled: true,
+                    effect: PostEffect::Vignette {
+                        intensity: 0.3,
+                        radius: 0.6,
+                        smoothness: 0.5,
+                    },
+                },
+                PostEffectSlot {
+                    enabled: false,
+                    effect: PostEffect::ColorGrading {
+                        contribution: 1.0,
+                        lut_path: String::new(),
+                    },
+                },
+                PostEffectSlot {
+                    enabled: true,
+                    effect: PostEffect::Fxaa {
+                        span_max: 8.0,
+                        reduce_mul: 0.125,
+                        reduce_min: 1.0 / 128.0,
+                    },
+                },
+            ],
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, bytemuck::Pod, bytemuck::Zeroable)]
+struct PostUniform {
+    bloom: [f32; 4],
+    chromatic_aberration: [f32; 4],
+    vignette: [f32; 4],
+    color_grading: [f32; 4],
+    fxaa: [f32; 4],
+    output: [f32; 4],
+}
+
+struct PostTargets {
+    _ldr_textures: [Tracked<wgpu::Texture>; 2],
+    _bloom_texture: Tracked<wgpu::Texture>,
+    ldr_views: [wgpu::TextureView; 2],
+    ldr_bind_groups: [Tracked<wgpu::BindGroup>; 2],
+    bloom_views: Vec<wgpu::TextureView>,
+    bloom_bind_groups: Vec<Tracked<wgpu::BindGroup>>,
+    bloom_prefilter_bind_group: Tracked<wgpu::BindGroup>,
+}
+
+pub struct PostProcessor {
+    pub settings: PostProcessSettings,
+    surface_format: wgpu::TextureFormat,
+    decode_srgb: bool,
+    bind_group_layout: wgpu::BindGroupLayout,
+    lut_bind_group_layout: wgpu::BindGroupLayout,
+    pipeline_layout: wgpu::PipelineLayout,
+    shader_module: wgpu::ShaderModule,
+    sampler: Tracked<wgpu::Sampler>,
+    uniform_buffer: Tracked<wgpu::Buffer>,
+    lut_size: u32,
+    _lut_texture: Tracked<wgpu::Texture>,
+    lut_bind_group: Tracked<wgpu::BindGroup>,
+    bloom_prefilter_pipeline: Tracked<wgpu::RenderPipeline>,
+    bloom_downsample_pipeline: Tracked<wgpu::RenderPipeline>,
+    bloom_upsample_pipeline: Tracked<wgpu::RenderPipeline>,
+    bloom_composite_pipeline: Tracked<wgpu::RenderPipeline>,
+    chromatic_aberration_pipeline: Tracked<wgpu::RenderPipeline>,
+    vignette_pipeline: Tracked<wgpu::RenderPipeline>,
+    color_grading_pipeline: Tracked<wgpu::RenderPipeline>,
+    fxaa_pipeline: Tracked<wgpu::RenderPipeline>,
+    output_pipeline: Tracked<wgpu::RenderPipeline>,
+    targets: PostTargets,
+    output_index: usize,
+}
+
+impl PostProcessor {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        surface_format: wgpu::TextureFormat,
+        sample_count: u32,
+        width: u32,
+        height: u32,
+        hdr_view: &wgpu::TextureView,
+    ) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Post Process Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let lut_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Color Grading LUT Bind Group Layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D3,
+                        multisampled: false,
+                    },
+                    count: None,
+                }],
+            });
+
+        let sampler =
+            ResourceTracker::new(device, OWNER).create_sampler(&wgpu::SamplerDescriptor {
+                label: Some("Post Process Sampler"),
+                address_mode_u: wgpu::AddressMode::ClampToEdge,
+                address_mode_v: wgpu::AddressMode::ClampToEdge,
+                address_mode_w: wgpu::AddressMode::ClampToEdge,
+                mag_filter: wgpu::FilterMode::Linear,
+                min_filter: wgpu::FilterMode::Linear,
+                ..Default::default()
+            });
+
+        let uniform_buffer =
+            ResourceTracker::new(device, OWNER).create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Post Process Uniform Buffer"),
+                size: std::mem::size_of::<PostUniform>() as u64,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+
+        let (lut_texture, lut_bind_group) = Self::create_lut(
+            device,
+            queue,
+            &lut_bind_group_layout,
+            IDENTITY_LUT_SIZE,
+            &identity_lut(IDENTITY_LUT_SIZE),
+        );
+
+        let shader_module = device.create_shader_module(wgpu::include_wgsl!("post.wgsl"));
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Post Process Pipeline Layout"),
+            bind_group_layouts: &[Some(&bind_group_layout)],
+            immediate_size: 0,
+        });
+        let color_grading_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Color Grading Pipeline Layout"),
+                bind_group_layouts: &[Some(&bind_group_layout), Some(&lut_bind_group_layout)],
+                immediate_size: 0,
+            });
+
+        let additive = Some(wgpu::BlendState {
+            color: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::One,
+                dst_factor: wgpu::BlendFactor::One,
+                operation: wgpu::BlendOperation::Add,
+            },
+            alpha: wgpu::BlendComponent::REPLACE,
+        });
+        let create_pipeline = |layout, entry_point, format, blend| {
+            Self::create_pipeline(
+                device,
+                layout,
+                &shader_module,
+                entry_point,
+                format,
+                blend,
+                1,
+            )
+        };
+        let bloom_prefilter_pipeline = create_pipeline(
+            &pipeline_layout,
+            "fs_bloom_prefilter",
+            crate::hdr::HDR_FORMAT,
+            None,
+        );
+        let bloom_downsample_pipeline = create_pipeline(
+            &pipeline_layout,
+            "fs_bloom_downsample",
+            crate::hdr::HDR_FORMAT,
+            None,
+        );
+        let bloom_upsample_pipeline = create_pipeline(
+            &pipeline_layout,
+            "fs_bloom_upsample",
+            crate::hdr::HDR_FORMAT,
+            additive,
+        );
+        let bloom_composite_pipeline = create_pipeline(
+            &pipeline_layout,
+            "fs_bloom_composite",
+            crate::hdr::HDR_FORMAT,
+            additive,
+        );
+        let chromatic_aberration_pipeline = create_pipeline(
+            &pipeline_layout,
+            "fs_chromatic_aberration",
+            LDR_FORMAT,
+            None,
+        );
+        let vignette_pipeline = create_pipeline(&pipeline_layout, "fs_vignette", LDR_FORMAT, None);
+        let color_grading_pipeline = create_pipeline(
+            &color_grading_pipeline_layout,
+            "fs_color_grading",
+            LDR_FORMAT,
+            None,
+        );
+        let fxaa_pipeline = create_pipeline(&pipeline_layout, "fs_fxaa", LDR_FORMAT, None);
+        let output_pipeline = Self::create_pipeline(
+            device,
+            &pipeline_layout,
+            &shader_module,
+            "fs_output",
+            surface_format,
+            None,
+            sample_count,
+        );
+
+        let targets = Self::create_targets(
+            device,
+            &bind_group_layout,
+            &sampler,
+            &uniform_buffer,
+            width,
+            height,
+            hdr_view,
+        );
+
+        Self {
+            settings: PostProcessSettings::default(),
+            surface_format,
+            decode_srgb: !Self::needs_srgb_encoding(surface_format),
+            bind_group_layout,
+            lut_bind_group_layout,
+            pipeline_layout,
+            shader_module,
+            sampler,
+            uniform_buffer,
+            lut_size: IDENTITY_LUT_SIZE,
+            _lut_texture: lut_texture,
+            lut_bind_group,
+            bloom_prefilter_pipeline,
+            bloom_downsample_pipeline,
+            bloom_upsample_pipeline,
+            bloom_composite_pipeline,
+            chromatic_aberration_pipeline,
+            vignette_pipeline,
+            color_grading_pipeline,
+            fxaa_pipeline,
+            output_pipeline,
+            targets,
+            output_index: 0,
+        }
+    }
+
+    pub fn ldr_view(&self) -> &wgpu::TextureView {
+        &self.targets.ldr_views[0]
+    }
+
+    pub fn set_sample_count(&mut self, device: &wgpu::Device, sample_count: u32) {
+        self.output_pipeline = Self::create_pipeline(
+            device,
+            &self.pipeline_layout,
+            &self.shader_module,
+            "fs_output",
+            self.surface_format,
+            None,
+            sample_count,
+        );
+    }
+
+    pub fn resize(
+        &mut self,
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        hdr_view: &wgpu::TextureView,
+    ) {
+        self.targets = Self::create_targets(
+            device,
+            &self.bind_group_layout,
+            &self.sampler,
+            &self.uniform_buffer,
+            width,
+            height,
+            hdr_view,
+        );
+    }
+
+    pub fn load_lut(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        source: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let (size, data) = parse_cube_lut(source)?;
+        (self._lut_texture, self.lut_bind_group) =
+            Self::create_lut(device, queue, &self.lut_bind_group_layout, size, &data);
+        self.lut_size = size;
+        log::info!("Loaded {size}x{size}x{size} color grading LUT");
+        Ok(())
+    }
+
+    pub fn reset_lut(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        (self._lut_texture, self.lut_bind_group) = Self::create_lut(
+            device,
+            queue,
+            &self.lut_bind_group_layout,
+            IDENTITY_LUT_SIZE,
+            &identity_lut(IDENTITY_LUT_SIZE),
+        );
+        self.lut_size = IDENTITY_LUT_SIZE;
+    }
+
+    pub fn update(&mut self, queue: &wgpu::Queue) {
+        self.output_index = self.enabled_effects(PostStage::Display).count() % 2;
+
+        let mut uniform = PostUniform {
+            output: [self.decode_srgb as u32 as f32, 0.0, 0.0, 0.0],
+            ..Default::default()
+        };
+        for slot in self.settings.effects.iter().filter(|slot| slot.enabled) {
+            match &slot.effect {
+                PostEffect::Bloom {
+                    threshold,
+                    knee,
+                    intensity,
+                    radius,
+                } => uniform.bloom = [*threshold, *knee, *intensity, *radius],
+                PostEffect::ChromaticAberration { amount } => {
+                    uniform.chromatic_aberration = [*amount, 0.0, 0.0, 0.0]
+                }
+                PostEffect::Vignette {
+                    intensity,
+                    radius,
+                    smoothness,
+                } => uniform.vignette = [*intensity, *radius, *smoothness, 0.0],
+                PostEffect::ColorGrading { contribution, .. } => {
+                    uniform.color_grading = [*contribution, self.lut_size as f32, 0.0, 0.0]
+                }
+                PostEffect::Fxaa {
+                    span_max,
+                    reduce_mul,
+                    reduce_min,
+                } => uniform.fxaa = [*span_max, *reduce_mul, *reduce_min, 0.0],
+            }
+        }
+        perf::write_buffer(
+            queue,
+            &self.uniform_buffer,
+            0,
+            bytemuck::cast_slice(&[uniform]),
+        );
+    }
+
+    pub fn import_targets<'a>(&'a self, graph: &mut RenderGraph<'a>) -> [TextureHandle; 2] {
+        [
+            graph.import_texture(&self.targets.ldr_views[0]),
+            graph.import_texture(&self.targets.ldr_views[1]),
+        ]
+    }
+
+    pub fn add_hdr_passes<'a>(&'a self, graph: &mut RenderGraph<'a>, hdr: TextureHandle) {
+        for slot in self.enabled_effects(PostStage::Hdr) {
+            if let PostEffect::Bloom { .. } = slot.effect {
+                graph.add_pass("Bloom Pass").write_texture(hdr).encode(
+                    move |encoder, resources| self.render_bloom(encoder, resources.texture(hdr)),
+                );
+            }
+        }
+    }
+
+    pub fn add_display_passes<'a>(
+        &'a self,
+        graph: &mut RenderGraph<'a>,
+        targets: [TextureHandle; 2],
+    ) -> TextureHandle {
+        let mut current = 0;
+        for slot in self.enabled_effects(PostStage::Display) {
+            let (pipeline, lut_bind_group) = match slot.effect {
+                PostEffect::Bloom { .. } => continue,
+                PostEffect::ChromaticAberration { .. } => {
+                    (&self.chromatic_aberration_pipeline, None)
+                }
+                PostEffect::Vignette { .. } => (&self.vignette_pipeline, None),
+                PostEffect::ColorGrading { .. } => {
+                    (&self.color_grading_pipeline, Some(&self.lut_bind_group))
+                }
+                PostEffect::Fxaa { .. } => (&self.fxaa_pipeline, None),
+            };
+            let bind_group = &self.targets.ldr_bind_groups[current];
+            let next = 1 - current;
+            graph
+                .add_pass(slot.effect.label())
+                .read_texture(targets[current])
+                .color_attachment(targets[next], None, wgpu::Color::BLACK)
+                .render(move |renderpass, _| {
+                    renderpass.set_pipeline(pipeline);
+                    renderpass.set_bind_group(0, bind_group, &[]);
+                    if let Some(lut_bind_group) = lut_bind_group {
+                        renderpass.set_bind_group(1, lut_bind_group, &[]);
+                    }
+                    renderpass.draw(0..3, 0..1);
+                    perf::record_draw(1);
+                });
+            current = next;
+        }
+        debug_assert_eq!(current, self.output_index);
+        targets[current]
+    }
+
+    pub fn render_output(&self, renderpass: &mut wgpu::RenderPass<'_>) {
+        renderpass.set_pipeline(&self.output_pipeline);
+        renderpass.set_bind_group(0, &self.targets.ldr_bind_groups[self.output_index], &[]);
+        renderpass.draw(0..3, 0..1);
+        perf::record_draw(1);
+    }
+
+    fn enabled_effects(&self, stage: PostStage) -> impl Iterator<Item = &PostEffectSlot> {
+        self.settings
+            .effects
+            .iter()
+            .filter(move |slot| slot.enabled && slot.effect.stage() == stage)
+    }
+
+    fn render_bloom(&self, encoder: &mut wgpu::CommandEncoder, hdr_view: &wgpu::TextureView) {
+        let targets = &self.targets;
+        Self::draw_fullscreen(
+            encoder,
+            "Bloom Prefilter Pass",
+            &targets.bloom_views[0],
+            wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+            &self.bloom_prefilter_pipeline,
+            &targets.bloom_prefilter_bind_group,
+        );
+        for (view, bind_group) in targets.bloom_views[1..]
+            .iter()
+            .zip(&targets.bloom_bind_groups)
+        {
+            Self::draw_fullscreen(
+                encoder,
+                "Bloom Downsample Pass",
+                view,
+                wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                &self.bloom_downsample_pipeline,
+                bind_group,
+            );
+        }
+        for mip_level in (1..targets.bloom_views.len()).rev() {
+            Self::draw_fullscreen(
+                encoder,
+                "Bloom Upsample Pass",
+                &targets.bloom_views[mip_level - 1],
+                wgpu::LoadOp::Load,
+                &self.bloom_upsample_pipeline,
+                &targets.bloom_bind_groups[mip_level],
+            );
+        }
+        Self::draw_fullscreen(
+            encoder,
+            "Bloom Composite Pass",
+            hdr_view,
+            wgpu::LoadOp::Load,
+            &self.bloom_composite_pipeline,
+            &targets.bloom_bind_groups[0],
+        );
+    }
+
+    fn needs_srgb_encoding(surface_format: wgpu::TextureFormat) -> bool {
+        !surface_format.is_srgb()
+            && !matches!(
+                surface_format,
+                wgpu::TextureFormat::Rgba16Float | wgpu::TextureFormat::Rgba32Float
+            )
+    }
+
+    fn draw_fullscreen(
+        encoder: &mut wgpu::CommandEncoder,
+        label: &str,
+        target: &wgpu::TextureView,
+        load: wgpu::LoadOp<wgpu::Color>,
+        pipeline: &wgpu::RenderPipeline,
+        bind_group: &wgpu::BindGroup,
+    ) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some(label),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load,
+                    store: wgpu::StoreOp::Store,
+                },
+                depth_slice: None,
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+            multiview_mask: None,
+        });
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(0, bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+        perf::record_draw(1);
+    }
+
+    fn create_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        source: &wgpu::TextureView,
+        sampler: &wgpu::Sampler,
+        uniform_buffer: &wgpu::Buffer,
+    ) -> Tracked<wgpu::BindGroup> {
+        ResourceTracker::new(device, OWNER).create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Post Process Bind Group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(source),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+            ],
+        })
+    }
+
+    fn create_lut(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layout: &wgpu::BindGroupLayout,
+        size: u32,
+        data: &[u8],
+    ) -> (Tracked<wgpu::Texture>, Tracked<wgpu::BindGroup>) {
+        let extent = wgpu::Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: size,
+        };
+        let texture =
+            ResourceTracker::new(device, OWNER).create_texture(&wgpu::TextureDescriptor {
+                label: Some("Color Grading LUT Texture"),
+                size: extent,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D3,
+                format: LUT_FORMAT,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                view_formats: &[],
+            });
+        queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            data,
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * size),
+                rows_per_image: Some(size),
+            },
+            extent,
+        );
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group =
+            ResourceTracker::new(device, OWNER).create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Color Grading LUT Bind Group"),
+                layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                }],
+            });
+        (texture, bind_group)
+    }
+
+    fn create_targets(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        sampler: &wgpu::Sampler,
+        uniform_buffer: &wgpu::Buffer,
+        width: u32,
+        height: u32,
+        hdr_view: &wgpu::TextureView,
+    ) -> PostTargets {
+        let (width, height) = (width.max(1), height.max(1));
+        let ldr_textures = [0, 1].map(|_| {
+            ResourceTracker::new(device, OWNER).create_texture(&wgpu::TextureDescriptor {
+                label: Some("Post Process Texture"),
+                size: wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: LDR_FORMAT,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            })
+        });
+        let ldr_views = ldr_textures
+            .each_ref()
+            .map(|texture| texture.create_view(&wgpu::TextureViewDescriptor::default()));
+        let ldr_bind_groups = [0, 1].map(|index| {
+            Self::create_bind_group(device, layout, &ldr_views[index], sampler, uniform_buffer)
+        });
+
+        let (bloom_width, bloom_height) = ((width / 2).max(1), (height / 2).max(1));
+        let bloom_mip_count = bloom_width
+            .min(bloom_height)
+            .ilog2()
+            .clamp(1, MAX_BLOOM_MIPS);
+        let bloom_texture =
+            ResourceTracker::new(device, OWNER).create_texture(&wgpu::TextureDescriptor {
+                label: Some("Bloom Texture"),
+                size: wgpu::Extent3d {
+                    width: bloom_width,
+                    height: bloom_height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: bloom_mip_count,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: crate::hdr::HDR_FORMAT,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            });
+        let bloom_views: Vec<wgpu::TextureView> = (0..bloom_mip_count)
+            .map(|mip_level| {
+                bloom_texture.create_view(&wgpu::TextureViewDescriptor {
+                    label: Some("Bloom Mip View"),
+                    base_mip_level: mip_level,
+                    mip_level_count: Some(1),
+                    ..Default::default()
+                })
+            })
+            .collect();
+        let bloom_bind_groups = bloom_views
+            .iter()
+            .map(|view| Self::create_bind_group(device, layout, view, sampler, uniform_buffer))
+            .collect();
+        let bloom_prefilter_bind_group =
+            Self::create_bind_group(device, layout, hdr_view, sampler, uniform_buffer);
+
+        PostTargets {
+            _ldr_textures: ldr_textures,
+            _bloom_texture: bloom_texture,
+            ldr_views,
+            ldr_bind_groups,
+            bloom_views,
+            bloom_bind_groups,
+            bloom_prefilter_bind_group,
+        }
+    }
+
+    fn create_pipeline(
+        device: &wgpu::Device,
+        layout: &wgpu::PipelineLayout,
+        shader_module: &wgpu::ShaderModule,
+        fragment_entry_point: &str,
+        format: wgpu::TextureFormat,
+        blend: Option<wgpu::BlendState>,
+        sample_count: u32,
+    ) -> Tracked<wgpu::RenderPipeline> {
+        ResourceTracker::new(device, OWNER).create_render_pipeline(
+            &wgpu::RenderPipelineDescriptor {
+                label: Some(fragment_entry_point),
+                layout: Some(layout),
+                vertex: wgpu::VertexState {
+                    module: shader_module,
+                    entry_point: Some("vs_fullscreen"),
+                    buffers: &[],
+                    compilation_options: Default::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: shader_module,
+                    entry_point: Some(fragment_entry_point),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                    compilation_options: Default::default(),
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState {
+                    count: sample_count,
+                    ..Default::default()
+                },
+                multiview_mask: None,
+                cache: None,
+            },
+        )
+    }
+}
+
+fn identity_lut(size: u32) -> Vec<u8> {
+    let scale = 255.0 / (size - 1) as f32;
+    let mut data = Vec::with_capacity((size * size * size * 4) as usize);
+    for blue in 0..size {
+        for green in 0..size {
+            for red in 0..size {
+                data.extend_from_slice(&[
+                    (red as f32 * scale).round() as u8,
+                    (green as f32 * scale).round() as u8,
+                    (blue as f32 * scale).round() as u8,
+                    255,
+                ]);
+            }
+        }
+    }
+    data
+}
+
+fn parse_cube_lut(source: &str) -> Result<(u32, Vec<u8>), Box<dyn std::error::Error>> {
+    let mut size = None;
+    let mut data = Vec::new();
+    for line in source.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(value) = line.strip_prefix("LUT_3D_SIZE") {
+            size = Some(value.trim().parse::<u32>()?);
+            continue;
+        }
+        if line.starts_with("LUT_1D_SIZE") {
+            return Err("1D LUTs are not supported".into());
+        }
+        if line.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            continue;
+        }
+        let values = line
+            .split_whitespace()
+            .map(str::parse::<f32>)
+            .collect::<Result<Vec<_>, _>>()?;
+        if values.len() != 3 {
+            return Err(format!("Invalid LUT entry: {line}").into());
+        }
+        data.extend(
+            values
+                .iter()
+                .map(|value| (value.clamp(0.0, 1.0) * 255.0).round() as u8),
+        );
+        data.push(255);
+    }
+
+    let size = size.ok_or("Missing LUT_3D_SIZE")?;
+    if !(2..=MAX_LUT_SIZE).contains(&size) {
+        return Err(format!("Unsupported LUT size {size}").into());
+    }
+    let expected = (size * size * size * 4) as usize;
+    if data.len() != expected {
+        return Err(format!(
+            "Expected {} LUT entries, found {}",
+            expected / 4,
+            data.len() / 4
+        )
+        .into());
+    }
+    Ok((size, data))
+}
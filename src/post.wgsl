@@ -0,0 +1,190 @@
+struct PostUniform {
+    bloom: vec4<f32>,
+    chromatic_aberration: vec4<f32>,
+    vignette: vec4<f32>,
+    color_grading: vec4<f32>,
+    fxaa: vec4<f32>,
+    output: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var source_texture: texture_2d<f32>;
+
+@group(0) @binding(1)
+var source_sampler: sampler;
+
+@group(0) @binding(2)
+var<uniform> post: PostUniform;
+
+@group(1) @binding(0)
+var grading_lut: texture_3d<f32>;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn vs_fullscreen(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    var out: VertexOutput;
+    out.position = vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
+
+fn sample_source(uv: vec2<f32>) -> vec3<f32> {
+    return textureSampleLevel(source_texture, source_sampler, uv, 0.0).rgb;
+}
+
+fn texel_size() -> vec2<f32> {
+    return 1.0 / vec2<f32>(textureDimensions(source_texture));
+}
+
+fn downsample_13(uv: vec2<f32>) -> vec3<f32> {
+    let texel = texel_size();
+    let a = sample_source(uv + texel * vec2<f32>(-2.0, -2.0));
+    let b = sample_source(uv + texel * vec2<f32>(0.0, -2.0));
+    let c = sample_source(uv + texel * vec2<f32>(2.0, -2.0));
+    let d = sample_source(uv + texel * vec2<f32>(-2.0, 0.0));
+    let e = sample_source(uv);
+    let f = sample_source(uv + texel * vec2<f32>(2.0, 0.0));
+    let g = sample_source(uv + texel * vec2<f32>(-2.0, 2.0));
+    let h = sample_source(uv + texel * vec2<f32>(0.0, 2.0));
+    let i = sample_source(uv + texel * vec2<f32>(2.0, 2.0));
+    let j = sample_source(uv + texel * vec2<f32>(-1.0, -1.0));
+    let k = sample_source(uv + texel * vec2<f32>(1.0, -1.0));
+    let l = sample_source(uv + texel * vec2<f32>(-1.0, 1.0));
+    let m = sample_source(uv + texel * vec2<f32>(1.0, 1.0));
+    return e * 0.125
+        + (a + c + g + i) * 0.03125
+        + (b + d + f + h) * 0.0625
+        + (j + k + l + m) * 0.125;
+}
+
+@fragment
+fn fs_bloom_prefilter(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = downsample_13(in.uv);
+    let threshold = post.bloom.x;
+    let knee = max(post.bloom.y, 0.0001);
+    let brightness = max(color.r, max(color.g, color.b));
+    let soft = clamp(brightness - threshold + knee, 0.0, 2.0 * knee);
+    let contribution = max(soft * soft / (4.0 * knee), brightness - threshold) / max(brightness, 0.0001);
+    return vec4<f32>(color * max(contribution, 0.0), 1.0);
+}
+
+@fragment
+fn fs_bloom_downsample(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(downsample_13(in.uv), 1.0);
+}
+
+@fragment
+fn fs_bloom_upsample(in: VertexOutput) -> @location(0) vec4<f32> {
+    let offset = texel_size() * post.bloom.w;
+    var color = sample_source(in.uv) * 4.0;
+    color += (sample_source(in.uv + vec2<f32>(-offset.x, 0.0))
+        + sample_source(in.uv + vec2<f32>(offset.x, 0.0))
+        + sample_source(in.uv + vec2<f32>(0.0, -offset.y))
+        + sample_source(in.uv + vec2<f32>(0.0, offset.y))) * 2.0;
+    color += sample_source(in.uv + vec2<f32>(-offset.x, -offset.y))
+        + sample_source(in.uv + vec2<f32>(offset.x, -offset.y))
+        + sample_source(in.uv + vec2<f32>(-offset.x, offset.y))
+        + sample_source(in.uv + vec2<f32>(offset.x, offset.y));
+    return vec4<f32>(color / 16.0, 1.0);
+}
+
+@fragment
+fn fs_bloom_composite(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(sample_source(in.uv) * post.bloom.z, 1.0);
+}
+
+@fragment
+fn fs_chromatic_aberration(in: VertexOutput) -> @location(0) vec4<f32> {
+    let offset = (in.uv - 0.5) * post.chromatic_aberration.x;
+    let red = textureSampleLevel(source_texture, source_sampler, in.uv + offset, 0.0).r;
+    let green = textureSampleLevel(source_texture, source_sampler, in.uv, 0.0).g;
+    let blue = textureSampleLevel(source_texture, source_sampler, in.uv - offset, 0.0).b;
+    return vec4<f32>(red, green, blue, 1.0);
+}
+
+@fragment
+fn fs_vignette(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = sample_source(in.uv);
+    let distance_from_center = length(in.uv - 0.5) * 1.41421356;
+    let radius = post.vignette.y;
+    let smoothness = max(post.vignette.z, 0.0001);
+    let falloff = smoothstep(radius, radius + smoothness, distance_from_center);
+    return vec4<f32>(color * (1.0 - falloff * post.vignette.x), 1.0);
+}
+
+@fragment
+fn fs_color_grading(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = clamp(sample_source(in.uv), vec3<f32>(0.0), vec3<f32>(1.0));
+    let lut_size = post.color_grading.y;
+    let lut_coords = color * ((lut_size - 1.0) / lut_size) + 0.5 / lut_size;
+    let graded = textureSampleLevel(grading_lut, source_sampler, lut_coords, 0.0).rgb;
+    return vec4<f32>(mix(color, graded, post.color_grading.x), 1.0);
+}
+
+fn fxaa_luma(color: vec3<f32>) -> f32 {
+    return dot(color, vec3<f32>(0.299, 0.587, 0.114));
+}
+
+@fragment
+fn fs_fxaa(in: VertexOutput) -> @location(0) vec4<f32> {
+    let texel = texel_size();
+    let span_max = post.fxaa.x;
+    let reduce_mul = post.fxaa.y;
+    let reduce_min = post.fxaa.z;
+
+    let rgb_nw = sample_source(in.uv + vec2<f32>(-1.0, -1.0) * texel);
+    let rgb_ne = sample_source(in.uv + vec2<f32>(1.0, -1.0) * texel);
+    let rgb_sw = sample_source(in.uv + vec2<f32>(-1.0, 1.0) * texel);
+    let rgb_se = sample_source(in.uv + vec2<f32>(1.0, 1.0) * texel);
+    let rgb_m = sample_source(in.uv);
+
+    let luma_nw = fxaa_luma(rgb_nw);
+    let luma_ne = fxaa_luma(rgb_ne);
+    let luma_sw = fxaa_luma(rgb_sw);
+    let luma_se = fxaa_luma(rgb_se);
+    let luma_m = fxaa_luma(rgb_m);
+    let luma_min = min(luma_m, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+    let luma_max = max(luma_m, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+
+    var direction = vec2<f32>(
+        -((luma_nw + luma_ne) - (luma_sw + luma_se)),
+        (luma_nw + luma_sw) - (luma_ne + luma_se),
+    );
+    let direction_reduce = max((luma_nw + luma_ne + luma_sw + luma_se) * 0.25 * reduce_mul, reduce_min);
+    let inverse_direction_min = 1.0 / (min(abs(direction.x), abs(direction.y)) + direction_reduce);
+    direction = clamp(direction * inverse_direction_min, vec2<f32>(-span_max), vec2<f32>(span_max)) * texel;
+
+    let rgb_a = 0.5 * (
+        sample_source(in.uv + direction * (1.0 / 3.0 - 0.5)) +
+        sample_source(in.uv + direction * (2.0 / 3.0 - 0.5))
+    );
+    let rgb_b = rgb_a * 0.5 + 0.25 * (
+        sample_source(in.uv + direction * -0.5) +
+        sample_source(in.uv + direction * 0.5)
+    );
+    let luma_b = fxaa_luma(rgb_b);
+    if (luma_b < luma_min || luma_b > luma_max) {
+        return vec4<f32>(rgb_a, 1.0);
+    }
+    return vec4<f32>(rgb_b, 1.0);
+}
+
+fn srgb_to_linear(color: vec3<f32>) -> vec3<f32> {
+    let high = pow((color + 0.055) / 1.055, vec3<f32>(2.4));
+    let low = color / 12.92;
+    return select(high, low, color <= vec3<f32>(0.04045));
+}
+
+@fragment
+fn fs_output(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = sample_source(in.uv);
+    if (post.output.x != 0.0) {
+        return vec4<f32>(srgb_to_linear(color), 1.0);
+    }
+    return vec4<f32>(color, 1.0);
+}
@@ -0,0 +1,803 @@
+use crate::perf::GpuTimer;
+use crate::resources::{ResourceTracker, Tracked};
+
+use std::num::NonZeroU32;
+
+const OWNER: &str = "Render Graph";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TextureHandle(usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BufferHandle(usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TransientTexture {
+    pub label: &'static str,
+    pub size: wgpu::Extent3d,
+    pub format: wgpu::TextureFormat,
+    pub sample_count: u32,
+    pub usage: wgpu::TextureUsages,
+    pub view_dimension: wgpu::TextureViewDimension,
+}
+
+impl TransientTexture {
+    pub fn attachment(
+        label: &'static str,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> Self {
+        Self {
+            label,
+            size: wgpu::Extent3d {
+                width: width.max(1),
+                height: height.max(1),
+                depth_or_array_layers: 1,
+            },
+            format,
+            sample_count,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_dimension: wgpu::TextureViewDimension::D2,
+        }
+    }
+}
+
+impl Poolable for TransientTexture {
+    fn is_compatible(&self, other: &Self) -> bool {
+        self.size == other.size
+            && self.format == other.format
+            && self.sample_count == other.sample_count
+            && self.usage == other.usage
+            && self.view_dimension == other.view_dimension
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TransientBuffer {
+    pub label: &'static str,
+    pub size: u64,
+    pub usage: wgpu::BufferUsages,
+}
+
+impl Poolable for TransientBuffer {
+    fn is_compatible(&self, other: &Self) -> bool {
+        self.size == other.size && self.usage == other.usage
+    }
+}
+
+enum TextureResource<'a> {
+    Imported(&'a wgpu::TextureView),
+    Transient(TransientTexture),
+}
+
+enum BufferResource<'a> {
+    Imported(&'a wgpu::Buffer),
+    Transient(TransientBuffer),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Resource {
+    Texture(usize),
+    Buffer(usize),
+}
+
+struct ColorAttachment {
+    target: TextureHandle,
+    resolve_target: Option<TextureHandle>,
+    clear: wgpu::Color,
+}
+
+struct DepthAttachment {
+    target: TextureHandle,
+    clear: f32,
+}
+
+type RenderCallback<'a> = Box<dyn FnOnce(&mut wgpu::RenderPass<'static>, &GraphResources) + 'a>;
+type EncodeCallback<'a> = Box<dyn FnOnce(&mut wgpu::CommandEncoder, &GraphResources) + 'a>;
+
+enum PassCallback<'a> {
+    Render(RenderCallback<'a>),
+    Encode(EncodeCallback<'a>),
+}
+
+struct PassNode<'a> {
+    label: &'static str,
+    reads: Vec<Resource>,
+    writes: Vec<Resource>,
+    color_attachments: Vec<ColorAttachment>,
+    depth_attachment: Option<DepthAttachment>,
+    multiview_mask: Option<NonZeroU32>,
+    side_effect: bool,
+    callback: Option<PassCallback<'a>>,
+}
+
+pub struct PassBuilder<'g, 'a> {
+    graph: &'g mut RenderGraph<'a>,
+    node: PassNode<'a>,
+}
+
+impl<'a> PassBuilder<'_, 'a> {
+    pub fn read_texture(mut self, texture: TextureHandle) -> Self {
+        self.node.reads.push(Resource::Texture(texture.0));
+        self
+    }
+
+    pub fn write_texture(mut self, texture: TextureHandle) -> Self {
+        self.node.writes.push(Resource::Texture(texture.0));
+        self
+    }
+
+    pub fn read_buffer(mut self, buffer: BufferHandle) -> Self {
+        self.node.reads.push(Resource::Buffer(buffer.0));
+        self
+    }
+
+    pub fn write_buffer(mut self, buffer: BufferHandle) -> Self {
+        self.node.writes.push(Resource::Buffer(buffer.0));
+        self
+    }
+
+    pub fn color_attachment(
+        mut self,
+        target: TextureHandle,
+        resolve_target: Option<TextureHandle>,
+        clear: wgpu::Color,
+    ) -> Self {
+        self.node.writes.push(Resource::Texture(target.0));
+        if let Some(resolve_target) = resolve_target {
+            self.node.writes.push(Resource::Texture(resolve_target.0));
+        }
+        self.node.color_attachments.push(ColorAttachment {
+            target,
+            resolve_target,
+            clear,
+        });
+        self
+    }
+
+    pub fn depth_attachment(mut self, target: TextureHandle, clear: f32) -> Self {
+        self.node.writes.push(Resource::Texture(target.0));
+        self.node.depth_attachment = Some(DepthAttachment { target, clear });
+        self
+    }
+
+    pub fn multiview_mask(mut self, multiview_mask: Option<NonZeroU32>) -> Self {
+        self.node.multiview_mask = multiview_mask;
+        self
+    }
+
+    pub fn side_effect(mut self) -> Self {
+        self.node.side_effect = true;
+        self
+    }
+
+    pub fn render(
+        mut self,
+        callback: impl FnOnce(&mut wgpu::RenderPass<'static>, &GraphResources) + 'a,
+    ) {
+        self.node.callback = Some(PassCallback::Render(Box::new(callback)));
+        self.graph.passes.push(self.node);
+    }
+
+    pub fn encode(
+        mut self,
+        callback: impl FnOnce(&mut wgpu::CommandEncoder, &GraphResources) + 'a,
+    ) {
+        debug_assert!(
+            self.node.color_attachments.is_empty() && self.node.depth_attachment.is_none(),
+            "Encoder pass {} declares attachments",
+            self.node.label
+        );
+        self.node.callback = Some(PassCallback::Encode(Box::new(callback)));
+        self.graph.passes.push(self.node);
+    }
+}
+
+pub struct GraphResources<'r> {
+    textures: Vec<Option<&'r wgpu::TextureView>>,
+    buffers: Vec<Option<&'r wgpu::Buffer>>,
+}
+
+impl GraphResources<'_> {
+    pub fn texture(&self, texture: TextureHandle) -> &wgpu::TextureView {
+        self.textures[texture.0].expect("Render graph texture was culled")
+    }
+
+    pub fn buffer(&self, buffer: BufferHandle) -> &wgpu::Buffer {
+        self.buffers[buffer.0].expect("Render graph buffer was culled")
+    }
+}
+
+trait Poolable: Copy {
+    fn is_compatible(&self, other: &Self) -> bool;
+}
+
+struct Pooled<D, R> {
+    descriptor: D,
+    resource: R,
+    busy_until: Option<usize>,
+}
+
+fn release<D, R>(pooled: &mut Vec<Pooled<D, R>>) {
+    pooled.retain(|entry| entry.busy_until.is_some());
+    for entry in pooled {
+        entry.busy_until = None;
+    }
+}
+
+fn acquire<D: Poolable, R>(
+    pooled: &mut Vec<Pooled<D, R>>,
+    descriptor: &D,
+    first_use: usize,
+    last_use: usize,
+    create: impl FnOnce() -> R,
+) -> usize {
+    let available = pooled.iter().position(|entry| {
+        entry.descriptor.is_compatible(descriptor)
+            && entry
+                .busy_until
+                .is_none_or(|busy_until| busy_until < first_use)
+    });
+    let index = available.unwrap_or_else(|| {
+        pooled.push(Pooled {
+            descriptor: *descriptor,
+            resource: create(),
+            busy_until: None,
+        });
+        pooled.len() - 1
+    });
+    pooled[index].busy_until = Some(last_use);
+    index
+}
+
+struct PooledTexture {
+    _texture: Tracked<wgpu::Texture>,
+    view: wgpu::TextureView,
+}
+
+#[derive(Default)]
+pub struct TransientPool {
+    textures: Vec<Pooled<TransientTexture, PooledTexture>>,
+    buffers: Vec<Pooled<TransientBuffer, Tracked<wgpu::Buffer>>>,
+}
+
+impl TransientPool {
+    pub fn texture_count(&self) -> usize {
+        self.textures.len()
+    }
+
+    pub fn buffer_count(&self) -> usize {
+        self.buffers.len()
+    }
+
+    fn begin_frame(&mut self) {
+        release(&mut self.textures);
+        release(&mut self.buffers);
+    }
+
+    fn acquire_texture(
+        &mut self,
+        device: &wgpu::Device,
+        descriptor: &TransientTexture,
+        first_use: usize,
+        last_use: usize,
+    ) -> usize {
+        acquire(&mut self.textures, descriptor, first_use, last_use, || {
+            let texture =
+                ResourceTracker::new(device, OWNER).create_texture(&wgpu::TextureDescriptor {
+                    label: Some(descriptor.label),
+                    size: descriptor.size,
+                    mip_level_count: 1,
+                    sample_count: descriptor.sample_count,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: descriptor.format,
+                    usage: descriptor.usage,
+                    view_formats: &[],
+                });
+            let view = texture.create_view(&wgpu::TextureViewDescriptor {
+                label: Some(descriptor.label),
+                dimension: Some(descriptor.view_dimension),
+                ..Default::default()
+            });
+            PooledTexture {
+                _texture: texture,
+                view,
+            }
+        })
+    }
+
+    fn acquire_buffer(
+        &mut self,
+        device: &wgpu::Device,
+        descriptor: &TransientBuffer,
+        first_use: usize,
+        last_use: usize,
+    ) -> usize {
+        acquire(&mut self.buffers, descriptor, first_use, last_use, || {
+            ResourceTracker::new(device, OWNER).create_buffer(&wgpu::BufferDescriptor {
+                label: Some(descriptor.label),
+                size: descriptor.size,
+                usage: descriptor.usage,
+                mapped_at_creation: false,
+            })
+        })
+    }
+}
+
+#[derive(Default)]
+pub struct RenderGraph<'a> {
+    textures: Vec<TextureResource<'a>>,
+    buffers: Vec<BufferResource<'a>>,
+    passes: Vec<PassNode<'a>>,
+    timer: Option<&'a mut GpuTimer>,
+}
+
+struct CompiledGraph {
+    order: Vec<usize>,
+    first_use: Vec<Option<usize>>,
+    last_use: Vec<Option<usize>>,
+    first_write: Vec<Option<usize>>,
+}
+
+impl CompiledGraph {
+    fn load_op<V>(&self, slot: usize, position: usize, clear: V) -> wgpu::LoadOp<V> {
+        if self.first_write[slot] == Some(position) {
+            wgpu::LoadOp::Clear(clear)
+        } else {
+            wgpu::LoadOp::Load
+        }
+    }
+
+    fn store_op(&self, slot: usize, position: usize, imported: bool) -> wgpu::StoreOp {
+        if imported || self.last_use[slot].is_some_and(|last| last > position) {
+            wgpu::StoreOp::Store
+        } else {
+            wgpu::StoreOp::Discard
+        }
+    }
+}
+
+impl<'a> RenderGraph<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn import_texture(&mut self, view: &'a wgpu::TextureView) -> TextureHandle {
+        self.textures.push(TextureResource::Imported(view));
+        TextureHandle(self.textures.len() - 1)
+    }
+
+    pub fn create_texture(&mut self, descriptor: TransientTexture) -> TextureHandle {
+        self.textures.push(TextureResource::Transient(descriptor));
+        TextureHandle(self.textures.len() - 1)
+    }
+
+    pub fn import_buffer(&mut self, buffer: &'a wgpu::Buffer) -> BufferHandle {
+        self.buffers.push(BufferResource::Imported(buffer));
+        BufferHandle(self.buffers.len() - 1)
+    }
+
+    pub fn create_buffer(&mut self, descriptor: TransientBuffer) -> BufferHandle {
+        self.buffers.push(BufferResource::Transient(descriptor));
+        BufferHandle(self.buffers.len() - 1)
+    }
+
+    pub fn set_timer(&mut self, timer: &'a mut GpuTimer) {
+        self.timer = Some(timer);
+    }
+
+    pub fn add_pass(&mut self, label: &'static str) -> PassBuilder<'_, 'a> {
+        PassBuilder {
+            graph: self,
+            node: PassNode {
+                label,
+                reads: Vec::new(),
+                writes: Vec::new(),
+                color_attachments: Vec::new(),
+                depth_attachment: None,
+                multiview_mask: None,
+                side_effect: false,
+                callback: None,
+            },
+        }
+    }
+
+    pub fn execute(
+        self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        pool: &mut TransientPool,
+    ) {
+        let compiled = self.compile();
+
+        pool.begin_frame();
+        let mut transient_textures = vec![None; self.textures.len()];
+        let mut transient_buffers = vec![None; self.buffers.len()];
+        let mut transients: Vec<(Resource, usize, usize)> = (0..self.textures.len())
+            .map(Resource::Texture)
+            .chain((0..self.buffers.len()).map(Resource::Buffer))
+            .filter_map(|resource| {
+                let slot = self.resource_slot(resource);
+                Some((
+                    resource,
+                    compiled.first_use[slot]?,
+                    compiled.last_use[slot]?,
+                ))
+            })
+            .collect();
+        transients.sort_by_key(|&(_, first_use, _)| first_use);
+        for (resource, first_use, last_use) in transients {
+            match resource {
+                Resource::Texture(index) => {
+                    if let TextureResource::Transient(descriptor) = &self.textures[index] {
+                        transient_textures[index] =
+                            Some(pool.acquire_texture(device, descriptor, first_use, last_use));
+                    }
+                }
+                Resource::Buffer(index) => {
+                    if let BufferResource::Transient(descriptor) = &self.buffers[index] {
+                        transient_buffers[index] =
+                            Some(pool.acquire_buffer(device, descriptor, first_use, last_use));
+                    }
+                }
+            }
+        }
+
+        let resources = GraphResources {
+            textures: self
+                .textures
+                .iter()
+                .zip(&transient_textures)
+                .map(|(texture, pooled)| match texture {
+                    TextureResource::Imported(view) => Some(*view),
+                    TextureResource::Transient(_) => {
+                        pooled.map(|index| &pool.textures[index].resource.view)
+                    }
+                })
+                .collect(),
+            buffers: self
+                .buffers
+                .iter()
+                .zip(&transient_buffers)
+                .map(|(buffer, pooled)| match buffer {
+                    BufferResource::Imported(buffer) => Some(*buffer),
+                    BufferResource::Transient(_) => {
+                        pooled.map(|index| &*pool.buffers[index].resource)
+                    }
+                })
+                .collect(),
+        };
+
+        let is_imported: Vec<bool> = self
+            .textures
+            .iter()
+            .map(|texture| matches!(texture, TextureResource::Imported(_)))
+            .collect();
+        let mut timer = self.timer;
+        let mut passes: Vec<Option<PassNode>> = self.passes.into_iter().map(Some).collect();
+        for (position, &pass_index) in compiled.order.iter().enumerate() {
+            let mut pass = passes[pass_index].take().expect("Pass scheduled twice");
+            let store_op = |texture: TextureHandle| {
+                compiled.store_op(texture.0, position, is_imported[texture.0])
+            };
+
+            match pass.callback.take().expect("Pass has no callback") {
+                PassCallback::Encode(callback) => {
+                    encoder.push_debug_group(pass.label);
+                    let query = timer
+                        .as_mut()
+                        .and_then(|timer| timer.begin_encoder_pass(encoder, pass.label));
+                    callback(encoder, &resources);
+                    if let Some(timer) = timer.as_ref() {
+                        timer.end_encoder_pass(encoder, query);
+                    }
+                    encoder.pop_debug_group();
+                }
+                PassCallback::Render(callback) => {
+                    let color_attachments: Vec<Option<wgpu::RenderPassColorAttachment>> = pass
+                        .color_attachments
+                        .iter()
+                        .map(|attachment| {
+                            Some(wgpu::RenderPassColorAttachment {
+                                view: resources.texture(attachment.target),
+                                resolve_target: attachment
+                                    .resolve_target
+                                    .map(|resolve_target| resources.texture(resolve_target)),
+                                ops: wgpu::Operations {
+                                    load: compiled.load_op(
+                                        attachment.target.0,
+                                        position,
+                                        attachment.clear,
+                                    ),
+                                    store: store_op(attachment.target),
+                                },
+                                depth_slice: None,
+                            })
+                        })
+                        .collect();
+                    let depth_stencil_attachment =
+                        pass.depth_attachment.as_ref().map(|attachment| {
+                            wgpu::RenderPassDepthStencilAttachment {
+                                view: resources.texture(attachment.target),
+                                depth_ops: Some(wgpu::Operations {
+                                    load: compiled.load_op(
+                                        attachment.target.0,
+                                        position,
+                                        attachment.clear,
+                                    ),
+                                    store: store_op(attachment.target),
+                                }),
+                                stencil_ops: None,
+                            }
+                        });
+                    let mut render_pass = encoder
+                        .begin_render_pass(&wgpu::RenderPassDescriptor {
+                            label: Some(pass.label),
+                            color_attachments: &color_attachments,
+                            depth_stencil_attachment,
+                            timestamp_writes: timer
+                                .as_mut()
+                                .and_then(|timer| timer.render_pass_writes(pass.label)),
+                            occlusion_query_set: None,
+                            multiview_mask: pass.multiview_mask,
+                        })
+                        .forget_lifetime();
+                    callback(&mut render_pass, &resources);
+                }
+            }
+        }
+    }
+
+    fn resource_slot(&self, resource: Resource) -> usize {
+        match resource {
+            Resource::Texture(index) => index,
+            Resource::Buffer(index) => self.textures.len() + index,
+        }
+    }
+
+    fn is_imported(&self, resource: Resource) -> bool {
+        match resource {
+            Resource::Texture(index) => {
+                matches!(self.textures[index], TextureResource::Imported(_))
+            }
+            Resource::Buffer(index) => matches!(self.buffers[index], BufferResource::Imported(_)),
+        }
+    }
+
+    fn compile(&self) -> CompiledGraph {
+        let pass_count = self.passes.len();
+        let slot_count = self.textures.len() + self.buffers.len();
+
+        let mut dependencies = vec![Vec::new(); pass_count];
+        let mut producers = vec![Vec::new(); pass_count];
+        let mut last_writer: Vec<Option<usize>> = vec![None; slot_count];
+        let mut readers: Vec<Vec<usize>> = vec![Vec::new(); slot_count];
+        for (index, pass) in self.passes.iter().enumerate() {
+            for &resource in &pass.reads {
+                let slot = self.resource_slot(resource);
+                if let Some(writer) = last_writer[slot] {
+                    dependencies[index].push(writer);
+                    producers[index].push(writer);
+                }
+                readers[slot].push(index);
+            }
+            for &resource in &pass.writes {
+                let slot = self.resource_slot(resource);
+                if let Some(writer) = last_writer[slot].filter(|&writer| writer != index) {
+                    dependencies[index].push(writer);
+                    producers[index].push(writer);
+                }
+                dependencies[index]
+                    .extend(readers[slot].drain(..).filter(|&reader| reader != index));
+                last_writer[slot] = Some(index);
+            }
+        }
+
+        let mut live = vec![false; pass_count];
+        for index in (0..pass_count).rev() {
+            let pass = &self.passes[index];
+            live[index] |= pass.side_effect
+                || pass
+                    .writes
+                    .iter()
+                    .any(|&resource| self.is_imported(resource));
+            if live[index] {
+                for &producer in &producers[index] {
+                    live[producer] = true;
+                }
+            }
+        }
+
+        let mut remaining: Vec<usize> = dependencies
+            .iter()
+            .map(|dependencies| dependencies.len())
+            .collect();
+        let mut dependents = vec![Vec::new(); pass_count];
+        for (index, dependencies) in dependencies.iter().enumerate() {
+            for &dependency in dependencies {
+                dependents[dependency].push(index);
+            }
+        }
+        let mut ready: std::collections::BinaryHeap<std::cmp::Reverse<usize>> = (0..pass_count)
+            .filter(|&index| remaining[index] == 0)
+            .map(std::cmp::Reverse)
+            .collect();
+        let mut order = Vec::with_capacity(pass_count);
+        while let Some(std::cmp::Reverse(index)) = ready.pop() {
+            if live[index] {
+                order.push(index);
+            }
+            for &dependent in &dependents[index] {
+                remaining[dependent] -= 1;
+                if remaining[dependent] == 0 {
+                    ready.push(std::cmp::Reverse(dependent));
+                }
+            }
+        }
+        assert!(
+            remaining.iter().all(|&count| count == 0),
+            "Render graph contains a dependency cycle"
+        );
+
+        let mut first_use = vec![None; slot_count];
+        let mut last_use = vec![None; slot_count];
+        let mut first_write = vec![None; slot_count];
+        for (position, &index) in order.iter().enumerate() {
+            let pass = &self.passes[index];
+            for &resource in pass.reads.iter().chain(&pass.writes) {
+                let slot = self.resource_slot(resource);
+                first_use[slot].get_or_insert(position);
+                last_use[slot] = Some(position);
+            }
+            for &resource in &pass.reads {
+                assert!(
+                    self.is_imported(resource)
+                        || first_write[self.resource_slot(resource)].is_some(),
+                    "Render graph pass '{}' reads a transient resource before any pass writes it",
+                    pass.label
+                );
+            }
+            for &resource in &pass.writes {
+                first_write[self.resource_slot(resource)].get_or_insert(position);
+            }
+        }
+
+        CompiledGraph {
+            order,
+            first_use,
+            last_use,
+            first_write,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn color(label: &'static str) -> TransientTexture {
+        TransientTexture::attachment(label, 64, 64, wgpu::TextureFormat::Rgba8Unorm, 1)
+    }
+
+    fn buffer(label: &'static str, size: u64) -> TransientBuffer {
+        TransientBuffer {
+            label,
+            size,
+            usage: wgpu::BufferUsages::STORAGE,
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "reads a transient resource before any pass writes it")]
+    fn pass_reading_and_writing_unwritten_transient_fails() {
+        let mut graph = RenderGraph::new();
+        let texture = graph.create_texture(color("Accumulation"));
+        graph
+            .add_pass("Accumulate")
+            .read_texture(texture)
+            .write_texture(texture)
+            .side_effect()
+            .encode(|_, _| {});
+        graph.compile();
+    }
+
+    #[test]
+    fn passes_without_live_consumers_are_culled() {
+        let mut graph = RenderGraph::new();
+        let unused = graph.create_texture(color("Unused"));
+        let shadow = graph.create_texture(color("Shadow"));
+        let scene = graph.create_texture(color("Scene"));
+        graph
+            .add_pass("Dead")
+            .color_attachment(unused, None, wgpu::Color::BLACK)
+            .render(|_, _| {});
+        graph
+            .add_pass("Shadow")
+            .depth_attachment(shadow, 1.0)
+            .render(|_, _| {});
+        graph
+            .add_pass("Scene")
+            .read_texture(shadow)
+            .color_attachment(scene, None, wgpu::Color::BLACK)
+            .side_effect()
+            .render(|_, _| {});
+
+        let compiled = graph.compile();
+        assert_eq!(compiled.order, [1, 2]);
+        assert_eq!(compiled.first_use[unused.0], None);
+        assert_eq!(compiled.first_use[shadow.0], Some(0));
+        assert_eq!(compiled.last_use[shadow.0], Some(1));
+    }
+
+    #[test]
+    fn load_and_store_ops_follow_resource_lifetimes() {
+        let mut graph = RenderGraph::new();
+        let scene = graph.create_texture(color("Scene"));
+        let depth = graph.create_texture(TransientTexture::attachment(
+            "Depth",
+            64,
+            64,
+            wgpu::TextureFormat::Depth32Float,
+            1,
+        ));
+        graph
+            .add_pass("Opaque")
+            .color_attachment(scene, None, wgpu::Color::BLACK)
+            .depth_attachment(depth, 1.0)
+            .render(|_, _| {});
+        graph
+            .add_pass("Overlay")
+            .color_attachment(scene, None, wgpu::Color::BLACK)
+            .side_effect()
+            .render(|_, _| {});
+
+        let compiled = graph.compile();
+        assert_eq!(compiled.order, [0, 1]);
+        assert_eq!(
+            compiled.load_op(scene.0, 0, wgpu::Color::BLACK),
+            wgpu::LoadOp::Clear(wgpu::Color::BLACK)
+        );
+        assert_eq!(
+            compiled.load_op(scene.0, 1, wgpu::Color::BLACK),
+            wgpu::LoadOp::Load
+        );
+        assert_eq!(compiled.load_op(depth.0, 0, 1.0), wgpu::LoadOp::Clear(1.0));
+        assert_eq!(compiled.store_op(scene.0, 0, false), wgpu::StoreOp::Store);
+        assert_eq!(compiled.store_op(scene.0, 1, false), wgpu::StoreOp::Discard);
+        assert_eq!(compiled.store_op(scene.0, 1, true), wgpu::StoreOp::Store);
+        assert_eq!(compiled.store_op(depth.0, 0, false), wgpu::StoreOp::Discard);
+    }
+
+    #[test]
+    fn transient_pool_aliases_disjoint_lifetimes() {
+        let mut pool: Vec<Pooled<TransientBuffer, usize>> = Vec::new();
+        let mut created = 0;
+        let mut create = || {
+            created += 1;
+            created
+        };
+
+        let first = acquire(&mut pool, &buffer("First", 256), 0, 1, &mut create);
+        let overlapping = acquire(&mut pool, &buffer("Overlapping", 256), 1, 2, &mut create);
+        let disjoint = acquire(&mut pool, &buffer("Disjoint", 256), 2, 3, &mut create);
+        let larger = acquire(&mut pool, &buffer("Larger", 512), 4, 4, &mut create);
+        let reused = acquire(&mut pool, &buffer("Reused", 256), 4, 5, &mut create);
+        assert_ne!(first, overlapping);
+        assert_eq!(first, disjoint);
+        assert_ne!(larger, first);
+        assert_ne!(larger, overlapping);
+        assert_eq!(reused, first);
+        assert_eq!(pool.len(), 3);
+        assert_eq!(pool[first].resource, 1);
+
+        pool[larger].busy_until = None;
+        release(&mut pool);
+        assert_eq!(pool.len(), 2);
+        assert!(pool.iter().all(|entry| entry.busy_until.is_none()));
+        let next_frame = acquire(&mut pool, &buffer("Next Frame", 256), 0, 9, &mut create);
+        assert_eq!(pool[next_frame].resource, 1);
+        assert_eq!(created, 3);
+    }
+}
@@ -0,0 +1,343 @@
+use crate::cvars::{Cvar, CvarRegistry};
+use crate::gamepad::GamepadState;
+use crate::input::{InputEvent, InputMap};
+
+use web_time::Duration;
+
+pub const RECORDING_VERSION: u32 = 1;
+
+#[derive(Clone, Copy)]
+pub struct ReplayCvars {
+    pub record: Cvar<String>,
+    pub replay: Cvar<String>,
+    pub exit_on_finish: Cvar<bool>,
+}
+
+impl ReplayCvars {
+    pub fn declare(cvars: &mut CvarRegistry) -> Self {
+        Self {
+            record: cvars.declare_transient(
+                "input.record",
+                "Path to record input and frame timing to (empty stops recording)",
+                String::new(),
+            ),
+            replay: cvars.declare_transient(
+                "input.replay",
+                "Path of an input recording to replay (empty stops replay)",
+                String::new(),
+            ),
+            exit_on_finish: cvars.declare_transient(
+                "input.replay_exit",
+                "Exit once the input replay has finished",
+                false,
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct RecordingHeader {
+    pub version: u32,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub cvars: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bindings: Option<InputMap>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct GuiInputRecord {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<egui::Event>,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub modifiers: egui::Modifiers,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub screen_rect: Option<egui::Rect>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time: Option<f64>,
+    pub predicted_dt: f32,
+    pub focused: bool,
+}
+
+impl GuiInputRecord {
+    pub fn capture(input: &egui::RawInput) -> Self {
+        Self {
+            events: input.events.clone(),
+            modifiers: input.modifiers,
+            screen_rect: input.screen_rect,
+            time: input.time,
+            predicted_dt: input.predicted_dt,
+            focused: input.focused,
+        }
+    }
+
+    pub fn apply(&self, input: &mut egui::RawInput) {
+        input.events = self.events.clone();
+        input.modifiers = self.modifiers;
+        if self.screen_rect.is_some() {
+            input.screen_rect = self.screen_rect;
+        }
+        input.time = self.time;
+        input.predicted_dt = self.predicted_dt;
+        input.focused = self.focused;
+    }
+}
+
+fn is_default<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct PoseRecord {
+    pub position: [f32; 3],
+    pub orientation: [f32; 4],
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct XrViewRecord {
+    pub pose: PoseRecord,
+    pub fov: [f32; 4],
+}
+
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct XrPoseRecord {
+    pub predicted_display_time: i64,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub views: Vec<XrViewRecord>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub left_hand: Option<PoseRecord>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub right_hand: Option<PoseRecord>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct FrameRecord {
+    pub delta_nanos: u64,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<InputEvent>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gui: Option<GuiInputRecord>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gamepad: Option<GamepadState>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<(u32, u32)>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub xr: Vec<(String, f32)>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub xr_poses: Option<XrPoseRecord>,
+}
+
+impl FrameRecord {
+    pub fn delta_time(&self) -> Duration {
+        Duration::from_nanos(self.delta_nanos)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InputRecording {
+    pub header: RecordingHeader,
+    pub frames: Vec<FrameRecord>,
+}
+
+impl InputRecording {
+    pub fn duration(&self) -> Duration {
+        self.frames.iter().map(FrameRecord::delta_time).sum()
+    }
+
+    pub fn to_json_lines(&self) -> Result<String, serde_json::Error> {
+        let mut lines = vec![serde_json::to_string(&self.header)?];
+        for frame in &self.frames {
+            lines.push(serde_json::to_string(frame)?);
+        }
+        lines.push(String::new());
+        Ok(lines.join("\n"))
+    }
+
+    pub fn from_json_lines(source: &str) -> Result<Self, String> {
+        let mut lines = source
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty());
+        let (_, header) = lines.next().ok_or("Recording is empty")?;
+        let header: RecordingHeader =
+            serde_json::from_str(header).map_err(|error| format!("header: {error}"))?;
+        if header.version != RECORDING_VERSION {
+            return Err(format!(
+                "Unsupported recording version {} (expected {RECORDING_VERSION})",
+                header.version
+            ));
+        }
+        let frames = lines
+            .map(|(number, line)| {
+                serde_json::from_str(line).map_err(|error| format!("line {}: {error}", number + 1))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { header, frames })
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self::from_json_lines(&std::fs::read_to_string(path)?)?)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        std::fs::write(path, self.to_json_lines()?)?;
+        Ok(())
+    }
+}
+
+pub struct InputRecorder {
+    path: String,
+    recording: InputRecording,
+    events: Vec<InputEvent>,
+    gamepad: GamepadState,
+    size: Option<(u32, u32)>,
+}
+
+impl InputRecorder {
+    pub fn new(path: &str, header: RecordingHeader) -> Self {
+        Self {
+            path: path.to_string(),
+            recording: InputRecording {
+                header,
+                frames: Vec::new(),
+            },
+            events: Vec::new(),
+            gamepad: GamepadState::default(),
+            size: None,
+        }
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn recording(&self) -> &InputRecording {
+        &self.recording
+    }
+
+    pub fn record_event(&mut self, event: InputEvent) {
+        self.events.push(event);
+    }
+
+    pub fn record_frame(&mut self, delta_time: Duration, gamepad: &GamepadState, size: (u32, u32)) {
+        let gamepad = (*gamepad != self.gamepad).then(|| {
+            self.gamepad = gamepad.clone();
+            gamepad.clone()
+        });
+        let size = (self.size != Some(size)).then(|| {
+            self.size = Some(size);
+            size
+        });
+        self.recording.frames.push(FrameRecord {
+            delta_nanos: delta_time.as_nanos() as u64,
+            events: std::mem::take(&mut self.events),
+            gamepad,
+            size,
+            ..Default::default()
+        });
+    }
+
+    pub fn record_gui(&mut self, input: &egui::RawInput) {
+        if let Some(frame) = self.recording.frames.last_mut() {
+            frame.gui = Some(GuiInputRecord::capture(input));
+        }
+    }
+
+    pub fn record_xr_frame(
+        &mut self,
+        delta_time: Duration,
+        sources: &[(String, f32)],
+        poses: &XrPoseRecord,
+    ) {
+        self.recording.frames.push(FrameRecord {
+            delta_nanos: delta_time.as_nanos() as u64,
+            xr: sources.to_vec(),
+            xr_poses: Some(poses.clone()),
+            ..Default::default()
+        });
+    }
+
+    pub fn finish(mut self) -> InputRecording {
+        if !self.events.is_empty() {
+            self.recording.frames.push(FrameRecord {
+                events: std::mem::take(&mut self.events),
+                ..Default::default()
+            });
+        }
+        self.recording
+    }
+
+    pub fn save(self) {
+        let path = self.path.clone();
+        let recording = self.finish();
+        #[cfg(not(target_arch = "wasm32"))]
+        match recording.save(&path) {
+            Ok(()) => log::info!(
+                "Saved input recording to {path} ({} frames, {:.1}s)",
+                recording.frames.len(),
+                recording.duration().as_secs_f32()
+            ),
+            Err(error) => log::error!("Failed to save input recording {path}: {error}"),
+        }
+        #[cfg(target_arch = "wasm32")]
+        match recording.to_json_lines() {
+            Ok(json) => log::info!("Input recording for {path}:\n{json}"),
+            Err(error) => log::error!("Failed to serialize input recording {path}: {error}"),
+        }
+    }
+}
+
+pub struct InputReplay {
+    recording: InputRecording,
+    position: usize,
+    gamepad: GamepadState,
+}
+
+impl InputReplay {
+    pub fn new(recording: InputRecording) -> Self {
+        Self {
+            recording,
+            position: 0,
+            gamepad: GamepadState::default(),
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self::new(InputRecording::load(path)?))
+    }
+
+    pub fn header(&self) -> &RecordingHeader {
+        &self.recording.header
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn len(&self) -> usize {
+        self.recording.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.recording.frames.is_empty()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.position >= self.recording.frames.len()
+    }
+
+    pub fn gamepad(&self) -> &GamepadState {
+        &self.gamepad
+    }
+
+    pub fn next_frame(&mut self) -> Option<FrameRecord> {
+        let frame = self.recording.frames.get(self.position)?.clone();
+        self.position += 1;
+        if let Some(gamepad) = &frame.gamepad {
+            self.gamepad = gamepad.clone();
+        }
+        Some(frame)
+    }
+}
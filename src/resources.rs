@@ -0,0 +1,491 @@
+use std::collections::BTreeMap;
+use std::ops::Deref;
+use std::sync::{Mutex, OnceLock};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ResourceKind {
+    Buffer,
+    Texture,
+    Sampler,
+    BindGroup,
+    RenderPipeline,
+    ComputePipeline,
+    QuerySet,
+}
+
+impl ResourceKind {
+    pub const ALL: [Self; 7] = [
+        Self::Buffer,
+        Self::Texture,
+        Self::Sampler,
+        Self::BindGroup,
+        Self::RenderPipeline,
+        Self::ComputePipeline,
+        Self::QuerySet,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Buffer => "Buffer",
+            Self::Texture => "Texture",
+            Self::Sampler => "Sampler",
+            Self::BindGroup => "Bind Group",
+            Self::RenderPipeline => "Render Pipeline",
+            Self::ComputePipeline => "Compute Pipeline",
+            Self::QuerySet => "Query Set",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResourceInfo {
+    pub kind: ResourceKind,
+    pub owner: &'static str,
+    pub label: String,
+    pub size: u64,
+    pub usage: String,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResourceTotals {
+    pub count: usize,
+    pub size: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct ResourceRegistry {
+    entries: BTreeMap<u64, ResourceInfo>,
+    next_id: u64,
+    created: usize,
+}
+
+impl ResourceRegistry {
+    fn insert(&mut self, info: ResourceInfo) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.created += 1;
+        self.entries.insert(id, info);
+        id
+    }
+
+    fn remove(&mut self, id: u64) {
+        self.entries.remove(&id);
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn created(&self) -> usize {
+        self.created
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = (u64, &ResourceInfo)> {
+        self.entries.iter().map(|(id, info)| (*id, info))
+    }
+
+    pub fn totals(&self) -> ResourceTotals {
+        self.totals_where(|_| true)
+    }
+
+    pub fn totals_where(&self, filter: impl Fn(&ResourceInfo) -> bool) -> ResourceTotals {
+        self.entries.values().filter(|info| filter(info)).fold(
+            ResourceTotals::default(),
+            |totals, info| ResourceTotals {
+                count: totals.count + 1,
+                size: totals.size + info.size,
+            },
+        )
+    }
+
+    pub fn owners(&self) -> Vec<&'static str> {
+        let mut owners = self
+            .entries
+            .values()
+            .map(|info| info.owner)
+            .collect::<Vec<_>>();
+        owners.sort_unstable();
+        owners.dedup();
+        owners
+    }
+}
+
+pub fn resource_registry() -> &'static Mutex<ResourceRegistry> {
+    static REGISTRY: OnceLock<Mutex<ResourceRegistry>> = OnceLock::new();
+    REGISTRY.get_or_init(Mutex::default)
+}
+
+pub fn leak_report() -> Vec<ResourceInfo> {
+    resource_registry()
+        .lock()
+        .map(|registry| registry.entries.values().cloned().collect())
+        .unwrap_or_default()
+}
+
+pub fn log_leak_report() {
+    let leaks = leak_report();
+    if leaks.is_empty() {
+        log::info!("No GPU resources leaked");
+        return;
+    }
+    log::warn!("{} GPU resources still alive at shutdown:", leaks.len());
+    for info in &leaks {
+        log::warn!(
+            "  [{}] {} '{}' ({})",
+            info.owner,
+            info.kind.label(),
+            info.label,
+            format_size(info.size)
+        );
+    }
+}
+
+pub fn format_size(size: u64) -> String {
+    match size {
+        0 => "-".to_string(),
+        size if size < 1024 => format!("{size} B"),
+        size if size < 1024 * 1024 => format!("{:.1} KiB", size as f64 / 1024.0),
+        size => format!("{:.1} MiB", size as f64 / (1024.0 * 1024.0)),
+    }
+}
+
+#[derive(Debug)]
+pub struct Tracked<T> {
+    resource: T,
+    id: u64,
+}
+
+impl<T> Tracked<T> {
+    pub fn new(resource: T, info: ResourceInfo) -> Self {
+        let id = resource_registry()
+            .lock()
+            .map(|mut registry| registry.insert(info))
+            .unwrap_or(u64::MAX);
+        Self { resource, id }
+    }
+}
+
+impl<T> Deref for Tracked<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.resource
+    }
+}
+
+impl<'a> From<&'a Tracked<wgpu::BindGroup>> for Option<&'a wgpu::BindGroup> {
+    fn from(bind_group: &'a Tracked<wgpu::BindGroup>) -> Self {
+        Some(bind_group)
+    }
+}
+
+impl<T> Drop for Tracked<T> {
+    fn drop(&mut self) {
+        if let Ok(mut registry) = resource_registry().lock() {
+            registry.remove(self.id);
+        }
+    }
+}
+
+fn flag_names<'a>(names: impl Iterator<Item = (&'a str, impl Sized)>) -> String {
+    let names = names.map(|(name, _)| name).collect::<Vec<_>>();
+    if names.is_empty() {
+        "-".to_string()
+    } else {
+        names.join(" | ")
+    }
+}
+
+pub fn texture_size(descriptor: &wgpu::TextureDescriptor) -> u64 {
+    let format = descriptor.format;
+    let bytes_per_block = format
+        .block_copy_size(None)
+        .or_else(|| format.target_pixel_byte_cost())
+        .unwrap_or(4) as u64;
+    let (block_width, block_height) = format.block_dimensions();
+    (0..descriptor.mip_level_count)
+        .filter_map(|level| descriptor.mip_level_size(level))
+        .map(|size| {
+            size.width.div_ceil(block_width) as u64
+                * size.height.div_ceil(block_height) as u64
+                * size.depth_or_array_layers as u64
+                * bytes_per_block
+        })
+        .sum::<u64>()
+        * descriptor.sample_count as u64
+}
+
+#[derive(Clone, Copy)]
+pub struct ResourceTracker<'d> {
+    pub device: &'d wgpu::Device,
+    pub owner: &'static str,
+}
+
+impl<'d> ResourceTracker<'d> {
+    pub fn new(device: &'d wgpu::Device, owner: &'static str) -> Self {
+        Self { device, owner }
+    }
+
+    fn info(
+        &self,
+        kind: ResourceKind,
+        label: Option<&str>,
+        size: u64,
+        usage: String,
+    ) -> ResourceInfo {
+        ResourceInfo {
+            kind,
+            owner: self.owner,
+            label: label.unwrap_or("Unlabeled").to_string(),
+            size,
+            usage,
+        }
+    }
+
+    pub fn create_buffer(&self, descriptor: &wgpu::BufferDescriptor) -> Tracked<wgpu::Buffer> {
+        Tracked::new(
+            self.device.create_buffer(descriptor),
+            self.info(
+                ResourceKind::Buffer,
+                descriptor.label,
+                descriptor.size,
+                flag_names(descriptor.usage.iter_names()),
+            ),
+        )
+    }
+
+    pub fn create_buffer_init(
+        &self,
+        descriptor: &wgpu::util::BufferInitDescriptor,
+    ) -> Tracked<wgpu::Buffer> {
+        Tracked::new(
+            wgpu::util::DeviceExt::create_buffer_init(self.device, descriptor),
+            self.info(
+                ResourceKind::Buffer,
+                descriptor.label,
+                descriptor.contents.len() as u64,
+                flag_names(descriptor.usage.iter_names()),
+            ),
+        )
+    }
+
+    pub fn create_texture(&self, descriptor: &wgpu::TextureDescriptor) -> Tracked<wgpu::Texture> {
+        self.track_texture(self.device.create_texture(descriptor), descriptor)
+    }
+
+    pub fn track_texture(
+        &self,
+        texture: wgpu::Texture,
+        descriptor: &wgpu::TextureDescriptor,
+    ) -> Tracked<wgpu::Texture> {
+        Tracked::new(
+            texture,
+            self.info(
+                ResourceKind::Texture,
+                descriptor.label,
+                texture_size(descriptor),
+                format!(
+                    "{:?} {}",
+                    descriptor.format,
+                    flag_names(descriptor.usage.iter_names())
+                ),
+            ),
+        )
+    }
+
+    pub fn create_sampler(&self, descriptor: &wgpu::SamplerDescriptor) -> Tracked<wgpu::Sampler> {
+        Tracked::new(
+            self.device.create_sampler(descriptor),
+            self.info(
+                ResourceKind::Sampler,
+                descriptor.label,
+                0,
+                format!("{:?}", descriptor.mag_filter),
+            ),
+        )
+    }
+
+    pub fn create_bind_group(
+        &self,
+        descriptor: &wgpu::BindGroupDescriptor,
+    ) -> Tracked<wgpu::BindGroup> {
+        Tracked::new(
+            self.device.create_bind_group(descriptor),
+            self.info(
+                ResourceKind::BindGroup,
+                descriptor.label,
+                0,
+                format!("{} entries", descriptor.entries.len()),
+            ),
+        )
+    }
+
+    pub fn create_render_pipeline(
+        &self,
+        descriptor: &wgpu::RenderPipelineDescriptor,
+    ) -> Tracked<wgpu::RenderPipeline> {
+        Tracked::new(
+            self.device.create_render_pipeline(descriptor),
+            self.info(
+                ResourceKind::RenderPipeline,
+                descriptor.label,
+                0,
+                format!("{}x MSAA", descriptor.multisample.count),
+            ),
+        )
+    }
+
+    pub fn create_compute_pipeline(
+        &self,
+        descriptor: &wgpu::ComputePipelineDescriptor,
+    ) -> Tracked<wgpu::ComputePipeline> {
+        Tracked::new(
+            self.device.create_compute_pipeline(descriptor),
+            self.info(
+                ResourceKind::ComputePipeline,
+                descriptor.label,
+                0,
+                descriptor.entry_point.unwrap_or("-").to_string(),
+            ),
+        )
+    }
+
+    pub fn create_query_set(
+        &self,
+        descriptor: &wgpu::QuerySetDescriptor,
+    ) -> Tracked<wgpu::QuerySet> {
+        Tracked::new(
+            self.device.create_query_set(descriptor),
+            self.info(
+                ResourceKind::QuerySet,
+                descriptor.label,
+                descriptor.count as u64 * 8,
+                format!("{:?}", descriptor.ty),
+            ),
+        )
+    }
+}
+
+#[derive(Default)]
+pub struct ResourceInspector {
+    pub visible: bool,
+    pub filter: String,
+    pub kind: Option<ResourceKind>,
+    pub owner: Option<&'static str>,
+}
+
+impl ResourceInspector {
+    pub fn show(&mut self, ctx: &egui::Context) {
+        let mut visible = self.visible;
+        egui::Window::new("GPU Resources")
+            .open(&mut visible)
+            .default_width(560.0)
+            .default_height(420.0)
+            .show(ctx, |ui| self.ui(ui));
+        self.visible = visible;
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui) {
+        let Ok(registry) = resource_registry().lock() else {
+            return;
+        };
+
+        let totals = registry.totals();
+        ui.label(format!(
+            "{} live resources, {} tracked ({} created)",
+            totals.count,
+            format_size(totals.size),
+            registry.created()
+        ));
+
+        egui::CollapsingHeader::new("Totals")
+            .default_open(true)
+            .show(ui, |ui| {
+                egui::Grid::new("resource_totals")
+                    .num_columns(3)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for kind in ResourceKind::ALL {
+                            let totals = registry.totals_where(|info| info.kind == kind);
+                            if totals.count == 0 {
+                                continue;
+                            }
+                            ui.label(kind.label());
+                            ui.monospace(totals.count.to_string());
+                            ui.monospace(format_size(totals.size));
+                            ui.end_row();
+                        }
+                        ui.separator();
+                        ui.end_row();
+                        for owner in registry.owners() {
+                            let totals = registry.totals_where(|info| info.owner == owner);
+                            ui.label(owner);
+                            ui.monospace(totals.count.to_string());
+                            ui.monospace(format_size(totals.size));
+                            ui.end_row();
+                        }
+                    });
+            });
+
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt("resource_kind")
+                .selected_text(self.kind.map_or("All Kinds", ResourceKind::label))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.kind, None, "All Kinds");
+                    for kind in ResourceKind::ALL {
+                        ui.selectable_value(&mut self.kind, Some(kind), kind.label());
+                    }
+                });
+            egui::ComboBox::from_id_salt("resource_owner")
+                .selected_text(self.owner.unwrap_or("All Owners"))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.owner, None, "All Owners");
+                    for owner in registry.owners() {
+                        ui.selectable_value(&mut self.owner, Some(owner), owner);
+                    }
+                });
+            ui.add(egui::TextEdit::singleline(&mut self.filter).hint_text("Filter"));
+        });
+
+        let filter = self.filter.to_lowercase();
+        let entries = registry
+            .entries()
+            .filter(|(_, info)| self.kind.is_none_or(|kind| info.kind == kind))
+            .filter(|(_, info)| self.owner.is_none_or(|owner| info.owner == owner))
+            .filter(|(_, info)| filter.is_empty() || info.label.to_lowercase().contains(&filter))
+            .collect::<Vec<_>>();
+
+        ui.separator();
+        let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+        egui::ScrollArea::both().auto_shrink(false).show_rows(
+            ui,
+            row_height,
+            entries.len(),
+            |ui, rows| {
+                egui::Grid::new("resource_list")
+                    .num_columns(5)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.strong("Kind");
+                        ui.strong("Owner");
+                        ui.strong("Label");
+                        ui.strong("Size");
+                        ui.strong("Usage");
+                        ui.end_row();
+                        for (_, info) in &entries[rows] {
+                            ui.label(info.kind.label());
+                            ui.label(info.owner);
+                            ui.label(&info.label);
+                            ui.monospace(format_size(info.size));
+                            ui.label(&info.usage);
+                            ui.end_row();
+                        }
+                    });
+            },
+        );
+    }
+}
@@ -0,0 +1,520 @@
+use crate::hierarchy::NodeInfo;
+use crate::history::{AddInstances, Command, CommandGroup, History, NodeEdit, RemoveInstances};
+use crate::instancing::InstanceData;
+use crate::selection::SelectionMode;
+use crate::{Scene, SceneObject};
+
+use std::collections::HashSet;
+
+const INDENT: f32 = 14.0;
+const CHILD_OFFSET: f32 = 0.5;
+const CHILD_SCALE: f32 = 0.2;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Row {
+    Batch {
+        batch: usize,
+    },
+    Object {
+        object: SceneObject,
+        depth: usize,
+        has_children: bool,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Action {
+    Select(SceneObject, SelectionMode),
+    ToggleExpanded(SceneObject),
+    ToggleBatch(usize),
+    SetVisible(SceneObject, bool),
+    SetLocked(SceneObject, bool),
+    BeginRename(SceneObject),
+    Rename(SceneObject, String),
+    Reparent(SceneObject, Option<SceneObject>),
+    Duplicate(SceneObject),
+    AddChild(SceneObject),
+    Delete(SceneObject),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SceneTree {
+    pub filter: String,
+    expanded: HashSet<SceneObject>,
+    collapsed_batches: HashSet<usize>,
+    renaming: Option<(SceneObject, String)>,
+    last_primary: Option<SceneObject>,
+}
+
+impl SceneTree {
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        scene: &mut Scene,
+        history: &mut History,
+        device: &wgpu::Device,
+    ) {
+        ui.horizontal(|ui| {
+            ui.label("🔍");
+            ui.add(
+                egui::TextEdit::singleline(&mut self.filter)
+                    .hint_text("Search")
+                    .desired_width(f32::INFINITY),
+            );
+        });
+        ui.checkbox(&mut scene.selection.multi_select, "Multi-Select");
+        ui.separator();
+
+        let primary = scene.selection.primary();
+        let reveal = primary != self.last_primary;
+        if reveal && let Some(primary) = primary {
+            self.expanded.extend(scene.ancestors(primary));
+            if let SceneObject::Instance { batch, .. } = primary {
+                self.collapsed_batches.remove(&batch);
+            }
+        }
+        self.last_primary = primary;
+
+        let rows = self.rows(scene);
+        let row_height = ui.spacing().interact_size.y;
+        let mut scroll_area = egui::ScrollArea::vertical().auto_shrink([false, false]);
+        if reveal
+            && let Some(index) = rows.iter().position(
+                |row| matches!(row, Row::Object { object, .. } if Some(*object) == primary),
+            )
+        {
+            let spacing = row_height + ui.spacing().item_spacing.y;
+            let visible_rows = (ui.available_height() / spacing).floor();
+            scroll_area = scroll_area
+                .vertical_scroll_offset((index as f32 - visible_rows * 0.5).max(0.0) * spacing);
+        }
+
+        let mode = scene.selection.mode(&ui.input(|input| input.modifiers));
+        let mut actions = Vec::new();
+        scroll_area.show_rows(ui, row_height, rows.len(), |ui, range| {
+            for row in &rows[range] {
+                match *row {
+                    Row::Batch { batch } => {
+                        self.batch_row(ui, scene, batch, &mut actions);
+                    }
+                    Row::Object {
+                        object,
+                        depth,
+                        has_children,
+                    } => {
+                        self.object_row(
+                            ui,
+                            scene,
+                            (object, depth, has_children),
+                            mode,
+                            &mut actions,
+                        );
+                    }
+                }
+            }
+        });
+
+        for action in actions {
+            self.apply(action, scene, history, device);
+        }
+    }
+
+    fn rows(&self, scene: &Scene) -> Vec<Row> {
+        let filter = self.filter.trim().to_lowercase();
+        let matches = (!filter.is_empty()).then(|| {
+            let mut matches = HashSet::new();
+            let objects = std::iter::once(SceneObject::Mesh).chain(
+                scene
+                    .batches
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(batch, instances)| {
+                        instances
+                            .iter()
+                            .map(move |(instance, _)| SceneObject::Instance { batch, instance })
+                    }),
+            );
+            for object in objects {
+                if scene.object_label(object).to_lowercase().contains(&filter) {
+                    matches.insert(object);
+                    matches.extend(scene.ancestors(object));
+                }
+            }
+            matches
+        });
+
+        let mut rows = Vec::new();
+        self.push_subtree(scene, SceneObject::Mesh, 0, matches.as_ref(), &mut rows);
+        for (batch_index, batch) in scene.batches.iter().enumerate() {
+            rows.push(Row::Batch { batch: batch_index });
+            if matches.is_none() && self.collapsed_batches.contains(&batch_index) {
+                continue;
+            }
+            for (instance, _) in batch.iter() {
+                let object = SceneObject::Instance {
+                    batch: batch_index,
+                    instance,
+                };
+                if scene.parent(object).is_none() {
+                    self.push_subtree(scene, object, 1, matches.as_ref(), &mut rows);
+                }
+            }
+        }
+        rows
+    }
+
+    fn push_subtree(
+        &self,
+        scene: &Scene,
+        object: SceneObject,
+        depth: usize,
+        matches: Option<&HashSet<SceneObject>>,
+        rows: &mut Vec<Row>,
+    ) {
+        if matches.is_some_and(|matches| !matches.contains(&object)) {
+            return;
+        }
+        let children = scene.children(object);
+        rows.push(Row::Object {
+            object,
+            depth,
+            has_children: !children.is_empty(),
+        });
+        if matches.is_some() || self.expanded.contains(&object) {
+            for child in children {
+                self.push_subtree(scene, child, depth + 1, matches, rows);
+            }
+        }
+    }
+
+    fn batch_row(&self, ui: &mut egui::Ui, scene: &Scene, batch: usize, actions: &mut Vec<Action>) {
+        let Some(instances) = scene.batches.get(batch) else {
+            return;
+        };
+        let collapsed = self.collapsed_batches.contains(&batch);
+        let icon = if collapsed { "⏵" } else { "⏷" };
+        let response = ui.add(
+            egui::Button::selectable(
+                false,
+                egui::RichText::new(format!("{icon} {} ({})", instances.name, instances.len()))
+                    .strong(),
+            )
+            .frame_when_inactive(false),
+        );
+        if response.clicked() {
+            actions.push(Action::ToggleBatch(batch));
+        }
+        drop_target(ui, &response, scene, None, actions);
+    }
+
+    fn object_row(
+        &mut self,
+        ui: &mut egui::Ui,
+        scene: &Scene,
+        (object, depth, has_children): (SceneObject, usize, bool),
+        mode: SelectionMode,
+        actions: &mut Vec<Action>,
+    ) {
+        let info = scene.hierarchy.info(object);
+        ui.horizontal(|ui| {
+            ui.add_space(depth as f32 * INDENT);
+            let toggle_width = ui.spacing().icon_width;
+            if has_children {
+                let icon = if self.expanded.contains(&object) {
+                    "⏷"
+                } else {
+                    "⏵"
+                };
+                if ui
+                    .add_sized(
+                        [toggle_width, ui.available_height()],
+                        egui::Button::new(icon).frame(false),
+                    )
+                    .clicked()
+                {
+                    actions.push(Action::ToggleExpanded(object));
+                }
+            } else {
+                ui.add_space(toggle_width);
+            }
+
+            let mut visible = info.visible;
+            if ui
+                .toggle_value(&mut visible, "👁")
+                .on_hover_text("Visible")
+                .changed()
+            {
+                actions.push(Action::SetVisible(object, visible));
+            }
+            let mut locked = info.locked;
+            if ui
+                .toggle_value(&mut locked, "🔒")
+                .on_hover_text("Locked")
+                .changed()
+            {
+                actions.push(Action::SetLocked(object, locked));
+            }
+
+            if let Some((renaming, name)) = &mut self.renaming
+                && *renaming == object
+            {
+                let response = ui.add(egui::TextEdit::singleline(name).desired_width(120.0));
+                if !response.has_focus() && !response.lost_focus() {
+                    response.request_focus();
+                }
+                if ui.input(|input| input.key_pressed(egui::Key::Escape)) {
+                    self.renaming = None;
+                } else if response.lost_focus() {
+                    actions.push(Action::Rename(object, name.clone()));
+                }
+                return;
+            }
+
+            let mut label = egui::RichText::new(scene.object_label(object));
+            if !scene.is_visible(object) {
+                label = label.weak();
+            }
+            if scene.is_locked(object) {
+                label = label.italics();
+            }
+            let response = ui.add(
+                egui::Button::selectable(scene.selection.contains(&object), label)
+                    .sense(egui::Sense::click_and_drag()),
+            );
+            if object != SceneObject::Mesh {
+                response.dnd_set_drag_payload(object);
+            }
+            if response.double_clicked() {
+                actions.push(Action::BeginRename(object));
+            } else if response.clicked() {
+                actions.push(Action::Select(object, mode));
+            }
+            drop_target(ui, &response, scene, Some(object), actions);
+
+            response.context_menu(|ui| {
+                if ui.button("Rename").clicked() {
+                    actions.push(Action::BeginRename(object));
+                    ui.close();
+                }
+                if ui.button("Add Child").clicked() {
+                    actions.push(Action::AddChild(object));
+                    ui.close();
+                }
+                let is_instance = object != SceneObject::Mesh;
+                if ui
+                    .add_enabled(is_instance, egui::Button::new("Duplicate"))
+                    .clicked()
+                {
+                    actions.push(Action::Duplicate(object));
+                    ui.close();
+                }
+                if ui
+                    .add_enabled(is_instance, egui::Button::new("Delete"))
+                    .clicked()
+                {
+                    actions.push(Action::Delete(object));
+                    ui.close();
+                }
+            });
+        });
+    }
+
+    fn apply(
+        &mut self,
+        action: Action,
+        scene: &mut Scene,
+        history: &mut History,
+        device: &wgpu::Device,
+    ) {
+        match action {
+            Action::Select(object, mode) => {
+                scene.selection.select(Some(object), mode);
+                self.last_primary = scene.selection.primary();
+            }
+            Action::ToggleExpanded(object) => {
+                if !self.expanded.remove(&object) {
+                    self.expanded.insert(object);
+                }
+            }
+            Action::ToggleBatch(batch) => {
+                if !self.collapsed_batches.remove(&batch) {
+                    self.collapsed_batches.insert(batch);
+                }
+            }
+            Action::SetVisible(object, visible) => {
+                let label = if visible { "Show" } else { "Hide" };
+                execute_node_edit(history, scene, label, object, |info| {
+                    info.visible = visible;
+                });
+            }
+            Action::SetLocked(object, locked) => {
+                let label = if locked { "Lock" } else { "Unlock" };
+                execute_node_edit(history, scene, label, object, |info| {
+                    info.locked = locked;
+                });
+            }
+            Action::BeginRename(object) => {
+                self.renaming = Some((object, scene.object_label(object)));
+            }
+            Action::Rename(object, name) => {
+                self.renaming = None;
+                let name = name.trim();
+                let name = (!name.is_empty() && name != scene.object_label(object))
+                    .then(|| name.to_string());
+                if name.is_some() {
+                    execute_node_edit(history, scene, "Rename", object, |info| {
+                        info.name = name;
+                    });
+                }
+            }
+            Action::Reparent(object, parent) => {
+                if let Some(parent) = parent {
+                    self.expanded.insert(parent);
+                }
+                execute_node_edit(history, scene, "Reparent", object, |info| {
+                    info.parent = parent;
+                });
+            }
+            Action::Duplicate(object) => {
+                let objects = if scene.selection.contains(&object) {
+                    scene.selection.iter().copied().collect()
+                } else {
+                    vec![object]
+                };
+                let roots = objects
+                    .iter()
+                    .copied()
+                    .filter(|object| {
+                        !objects
+                            .iter()
+                            .any(|other| scene.hierarchy.descendants(*other).contains(object))
+                    })
+                    .collect::<Vec<_>>();
+                let mut group = CommandGroup::new("Duplicate");
+                let mut duplicates = Vec::new();
+                for object in roots {
+                    let info = scene.hierarchy.info(object);
+                    let info = NodeInfo {
+                        name: info.name.map(|name| format!("{name} Copy")),
+                        ..info
+                    };
+                    duplicates.extend(duplicate_subtree(scene, &mut group, object, info));
+                }
+                if !duplicates.is_empty() {
+                    history.push(group);
+                    scene
+                        .selection
+                        .select_all(duplicates, SelectionMode::Replace);
+                }
+            }
+            Action::AddChild(parent) => {
+                let Some(transform) = scene.object_transform(parent) else {
+                    return;
+                };
+                let position =
+                    transform.column(3).xyz() + nalgebra_glm::vec3(0.0, CHILD_OFFSET, 0.0);
+                let model = nalgebra_glm::translation(&position)
+                    * nalgebra_glm::scaling(&nalgebra_glm::vec3(
+                        CHILD_SCALE,
+                        CHILD_SCALE,
+                        CHILD_SCALE,
+                    ));
+                let batch = scene.cube_batch(device);
+                let mut command = AddInstances::new(
+                    batch,
+                    vec![InstanceData::new(
+                        &model,
+                        nalgebra_glm::vec4(0.9, 0.9, 0.9, 1.0),
+                    )],
+                )
+                .with_info(NodeInfo {
+                    parent: Some(parent),
+                    ..NodeInfo::default()
+                });
+                command.apply(scene);
+                scene
+                    .selection
+                    .select_all(command.objects(), SelectionMode::Replace);
+                self.expanded.insert(parent);
+                history.push(command);
+            }
+            Action::Delete(object) => {
+                let objects = if scene.selection.contains(&object) {
+                    scene.with_descendants(scene.selection.iter().copied())
+                } else {
+                    scene.with_descendants([object])
+                };
+                let command = RemoveInstances::new(scene, objects);
+                if !command.is_empty() {
+                    history.execute(command, scene);
+                }
+            }
+        }
+    }
+}
+
+fn drop_target(
+    ui: &egui::Ui,
+    response: &egui::Response,
+    scene: &Scene,
+    parent: Option<SceneObject>,
+    actions: &mut Vec<Action>,
+) {
+    let Some(dragged) = response.dnd_hover_payload::<SceneObject>() else {
+        return;
+    };
+    let valid = scene.hierarchy.can_reparent(*dragged, parent);
+    let color = if valid {
+        ui.visuals().selection.stroke.color
+    } else {
+        ui.visuals().error_fg_color
+    };
+    ui.painter().rect_stroke(
+        response.rect,
+        2.0,
+        egui::Stroke::new(1.0_f32, color),
+        egui::StrokeKind::Inside,
+    );
+    if valid && let Some(dragged) = response.dnd_release_payload::<SceneObject>() {
+        actions.push(Action::Reparent(*dragged, parent));
+    }
+}
+
+fn duplicate_subtree(
+    scene: &mut Scene,
+    group: &mut CommandGroup,
+    object: SceneObject,
+    info: NodeInfo,
+) -> Option<SceneObject> {
+    let SceneObject::Instance { batch, instance } = object else {
+        return None;
+    };
+    let data = *scene.batches.get(batch)?.get(instance)?;
+    let mut command = AddInstances::new(batch, vec![data]).with_info(info);
+    command.apply(scene);
+    let duplicate = command.objects().next();
+    group.push(command);
+    if let Some(duplicate) = duplicate {
+        for child in scene.hierarchy.children(object).to_vec() {
+            let info = NodeInfo {
+                parent: Some(duplicate),
+                ..scene.hierarchy.info(child)
+            };
+            duplicate_subtree(scene, group, child, info);
+        }
+    }
+    duplicate
+}
+
+fn execute_node_edit(
+    history: &mut History,
+    scene: &mut Scene,
+    label: &str,
+    object: SceneObject,
+    edit: impl FnOnce(&mut NodeInfo),
+) {
+    let command = NodeEdit::new(label, scene, object, edit);
+    if !command.is_empty() {
+        history.execute(command, scene);
+    }
+}
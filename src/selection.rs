@@ -0,0 +1,93 @@
+use crate::SceneObject;
+
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectionMode {
+    Replace,
+    Add,
+    Toggle,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Selection {
+    pub multi_select: bool,
+    pub hovered: Option<SceneObject>,
+    objects: Vec<SceneObject>,
+    lookup: HashSet<SceneObject>,
+}
+
+impl Selection {
+    pub fn mode(&self, modifiers: &egui::Modifiers) -> SelectionMode {
+        if modifiers.command {
+            SelectionMode::Toggle
+        } else if modifiers.shift {
+            SelectionMode::Add
+        } else if self.multi_select {
+            SelectionMode::Toggle
+        } else {
+            SelectionMode::Replace
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
+    pub fn contains(&self, object: &SceneObject) -> bool {
+        self.lookup.contains(object)
+    }
+
+    pub fn primary(&self) -> Option<SceneObject> {
+        self.objects.last().copied()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &SceneObject> {
+        self.objects.iter()
+    }
+
+    pub fn clear(&mut self) {
+        self.objects.clear();
+        self.lookup.clear();
+    }
+
+    pub fn retain(&mut self, mut keep: impl FnMut(&SceneObject) -> bool) {
+        if self.hovered.is_some_and(|hovered| !keep(&hovered)) {
+            self.hovered = None;
+        }
+        self.objects.retain(keep);
+        if self.objects.len() != self.lookup.len() {
+            self.lookup = self.objects.iter().copied().collect();
+        }
+    }
+
+    pub fn select(&mut self, object: Option<SceneObject>, mode: SelectionMode) {
+        self.select_all(object, mode);
+    }
+
+    pub fn select_all(
+        &mut self,
+        objects: impl IntoIterator<Item = SceneObject>,
+        mode: SelectionMode,
+    ) {
+        if mode == SelectionMode::Replace {
+            self.clear();
+        }
+        let mut deselected = HashSet::new();
+        for object in objects {
+            if self.lookup.insert(object) {
+                self.objects.push(object);
+            } else if mode == SelectionMode::Toggle {
+                self.lookup.remove(&object);
+                deselected.insert(object);
+            }
+        }
+        if !deselected.is_empty() {
+            self.objects.retain(|object| !deselected.contains(object));
+        }
+    }
+}
@@ -0,0 +1,71 @@
+struct ShadowUniform {
+    cascade_view_proj: array<mat4x4<f32>, 4>,
+    light_direction: vec4<f32>,
+    light_color: vec4<f32>,
+    params: vec4<f32>,
+    bias: vec4<f32>,
+};
+
+@group(1) @binding(0)
+var shadow_map: texture_depth_2d_array;
+
+@group(1) @binding(1)
+var shadow_sampler: sampler_comparison;
+
+@group(1) @binding(2)
+var<uniform> shadow: ShadowUniform;
+
+fn shadow_cascade(world_pos: vec3<f32>) -> i32 {
+    let cascade_count = i32(shadow.params.x);
+    for (var cascade = 0; cascade < cascade_count; cascade++) {
+        let clip = shadow.cascade_view_proj[cascade] * vec4<f32>(world_pos, 1.0);
+        let ndc = clip.xyz / clip.w;
+        if (all(abs(ndc.xy) < vec2<f32>(0.98)) && ndc.z >= 0.0 && ndc.z <= 1.0) {
+            return cascade;
+        }
+    }
+    return -1;
+}
+
+fn shadow_factor(world_pos: vec3<f32>, surface_normal: vec3<f32>) -> f32 {
+    let cascade = shadow_cascade(world_pos);
+    if (cascade < 0) {
+        return 1.0;
+    }
+
+    let to_light = -shadow.light_direction.xyz;
+    let normal = select(surface_normal, -surface_normal, dot(surface_normal, to_light) < 0.0);
+    let biased_pos = world_pos + normal * shadow.bias.y;
+    let clip = shadow.cascade_view_proj[cascade] * vec4<f32>(biased_pos, 1.0);
+    let ndc = clip.xyz / clip.w;
+    let uv = vec2<f32>(ndc.x * 0.5 + 0.5, 0.5 - ndc.y * 0.5);
+    let depth = ndc.z - shadow.bias.x;
+
+    let radius = i32(shadow.params.y);
+    let texel_size = shadow.params.z;
+    var total = 0.0;
+    var samples = 0.0;
+    for (var y = -radius; y <= radius; y++) {
+        for (var x = -radius; x <= radius; x++) {
+            let offset = vec2<f32>(f32(x), f32(y)) * texel_size;
+            total += textureSampleCompareLevel(shadow_map, shadow_sampler, uv + offset, cascade, depth);
+            samples += 1.0;
+        }
+    }
+    return total / samples;
+}
+
+fn shadow_debug_tint(color: vec3<f32>, world_pos: vec3<f32>) -> vec3<f32> {
+    if (shadow.params.w < 0.5) {
+        return color;
+    }
+    var tint = vec3<f32>(1.0);
+    switch shadow_cascade(world_pos) {
+        case 0: { tint = vec3<f32>(1.0, 0.25, 0.25); }
+        case 1: { tint = vec3<f32>(0.25, 1.0, 0.25); }
+        case 2: { tint = vec3<f32>(0.25, 0.25, 1.0); }
+        case 3: { tint = vec3<f32>(1.0, 1.0, 0.25); }
+        default: {}
+    }
+    return mix(color, tint, 0.5);
+}
@@ -0,0 +1,23 @@
+struct Caster {
+    light_mvp: mat4x4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> caster: Caster;
+
+@vertex
+fn vertex_main(@location(0) position: vec4<f32>) -> @builtin(position) vec4<f32> {
+    return caster.light_mvp * position;
+}
+
+@vertex
+fn vertex_instanced(
+    @location(0) position: vec4<f32>,
+    @location(2) model_0: vec4<f32>,
+    @location(3) model_1: vec4<f32>,
+    @location(4) model_2: vec4<f32>,
+    @location(5) model_3: vec4<f32>,
+) -> @builtin(position) vec4<f32> {
+    let model = mat4x4<f32>(model_0, model_1, model_2, model_3);
+    return caster.light_mvp * model * position;
+}
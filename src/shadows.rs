@@ -0,0 +1,616 @@
+use crate::perf;
+use crate::resources::{ResourceTracker, Tracked};
+use crate::uniform_ring::{UniformRing, UniformSlot};
+
+const OWNER: &str = "Shadows";
+
+pub const MAX_CASCADES: usize = 4;
+pub const SHADOW_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+pub const SHADOW_SHADER_SOURCE: &str = include_str!("shadow.wgsl");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum ShadowFilter {
+    Hard,
+    Pcf3x3,
+    Pcf5x5,
+}
+
+impl ShadowFilter {
+    pub const ALL: [Self; 3] = [Self::Hard, Self::Pcf3x3, Self::Pcf5x5];
+
+    pub fn kernel_radius(self) -> u32 {
+        match self {
+            Self::Hard => 0,
+            Self::Pcf3x3 => 1,
+            Self::Pcf5x5 => 2,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Hard => "Hard",
+            Self::Pcf3x3 => "PCF 3x3",
+            Self::Pcf5x5 => "PCF 5x5",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ShadowSettings {
+    pub cascade_count: u32,
+    pub resolution: u32,
+    pub split_lambda: f32,
+    pub filter: ShadowFilter,
+    pub max_distance: f32,
+    pub depth_bias: f32,
+    pub normal_bias: f32,
+    pub debug_cascades: bool,
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        Self {
+            cascade_count: 4,
+            resolution: 2048,
+            split_lambda: 0.75,
+            filter: ShadowFilter::Pcf3x3,
+            max_distance: 50.0,
+            depth_bias: 0.0005,
+            normal_bias: 0.02,
+            debug_cascades: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct DirectionalLight {
+    pub direction: nalgebra_glm::Vec3,
+    pub color: nalgebra_glm::Vec3,
+    pub intensity: f32,
+}
+
+impl Default for DirectionalLight {
+    fn default() -> Self {
+        Self {
+            direction: nalgebra_glm::normalize(&nalgebra_glm::vec3(0.0, -0.5, 1.0)),
+            color: nalgebra_glm::vec3(1.0, 0.95, 0.8),
+            intensity: 3.0,
+        }
+    }
+}
+
+impl DirectionalLight {
+    pub fn sun_direction(&self) -> nalgebra_glm::Vec3 {
+        -nalgebra_glm::normalize(&self.direction)
+    }
+}
+
+pub struct ShadowView {
+    pub inverse_view_projection: nalgebra_glm::Mat4,
+    pub near: f32,
+    pub far: f32,
+}
+
+impl ShadowView {
+    pub fn new(view_projection: &nalgebra_glm::Mat4, near: f32, far: f32) -> Self {
+        Self {
+            inverse_view_projection: nalgebra_glm::inverse(view_projection),
+            near,
+            far,
+        }
+    }
+
+    pub fn frustum_corners(&self) -> [nalgebra_glm::Vec3; 8] {
+        let mut corners = [nalgebra_glm::Vec3::zeros(); 8];
+        let mut index = 0;
+        for z in [0.0, 1.0] {
+            for y in [-1.0, 1.0] {
+                for x in [-1.0, 1.0] {
+                    let corner = self.inverse_view_projection * nalgebra_glm::vec4(x, y, z, 1.0);
+                    corners[index] = corner.xyz() / corner.w;
+                    index += 1;
+                }
+            }
+        }
+        corners
+    }
+
+    pub fn slice_corners(&self, split_near: f32, split_far: f32) -> [nalgebra_glm::Vec3; 8] {
+        let corners = self.frustum_corners();
+        let depth_range = (self.far - self.near).max(f32::EPSILON);
+        let near_t = (split_near - self.near) / depth_range;
+        let far_t = (split_far - self.near) / depth_range;
+        let mut slice = [nalgebra_glm::Vec3::zeros(); 8];
+        for corner_index in 0..4 {
+            let near_corner = corners[corner_index];
+            let far_corner = corners[corner_index + 4];
+            slice[corner_index] = nalgebra_glm::lerp(&near_corner, &far_corner, near_t);
+            slice[corner_index + 4] = nalgebra_glm::lerp(&near_corner, &far_corner, far_t);
+        }
+        slice
+    }
+}
+
+pub fn cascade_splits(near: f32, far: f32, cascade_count: u32, lambda: f32) -> Vec<f32> {
+    let cascade_count = cascade_count.max(1);
+    (0..=cascade_count)
+        .map(|index| {
+            let fraction = index as f32 / cascade_count as f32;
+            let logarithmic = near * (far / near).powf(fraction);
+            let uniform = near + (far - near) * fraction;
+            lambda * logarithmic + (1.0 - lambda) * uniform
+        })
+        .collect()
+}
+
+pub fn fit_cascade(
+    corners: &[nalgebra_glm::Vec3],
+    light_direction: &nalgebra_glm::Vec3,
+    resolution: u32,
+    caster_extension: f32,
+) -> nalgebra_glm::Mat4 {
+    let center = corners
+        .iter()
+        .fold(nalgebra_glm::Vec3::zeros(), |sum, corner| sum + corner)
+        / corners.len().max(1) as f32;
+    let radius = corners
+        .iter()
+        .map(|corner| nalgebra_glm::distance(corner, &center))
+        .fold(0.0_f32, f32::max);
+    let radius = (radius * 16.0).ceil() / 16.0;
+
+    let direction = nalgebra_glm::normalize(light_direction);
+    let up = if direction.y.abs() > 0.99 {
+        nalgebra_glm::Vec3::z()
+    } else {
+        nalgebra_glm::Vec3::y()
+    };
+    let view = nalgebra_glm::look_at_rh(&center, &(center + direction), &up);
+    let mut projection = nalgebra_glm::ortho_rh_zo(
+        -radius,
+        radius,
+        -radius,
+        radius,
+        -(radius + caster_extension),
+        radius,
+    );
+
+    let half_resolution = resolution as f32 * 0.5;
+    let origin = projection * view * nalgebra_glm::vec4(0.0, 0.0, 0.0, 1.0);
+    let texel_origin = origin.xy() * half_resolution;
+    let snapped = nalgebra_glm::round(&texel_origin);
+    let offset = (snapped - texel_origin) / half_resolution;
+    projection[(0, 3)] += offset.x;
+    projection[(1, 3)] += offset.y;
+
+    projection * view
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ShadowUniform {
+    cascade_view_proj: [[[f32; 4]; 4]; MAX_CASCADES],
+    light_direction: [f32; 4],
+    light_color: [f32; 4],
+    params: [f32; 4],
+    bias: [f32; 4],
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct CasterUniform {
+    light_mvp: [[f32; 4]; 4],
+}
+
+pub struct ShadowCaster<'a> {
+    pub vertex_buffer: &'a wgpu::Buffer,
+    pub index_buffer: &'a wgpu::Buffer,
+    pub index_count: u32,
+    pub model: nalgebra_glm::Mat4,
+    pub instances: Option<(&'a wgpu::Buffer, u32)>,
+}
+
+pub struct CascadedShadowMap {
+    settings: ShadowSettings,
+    _map_texture: Tracked<wgpu::Texture>,
+    cascade_views: Vec<wgpu::TextureView>,
+    map_view: wgpu::TextureView,
+    cascade_view_projections: [nalgebra_glm::Mat4; MAX_CASCADES],
+    uniform_buffer: Tracked<wgpu::Buffer>,
+    sampler: Tracked<wgpu::Sampler>,
+    pub bind_group_layout: wgpu::BindGroupLayout,
+    pub bind_group: Tracked<wgpu::BindGroup>,
+    casters: UniformRing<CasterUniform>,
+    caster_slots: Vec<UniformSlot>,
+    pipeline: Tracked<wgpu::RenderPipeline>,
+    instanced_pipeline: Tracked<wgpu::RenderPipeline>,
+}
+
+impl CascadedShadowMap {
+    pub fn new(device: &wgpu::Device, settings: ShadowSettings) -> Self {
+        let settings = Self::sanitize(settings);
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Shadow Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Depth,
+                        view_dimension: wgpu::TextureViewDimension::D2Array,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let uniform_buffer =
+            ResourceTracker::new(device, OWNER).create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Shadow Uniform Buffer"),
+                size: std::mem::size_of::<ShadowUniform>() as u64,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+
+        let sampler =
+            ResourceTracker::new(device, OWNER).create_sampler(&wgpu::SamplerDescriptor {
+                label: Some("Shadow Sampler"),
+                address_mode_u: wgpu::AddressMode::ClampToEdge,
+                address_mode_v: wgpu::AddressMode::ClampToEdge,
+                address_mode_w: wgpu::AddressMode::ClampToEdge,
+                mag_filter: wgpu::FilterMode::Linear,
+                min_filter: wgpu::FilterMode::Linear,
+                compare: Some(wgpu::CompareFunction::LessEqual),
+                ..Default::default()
+            });
+
+        let (map_texture, cascade_views, map_view, bind_group) = Self::create_targets(
+            device,
+            &settings,
+            &bind_group_layout,
+            &sampler,
+            &uniform_buffer,
+        );
+
+        let casters = UniformRing::new(device, "Shadow Caster Ring", wgpu::ShaderStages::VERTEX);
+
+        let pipeline = Self::create_pipeline(device, &casters.bind_group_layout, false);
+        let instanced_pipeline = Self::create_pipeline(device, &casters.bind_group_layout, true);
+
+        Self {
+            settings,
+            _map_texture: map_texture,
+            cascade_views,
+            map_view,
+            cascade_view_projections: [nalgebra_glm::Mat4::identity(); MAX_CASCADES],
+            uniform_buffer,
+            sampler,
+            bind_group_layout,
+            bind_group,
+            casters,
+            caster_slots: Vec::new(),
+            pipeline,
+            instanced_pipeline,
+        }
+    }
+
+    pub fn map_view(&self) -> &wgpu::TextureView {
+        &self.map_view
+    }
+
+    pub fn settings(&self) -> ShadowSettings {
+        self.settings
+    }
+
+    pub fn set_settings(&mut self, device: &wgpu::Device, settings: ShadowSettings) {
+        let settings = Self::sanitize(settings);
+        let recreate = settings.resolution != self.settings.resolution
+            || settings.cascade_count != self.settings.cascade_count;
+        self.settings = settings;
+        if recreate {
+            let (map_texture, cascade_views, map_view, bind_group) = Self::create_targets(
+                device,
+                &self.settings,
+                &self.bind_group_layout,
+                &self.sampler,
+                &self.uniform_buffer,
+            );
+            self._map_texture = map_texture;
+            self.cascade_views = cascade_views;
+            self.map_view = map_view;
+            self.bind_group = bind_group;
+        }
+    }
+
+    pub fn update(&mut self, queue: &wgpu::Queue, light: &DirectionalLight, views: &[ShadowView]) {
+        let settings = self.settings;
+        let near = views
+            .iter()
+            .map(|view| view.near)
+            .fold(f32::INFINITY, f32::min);
+        let far = views
+            .iter()
+            .map(|view| view.far)
+            .fold(0.0_f32, f32::max)
+            .min(settings.max_distance)
+            .max(near + f32::EPSILON);
+        let splits = cascade_splits(near, far, settings.cascade_count, settings.split_lambda);
+
+        for cascade in 0..settings.cascade_count as usize {
+            let corners: Vec<nalgebra_glm::Vec3> = views
+                .iter()
+                .flat_map(|view| view.slice_corners(splits[cascade], splits[cascade + 1]))
+                .collect();
+            self.cascade_view_projections[cascade] = fit_cascade(
+                &corners,
+                &light.direction,
+                settings.resolution,
+                settings.max_distance,
+            );
+        }
+
+        let direction = nalgebra_glm::normalize(&light.direction);
+        let uniform = ShadowUniform {
+            cascade_view_proj: self.cascade_view_projections.map(Into::into),
+            light_direction: [direction.x, direction.y, direction.z, 0.0],
+            light_color: [light.color.x, light.color.y, light.color.z, light.intensity],
+            params: [
+                settings.cascade_count as f32,
+                settings.filter.kernel_radius() as f32,
+                1.0 / settings.resolution as f32,
+                if settings.debug_cascades { 1.0 } else { 0.0 },
+            ],
+            bias: [settings.depth_bias, settings.normal_bias, 0.0, 0.0],
+        };
+        perf::write_buffer(
+            queue,
+            &self.uniform_buffer,
+            0,
+            bytemuck::cast_slice(&[uniform]),
+        );
+    }
+
+    pub fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        casters: &[ShadowCaster],
+    ) {
+        let cascade_count = self.settings.cascade_count as usize;
+        self.casters.begin_frame();
+        self.caster_slots.clear();
+        for cascade in 0..cascade_count {
+            for caster in casters {
+                let slot = self.casters.push(&CasterUniform {
+                    light_mvp: (self.cascade_view_projections[cascade] * caster.model).into(),
+                });
+                self.caster_slots.push(slot);
+            }
+        }
+        self.casters.upload(device, queue);
+    }
+
+    pub fn render(&self, encoder: &mut wgpu::CommandEncoder, casters: &[ShadowCaster]) {
+        let cascade_count = self.settings.cascade_count as usize;
+        for (cascade, cascade_view) in self.cascade_views.iter().enumerate().take(cascade_count) {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Shadow Cascade Pass"),
+                color_attachments: &[],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: cascade_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }),
+                timestamp_writes: None,
+                occlusion_query_set: None,
+                multiview_mask: None,
+            });
+
+            for (caster_index, caster) in casters.iter().enumerate() {
+                let slot = self.caster_slots[cascade * casters.len() + caster_index];
+                render_pass.set_bind_group(
+                    0,
+                    self.casters.bind_group(),
+                    &[self.casters.offset(slot)],
+                );
+                render_pass.set_vertex_buffer(0, caster.vertex_buffer.slice(..));
+                render_pass
+                    .set_index_buffer(caster.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                match caster.instances {
+                    Some((instance_buffer, instance_count)) => {
+                        render_pass.set_pipeline(&self.instanced_pipeline);
+                        render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
+                        render_pass.draw_indexed(0..caster.index_count, 0, 0..instance_count);
+                        perf::record_draw(
+                            u64::from(caster.index_count / 3) * u64::from(instance_count),
+                        );
+                    }
+                    None => {
+                        render_pass.set_pipeline(&self.pipeline);
+                        render_pass.draw_indexed(0..caster.index_count, 0, 0..1);
+                        perf::record_draw(u64::from(caster.index_count / 3));
+                    }
+                }
+            }
+        }
+    }
+
+    fn sanitize(settings: ShadowSettings) -> ShadowSettings {
+        ShadowSettings {
+            cascade_count: settings.cascade_count.clamp(1, MAX_CASCADES as u32),
+            resolution: settings.resolution.clamp(256, 8192),
+            split_lambda: settings.split_lambda.clamp(0.0, 1.0),
+            max_distance: settings.max_distance.max(1.0),
+            ..settings
+        }
+    }
+
+    fn create_targets(
+        device: &wgpu::Device,
+        settings: &ShadowSettings,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        sampler: &wgpu::Sampler,
+        uniform_buffer: &wgpu::Buffer,
+    ) -> (
+        Tracked<wgpu::Texture>,
+        Vec<wgpu::TextureView>,
+        wgpu::TextureView,
+        Tracked<wgpu::BindGroup>,
+    ) {
+        let layer_count = settings.cascade_count.max(2);
+        let texture =
+            ResourceTracker::new(device, OWNER).create_texture(&wgpu::TextureDescriptor {
+                label: Some("Shadow Map Texture"),
+                size: wgpu::Extent3d {
+                    width: settings.resolution,
+                    height: settings.resolution,
+                    depth_or_array_layers: layer_count,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: SHADOW_FORMAT,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            });
+
+        let cascade_views = (0..settings.cascade_count)
+            .map(|layer| {
+                texture.create_view(&wgpu::TextureViewDescriptor {
+                    label: Some("Shadow Cascade View"),
+                    format: Some(SHADOW_FORMAT),
+                    dimension: Some(wgpu::TextureViewDimension::D2),
+                    aspect: wgpu::TextureAspect::DepthOnly,
+                    base_mip_level: 0,
+                    mip_level_count: None,
+                    base_array_layer: layer,
+                    array_layer_count: Some(1),
+                    usage: None,
+                })
+            })
+            .collect();
+
+        let array_view = texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("Shadow Map Array View"),
+            format: Some(SHADOW_FORMAT),
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            aspect: wgpu::TextureAspect::DepthOnly,
+            base_mip_level: 0,
+            mip_level_count: None,
+            base_array_layer: 0,
+            array_layer_count: Some(layer_count),
+            usage: None,
+        });
+
+        let bind_group =
+            ResourceTracker::new(device, OWNER).create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Shadow Bind Group"),
+                layout: bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&array_view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(sampler),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: uniform_buffer.as_entire_binding(),
+                    },
+                ],
+            });
+
+        (texture, cascade_views, array_view, bind_group)
+    }
+
+    fn create_pipeline(
+        device: &wgpu::Device,
+        caster_bind_group_layout: &wgpu::BindGroupLayout,
+        instanced: bool,
+    ) -> Tracked<wgpu::RenderPipeline> {
+        let shader = device.create_shader_module(wgpu::include_wgsl!("shadow_depth.wgsl"));
+        let vertex_attributes = crate::Vertex::vertex_attributes();
+        let instance_attributes = crate::instancing::InstanceData::vertex_attributes();
+        let mut buffers = vec![crate::Vertex::description(&vertex_attributes)];
+        if instanced {
+            buffers.push(crate::instancing::InstanceData::description(
+                &instance_attributes,
+            ));
+        }
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Shadow Pipeline Layout"),
+            bind_group_layouts: &[Some(caster_bind_group_layout)],
+            immediate_size: 0,
+        });
+
+        ResourceTracker::new(device, OWNER).create_render_pipeline(
+            &wgpu::RenderPipelineDescriptor {
+                label: Some(if instanced {
+                    "Instanced Shadow Pipeline"
+                } else {
+                    "Shadow Pipeline"
+                }),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: Some(if instanced {
+                        "vertex_instanced"
+                    } else {
+                        "vertex_main"
+                    }),
+                    buffers: &buffers,
+                    compilation_options: Default::default(),
+                },
+                fragment: None,
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    strip_index_format: None,
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: None,
+                    unclipped_depth: false,
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    conservative: false,
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: SHADOW_FORMAT,
+                    depth_write_enabled: Some(true),
+                    depth_compare: Some(wgpu::CompareFunction::LessEqual),
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState {
+                        constant: 2,
+                        slope_scale: 2.0,
+                        clamp: 0.0,
+                    },
+                }),
+                multisample: wgpu::MultisampleState::default(),
+                multiview_mask: None,
+                cache: None,
+            },
+        )
+    }
+}
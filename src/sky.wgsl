@@ -1,6 +1,7 @@
 struct Uniform {
     proj_inv: array<mat4x4<f32>, 2>,
     view: array<mat4x4<f32>, 2>,
+    sun_direction: vec4<f32>,
 };
 
 @group(0) @binding(0)
@@ -56,7 +57,7 @@ fn fs_sky(in: VertexOutput) -> @location(0) vec4<f32> {
 
     sky_color = sky_color * 1.3;
 
-    let sun_direction = normalize(vec3<f32>(0.0, 0.5, -1.0));
+    let sun_direction = normalize(u.sun_direction.xyz);
     let sun_angle = acos(dot(dir, sun_direction));
     let sun_disk = 1.0 - smoothstep(0.0, 0.02, sun_angle);
     let sun_color = vec3<f32>(1.0, 0.95, 0.8);
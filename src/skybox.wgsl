@@ -0,0 +1,5 @@
+@fragment
+fn fs_environment(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSampleLevel(environment_map, environment_sampler, normalize(in.world_dir), 0.0).rgb;
+    return vec4<f32>(color * environment.sky_intensity, 1.0);
+}
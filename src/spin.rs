@@ -0,0 +1,84 @@
+use crate::inspector::{Component, property};
+use crate::{Scene, SceneObject};
+
+const DEFAULT_SPIN_DEGREES: f32 = 45.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Spin {
+    pub axis: nalgebra_glm::Vec3,
+    pub degrees_per_second: f32,
+}
+
+impl Default for Spin {
+    fn default() -> Self {
+        Self {
+            axis: nalgebra_glm::Vec3::y(),
+            degrees_per_second: DEFAULT_SPIN_DEGREES,
+        }
+    }
+}
+
+pub fn update(scene: &mut Scene, delta_time: f32) {
+    let spinning = scene
+        .components
+        .iter::<Spin>()
+        .map(|(object, spin)| (object, *spin))
+        .collect::<Vec<_>>();
+    for (object, spin) in spinning {
+        if scene.is_locked(object) || spin.degrees_per_second == 0.0 {
+            continue;
+        }
+        let Some(transform) = scene.object_transform(object) else {
+            continue;
+        };
+        let transform = nalgebra_glm::rotate(
+            &transform,
+            spin.degrees_per_second.to_radians() * delta_time,
+            &spin.axis,
+        );
+        scene.set_object_transform(object, &transform);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpinComponent;
+
+impl Component for SpinComponent {
+    type Value = Option<Spin>;
+
+    fn name(&self) -> &str {
+        "Spin"
+    }
+
+    fn get(&self, scene: &Scene, object: Option<SceneObject>) -> Option<Option<Spin>> {
+        match object? {
+            SceneObject::Mesh => None,
+            object @ SceneObject::Instance { .. } => {
+                Some(scene.components.get::<Spin>(object).copied())
+            }
+        }
+    }
+
+    fn set(scene: &mut Scene, object: Option<SceneObject>, value: &Option<Spin>) {
+        if let Some(object) = object {
+            scene.components.set(object, *value);
+        }
+    }
+
+    fn inspect(&mut self, ui: &mut egui::Ui, value: &mut Option<Spin>) -> bool {
+        let mut enabled = value.is_some();
+        let mut changed = property(ui, "Enabled", &mut enabled);
+        if changed {
+            *value = enabled.then(Spin::default);
+        }
+        if let Some(spin) = value {
+            let mut axis = spin.axis;
+            if property(ui, "Axis", &mut axis) && nalgebra_glm::length(&axis) > f32::EPSILON {
+                spin.axis = nalgebra_glm::normalize(&axis);
+                changed = true;
+            }
+            changed |= property(ui, "Degrees per Second", &mut spin.degrees_per_second);
+        }
+        changed
+    }
+}
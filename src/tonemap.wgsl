@@ -0,0 +1,131 @@
+struct TonemapUniform {
+    exposure: f32,
+    min_log_luminance: f32,
+    max_log_luminance: f32,
+    adaptation: f32,
+    tonemapper: u32,
+    auto_exposure: u32,
+    _padding0: u32,
+    _padding1: u32,
+};
+
+@group(0) @binding(0)
+var source_texture: texture_2d<f32>;
+
+@group(0) @binding(1)
+var source_sampler: sampler;
+
+@group(0) @binding(2)
+var<uniform> tonemap: TonemapUniform;
+
+@group(0) @binding(3)
+var adapted_luminance: texture_2d<f32>;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn vs_fullscreen(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    var out: VertexOutput;
+    out.position = vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
+
+fn luminance(color: vec3<f32>) -> f32 {
+    return dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+}
+
+@fragment
+fn fs_luminance(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSampleLevel(source_texture, source_sampler, in.uv, 0.0).rgb;
+    let log_luminance = log2(max(luminance(color), 0.0001));
+    return vec4<f32>(clamp(log_luminance, tonemap.min_log_luminance, tonemap.max_log_luminance), 0.0, 0.0, 1.0);
+}
+
+@fragment
+fn fs_downsample(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSampleLevel(source_texture, source_sampler, in.uv, 0.0);
+}
+
+@fragment
+fn fs_adapt(in: VertexOutput) -> @location(0) vec4<f32> {
+    let current = textureLoad(source_texture, vec2<i32>(0, 0), 0).r;
+    let previous = textureLoad(adapted_luminance, vec2<i32>(0, 0), 0).r;
+    return vec4<f32>(mix(previous, current, tonemap.adaptation), 0.0, 0.0, 1.0);
+}
+
+fn exposure() -> f32 {
+    if (tonemap.auto_exposure != 0u) {
+        let average = exp2(textureLoad(adapted_luminance, vec2<i32>(0, 0), 0).r);
+        return tonemap.exposure * 0.18 / max(average, 0.0001);
+    }
+    return tonemap.exposure;
+}
+
+fn tonemap_aces(color: vec3<f32>) -> vec3<f32> {
+    let input_matrix = mat3x3<f32>(
+        vec3<f32>(0.59719, 0.07600, 0.02840),
+        vec3<f32>(0.35458, 0.90834, 0.13383),
+        vec3<f32>(0.04823, 0.01566, 0.83777),
+    );
+    let output_matrix = mat3x3<f32>(
+        vec3<f32>(1.60475, -0.10208, -0.00327),
+        vec3<f32>(-0.53108, 1.10813, -0.07276),
+        vec3<f32>(-0.07367, -0.00605, 1.07602),
+    );
+    let v = input_matrix * color;
+    let a = v * (v + 0.0245786) - 0.000090537;
+    let b = v * (0.983729 * v + 0.4329510) + 0.238081;
+    return clamp(output_matrix * (a / b), vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
+fn agx_contrast(x: vec3<f32>) -> vec3<f32> {
+    let x2 = x * x;
+    let x4 = x2 * x2;
+    return 15.5 * x4 * x2 - 40.14 * x4 * x + 31.96 * x4 - 6.868 * x2 * x + 0.4298 * x2 + 0.1191 * x - 0.00232;
+}
+
+fn tonemap_agx(color: vec3<f32>) -> vec3<f32> {
+    let inset = mat3x3<f32>(
+        0.842479062253094, 0.0423282422610123, 0.0423756549057051,
+        0.0784335999999992, 0.878468636469772, 0.0784336,
+        0.0792237451477643, 0.0791661274605434, 0.879142973793104,
+    );
+    let outset = mat3x3<f32>(
+        1.19687900512017, -0.0528968517574562, -0.0529716355144438,
+        -0.0980208811401368, 1.15190312990417, -0.0980434501171241,
+        -0.0990297440797205, -0.0989611768448433, 1.15107367264116,
+    );
+    let min_ev = -12.47393;
+    let max_ev = 4.026069;
+    let encoded = clamp(log2(max(inset * color, vec3<f32>(1e-10))), vec3<f32>(min_ev), vec3<f32>(max_ev));
+    let normalized = (encoded - min_ev) / (max_ev - min_ev);
+    let display = outset * agx_contrast(normalized);
+    return pow(clamp(display, vec3<f32>(0.0), vec3<f32>(1.0)), vec3<f32>(2.2));
+}
+
+fn tonemap_reinhard(color: vec3<f32>) -> vec3<f32> {
+    return color / (1.0 + color);
+}
+
+fn linear_to_srgb(color: vec3<f32>) -> vec3<f32> {
+    let high = 1.055 * pow(color, vec3<f32>(1.0 / 2.4)) - 0.055;
+    let low = color * 12.92;
+    return select(high, low, color <= vec3<f32>(0.0031308));
+}
+
+@fragment
+fn fs_tonemap(in: VertexOutput) -> @location(0) vec4<f32> {
+    let hdr = textureSampleLevel(source_texture, source_sampler, in.uv, 0.0).rgb * exposure();
+    var color: vec3<f32>;
+    switch tonemap.tonemapper {
+        case 0u: { color = tonemap_aces(hdr); }
+        case 1u: { color = tonemap_agx(hdr); }
+        default: { color = tonemap_reinhard(hdr); }
+    }
+    return vec4<f32>(linear_to_srgb(color), 1.0);
+}
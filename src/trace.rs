@@ -0,0 +1,231 @@
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use web_time::Instant;
+
+pub const TRACE_PATH: &str = "trace.json";
+pub const MAX_TRACE_EVENTS: usize = 1 << 20;
+
+static CAPTURING: AtomicBool = AtomicBool::new(false);
+static NEXT_THREAD: AtomicU64 = AtomicU64::new(1);
+
+thread_local! {
+    static THREAD: Cell<Option<u64>> = const { Cell::new(None) };
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceEvent {
+    pub name: &'static str,
+    pub thread: u64,
+    pub start: f64,
+    pub duration: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TraceCapture {
+    pub events: Vec<TraceEvent>,
+    pub threads: Vec<(u64, String)>,
+    pub dropped: usize,
+}
+
+impl TraceCapture {
+    pub fn to_chrome_json(&self) -> String {
+        let thread_names = self.threads.iter().map(|(thread, name)| {
+            serde_json::json!({
+                "name": "thread_name",
+                "ph": "M",
+                "pid": 1,
+                "tid": thread,
+                "args": { "name": name },
+            })
+        });
+        let events = self.events.iter().map(|event| match event.duration {
+            Some(duration) => serde_json::json!({
+                "name": event.name,
+                "cat": "cpu",
+                "ph": "X",
+                "pid": 1,
+                "tid": event.thread,
+                "ts": event.start,
+                "dur": duration,
+            }),
+            None => serde_json::json!({
+                "name": event.name,
+                "cat": "frame",
+                "ph": "i",
+                "s": "g",
+                "pid": 1,
+                "tid": event.thread,
+                "ts": event.start,
+            }),
+        });
+        serde_json::json!({
+            "traceEvents": thread_names.chain(events).collect::<Vec<_>>(),
+            "displayTimeUnit": "ms",
+        })
+        .to_string()
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn write_chrome_trace(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        std::fs::write(path, self.to_chrome_json())?;
+        log::info!("Wrote {} trace events to {path}", self.events.len());
+        if self.dropped > 0 {
+            log::warn!("Trace dropped {} events past capacity", self.dropped);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct TraceState {
+    capture: TraceCapture,
+    epoch: Option<Instant>,
+}
+
+fn trace_state() -> &'static Mutex<TraceState> {
+    static STATE: OnceLock<Mutex<TraceState>> = OnceLock::new();
+    STATE.get_or_init(Mutex::default)
+}
+
+fn thread_id() -> u64 {
+    THREAD.with(|thread| {
+        if let Some(id) = thread.get() {
+            return id;
+        }
+        let id = NEXT_THREAD.fetch_add(1, Ordering::Relaxed);
+        thread.set(Some(id));
+        let name = std::thread::current()
+            .name()
+            .map_or_else(|| format!("Thread {id}"), str::to_string);
+        if let Ok(mut state) = trace_state().lock() {
+            state.capture.threads.push((id, name));
+        }
+        id
+    })
+}
+
+fn record(name: &'static str, start: Instant, duration: Option<f64>) {
+    let thread = thread_id();
+    let Ok(mut state) = trace_state().lock() else {
+        return;
+    };
+    let Some(epoch) = state.epoch else {
+        return;
+    };
+    if state.capture.events.len() >= MAX_TRACE_EVENTS {
+        state.capture.dropped += 1;
+        return;
+    }
+    let start = start.saturating_duration_since(epoch).as_secs_f64() * 1_000_000.0;
+    state.capture.events.push(TraceEvent {
+        name,
+        thread,
+        start,
+        duration,
+    });
+}
+
+pub fn init() {
+    #[cfg(feature = "tracy")]
+    let _ = tracy_client::Client::start();
+}
+
+pub fn log_status() {
+    #[cfg(feature = "tracy")]
+    log::info!("Tracy profiler enabled, waiting for a client on localhost");
+    if is_capturing() {
+        log::info!("Trace capture running, writing {TRACE_PATH} on exit");
+    }
+}
+
+pub fn is_capturing() -> bool {
+    CAPTURING.load(Ordering::Relaxed)
+}
+
+pub fn start_capture() {
+    if let Ok(mut state) = trace_state().lock() {
+        state.capture.events.clear();
+        state.capture.dropped = 0;
+        state.epoch = Some(Instant::now());
+    }
+    CAPTURING.store(true, Ordering::Release);
+    log::info!("Trace capture started");
+}
+
+pub fn stop_capture() -> TraceCapture {
+    CAPTURING.store(false, Ordering::Release);
+    let Ok(mut state) = trace_state().lock() else {
+        return TraceCapture::default();
+    };
+    state.epoch = None;
+    TraceCapture {
+        events: std::mem::take(&mut state.capture.events),
+        threads: state.capture.threads.clone(),
+        dropped: std::mem::take(&mut state.capture.dropped),
+    }
+}
+
+pub fn capture_from_args(arguments: impl IntoIterator<Item = String>) -> bool {
+    let requested = arguments.into_iter().any(|argument| argument == "--trace");
+    if requested {
+        start_capture();
+    }
+    requested
+}
+
+pub fn finish_capture(path: &str) {
+    if !is_capturing() {
+        return;
+    }
+    let capture = stop_capture();
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Err(error) = capture.write_chrome_trace(path) {
+        log::error!("Failed to write trace {path}: {error}");
+    }
+    #[cfg(target_arch = "wasm32")]
+    log::info!("Trace for {path}: {}", capture.to_chrome_json());
+}
+
+pub fn frame_mark() {
+    if is_capturing() {
+        record("Frame", Instant::now(), None);
+    }
+    #[cfg(feature = "tracy")]
+    if let Some(client) = tracy_client::Client::running() {
+        client.frame_mark();
+    }
+}
+
+pub struct Scope {
+    name: &'static str,
+    start: Option<Instant>,
+    #[cfg(feature = "tracy")]
+    _span: Option<tracy_client::Span>,
+}
+
+#[track_caller]
+pub fn scope(name: &'static str) -> Scope {
+    #[cfg(feature = "tracy")]
+    let _span = tracy_client::Client::running().map(|client| {
+        let location = std::panic::Location::caller();
+        client.span_alloc(Some(name), "", location.file(), location.line(), 0)
+    });
+    Scope {
+        name,
+        start: is_capturing().then(Instant::now),
+        #[cfg(feature = "tracy")]
+        _span,
+    }
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        if let Some(start) = self.start
+            && is_capturing()
+        {
+            let duration = start.elapsed().as_secs_f64() * 1_000_000.0;
+            record(self.name, start, Some(duration));
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use crate::perf;
+use crate::resources::{ResourceTracker, Tracked};
+
+const FRAMES_IN_FLIGHT: u64 = 3;
+const INITIAL_CAPACITY: u64 = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UniformSlot(u32);
+
+pub struct UniformRing<T> {
+    label: &'static str,
+    pub bind_group_layout: wgpu::BindGroupLayout,
+    buffer: Tracked<wgpu::Buffer>,
+    bind_group: Tracked<wgpu::BindGroup>,
+    stride: u64,
+    capacity: u64,
+    frame: u64,
+    staging: Vec<u8>,
+    len: u64,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: bytemuck::Pod> UniformRing<T> {
+    pub fn new(device: &wgpu::Device, label: &'static str, visibility: wgpu::ShaderStages) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: true,
+                    min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<T>() as u64),
+                },
+                count: None,
+            }],
+            label: Some(label),
+        });
+
+        let alignment = device.limits().min_uniform_buffer_offset_alignment as u64;
+        let stride = (std::mem::size_of::<T>() as u64).div_ceil(alignment) * alignment;
+        let (buffer, bind_group) =
+            Self::create_buffer(device, label, &bind_group_layout, INITIAL_CAPACITY, stride);
+
+        Self {
+            label,
+            bind_group_layout,
+            buffer,
+            bind_group,
+            stride,
+            capacity: INITIAL_CAPACITY,
+            frame: 0,
+            staging: Vec::new(),
+            len: 0,
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    pub fn bind_group(&self) -> &wgpu::BindGroup {
+        &self.bind_group
+    }
+
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn begin_frame(&mut self) {
+        self.frame = (self.frame + 1) % FRAMES_IN_FLIGHT;
+        self.staging.clear();
+        self.len = 0;
+    }
+
+    pub fn push(&mut self, value: &T) -> UniformSlot {
+        let slot = UniformSlot(self.len as u32);
+        self.staging.extend_from_slice(bytemuck::bytes_of(value));
+        self.staging
+            .resize((self.len + 1) as usize * self.stride as usize, 0);
+        self.len += 1;
+        slot
+    }
+
+    pub fn upload(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if self.len > self.capacity {
+            self.capacity = self.len.next_power_of_two();
+            let (buffer, bind_group) = Self::create_buffer(
+                device,
+                self.label,
+                &self.bind_group_layout,
+                self.capacity,
+                self.stride,
+            );
+            self.buffer = buffer;
+            self.bind_group = bind_group;
+        }
+        if !self.staging.is_empty() {
+            perf::write_buffer(queue, &self.buffer, self.frame_base(), &self.staging);
+        }
+    }
+
+    pub fn offset(&self, slot: UniformSlot) -> u32 {
+        (self.frame_base() + slot.0 as u64 * self.stride) as u32
+    }
+
+    fn frame_base(&self) -> u64 {
+        self.frame * self.capacity * self.stride
+    }
+
+    fn create_buffer(
+        device: &wgpu::Device,
+        label: &str,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        capacity: u64,
+        stride: u64,
+    ) -> (Tracked<wgpu::Buffer>, Tracked<wgpu::BindGroup>) {
+        let resources = ResourceTracker::new(device, "Uniform Ring");
+        let buffer = resources.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size: FRAMES_IN_FLIGHT * capacity * stride,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = resources.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer: &buffer,
+                    offset: 0,
+                    size: wgpu::BufferSize::new(std::mem::size_of::<T>() as u64),
+                }),
+            }],
+            label: Some(label),
+        });
+        (buffer, bind_group)
+    }
+}
@@ -1,9 +1,26 @@
-use crate::Scene;
+use crate::cvars::CvarRegistry;
+use crate::input::{Actions, InputMap, InputSource};
+use crate::instancing::InstanceData;
+use crate::outline::SelectionOutline;
+use crate::perf;
+use crate::render_graph::{RenderGraph, TransientPool, TransientTexture};
+use crate::replay::{
+    self, InputRecorder, InputReplay, PoseRecord, RecordingHeader, ReplayCvars, XrPoseRecord,
+    XrViewRecord,
+};
+use crate::resources::{ResourceTracker, Tracked};
+use crate::shadows::{CascadedShadowMap, ShadowCaster, ShadowSettings, ShadowView};
+use crate::spin;
+use crate::trace;
+use crate::uniform_ring::UniformRing;
+use crate::{GridUniform, Scene, SceneCvars, SceneObject, SkyUniform};
 use ash::vk::{self, Handle};
 use openxr as xr;
 use std::ffi::{CString, c_char, c_void};
 use web_time::Instant;
 
+const OWNER: &str = "XR";
+
 const MULTIVIEW_MASK: Option<std::num::NonZeroU32> = std::num::NonZeroU32::new(0b11);
 
 const VK_TARGET_VERSION: xr::Version = xr::Version::new(1, 1, 0);
@@ -18,44 +35,30 @@ const VK_TARGET_VERSION_ASH: u32 = vk::make_api_version(
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 struct MeshUniform {
     mvp: [[[f32; 4]; 4]; 2],
-}
-
-#[repr(C)]
-#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
-struct GridUniform {
-    view_proj: [[[f32; 4]; 4]; 2],
-    camera_world_pos: [[f32; 4]; 2],
-    grid_size: f32,
-    grid_min_pixels: f32,
-    grid_cell_size: f32,
-    orthographic_scale: f32,
-    is_orthographic: f32,
-    _padding: [f32; 3],
-}
-
-#[repr(C)]
-#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
-struct SkyUniform {
-    proj_inv: [[[f32; 4]; 4]; 2],
-    view: [[[f32; 4]; 4]; 2],
+    model: [[f32; 4]; 4],
 }
 
 pub struct XrContext {
-    swapchain_buffers: Vec<wgpu::Texture>,
-    depth_view: wgpu::TextureView,
-    _depth_texture: wgpu::Texture,
-    cube_vertex_buffer: wgpu::Buffer,
-    cube_index_buffer: wgpu::Buffer,
-    green_cube_vertex_buffer: wgpu::Buffer,
-    mesh_uniform_buffer: wgpu::Buffer,
-    mesh_bind_group: wgpu::BindGroup,
-    mesh_pipeline: wgpu::RenderPipeline,
-    grid_uniform_buffer: wgpu::Buffer,
-    grid_bind_group: wgpu::BindGroup,
-    grid_pipeline: wgpu::RenderPipeline,
-    sky_uniform_buffer: wgpu::Buffer,
-    sky_bind_group: wgpu::BindGroup,
-    sky_pipeline: wgpu::RenderPipeline,
+    swapchain_buffers: Vec<Tracked<wgpu::Texture>>,
+    transient_pool: TransientPool,
+    sample_count: u32,
+    supported_sample_counts: Vec<u32>,
+    grid_bind_group_layout: wgpu::BindGroupLayout,
+    sky_bind_group_layout: wgpu::BindGroupLayout,
+    cube_vertex_buffer: Tracked<wgpu::Buffer>,
+    cube_index_buffer: Tracked<wgpu::Buffer>,
+    green_cube_vertex_buffer: Tracked<wgpu::Buffer>,
+    mesh_uniforms: UniformRing<MeshUniform>,
+    mesh_pipeline: Tracked<wgpu::RenderPipeline>,
+    instanced_mesh_pipeline: Tracked<wgpu::RenderPipeline>,
+    grid_uniform_buffer: Tracked<wgpu::Buffer>,
+    grid_bind_group: Tracked<wgpu::BindGroup>,
+    grid_pipeline: Tracked<wgpu::RenderPipeline>,
+    sky_uniform_buffer: Tracked<wgpu::Buffer>,
+    sky_bind_group: Tracked<wgpu::BindGroup>,
+    sky_pipeline: Tracked<wgpu::RenderPipeline>,
+    shadows: CascadedShadowMap,
+    outline: SelectionOutline,
     swapchain: xr::Swapchain<xr::Vulkan>,
     stage: xr::Space,
     left_hand_space: xr::Space,
@@ -64,6 +67,7 @@ pub struct XrContext {
     left_trigger_action: xr::Action<f32>,
     right_trigger_action: xr::Action<f32>,
     action_set: xr::ActionSet,
+    actions: Actions,
     frame_stream: xr::FrameStream<xr::Vulkan>,
     frame_wait: xr::FrameWaiter,
     session: xr::Session<xr::Vulkan>,
@@ -290,6 +294,20 @@ impl XrContext {
             unsafe { wgpu::Instance::from_hal::<wgpu_hal::api::Vulkan>(wgpu_vk_instance) };
         let wgpu_adapter = unsafe { wgpu_instance.create_adapter_from_hal(wgpu_exposed_adapter) };
         let limits = wgpu_adapter.limits();
+        let supported_sample_counts = if wgpu_features.contains(wgpu::Features::MULTISAMPLE_ARRAY) {
+            crate::supported_sample_counts(
+                &wgpu_adapter,
+                &[
+                    wgpu::TextureFormat::Rgba8UnormSrgb,
+                    wgpu::TextureFormat::Depth32Float,
+                ],
+            )
+        } else {
+            vec![1]
+        };
+        let sample_count =
+            crate::preferred_sample_count(&supported_sample_counts, crate::DEFAULT_SAMPLE_COUNT);
+        log::info!("XR MSAA sample counts: {supported_sample_counts:?}, using {sample_count}x");
         let (wgpu_device, wgpu_queue) = unsafe {
             wgpu_adapter.create_device_from_hal(
                 wgpu_open_device,
@@ -383,7 +401,7 @@ impl XrContext {
         })?;
 
         let swapchain_images = swapchain.enumerate_images()?;
-        let swapchain_buffers: Vec<wgpu::Texture> = swapchain_images
+        let swapchain_buffers: Vec<Tracked<wgpu::Texture>> = swapchain_images
             .into_iter()
             .map(|color_image| {
                 let color_image = vk::Image::from_raw(color_image);
@@ -412,32 +430,31 @@ impl XrContext {
                         wgpu_hal::vulkan::TextureMemory::External,
                     )
                 };
+                let descriptor = wgpu::TextureDescriptor {
+                    label: Some("VR Swapchain"),
+                    size: wgpu::Extent3d {
+                        width: resolution.0,
+                        height: resolution.1,
+                        depth_or_array_layers: 2,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: wgpu::TextureFormat::Rgba8UnormSrgb,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_DST,
+                    view_formats: &[],
+                };
                 let texture = unsafe {
                     wgpu_device.create_texture_from_hal::<wgpu_hal::vulkan::Api>(
                         wgpu_hal_texture,
-                        &wgpu::TextureDescriptor {
-                            label: Some("VR Swapchain"),
-                            size: wgpu::Extent3d {
-                                width: resolution.0,
-                                height: resolution.1,
-                                depth_or_array_layers: 2,
-                            },
-                            mip_level_count: 1,
-                            sample_count: 1,
-                            dimension: wgpu::TextureDimension::D2,
-                            format: wgpu::TextureFormat::Rgba8UnormSrgb,
-                            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
-                                | wgpu::TextureUsages::COPY_DST,
-                            view_formats: &[],
-                        },
+                        &descriptor,
                     )
                 };
-                Ok(texture)
+                Ok(ResourceTracker::new(&wgpu_device, OWNER).track_texture(texture, &descriptor))
             })
             .collect::<Result<Vec<_>, Box<dyn std::error::Error>>>()?;
 
-        let cube_vertex_buffer = wgpu::util::DeviceExt::create_buffer_init(
-            &wgpu_device,
+        let cube_vertex_buffer = ResourceTracker::new(&wgpu_device, OWNER).create_buffer_init(
             &wgpu::util::BufferInitDescriptor {
                 label: Some("Cube Vertex Buffer"),
                 contents: bytemuck::cast_slice(&crate::CUBE_VERTICES),
@@ -445,8 +462,7 @@ impl XrContext {
             },
         );
 
-        let cube_index_buffer = wgpu::util::DeviceExt::create_buffer_init(
-            &wgpu_device,
+        let cube_index_buffer = ResourceTracker::new(&wgpu_device, OWNER).create_buffer_init(
             &wgpu::util::BufferInitDescriptor {
                 label: Some("Cube Index Buffer"),
                 contents: bytemuck::cast_slice(&crate::CUBE_INDICES),
@@ -454,103 +470,43 @@ impl XrContext {
             },
         );
 
-        let green_cube_vertex_buffer = wgpu::util::DeviceExt::create_buffer_init(
-            &wgpu_device,
-            &wgpu::util::BufferInitDescriptor {
+        let green_cube_vertex_buffer = ResourceTracker::new(&wgpu_device, OWNER)
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
                 label: Some("Green Cube Vertex Buffer"),
                 contents: bytemuck::cast_slice(&crate::GREEN_CUBE_VERTICES),
                 usage: wgpu::BufferUsages::VERTEX,
-            },
-        );
-
-        let mesh_uniform_buffer = wgpu_device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Mesh Uniform Buffer"),
-            size: std::mem::size_of::<MeshUniform>() as u64,
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
-
-        let mesh_bind_group_layout =
-            wgpu_device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                entries: &[wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                }],
-                label: Some("Mesh Bind Group Layout"),
             });
 
-        let mesh_bind_group = wgpu_device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &mesh_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: mesh_uniform_buffer.as_entire_binding(),
-            }],
-            label: Some("Mesh Bind Group"),
-        });
-
-        let mesh_shader = wgpu_device.create_shader_module(wgpu::include_wgsl!("mesh.wgsl"));
+        let mesh_uniforms = UniformRing::new(
+            &wgpu_device,
+            "Mesh Uniform Ring",
+            wgpu::ShaderStages::VERTEX,
+        );
 
-        let mesh_pipeline_layout =
-            wgpu_device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("Mesh Pipeline Layout"),
-                bind_group_layouts: &[Some(&mesh_bind_group_layout)],
-                immediate_size: 0,
-            });
+        let shadows = CascadedShadowMap::new(&wgpu_device, ShadowSettings::default());
 
-        let mesh_pipeline = wgpu_device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Mesh Pipeline"),
-            layout: Some(&mesh_pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &mesh_shader,
-                entry_point: Some("vertex_main"),
-                buffers: &[crate::Vertex::description(
-                    &crate::Vertex::vertex_attributes(),
-                )],
-                compilation_options: Default::default(),
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &mesh_shader,
-                entry_point: Some("fragment_main"),
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: wgpu::TextureFormat::Rgba8UnormSrgb,
-                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-                compilation_options: Default::default(),
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleStrip,
-                strip_index_format: Some(wgpu::IndexFormat::Uint32),
-                front_face: wgpu::FrontFace::Cw,
-                cull_mode: None,
-                unclipped_depth: false,
-                polygon_mode: wgpu::PolygonMode::Fill,
-                conservative: false,
-            },
-            depth_stencil: Some(wgpu::DepthStencilState {
-                format: wgpu::TextureFormat::Depth32Float,
-                depth_write_enabled: Some(true),
-                depth_compare: Some(wgpu::CompareFunction::Less),
-                stencil: wgpu::StencilState::default(),
-                bias: wgpu::DepthBiasState::default(),
-            }),
-            multisample: wgpu::MultisampleState::default(),
-            multiview_mask: MULTIVIEW_MASK,
-            cache: None,
-        });
+        let mesh_pipeline = Self::create_mesh_pipeline(
+            &wgpu_device,
+            &mesh_uniforms.bind_group_layout,
+            &shadows,
+            sample_count,
+            false,
+        );
+        let instanced_mesh_pipeline = Self::create_mesh_pipeline(
+            &wgpu_device,
+            &mesh_uniforms.bind_group_layout,
+            &shadows,
+            sample_count,
+            true,
+        );
 
-        let grid_uniform_buffer = wgpu_device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Grid Uniform Buffer"),
-            size: std::mem::size_of::<GridUniform>() as u64,
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
+        let grid_uniform_buffer =
+            ResourceTracker::new(&wgpu_device, OWNER).create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Grid Uniform Buffer"),
+                size: std::mem::size_of::<GridUniform>() as u64,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
 
         let grid_bind_group_layout =
             wgpu_device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
@@ -567,82 +523,32 @@ impl XrContext {
                 label: Some("Grid Bind Group Layout"),
             });
 
-        let grid_bind_group = wgpu_device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &grid_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: grid_uniform_buffer.as_entire_binding(),
-            }],
-            label: Some("Grid Bind Group"),
-        });
+        let grid_bind_group = ResourceTracker::new(&wgpu_device, OWNER).create_bind_group(
+            &wgpu::BindGroupDescriptor {
+                layout: &grid_bind_group_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: grid_uniform_buffer.as_entire_binding(),
+                }],
+                label: Some("Grid Bind Group"),
+            },
+        );
 
-        let grid_shader = wgpu_device.create_shader_module(wgpu::include_wgsl!("grid.wgsl"));
+        let grid_pipeline = Self::create_grid_pipeline(
+            &wgpu_device,
+            &grid_bind_group_layout,
+            &shadows,
+            sample_count,
+        );
 
-        let grid_pipeline_layout =
-            wgpu_device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("Grid Pipeline Layout"),
-                bind_group_layouts: &[Some(&grid_bind_group_layout)],
-                immediate_size: 0,
+        let sky_uniform_buffer =
+            ResourceTracker::new(&wgpu_device, OWNER).create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Sky Uniform Buffer"),
+                size: std::mem::size_of::<SkyUniform>() as u64,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
             });
 
-        let grid_pipeline = wgpu_device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Grid Pipeline"),
-            layout: Some(&grid_pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &grid_shader,
-                entry_point: Some("vertex_main"),
-                buffers: &[],
-                compilation_options: Default::default(),
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &grid_shader,
-                entry_point: Some("fragment_main"),
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: wgpu::TextureFormat::Rgba8UnormSrgb,
-                    blend: Some(wgpu::BlendState {
-                        color: wgpu::BlendComponent {
-                            src_factor: wgpu::BlendFactor::SrcAlpha,
-                            dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
-                            operation: wgpu::BlendOperation::Add,
-                        },
-                        alpha: wgpu::BlendComponent::OVER,
-                    }),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-                compilation_options: Default::default(),
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: None,
-                unclipped_depth: false,
-                polygon_mode: wgpu::PolygonMode::Fill,
-                conservative: false,
-            },
-            depth_stencil: Some(wgpu::DepthStencilState {
-                format: wgpu::TextureFormat::Depth32Float,
-                depth_write_enabled: Some(false),
-                depth_compare: Some(wgpu::CompareFunction::LessEqual),
-                stencil: wgpu::StencilState::default(),
-                bias: wgpu::DepthBiasState {
-                    constant: 2,
-                    slope_scale: 2.0,
-                    clamp: 0.0,
-                },
-            }),
-            multisample: wgpu::MultisampleState::default(),
-            multiview_mask: MULTIVIEW_MASK,
-            cache: None,
-        });
-
-        let sky_uniform_buffer = wgpu_device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Sky Uniform Buffer"),
-            size: std::mem::size_of::<SkyUniform>() as u64,
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
-
         let sky_bind_group_layout =
             wgpu_device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                 label: Some("Sky Bind Group Layout"),
@@ -658,81 +564,27 @@ impl XrContext {
                 }],
             });
 
-        let sky_bind_group = wgpu_device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &sky_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: sky_uniform_buffer.as_entire_binding(),
-            }],
-            label: Some("Sky Bind Group"),
-        });
-
-        let sky_shader = wgpu_device.create_shader_module(wgpu::include_wgsl!("sky.wgsl"));
-
-        let sky_pipeline_layout =
-            wgpu_device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("Sky Pipeline Layout"),
-                bind_group_layouts: &[Some(&sky_bind_group_layout)],
-                immediate_size: 0,
-            });
-
-        let sky_pipeline = wgpu_device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Sky Pipeline"),
-            layout: Some(&sky_pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &sky_shader,
-                entry_point: Some("vs_sky"),
-                buffers: &[],
-                compilation_options: Default::default(),
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &sky_shader,
-                entry_point: Some("fs_sky"),
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: wgpu::TextureFormat::Rgba8UnormSrgb,
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-                compilation_options: Default::default(),
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: None,
-                ..Default::default()
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState::default(),
-            multiview_mask: MULTIVIEW_MASK,
-            cache: None,
-        });
-
-        let depth_texture = wgpu_device.create_texture(&wgpu::TextureDescriptor {
-            label: Some("XR Depth Texture"),
-            size: wgpu::Extent3d {
-                width: resolution.0,
-                height: resolution.1,
-                depth_or_array_layers: 2,
+        let sky_bind_group = ResourceTracker::new(&wgpu_device, OWNER).create_bind_group(
+            &wgpu::BindGroupDescriptor {
+                layout: &sky_bind_group_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: sky_uniform_buffer.as_entire_binding(),
+                }],
+                label: Some("Sky Bind Group"),
             },
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Depth32Float,
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            view_formats: &[],
-        });
+        );
 
-        let depth_view = depth_texture.create_view(&wgpu::TextureViewDescriptor {
-            label: Some("XR Depth View"),
-            format: Some(wgpu::TextureFormat::Depth32Float),
-            dimension: Some(wgpu::TextureViewDimension::D2Array),
-            aspect: wgpu::TextureAspect::All,
-            base_mip_level: 0,
-            mip_level_count: None,
-            base_array_layer: 0,
-            array_layer_count: Some(2),
-            usage: None,
-        });
+        let sky_pipeline =
+            Self::create_sky_pipeline(&wgpu_device, &sky_bind_group_layout, sample_count);
+        let outline = SelectionOutline::new(
+            &wgpu_device,
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+            sample_count,
+            MULTIVIEW_MASK,
+            resolution.0,
+            resolution.1,
+        );
 
         log::info!("OpenXR session created successfully");
 
@@ -754,23 +606,29 @@ impl XrContext {
                 move_action,
                 left_trigger_action,
                 right_trigger_action,
+                actions: Actions::new(InputMap::load_or_default()),
                 left_hand_space,
                 right_hand_space,
                 player_position: nalgebra_glm::vec3(0.0, 0.0, 0.0),
                 cube_vertex_buffer,
                 cube_index_buffer,
                 green_cube_vertex_buffer,
-                mesh_uniform_buffer,
-                mesh_bind_group,
+                mesh_uniforms,
                 mesh_pipeline,
+                instanced_mesh_pipeline,
                 grid_uniform_buffer,
                 grid_bind_group,
                 grid_pipeline,
                 sky_uniform_buffer,
                 sky_bind_group,
                 sky_pipeline,
-                depth_view,
-                _depth_texture: depth_texture,
+                shadows,
+                outline,
+                grid_bind_group_layout,
+                sky_bind_group_layout,
+                sample_count,
+                supported_sample_counts,
+                transient_pool: TransientPool::default(),
                 session_running: false,
             },
             wgpu_device,
@@ -778,6 +636,263 @@ impl XrContext {
         ))
     }
 
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    pub fn supported_sample_counts(&self) -> &[u32] {
+        &self.supported_sample_counts
+    }
+
+    pub fn set_sample_count(
+        &mut self,
+        device: &wgpu::Device,
+        sample_count: u32,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.supported_sample_counts.contains(&sample_count) {
+            return Err(format!("{sample_count}x MSAA is not supported by the XR device").into());
+        }
+        if sample_count == self.sample_count {
+            return Ok(());
+        }
+        self.sample_count = sample_count;
+        self.mesh_pipeline = Self::create_mesh_pipeline(
+            device,
+            &self.mesh_uniforms.bind_group_layout,
+            &self.shadows,
+            sample_count,
+            false,
+        );
+        self.instanced_mesh_pipeline = Self::create_mesh_pipeline(
+            device,
+            &self.mesh_uniforms.bind_group_layout,
+            &self.shadows,
+            sample_count,
+            true,
+        );
+        self.grid_pipeline = Self::create_grid_pipeline(
+            device,
+            &self.grid_bind_group_layout,
+            &self.shadows,
+            sample_count,
+        );
+        self.sky_pipeline =
+            Self::create_sky_pipeline(device, &self.sky_bind_group_layout, sample_count);
+        self.outline.set_sample_count(device, sample_count);
+        Ok(())
+    }
+
+    fn create_mesh_pipeline(
+        device: &wgpu::Device,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        shadows: &CascadedShadowMap,
+        sample_count: u32,
+        instanced: bool,
+    ) -> Tracked<wgpu::RenderPipeline> {
+        let vertex_attributes = crate::Vertex::vertex_attributes();
+        let instance_attributes = InstanceData::vertex_attributes();
+        let mut buffers = vec![crate::Vertex::description(&vertex_attributes)];
+        if instanced {
+            buffers.push(InstanceData::description(&instance_attributes));
+        }
+
+        let mesh_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Mesh Shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                concat!(include_str!("shadow.wgsl"), include_str!("mesh.wgsl")).into(),
+            ),
+        });
+
+        let mesh_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Mesh Pipeline Layout"),
+            bind_group_layouts: &[Some(bind_group_layout), Some(&shadows.bind_group_layout)],
+            immediate_size: 0,
+        });
+
+        ResourceTracker::new(device, OWNER).create_render_pipeline(
+            &wgpu::RenderPipelineDescriptor {
+                label: Some(if instanced {
+                    "Instanced Mesh Pipeline"
+                } else {
+                    "Mesh Pipeline"
+                }),
+                layout: Some(&mesh_pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &mesh_shader,
+                    entry_point: Some(if instanced {
+                        "vertex_instanced"
+                    } else {
+                        "vertex_main"
+                    }),
+                    buffers: &buffers,
+                    compilation_options: Default::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &mesh_shader,
+                    entry_point: Some("fragment_main"),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: wgpu::TextureFormat::Rgba8UnormSrgb,
+                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                    compilation_options: Default::default(),
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: if instanced {
+                        wgpu::PrimitiveTopology::TriangleList
+                    } else {
+                        wgpu::PrimitiveTopology::TriangleStrip
+                    },
+                    strip_index_format: (!instanced).then_some(wgpu::IndexFormat::Uint32),
+                    front_face: wgpu::FrontFace::Cw,
+                    cull_mode: None,
+                    unclipped_depth: false,
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    conservative: false,
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: wgpu::TextureFormat::Depth32Float,
+                    depth_write_enabled: Some(true),
+                    depth_compare: Some(wgpu::CompareFunction::Less),
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState {
+                    count: sample_count,
+                    ..Default::default()
+                },
+                multiview_mask: MULTIVIEW_MASK,
+                cache: None,
+            },
+        )
+    }
+
+    fn create_grid_pipeline(
+        device: &wgpu::Device,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        shadows: &CascadedShadowMap,
+        sample_count: u32,
+    ) -> Tracked<wgpu::RenderPipeline> {
+        let grid_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Grid Shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                concat!(include_str!("shadow.wgsl"), include_str!("grid.wgsl")).into(),
+            ),
+        });
+
+        let grid_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Grid Pipeline Layout"),
+            bind_group_layouts: &[Some(bind_group_layout), Some(&shadows.bind_group_layout)],
+            immediate_size: 0,
+        });
+
+        ResourceTracker::new(device, OWNER).create_render_pipeline(
+            &wgpu::RenderPipelineDescriptor {
+                label: Some("Grid Pipeline"),
+                layout: Some(&grid_pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &grid_shader,
+                    entry_point: Some("vertex_main"),
+                    buffers: &[],
+                    compilation_options: Default::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &grid_shader,
+                    entry_point: Some("fragment_main"),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: wgpu::TextureFormat::Rgba8UnormSrgb,
+                        blend: Some(wgpu::BlendState {
+                            color: wgpu::BlendComponent {
+                                src_factor: wgpu::BlendFactor::SrcAlpha,
+                                dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                                operation: wgpu::BlendOperation::Add,
+                            },
+                            alpha: wgpu::BlendComponent::OVER,
+                        }),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                    compilation_options: Default::default(),
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    strip_index_format: None,
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: None,
+                    unclipped_depth: false,
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    conservative: false,
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: wgpu::TextureFormat::Depth32Float,
+                    depth_write_enabled: Some(false),
+                    depth_compare: Some(wgpu::CompareFunction::LessEqual),
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState {
+                        constant: 2,
+                        slope_scale: 2.0,
+                        clamp: 0.0,
+                    },
+                }),
+                multisample: wgpu::MultisampleState {
+                    count: sample_count,
+                    ..Default::default()
+                },
+                multiview_mask: MULTIVIEW_MASK,
+                cache: None,
+            },
+        )
+    }
+
+    fn create_sky_pipeline(
+        device: &wgpu::Device,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        sample_count: u32,
+    ) -> Tracked<wgpu::RenderPipeline> {
+        let sky_shader = device.create_shader_module(wgpu::include_wgsl!("sky.wgsl"));
+
+        let sky_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Sky Pipeline Layout"),
+            bind_group_layouts: &[Some(bind_group_layout)],
+            immediate_size: 0,
+        });
+
+        ResourceTracker::new(device, OWNER).create_render_pipeline(
+            &wgpu::RenderPipelineDescriptor {
+                label: Some("Sky Pipeline"),
+                layout: Some(&sky_pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &sky_shader,
+                    entry_point: Some("vs_sky"),
+                    buffers: &[],
+                    compilation_options: Default::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &sky_shader,
+                    entry_point: Some("fs_sky"),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: wgpu::TextureFormat::Rgba8UnormSrgb,
+                        blend: Some(wgpu::BlendState::REPLACE),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                    compilation_options: Default::default(),
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: None,
+                    ..Default::default()
+                },
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState {
+                    count: sample_count,
+                    ..Default::default()
+                },
+                multiview_mask: MULTIVIEW_MASK,
+                cache: None,
+            },
+        )
+    }
+
     pub fn poll_events(&mut self) -> Result<bool, Box<dyn std::error::Error>> {
         let mut event_buffer = xr::EventDataBuffer::new();
         while let Some(event) = self.instance.poll_event(&mut event_buffer)? {
@@ -857,50 +972,132 @@ impl XrContext {
     }
 
     pub fn wait_frame(&mut self) -> Result<xr::FrameState, Box<dyn std::error::Error>> {
+        let _scope = trace::scope("XR::wait_frame");
         Ok(self.frame_wait.wait()?)
     }
 
-    pub fn update_movement(
+    pub fn sync_action_sources(
         &mut self,
-        delta_time: f32,
-        predicted_display_time: xr::Time,
-    ) -> Result<(), Box<dyn std::error::Error>> {
+    ) -> Result<Vec<(String, f32)>, Box<dyn std::error::Error>> {
         self.session.sync_actions(&[(&self.action_set).into()])?;
 
         let move_state = self.move_action.state(&self.session, xr::Path::NULL)?;
+        let left_trigger = self
+            .left_trigger_action
+            .state(&self.session, xr::Path::NULL)?;
+        let right_trigger = self
+            .right_trigger_action
+            .state(&self.session, xr::Path::NULL)?;
+        Ok([
+            ("move_x", move_state.current_state.x),
+            ("move_y", move_state.current_state.y),
+            ("left_trigger", left_trigger.current_state),
+            ("right_trigger", right_trigger.current_state),
+        ]
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value))
+        .collect())
+    }
 
-        if move_state.current_state.x.abs() > 0.1 || move_state.current_state.y.abs() > 0.1 {
-            let (_, views) = self.session.locate_views(
-                xr::ViewConfigurationType::PRIMARY_STEREO,
-                predicted_display_time,
-                &self.stage,
-            )?;
+    pub fn update_movement(
+        &mut self,
+        delta_time: f32,
+        poses: &XrPoseRecord,
+        sources: &[(String, f32)],
+    ) {
+        for (name, value) in sources {
+            self.actions
+                .set_source(InputSource::Xr(name.clone()), *value);
+        }
+        self.actions.update();
 
-            if !views.is_empty() {
-                let head_pose = &views[0].pose;
-                let head_quat = nalgebra_glm::quat(
-                    head_pose.orientation.w,
-                    head_pose.orientation.z,
-                    head_pose.orientation.y,
-                    head_pose.orientation.x,
-                );
-                let head_forward =
-                    nalgebra_glm::quat_rotate_vec3(&head_quat, &nalgebra_glm::vec3(0.0, 0.0, -1.0));
-                let head_yaw = (-head_forward.x).atan2(-head_forward.z);
+        let movement = self.actions.vector2("move");
+        if (movement.x.abs() > 0.1 || movement.y.abs() > 0.1)
+            && let Some(view) = poses.views.first()
+        {
+            let [x, y, z, w] = view.pose.orientation;
+            let head_quat = nalgebra_glm::quat(w, z, y, x);
+            let head_forward =
+                nalgebra_glm::quat_rotate_vec3(&head_quat, &nalgebra_glm::vec3(0.0, 0.0, -1.0));
+            let head_yaw = (-head_forward.x).atan2(-head_forward.z);
 
-                let move_speed = 2.0;
-                let move_x = move_state.current_state.x;
-                let move_z = -move_state.current_state.y;
+            let move_speed = 2.0;
+            let move_x = movement.x;
+            let move_z = -movement.y;
 
-                let rotated_x = move_x * head_yaw.cos() - move_z * head_yaw.sin();
-                let rotated_z = move_x * head_yaw.sin() + move_z * head_yaw.cos();
+            let rotated_x = move_x * head_yaw.cos() - move_z * head_yaw.sin();
+            let rotated_z = move_x * head_yaw.sin() + move_z * head_yaw.cos();
 
-                self.player_position.x += rotated_x * move_speed * delta_time;
-                self.player_position.z += rotated_z * move_speed * delta_time;
-            }
+            self.player_position.x += rotated_x * move_speed * delta_time;
+            self.player_position.z += rotated_z * move_speed * delta_time;
         }
+    }
 
-        Ok(())
+    pub fn locate_poses(
+        &self,
+        predicted_display_time: xr::Time,
+    ) -> Result<XrPoseRecord, Box<dyn std::error::Error>> {
+        let (view_state_flags, views) = {
+            let _scope = trace::scope("XR::locate_views");
+            self.session.locate_views(
+                xr::ViewConfigurationType::PRIMARY_STEREO,
+                predicted_display_time,
+                &self.stage,
+            )?
+        };
+        let views = if view_state_flags
+            .contains(xr::ViewStateFlags::POSITION_VALID | xr::ViewStateFlags::ORIENTATION_VALID)
+        {
+            views
+                .iter()
+                .map(|view| XrViewRecord {
+                    pose: pose_record(&view.pose),
+                    fov: [
+                        view.fov.angle_left,
+                        view.fov.angle_right,
+                        view.fov.angle_up,
+                        view.fov.angle_down,
+                    ],
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+        Ok(XrPoseRecord {
+            predicted_display_time: predicted_display_time.as_nanos(),
+            views,
+            left_hand: self.locate_hand(&self.left_hand_space, predicted_display_time),
+            right_hand: self.locate_hand(&self.right_hand_space, predicted_display_time),
+        })
+    }
+
+    fn locate_hand(&self, hand_space: &xr::Space, time: xr::Time) -> Option<PoseRecord> {
+        let location = hand_space.locate(&self.stage, time).ok()?;
+        location
+            .location_flags
+            .contains(
+                xr::SpaceLocationFlags::POSITION_VALID | xr::SpaceLocationFlags::ORIENTATION_VALID,
+            )
+            .then(|| pose_record(&location.pose))
+    }
+
+    fn hand_model(&self, hand_pose: &PoseRecord) -> nalgebra_glm::Mat4 {
+        let rotation = {
+            let [x, y, z, w] = hand_pose.orientation;
+            let flip_x = nalgebra_glm::quat_angle_axis(
+                180.0_f32.to_radians(),
+                &nalgebra_glm::vec3(1.0, 0.0, 0.0),
+            );
+            let openxr_quat = nalgebra_glm::quat(w, z, y, x);
+            flip_x * openxr_quat
+        };
+        let [x, y, z] = hand_pose.position;
+        let translation = nalgebra_glm::vec3(-x, y, -z);
+        let hand_world_position = translation + self.player_position;
+
+        let rotation_matrix = nalgebra_glm::quat_to_mat4(&rotation);
+        let translation_matrix = nalgebra_glm::translation(&hand_world_position);
+        translation_matrix * rotation_matrix
     }
 
     pub fn render_frame(
@@ -910,8 +1107,13 @@ impl XrContext {
         scene: &mut Scene,
         _delta_time: f32,
         frame_state: xr::FrameState,
+        poses: &XrPoseRecord,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        self.frame_stream.begin()?;
+        let _scope = trace::scope("XrContext::render_frame");
+        {
+            let _scope = trace::scope("XR::begin_frame");
+            self.frame_stream.begin()?;
+        }
 
         if !frame_state.should_render {
             self.frame_stream.end(
@@ -922,15 +1124,8 @@ impl XrContext {
             return Ok(());
         }
 
-        let (view_state_flags, views) = self.session.locate_views(
-            xr::ViewConfigurationType::PRIMARY_STEREO,
-            frame_state.predicted_display_time,
-            &self.stage,
-        )?;
-
-        if !view_state_flags
-            .contains(xr::ViewStateFlags::POSITION_VALID | xr::ViewStateFlags::ORIENTATION_VALID)
-        {
+        let views = poses.views.iter().map(xr_view).collect::<Vec<_>>();
+        if views.len() < 2 {
             self.frame_stream.end(
                 frame_state.predicted_display_time,
                 xr::EnvironmentBlendMode::OPAQUE,
@@ -939,8 +1134,12 @@ impl XrContext {
             return Ok(());
         }
 
-        let image_index = self.swapchain.acquire_image()?;
-        self.swapchain.wait_image(xr::Duration::INFINITE)?;
+        let image_index = {
+            let _scope = trace::scope("XR::acquire_image");
+            let image_index = self.swapchain.acquire_image()?;
+            self.swapchain.wait_image(xr::Duration::INFINITE)?;
+            image_index
+        };
 
         let swapchain_texture = &self.swapchain_buffers[image_index as usize];
         let resolution = self.resolution;
@@ -1030,99 +1229,103 @@ impl XrContext {
             camera_positions[view_index] = camera_position;
         }
 
+        let model_translation = nalgebra_glm::translation(&nalgebra_glm::vec3(0.0, 1.5, 2.0));
+        let model = model_translation * scene.model;
+        let left_hand_model = poses.left_hand.map(|pose| self.hand_model(&pose));
+        let right_hand_model = poses.right_hand.map(|pose| self.hand_model(&pose));
+
+        let shadow_views = [
+            ShadowView::new(&(projection_matrices[0] * view_matrices[0]), 0.1, 1000.0),
+            ShadowView::new(&(projection_matrices[1] * view_matrices[1]), 0.1, 1000.0),
+        ];
+        self.shadows.update(queue, &scene.light, &shadow_views);
+
+        let view_projections = [
+            projection_matrices[0] * view_matrices[0],
+            projection_matrices[1] * view_matrices[1],
+        ];
+        scene.prepare_instances(device, queue, &view_projections);
+        self.outline
+            .prepare(device, queue, scene, &model, &view_projections);
+
+        let mesh_visible = scene.is_visible(SceneObject::Mesh);
+        let mut casters = Scene::shadow_casters(
+            &scene.vertex_buffer,
+            &scene.index_buffer,
+            mesh_visible.then_some(model),
+            &scene.batches,
+        );
+        for hand_model in [left_hand_model, right_hand_model].into_iter().flatten() {
+            casters.push(ShadowCaster {
+                vertex_buffer: &self.cube_vertex_buffer,
+                index_buffer: &self.cube_index_buffer,
+                index_count: crate::CUBE_INDICES.len() as u32,
+                model: hand_model,
+                instances: None,
+            });
+        }
+
+        self.shadows.prepare(device, queue, &casters);
+
+        let sun_direction = scene.light.sun_direction();
         let sky_uniform = SkyUniform {
             proj_inv: [
                 nalgebra_glm::inverse(&projection_matrices[0]).into(),
                 nalgebra_glm::inverse(&projection_matrices[1]).into(),
             ],
             view: [view_matrices[0].into(), view_matrices[1].into()],
+            sun_direction: [sun_direction.x, sun_direction.y, sun_direction.z, 0.0],
         };
-        queue.write_buffer(
+        perf::write_buffer(
+            queue,
             &self.sky_uniform_buffer,
             0,
             bytemuck::cast_slice(&[sky_uniform]),
         );
 
-        let mut sky_encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
-            label: Some("Sky Render Encoder"),
-        });
-
-        {
-            let mut render_pass = sky_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Sky Render Pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &color_view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
-                        store: wgpu::StoreOp::Store,
-                    },
-                    depth_slice: None,
-                })],
-                depth_stencil_attachment: None,
-                timestamp_writes: None,
-                occlusion_query_set: None,
-                multiview_mask: MULTIVIEW_MASK,
-            });
-
-            render_pass.set_pipeline(&self.sky_pipeline);
-            render_pass.set_bind_group(0, &self.sky_bind_group, &[]);
-            render_pass.draw(0..3, 0..1);
+        let mut mesh_draws = Vec::new();
+        if mesh_visible {
+            mesh_draws.push((model, &scene.vertex_buffer, &scene.index_buffer, 3));
         }
-
-        queue.submit(std::iter::once(sky_encoder.finish()));
-
-        let model_translation = nalgebra_glm::translation(&nalgebra_glm::vec3(0.0, 1.5, 2.0));
-        let model = model_translation * scene.model;
-        let triangle_uniform = MeshUniform {
-            mvp: [
-                (projection_matrices[0] * view_matrices[0] * model).into(),
-                (projection_matrices[1] * view_matrices[1] * model).into(),
-            ],
-        };
-        queue.write_buffer(
-            &self.mesh_uniform_buffer,
-            0,
-            bytemuck::cast_slice(&[triangle_uniform]),
-        );
-
-        let mut triangle_encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
-            label: Some("Triangle Render Encoder"),
-        });
-
-        {
-            let mut render_pass = triangle_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Triangle Render Pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &color_view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Load,
-                        store: wgpu::StoreOp::Store,
-                    },
-                    depth_slice: None,
-                })],
-                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                    view: &self.depth_view,
-                    depth_ops: Some(wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(1.0),
-                        store: wgpu::StoreOp::Store,
-                    }),
-                    stencil_ops: None,
-                }),
-                timestamp_writes: None,
-                occlusion_query_set: None,
-                multiview_mask: MULTIVIEW_MASK,
-            });
-
-            render_pass.set_pipeline(&self.mesh_pipeline);
-            render_pass.set_bind_group(0, &self.mesh_bind_group, &[]);
-            render_pass.set_vertex_buffer(0, scene.vertex_buffer.slice(..));
-            render_pass.set_index_buffer(scene.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
-            render_pass.draw_indexed(0..3, 0, 0..1);
+        let hands = [
+            (left_hand_model, "left_trigger"),
+            (right_hand_model, "right_trigger"),
+        ];
+        for (hand_model, trigger_action) in hands {
+            let Some(hand_model) = hand_model else {
+                continue;
+            };
+            let trigger_pulled = self.actions.down(trigger_action);
+            let vertex_buffer = if trigger_pulled {
+                &self.green_cube_vertex_buffer
+            } else {
+                &self.cube_vertex_buffer
+            };
+            mesh_draws.push((
+                hand_model,
+                vertex_buffer,
+                &self.cube_index_buffer,
+                crate::CUBE_INDICES.len() as u32,
+            ));
         }
-
-        queue.submit(std::iter::once(triangle_encoder.finish()));
+        self.mesh_uniforms.begin_frame();
+        let mesh_slots = mesh_draws
+            .iter()
+            .map(|(mesh_model, _, _, _)| {
+                self.mesh_uniforms.push(&MeshUniform {
+                    mvp: [
+                        (projection_matrices[0] * view_matrices[0] * mesh_model).into(),
+                        (projection_matrices[1] * view_matrices[1] * mesh_model).into(),
+                    ],
+                    model: (*mesh_model).into(),
+                })
+            })
+            .collect::<Vec<_>>();
+        let instanced_slot = self.mesh_uniforms.push(&MeshUniform {
+            mvp: view_projections.map(Into::into),
+            model: nalgebra_glm::Mat4::identity().into(),
+        });
+        self.mesh_uniforms.upload(device, queue);
 
         let grid_uniform = GridUniform {
             view_proj: [
@@ -1150,239 +1353,132 @@ impl XrContext {
             is_orthographic: 0.0,
             _padding: [0.0; 3],
         };
-        queue.write_buffer(
+        perf::write_buffer(
+            queue,
             &self.grid_uniform_buffer,
             0,
             bytemuck::cast_slice(&[grid_uniform]),
         );
 
-        let mut grid_encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
-            label: Some("Grid Render Encoder"),
+        let sample_count = self.sample_count;
+        let array_size = wgpu::Extent3d {
+            width: resolution.0,
+            height: resolution.1,
+            depth_or_array_layers: 2,
+        };
+
+        let mut graph = RenderGraph::new();
+        let shadow_map = graph.import_texture(self.shadows.map_view());
+        let swapchain_color = graph.import_texture(&color_view);
+        let depth = graph.create_texture(TransientTexture {
+            label: "XR Depth Texture",
+            size: array_size,
+            format: wgpu::TextureFormat::Depth32Float,
+            sample_count,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_dimension: wgpu::TextureViewDimension::D2Array,
         });
+        let color = if sample_count > 1 {
+            graph.create_texture(TransientTexture {
+                label: "XR Multisampled Color Texture",
+                size: array_size,
+                format: wgpu::TextureFormat::Rgba8UnormSrgb,
+                sample_count,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                view_dimension: wgpu::TextureViewDimension::D2Array,
+            })
+        } else {
+            swapchain_color
+        };
 
-        {
-            let mut render_pass = grid_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Grid Render Pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &color_view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Load,
-                        store: wgpu::StoreOp::Store,
-                    },
-                    depth_slice: None,
-                })],
-                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                    view: &self.depth_view,
-                    depth_ops: Some(wgpu::Operations {
-                        load: wgpu::LoadOp::Load,
-                        store: wgpu::StoreOp::Store,
-                    }),
-                    stencil_ops: None,
-                }),
-                timestamp_writes: None,
-                occlusion_query_set: None,
-                multiview_mask: MULTIVIEW_MASK,
+        let shadows = &self.shadows;
+        graph
+            .add_pass("Shadow Pass")
+            .write_texture(shadow_map)
+            .encode(|encoder, _| shadows.render(encoder, &casters));
+
+        graph
+            .add_pass("Sky Render Pass")
+            .color_attachment(color, None, wgpu::Color::BLACK)
+            .multiview_mask(MULTIVIEW_MASK)
+            .render(|render_pass, _| {
+                render_pass.set_pipeline(&self.sky_pipeline);
+                render_pass.set_bind_group(0, &self.sky_bind_group, &[]);
+                render_pass.draw(0..3, 0..1);
+                perf::record_draw(1);
             });
 
-            render_pass.set_pipeline(&self.grid_pipeline);
-            render_pass.set_bind_group(0, &self.grid_bind_group, &[]);
-            render_pass.draw(0..6, 0..1);
+        let culled_buffers = scene.culler.add_pass(&mut graph, &scene.batches);
+        let mut mesh_pass = graph.add_pass("Mesh Render Pass");
+        for buffer in &culled_buffers {
+            mesh_pass = mesh_pass.read_buffer(*buffer);
         }
+        mesh_pass
+            .read_texture(shadow_map)
+            .color_attachment(color, None, wgpu::Color::BLACK)
+            .depth_attachment(depth, 1.0)
+            .multiview_mask(MULTIVIEW_MASK)
+            .render(|render_pass, _| {
+                render_pass.set_pipeline(&self.mesh_pipeline);
+                render_pass.set_bind_group(1, &self.shadows.bind_group, &[]);
+                for ((_, vertex_buffer, index_buffer, index_count), slot) in
+                    mesh_draws.iter().zip(&mesh_slots)
+                {
+                    render_pass.set_bind_group(
+                        0,
+                        self.mesh_uniforms.bind_group(),
+                        &[self.mesh_uniforms.offset(*slot)],
+                    );
+                    render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+                    render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                    render_pass.draw_indexed(0..*index_count, 0, 0..1);
+                    perf::record_draw(u64::from(*index_count / 3));
+                }
 
-        queue.submit(std::iter::once(grid_encoder.finish()));
-
-        let left_hand_location = self
-            .left_hand_space
-            .locate(&self.stage, frame_state.predicted_display_time);
-        if let Ok(location) = left_hand_location
-            && location.location_flags.contains(
-                xr::SpaceLocationFlags::POSITION_VALID | xr::SpaceLocationFlags::ORIENTATION_VALID,
-            )
-        {
-            let hand_pose = location.pose;
-            let rotation = {
-                let o = hand_pose.orientation;
-                let flip_x = nalgebra_glm::quat_angle_axis(
-                    180.0_f32.to_radians(),
-                    &nalgebra_glm::vec3(1.0, 0.0, 0.0),
+                render_pass.set_pipeline(&self.instanced_mesh_pipeline);
+                render_pass.set_bind_group(
+                    0,
+                    self.mesh_uniforms.bind_group(),
+                    &[self.mesh_uniforms.offset(instanced_slot)],
                 );
-                let openxr_quat = nalgebra_glm::quat(o.w, o.z, o.y, o.x);
-                flip_x * openxr_quat
-            };
-            let translation = nalgebra_glm::vec3(
-                -hand_pose.position.x,
-                hand_pose.position.y,
-                -hand_pose.position.z,
-            );
-            let hand_world_position = translation + self.player_position;
-
-            let rotation_matrix = nalgebra_glm::quat_to_mat4(&rotation);
-            let translation_matrix = nalgebra_glm::translation(&hand_world_position);
-            let hand_model = translation_matrix * rotation_matrix;
-
-            let left_hand_uniform = MeshUniform {
-                mvp: [
-                    (projection_matrices[0] * view_matrices[0] * hand_model).into(),
-                    (projection_matrices[1] * view_matrices[1] * hand_model).into(),
-                ],
-            };
-            queue.write_buffer(
-                &self.mesh_uniform_buffer,
-                0,
-                bytemuck::cast_slice(&[left_hand_uniform]),
-            );
-
-            let left_trigger_state = self
-                .left_trigger_action
-                .state(&self.session, xr::Path::NULL)
-                .ok();
-            let left_trigger_pulled = left_trigger_state
-                .map(|s| s.current_state > 0.5)
-                .unwrap_or(false);
-            let left_cube_buffer = if left_trigger_pulled {
-                &self.green_cube_vertex_buffer
-            } else {
-                &self.cube_vertex_buffer
-            };
+                scene.render_instances(render_pass);
+            });
 
-            let mut left_hand_encoder =
-                device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                    label: Some("Left Hand Encoder"),
-                });
-
-            {
-                let mut render_pass =
-                    left_hand_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                        label: Some("Left Hand Render Pass"),
-                        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                            view: &color_view,
-                            resolve_target: None,
-                            ops: wgpu::Operations {
-                                load: wgpu::LoadOp::Load,
-                                store: wgpu::StoreOp::Store,
-                            },
-                            depth_slice: None,
-                        })],
-                        depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                            view: &self.depth_view,
-                            depth_ops: Some(wgpu::Operations {
-                                load: wgpu::LoadOp::Load,
-                                store: wgpu::StoreOp::Store,
-                            }),
-                            stencil_ops: None,
-                        }),
-                        timestamp_writes: None,
-                        occlusion_query_set: None,
-                        multiview_mask: MULTIVIEW_MASK,
-                    });
+        graph
+            .add_pass("Grid Render Pass")
+            .read_texture(shadow_map)
+            .color_attachment(color, None, wgpu::Color::BLACK)
+            .depth_attachment(depth, 1.0)
+            .multiview_mask(MULTIVIEW_MASK)
+            .render(|render_pass, _| {
+                render_pass.set_pipeline(&self.grid_pipeline);
+                render_pass.set_bind_group(0, &self.grid_bind_group, &[]);
+                render_pass.set_bind_group(1, &self.shadows.bind_group, &[]);
+                render_pass.draw(0..6, 0..1);
+                perf::record_draw(2);
+            });
 
-                render_pass.set_pipeline(&self.mesh_pipeline);
-                render_pass.set_bind_group(0, &self.mesh_bind_group, &[]);
-                render_pass.set_vertex_buffer(0, left_cube_buffer.slice(..));
-                render_pass
-                    .set_index_buffer(self.cube_index_buffer.slice(..), wgpu::IndexFormat::Uint32);
-                render_pass.draw_indexed(0..36, 0, 0..1);
-            }
+        if self.outline.is_active() {
+            self.outline.add_passes(&mut graph, scene, color);
+        }
 
-            queue.submit(std::iter::once(left_hand_encoder.finish()));
+        if sample_count > 1 {
+            graph
+                .add_pass("XR Resolve Pass")
+                .color_attachment(color, Some(swapchain_color), wgpu::Color::BLACK)
+                .multiview_mask(MULTIVIEW_MASK)
+                .render(|_, _| {});
         }
 
-        let right_hand_location = self
-            .right_hand_space
-            .locate(&self.stage, frame_state.predicted_display_time);
-        if let Ok(location) = right_hand_location
-            && location.location_flags.contains(
-                xr::SpaceLocationFlags::POSITION_VALID | xr::SpaceLocationFlags::ORIENTATION_VALID,
-            )
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("XR Render Encoder"),
+        });
+        graph.execute(device, &mut encoder, &mut self.transient_pool);
         {
-            let hand_pose = location.pose;
-            let rotation = {
-                let o = hand_pose.orientation;
-                let flip_x = nalgebra_glm::quat_angle_axis(
-                    180.0_f32.to_radians(),
-                    &nalgebra_glm::vec3(1.0, 0.0, 0.0),
-                );
-                let openxr_quat = nalgebra_glm::quat(o.w, o.z, o.y, o.x);
-                flip_x * openxr_quat
-            };
-            let translation = nalgebra_glm::vec3(
-                -hand_pose.position.x,
-                hand_pose.position.y,
-                -hand_pose.position.z,
-            );
-            let hand_world_position = translation + self.player_position;
-
-            let rotation_matrix = nalgebra_glm::quat_to_mat4(&rotation);
-            let translation_matrix = nalgebra_glm::translation(&hand_world_position);
-            let hand_model = translation_matrix * rotation_matrix;
-
-            let right_hand_uniform = MeshUniform {
-                mvp: [
-                    (projection_matrices[0] * view_matrices[0] * hand_model).into(),
-                    (projection_matrices[1] * view_matrices[1] * hand_model).into(),
-                ],
-            };
-            queue.write_buffer(
-                &self.mesh_uniform_buffer,
-                0,
-                bytemuck::cast_slice(&[right_hand_uniform]),
-            );
-
-            let right_trigger_state = self
-                .right_trigger_action
-                .state(&self.session, xr::Path::NULL)
-                .ok();
-            let right_trigger_pulled = right_trigger_state
-                .map(|s| s.current_state > 0.5)
-                .unwrap_or(false);
-            let right_cube_buffer = if right_trigger_pulled {
-                &self.green_cube_vertex_buffer
-            } else {
-                &self.cube_vertex_buffer
-            };
-
-            let mut right_hand_encoder =
-                device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                    label: Some("Right Hand Encoder"),
-                });
-
-            {
-                let mut render_pass =
-                    right_hand_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                        label: Some("Right Hand Render Pass"),
-                        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                            view: &color_view,
-                            resolve_target: None,
-                            ops: wgpu::Operations {
-                                load: wgpu::LoadOp::Load,
-                                store: wgpu::StoreOp::Store,
-                            },
-                            depth_slice: None,
-                        })],
-                        depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                            view: &self.depth_view,
-                            depth_ops: Some(wgpu::Operations {
-                                load: wgpu::LoadOp::Load,
-                                store: wgpu::StoreOp::Store,
-                            }),
-                            stencil_ops: None,
-                        }),
-                        timestamp_writes: None,
-                        occlusion_query_set: None,
-                        multiview_mask: MULTIVIEW_MASK,
-                    });
-
-                render_pass.set_pipeline(&self.mesh_pipeline);
-                render_pass.set_bind_group(0, &self.mesh_bind_group, &[]);
-                render_pass.set_vertex_buffer(0, right_cube_buffer.slice(..));
-                render_pass
-                    .set_index_buffer(self.cube_index_buffer.slice(..), wgpu::IndexFormat::Uint32);
-                render_pass.draw_indexed(0..36, 0, 0..1);
-            }
-
-            queue.submit(std::iter::once(right_hand_encoder.finish()));
+            let _scope = trace::scope("Queue::submit");
+            queue.submit(std::iter::once(encoder.finish()));
         }
+        scene.culler.finish_frame();
 
         self.swapchain.release_image()?;
 
@@ -1414,6 +1510,7 @@ impl XrContext {
             .space(&self.stage)
             .views(&sub_images);
 
+        let _scope = trace::scope("XR::end_frame");
         self.frame_stream.end(
             frame_state.predicted_display_time,
             xr::EnvironmentBlendMode::OPAQUE,
@@ -1424,15 +1521,89 @@ impl XrContext {
     }
 }
 
+fn pose_record(pose: &xr::Posef) -> PoseRecord {
+    PoseRecord {
+        position: [pose.position.x, pose.position.y, pose.position.z],
+        orientation: [
+            pose.orientation.x,
+            pose.orientation.y,
+            pose.orientation.z,
+            pose.orientation.w,
+        ],
+    }
+}
+
+fn xr_view(view: &XrViewRecord) -> xr::View {
+    let [x, y, z] = view.pose.position;
+    let [qx, qy, qz, qw] = view.pose.orientation;
+    let [angle_left, angle_right, angle_up, angle_down] = view.fov;
+    xr::View {
+        pose: xr::Posef {
+            orientation: xr::Quaternionf {
+                x: qx,
+                y: qy,
+                z: qz,
+                w: qw,
+            },
+            position: xr::Vector3f { x, y, z },
+        },
+        fov: xr::Fovf {
+            angle_left,
+            angle_right,
+            angle_up,
+            angle_down,
+        },
+    }
+}
+
 pub fn run_xr() -> Result<(), Box<dyn std::error::Error>> {
     #[cfg(not(target_os = "android"))]
     env_logger::init();
+    trace::log_status();
     log::info!("Initializing OpenXR mode");
 
     let (mut xr_context, device, queue) = XrContext::new()?;
-    let mut scene = Scene::new(&device, wgpu::TextureFormat::Rgba8UnormSrgb);
+    let mut scene = Scene::new(&device, &queue, wgpu::TextureFormat::Rgba8UnormSrgb, 1);
     let mut last_render_time = Instant::now();
 
+    let mut cvars = CvarRegistry::default();
+    let scene_cvars = SceneCvars::declare(&mut cvars);
+    let replay_cvars = ReplayCvars::declare(&mut cvars);
+    for error in cvars.apply_args(std::env::args().skip(1)) {
+        log::warn!("Command line: {error}");
+    }
+    let record_path = cvars.get(replay_cvars.record);
+    let mut input_recorder = (!record_path.is_empty()).then(|| {
+        log::info!("Recording XR input to {record_path}");
+        InputRecorder::new(
+            &record_path,
+            RecordingHeader {
+                version: replay::RECORDING_VERSION,
+                cvars: cvars.to_config(),
+                bindings: Some(xr_context.actions.map().clone()),
+            },
+        )
+    });
+    let replay_path = cvars.get(replay_cvars.replay);
+    let mut input_replay = None;
+    if !replay_path.is_empty() {
+        match InputReplay::load(&replay_path) {
+            Ok(replay) => {
+                for error in cvars.apply_config(&replay.header().cvars) {
+                    log::warn!("Replay cvars: {error}");
+                }
+                if let Some(bindings) = replay.header().bindings.clone() {
+                    xr_context.actions.set_map(bindings);
+                }
+                log::info!("Replaying {} recorded XR frames", replay.len());
+                input_replay = Some(replay);
+            }
+            Err(error) => log::error!("input.replay: failed to load {replay_path}: {error}"),
+        }
+    }
+
+    scene_cvars.apply(&mut cvars, &mut scene);
+
     log::info!("Starting XR render loop");
 
     loop {
@@ -1454,14 +1625,29 @@ pub fn run_xr() -> Result<(), Box<dyn std::error::Error>> {
         }
 
         let now = Instant::now();
-        let delta_time = (now - last_render_time).as_secs_f32();
+        let mut frame_time = now - last_render_time;
         last_render_time = now;
 
+        let replay_frame = input_replay.as_mut().map(InputReplay::next_frame);
+        if let Some(None) = replay_frame {
+            log::info!("Input replay finished");
+            input_replay = None;
+            if cvars.get(replay_cvars.exit_on_finish) {
+                break;
+            }
+        }
+        let replay_frame = replay_frame.flatten();
+        if let Some(frame) = replay_frame.as_ref() {
+            frame_time = frame.delta_time();
+        }
+        let delta_time = frame_time.as_secs_f32();
+
         scene.model = nalgebra_glm::rotate(
             &scene.model,
-            30_f32.to_radians() * delta_time,
+            scene.spin_speed.to_radians() * delta_time,
             &nalgebra_glm::Vec3::y(),
         );
+        spin::update(&mut scene, delta_time);
 
         let frame_state = match xr_context.wait_frame() {
             Ok(frame_state) => frame_state,
@@ -1471,19 +1657,41 @@ pub fn run_xr() -> Result<(), Box<dyn std::error::Error>> {
             }
         };
 
-        if let Err(error) =
-            xr_context.update_movement(delta_time, frame_state.predicted_display_time)
-        {
-            log::warn!("Action sync failed, shutting down: {error}");
-            break;
+        let sources = match xr_context.sync_action_sources() {
+            Ok(sources) => sources,
+            Err(error) => {
+                log::warn!("Action sync failed, shutting down: {error}");
+                break;
+            }
+        };
+        let poses = match xr_context.locate_poses(frame_state.predicted_display_time) {
+            Ok(poses) => poses,
+            Err(error) => {
+                log::warn!("Pose tracking failed, shutting down: {error}");
+                break;
+            }
+        };
+        let (sources, poses) = match replay_frame {
+            Some(frame) => (frame.xr, frame.xr_poses.unwrap_or(poses)),
+            None => (sources, poses),
+        };
+        if let Some(recorder) = input_recorder.as_mut() {
+            recorder.record_xr_frame(frame_time, &sources, &poses);
         }
 
+        xr_context.update_movement(delta_time, &poses, &sources);
+
         if let Err(error) =
-            xr_context.render_frame(&device, &queue, &mut scene, delta_time, frame_state)
+            xr_context.render_frame(&device, &queue, &mut scene, delta_time, frame_state, &poses)
         {
             log::warn!("Frame rendering failed, shutting down: {error}");
             break;
         }
+        trace::frame_mark();
+    }
+
+    if let Some(recorder) = input_recorder {
+        recorder.save();
     }
 
     log::info!("Draining GPU work before teardown");